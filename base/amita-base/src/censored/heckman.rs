@@ -0,0 +1,607 @@
+//! Heckman sample-selection model.
+//!
+//! The outcome y = x'β + u is observed only when the selection index
+//! s* = z'γ + v is positive, where (u, v) are bivariate normal with
+//! Var(u) = σ², Var(v) = 1 and Corr(u, v) = ρ. Then
+//! E(y | x, s* > 0) = x'β + ρσ λ(z'γ), with λ = φ/Φ the inverse Mills ratio.
+//!
+//! Parameters are stacked as [β, γ, atanh ρ, ln σ] for maximum likelihood,
+//! and as [β, γ, β_λ] for the two-step estimator, where β_λ = ρσ is the
+//! coefficient of the inverse Mills ratio.
+
+use amita_error::AmitaError;
+use amita_utils::hypothesis::TestStatistic;
+use amita_utils::inference::SolverSEType;
+use amita_utils::iterations::TerminationCondition;
+use amita_utils::likelihood::{maximize, ml_vcov};
+use amita_utils::linalg::{inverse, solve};
+use amita_utils::summary::coef_table;
+use amita_utils::traits::{BaseResults, BaseSolver};
+use ndarray::prelude::*;
+use statrs::distribution::{ContinuousCDF, Normal};
+
+use super::normal::{ln_cdf, ln_pdf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeckmanMethod {
+    /// Probit of selection, then least squares on the selected sample with
+    /// the inverse Mills ratio as an extra regressor (Heckit)
+    TwoStep,
+    /// Full-information maximum likelihood of both equations
+    MaximumLikelihood,
+}
+
+#[derive(Debug, Clone)]
+pub struct HeckmanResults {
+    method: HeckmanMethod,
+    n_obs: usize,
+    n_selected: usize,
+    n_regressors: usize,
+    n_selection_regressors: usize,
+
+    coef: Option<Array1<f64>>,
+    se: Option<Array1<f64>>,
+    t: Option<Array1<f64>>, // z statistics
+    p_vals: Option<Array1<f64>>,
+    vcov: Option<Array2<f64>>,
+
+    rho: Option<f64>,
+    sigma: Option<f64>,
+    lambda: Option<f64>,
+    lambda_se: Option<f64>,
+    independence_test: Option<TestStatistic>,
+
+    loglik: Option<f64>, // maximum likelihood only
+    n_iter: Option<u64>,
+    termination: Option<TerminationCondition>,
+}
+
+impl HeckmanResults {
+    pub fn method(&self) -> HeckmanMethod {
+        self.method
+    }
+
+    pub fn n_obs(&self) -> usize {
+        self.n_obs
+    }
+
+    /// Observations with an observed outcome
+    pub fn n_selected(&self) -> usize {
+        self.n_selected
+    }
+
+    /// Coefficients β of the outcome equation
+    pub fn beta(&self) -> Result<Array1<f64>, AmitaError> {
+        Ok( self.coef()?.slice(s![..self.n_regressors]).to_owned() )
+    }
+
+    /// Coefficients γ of the selection probit
+    pub fn gamma(&self) -> Result<Array1<f64>, AmitaError> {
+        let end = self.n_regressors + self.n_selection_regressors;
+        Ok( self.coef()?.slice(s![self.n_regressors..end]).to_owned() )
+    }
+
+    /// Correlation ρ between the outcome and selection errors, truncated to
+    /// [-1, 1] by the two-step estimator
+    pub fn rho(&self) -> Result<f64, AmitaError> {
+        self.rho.ok_or(AmitaError::NotSolved)
+    }
+
+    /// Standard deviation σ of the outcome error
+    pub fn sigma(&self) -> Result<f64, AmitaError> {
+        self.sigma.ok_or(AmitaError::NotSolved)
+    }
+
+    /// Selectivity effect λ = ρσ, the coefficient of the inverse Mills ratio
+    pub fn lambda(&self) -> Result<f64, AmitaError> {
+        self.lambda.ok_or(AmitaError::NotSolved)
+    }
+
+    pub fn lambda_se(&self) -> Result<f64, AmitaError> {
+        self.lambda_se.ok_or(AmitaError::NotSolved)
+    }
+
+    /// Test of independent equations, ρ = 0: the likelihood-ratio test
+    /// against separate probit and OLS fits for maximum likelihood, and the
+    /// Wald test of λ = 0 for the two-step estimator
+    pub fn independence_test(&self) -> Result<TestStatistic, AmitaError> {
+        self.independence_test.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    /// Log-likelihood of the maximum likelihood fit, None for two-step
+    pub fn loglik(&self) -> Result<Option<f64>, AmitaError> {
+        self.coef()?;
+        Ok( self.loglik )
+    }
+
+    pub fn n_iter(&self) -> Result<u64, AmitaError> {
+        self.n_iter.ok_or(AmitaError::NotSolved)
+    }
+
+    pub fn termination(&self) -> Result<TerminationCondition, AmitaError> {
+        self.termination.ok_or(AmitaError::NotSolved)
+    }
+}
+
+impl BaseResults for HeckmanResults {
+    fn coef(&self) -> Result<Array1<f64>, AmitaError> {
+        self.coef.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn se(&self) -> Result<Array1<f64>, AmitaError> {
+        self.se.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn t(&self) -> Result<Array1<f64>, AmitaError> {
+        self.t.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn p_vals(&self) -> Result<Array1<f64>, AmitaError> {
+        self.p_vals.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn vcov(&self) -> Result<Array2<f64>, AmitaError> {
+        self.vcov.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn summary(&self) -> Result<String, AmitaError> {
+        coef_table(self)
+    }
+}
+
+/// First-step probit and second-step least squares
+#[derive(Debug, Clone)]
+struct TwoStepFit {
+    gamma: Array1<f64>,
+    probit_loglik: f64,
+    /// [β, β_λ]
+    outcome_coef: Array1<f64>,
+    /// Log-likelihood of the normal regression of y on x alone, on the
+    /// selected sample
+    ols_loglik: f64,
+    rho: f64,
+    sigma: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct HeckmanSolver {
+    y: Array1<f64>, // selected observations only
+    x: Array2<f64>, // selected observations only
+    selected: Array1<bool>,
+    z: Array2<f64>,
+
+    method: HeckmanMethod,
+    se_type: SolverSEType,
+    max_iter: u64,
+    max_tolerance: f64,
+
+    two_step: Option<TwoStepFit>,
+
+    results: HeckmanResults,
+}
+
+impl HeckmanSolver {
+    /// `y` and `x` are read on the rows with `selected` = 1 only, so the
+    /// outcome of unselected observations may be NaN. `z` holds the
+    /// selection regressors, which should include some excluded from `x`.
+    pub fn new(
+        y: &Array1<f64>,
+        x: &Array2<f64>,
+        selected: &Array1<i32>,
+        z: &Array2<f64>,
+    ) -> Result<Self, AmitaError> {
+        let n_obs = selected.len();
+        if y.len() != n_obs || x.shape()[0] != n_obs || z.shape()[0] != n_obs {
+            return Err(AmitaError::NotSameObservations);
+        }
+        if selected.iter().any(|s| *s != 0 && *s != 1) {
+            return Err(AmitaError::NonBinary { matrix_name: "`selected` of Heckman model".to_string() });
+        }
+
+        let rows = (0..n_obs).filter(|i| selected[*i] == 1).collect::<Vec<_>>();
+        let y = y.select(Axis(0), &rows);
+        let x = x.select(Axis(0), &rows);
+        if let Some(y_i) = y.iter().find(|y_i| !y_i.is_finite()) {
+            return Err(AmitaError::InvalidOutcome { outcome: y_i.to_string() });
+        }
+
+        let results = HeckmanResults {
+            method: HeckmanMethod::MaximumLikelihood,
+            n_obs,
+            n_selected: rows.len(),
+            n_regressors: x.shape()[1],
+            n_selection_regressors: z.shape()[1],
+
+            coef: None,
+            se: None,
+            t: None,
+            p_vals: None,
+            vcov: None,
+
+            rho: None,
+            sigma: None,
+            lambda: None,
+            lambda_se: None,
+            independence_test: None,
+
+            loglik: None,
+            n_iter: None,
+            termination: None,
+        };
+
+        Ok( Self {
+            y,
+            x,
+            selected: selected.map(|s| *s == 1),
+            z: z.to_owned(),
+
+            method: HeckmanMethod::MaximumLikelihood,
+            se_type: SolverSEType::NonRobust,
+            max_iter: 100,
+            max_tolerance: 1e-8,
+
+            two_step: None,
+
+            results,
+        } )
+    }
+
+    pub fn with_method(mut self, method: HeckmanMethod) -> Self {
+        self.method = method;
+        self.results.method = method;
+        self
+    }
+
+    /// Standard errors of the maximum likelihood estimator. The two-step
+    /// estimator only has Heckman's corrected non-robust covariance.
+    pub fn with_se_type(mut self, se_type: SolverSEType) -> Self {
+        self.se_type = se_type;
+        self
+    }
+
+    pub fn with_max_iter(mut self, max_iter: u64) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    pub fn with_max_tolerance(mut self, max_tolerance: f64) -> Self {
+        self.max_tolerance = max_tolerance;
+        self
+    }
+}
+
+impl BaseSolver<HeckmanResults> for HeckmanSolver {
+    fn results(&self) -> HeckmanResults {
+        self.results.clone()
+    }
+
+    fn solve(self) -> Result<Self, AmitaError> {
+        match self.method {
+            HeckmanMethod::TwoStep => self.run_two_step()?.solve_two_step_se()?.solve_z_pvals(),
+            HeckmanMethod::MaximumLikelihood => self.run_two_step()?.run_mle()?.solve_se()?.solve_z_pvals(),
+        }
+    }
+}
+
+impl HeckmanSolver {
+    fn run_two_step(mut self) -> Result<Self, AmitaError> {
+        let state = maximize(
+            Array1::zeros(self.z.shape()[1]),
+            self.max_iter,
+            self.max_tolerance,
+            |gamma| self.probit_loglik_obs(gamma),
+        )?;
+        let probit_loglik = - state.cost * self.results.n_obs as f64;
+        let gamma = state.param;
+
+        let mills = self.selected_index(&gamma).map(|zg| inverse_mills(*zg));
+        let w = ndarray::concatenate![Axis(1), self.x, mills.insert_axis(Axis(1))];
+        let outcome_coef = solve(&w.t().dot(&w), &w.t().dot(&self.y), "W'W of the Heckman second step")?;
+
+        let n_selected = self.results.n_selected as f64;
+        let resid = &self.y - &w.dot(&outcome_coef);
+        let beta_lambda = outcome_coef[outcome_coef.len() - 1];
+        let sigma = ( resid.dot(&resid) / n_selected + beta_lambda.powi(2) * self.mills_delta(&gamma).mean().unwrap() ).sqrt();
+
+        let beta_ols = solve(&self.x.t().dot(&self.x), &self.x.t().dot(&self.y), "X'X of the Heckman outcome")?;
+        let resid_ols = &self.y - &self.x.dot(&beta_ols);
+        let variance_ols = resid_ols.dot(&resid_ols) / n_selected;
+        let ols_loglik = - 0.5 * n_selected * ( (2. * std::f64::consts::PI * variance_ols).ln() + 1. );
+
+        self.two_step = Some( TwoStepFit {
+            gamma,
+            probit_loglik,
+            outcome_coef,
+            ols_loglik,
+            rho: (beta_lambda / sigma).clamp(-1., 1.),
+            sigma,
+        } );
+
+        Ok(self)
+    }
+
+    /// Heckman's covariance of the second step, corrected for the estimated
+    /// inverse Mills ratio:
+    /// σ² (W'W)^{-1} [W'(I - ρ²Δ)W + ρ² (W'ΔZ) V_γ (Z'ΔW)] (W'W)^{-1},
+    /// with δ_i = λ_i (λ_i + z_i'γ). The equations are reported as
+    /// uncorrelated.
+    fn solve_two_step_se(mut self) -> Result<Self, AmitaError> {
+        if !matches!(self.se_type, SolverSEType::NonRobust | SolverSEType::Homoscedastic) {
+            return Err(AmitaError::UnsupportedSEType { se_type: format!("{:?} for the Heckman two-step estimator", self.se_type) });
+        }
+
+        let fit = self.two_step.clone().ok_or(AmitaError::NotSolved)?;
+        let k = self.results.n_regressors;
+        let m = self.results.n_selection_regressors;
+
+        let probit_vcov = ml_vcov(&fit.gamma, &SolverSEType::NonRobust, |gamma| self.probit_loglik_obs(gamma))?;
+
+        let mills = self.selected_index(&fit.gamma).map(|zg| inverse_mills(*zg));
+        let w = ndarray::concatenate![Axis(1), self.x, mills.insert_axis(Axis(1))];
+        let z_selected = self.selected_z();
+        let delta = self.mills_delta(&fit.gamma);
+        let rho_sq = fit.rho * fit.rho;
+
+        let wtw_inv = inverse(&w.t().dot(&w), "W'W of the Heckman second step")?;
+        let w_delta = &w * &delta.view().insert_axis(Axis(1));
+        let adjusted = w.t().dot(&w) - w.t().dot(&w_delta) * rho_sq;
+        let q = w_delta.t().dot(&z_selected).dot(&probit_vcov).dot(&z_selected.t().dot(&w_delta)) * rho_sq;
+        let outcome_vcov = wtw_inv.dot(&(adjusted + q)).dot(&wtw_inv) * fit.sigma.powi(2);
+
+        let mut coef = Array1::zeros(k + m + 1);
+        coef.slice_mut(s![..k]).assign(&fit.outcome_coef.slice(s![..k]));
+        coef.slice_mut(s![k..k + m]).assign(&fit.gamma);
+        coef[k + m] = fit.outcome_coef[k];
+
+        // outcome coefficients sit at 0..k and k + m, the probit at k..k + m
+        let outcome_index = (0..k).chain(std::iter::once(k + m)).collect::<Vec<_>>();
+        let mut vcov = Array2::zeros((k + m + 1, k + m + 1));
+        for (a, i) in outcome_index.iter().enumerate() {
+            for (b, j) in outcome_index.iter().enumerate() {
+                vcov[[*i, *j]] = outcome_vcov[[a, b]];
+            }
+        }
+        vcov.slice_mut(s![k..k + m, k..k + m]).assign(&probit_vcov);
+
+        let lambda_se = vcov[[k + m, k + m]].sqrt();
+        let lambda = coef[k + m];
+
+        self.results.rho = Some(fit.rho);
+        self.results.sigma = Some(fit.sigma);
+        self.results.lambda = Some(lambda);
+        self.results.lambda_se = Some(lambda_se);
+        self.results.independence_test = Some(
+            TestStatistic::chi_squared("Wald test of lambda = 0", (lambda / lambda_se).powi(2), 1.)
+        );
+        self.results.se = Some(vcov.diag().map(|x| x.sqrt()));
+        self.results.vcov = Some(vcov);
+        self.results.coef = Some(coef);
+
+        Ok(self)
+    }
+
+    fn run_mle(mut self) -> Result<Self, AmitaError> {
+        let fit = self.two_step.clone().ok_or(AmitaError::NotSolved)?;
+        let k = self.results.n_regressors;
+
+        let mut init = fit.outcome_coef.slice(s![..k]).to_vec();
+        init.extend(fit.gamma.iter());
+        init.push( fit.rho.clamp(-0.9, 0.9).atanh() );
+        init.push( fit.sigma.ln() );
+
+        let state = maximize(Array1::from(init), self.max_iter, self.max_tolerance, |param| self.loglik_obs(param))?;
+        let loglik = - state.cost * self.results.n_obs as f64;
+
+        self.results.loglik = Some(loglik);
+        self.results.n_iter = Some(state.n_iter);
+        self.results.termination = Some(state.termination);
+        self.results.coef = Some(state.param);
+        self.results.independence_test = Some( TestStatistic::chi_squared(
+            "LR test of rho = 0",
+            2. * (loglik - fit.probit_loglik - fit.ols_loglik),
+            1.,
+        ) );
+
+        Ok(self)
+    }
+
+    fn solve_se(mut self) -> Result<Self, AmitaError> {
+        let coef = self.results.coef()?;
+        let vcov = ml_vcov(&coef, &self.se_type, |param| self.loglik_obs(param))?;
+
+        // ρ = tanh(atanh ρ), σ = exp(ln σ), λ = ρσ by the delta method
+        let j = coef.len() - 2;
+        let rho = coef[j].tanh();
+        let sigma = coef[j + 1].exp();
+        let gradient = array![(1. - rho * rho) * sigma, rho * sigma];
+        let ancillary_vcov = vcov.slice(s![j.., j..]).to_owned();
+
+        self.results.rho = Some(rho);
+        self.results.sigma = Some(sigma);
+        self.results.lambda = Some(rho * sigma);
+        self.results.lambda_se = Some(gradient.dot(&ancillary_vcov.dot(&gradient)).sqrt());
+        self.results.se = Some(vcov.diag().map(|x| x.sqrt()));
+        self.results.vcov = Some(vcov);
+
+        Ok(self)
+    }
+
+    fn solve_z_pvals(mut self) -> Result<Self, AmitaError> {
+        let z = self.results.coef()? / self.results.se()?;
+
+        let normal = Normal::new(0., 1.).unwrap();
+        let p_vals = z.map(|x| 2. * ( 1. - normal.cdf(x.abs()) ) );
+
+        self.results.t = Some(z);
+        self.results.p_vals = Some(p_vals);
+
+        Ok(self)
+    }
+
+    /// Selection regressors of the selected observations
+    fn selected_z(&self) -> Array2<f64> {
+        let rows = (0..self.selected.len()).filter(|i| self.selected[*i]).collect::<Vec<_>>();
+        self.z.select(Axis(0), &rows)
+    }
+
+    fn selected_index(&self, gamma: &Array1<f64>) -> Array1<f64> {
+        self.selected_z().dot(gamma)
+    }
+
+    /// δ_i = λ_i (λ_i + z_i'γ) on the selected observations
+    fn mills_delta(&self, gamma: &Array1<f64>) -> Array1<f64> {
+        self.selected_index(gamma).map(|zg| {
+            let mills = inverse_mills(*zg);
+            mills * (mills + zg)
+        })
+    }
+
+    fn probit_loglik_obs(&self, gamma: &Array1<f64>) -> (Array1<f64>, Array2<f64>) {
+        let index = self.z.dot(gamma);
+
+        let mut loglik = Array1::zeros(index.len());
+        let mut scores = Array2::zeros(self.z.raw_dim());
+        for (i, zg) in index.iter().enumerate() {
+            let sign = if self.selected[i] { 1. } else { - 1. };
+            loglik[i] = ln_cdf(sign * zg);
+            scores.row_mut(i).assign(&(&self.z.row(i) * (sign * inverse_mills(sign * zg))));
+        }
+
+        (loglik, scores)
+    }
+
+    /// Full-information log-likelihood: ln Φ(-z'γ) when unselected, and
+    /// ln Φ((z'γ + ρ e/σ) / √(1 - ρ²)) + ln φ(e/σ) - ln σ with e = y - x'β
+    /// when selected
+    fn loglik_obs(&self, param: &Array1<f64>) -> (Array1<f64>, Array2<f64>) {
+        let k = self.results.n_regressors;
+        let m = self.results.n_selection_regressors;
+        let beta = param.slice(s![..k]);
+        let gamma = param.slice(s![k..k + m]);
+        let rho = param[k + m].tanh();
+        let ln_sigma = param[k + m + 1];
+        let sigma = ln_sigma.exp();
+        let sqrt_one_minus = (1. - rho * rho).sqrt();
+
+        let index = self.z.dot(&gamma);
+        let fitted = self.x.dot(&beta);
+
+        let n_obs = self.selected.len();
+        let mut loglik = Array1::zeros(n_obs);
+        let mut scores = Array2::zeros((n_obs, k + m + 2));
+        let mut selected_row = 0;
+        for i in 0..n_obs {
+            let zg = index[i];
+            if !self.selected[i] {
+                loglik[i] = ln_cdf(- zg);
+                scores.slice_mut(s![i, k..k + m]).assign(&(&self.z.row(i) * (- inverse_mills(- zg))));
+                continue;
+            }
+
+            let r = (self.y[selected_row] - fitted[selected_row]) / sigma;
+            let a = (zg + rho * r) / sqrt_one_minus;
+            let mills = inverse_mills(a);
+
+            loglik[i] = ln_cdf(a) + ln_pdf(r) - ln_sigma;
+            scores.slice_mut(s![i, ..k])
+                .assign(&(&self.x.row(selected_row) * ( r / sigma - mills * rho / (sigma * sqrt_one_minus) )));
+            scores.slice_mut(s![i, k..k + m]).assign(&(&self.z.row(i) * (mills / sqrt_one_minus)));
+            scores[[i, k + m]] = mills * ( r * sqrt_one_minus + rho * a );
+            scores[[i, k + m + 1]] = r * r - 1. - mills * rho * r / sqrt_one_minus;
+
+            selected_row += 1;
+        }
+
+        (loglik, scores)
+    }
+}
+
+/// λ(z) = φ(z)/Φ(z)
+fn inverse_mills(z: f64) -> f64 {
+    (ln_pdf(z) - ln_cdf(z)).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use amita_utils::math::numerical_jacobian;
+
+    use super::*;
+
+    /// Selection sample with ρ = 0.6, σ = 2 and an excluded instrument,
+    /// driven by low-discrepancy normal draws
+    fn data(n: usize) -> (Array1<f64>, Array2<f64>, Array1<i32>, Array2<f64>) {
+        let normal = Normal::new(0., 1.).unwrap();
+        let draw = |i: usize, step: f64| normal.inverse_cdf( ((i as f64 + 0.5) * step).fract() );
+
+        let mut y = Array1::zeros(n);
+        let mut x = Array2::ones((n, 2));
+        let mut selected = Array1::zeros(n);
+        let mut z = Array2::ones((n, 3));
+        for i in 0..n {
+            let x1 = draw(i, 5_f64.sqrt());
+            let w = draw(i, 7_f64.sqrt());
+            let v = draw(i, 2_f64.sqrt());
+            let u = 2. * ( 0.6 * v + 0.8 * draw(i, 3_f64.sqrt()) );
+
+            x[[i, 1]] = x1;
+            z[[i, 1]] = x1;
+            z[[i, 2]] = w;
+            if 0.3 + 0.5 * x1 + 1. * w + v > 0. {
+                selected[i] = 1;
+                y[i] = 1. + 2. * x1 + u;
+            } else {
+                y[i] = f64::NAN;
+            }
+        }
+
+        (y, x, selected, z)
+    }
+
+    #[test]
+    fn test_scores() -> Result<(), AmitaError> {
+        let (y, x, selected, z) = data(60);
+        let solver = HeckmanSolver::new(&y, &x, &selected, &z)?;
+
+        let param = array![0.8, 1.7, 0.2, 0.4, 0.9, 0.5, 0.6];
+        let (_, scores) = solver.loglik_obs(&param);
+        let numerical = numerical_jacobian(|p| solver.loglik_obs(p).0, &param);
+
+        assert!( (&scores - &numerical).iter().all(|d| d.abs() < 1e-6) );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_two_step_and_mle() -> Result<(), AmitaError> {
+        let (y, x, selected, z) = data(2_000);
+
+        let two_step = HeckmanSolver::new(&y, &x, &selected, &z)?
+            .with_method(HeckmanMethod::TwoStep)
+            .solve()?
+            .results();
+        let mle = HeckmanSolver::new(&y, &x, &selected, &z)?.solve()?.results();
+
+        for results in [&two_step, &mle] {
+            assert!( (results.beta()?[1] - 2.).abs() < 0.2 );
+            assert!( (results.gamma()?[2] - 1.).abs() < 0.15 );
+            assert!( (results.rho()? - 0.6).abs() < 0.2 );
+            assert!( (results.sigma()? - 2.).abs() < 0.2 );
+            assert!( results.independence_test()?.p_val() < 0.01 );
+        }
+        assert!( (mle.lambda()? - mle.rho()? * mle.sigma()?).abs() < 1e-12 );
+        assert!( mle.loglik()?.is_some() && two_step.loglik()?.is_none() );
+
+        // [β, γ, β_λ] against [β, γ, atanh ρ, ln σ]
+        assert_eq!(two_step.coef()?.len(), 6);
+        assert_eq!(mle.coef()?.len(), 7);
+        assert!( (two_step.lambda()? - two_step.coef()?[5]).abs() < 1e-12 );
+
+        let robust = HeckmanSolver::new(&y, &x, &selected, &z)?
+            .with_method(HeckmanMethod::TwoStep)
+            .with_se_type(SolverSEType::Robust)
+            .solve();
+        assert!( matches!(robust, Err(AmitaError::UnsupportedSEType { .. })) );
+
+        Ok(())
+    }
+}
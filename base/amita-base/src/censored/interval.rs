@@ -0,0 +1,179 @@
+//! Interval regression: the latent y* = x'β + σε, ε ~ N(0, 1), is only known
+//! to lie in [lower_i, upper_i]. Equal bounds are exact observations, and an
+//! infinite or NaN bound leaves the interval open on that side.
+
+use amita_error::AmitaError;
+use amita_utils::inference::SolverSEType;
+use amita_utils::likelihood::{maximize, ml_vcov};
+use amita_utils::traits::{BaseResults, BaseSolver};
+use ndarray::prelude::*;
+
+use super::normal::{init_param, interval_density, loglik_obs};
+use super::results::{CensoredModel, CensoredResults};
+
+#[derive(Debug, Clone)]
+pub struct IntervalSolver {
+    lower: Array1<f64>,
+    upper: Array1<f64>,
+    x: Array2<f64>,
+    se_type: SolverSEType,
+
+    max_iter: u64,
+    max_tolerance: f64,
+
+    results: CensoredResults,
+}
+
+impl IntervalSolver {
+    pub fn new(
+        lower: &Array1<f64>,
+        upper: &Array1<f64>,
+        x: &Array2<f64>,
+    ) -> Result<Self, AmitaError> {
+        if lower.len() != upper.len() || lower.len() != x.shape()[0] {
+            return Err(AmitaError::NotSameObservations);
+        }
+
+        let lower = lower.map(|l| if l.is_nan() { f64::NEG_INFINITY } else { *l });
+        let upper = upper.map(|u| if u.is_nan() { f64::INFINITY } else { *u });
+
+        for (l, u) in lower.iter().zip(upper.iter()) {
+            if l > u || *l == f64::INFINITY || *u == f64::NEG_INFINITY || !(l.is_finite() || u.is_finite()) {
+                return Err(AmitaError::InvalidOutcome { outcome: format!("[{l}, {u}]") });
+            }
+        }
+
+        let mut results = CensoredResults::new(CensoredModel::Interval, x.shape()[0], x.shape()[1]);
+        results.set_censoring(&lower, &upper);
+
+        Ok( Self::from_bounds(lower, upper, x.to_owned(), results) )
+    }
+
+    /// Solver over bounds that are already validated
+    pub(crate) fn from_bounds(
+        lower: Array1<f64>,
+        upper: Array1<f64>,
+        x: Array2<f64>,
+        results: CensoredResults,
+    ) -> Self {
+        Self {
+            lower,
+            upper,
+            x,
+            se_type: SolverSEType::NonRobust,
+
+            max_iter: 100,
+            max_tolerance: 1e-8,
+
+            results,
+        }
+    }
+
+    pub fn with_se_type(mut self, se_type: SolverSEType) -> Self {
+        self.se_type = se_type;
+        self
+    }
+
+    pub fn with_max_iter(mut self, max_iter: u64) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    pub fn with_max_tolerance(mut self, max_tolerance: f64) -> Self {
+        self.max_tolerance = max_tolerance;
+        self
+    }
+}
+
+impl BaseSolver<CensoredResults> for IntervalSolver {
+    fn results(&self) -> CensoredResults {
+        self.results.clone()
+    }
+
+    fn solve(self) -> Result<Self, AmitaError> {
+        self
+        .run_solver()?
+        .solve_se()?
+        .solve_z_pvals()
+    }
+}
+
+impl IntervalSolver {
+    fn run_solver(mut self) -> Result<Self, AmitaError> {
+        let init = init_param(&self.lower, &self.upper, &self.x)?;
+        let state = maximize(init, self.max_iter, self.max_tolerance, |param| self.loglik_obs(param))?;
+
+        self.results.set_fit(state);
+        Ok(self)
+    }
+
+    fn solve_se(mut self) -> Result<Self, AmitaError> {
+        let coef = self.results.coef()?;
+        let vcov = ml_vcov(&coef, &self.se_type, |param| self.loglik_obs(param))?;
+
+        self.results.set_vcov(vcov);
+        Ok(self)
+    }
+
+    fn solve_z_pvals(mut self) -> Result<Self, AmitaError> {
+        self.results.set_z_pvals()?;
+        Ok(self)
+    }
+
+    fn loglik_obs(&self, param: &Array1<f64>) -> (Array1<f64>, Array2<f64>) {
+        loglik_obs(&self.x, param.view(), |i, xb, ln_sigma| {
+            interval_density(self.lower[i], self.upper[i], xb, ln_sigma)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+
+    #[test]
+    fn test_exact_bounds_are_least_squares() -> Result<(), AmitaError> {
+        let y = array![1.2, 2.9, 2.1, 4.8, 3.7, 6.1, 5.2, 8.3];
+        let x1 = array![0.5, 1.1, 1.4, 2.2, 2.0, 3.1, 2.7, 4.0];
+        let x = ndarray::stack![Axis(1), Array1::<f64>::ones(8), x1];
+
+        let results = IntervalSolver::new(&y, &y, &x)?.solve()?.results();
+
+        // the normal MLE is OLS, with σ² = SSR / n
+        let xtx = x.t().dot(&x);
+        let ols = amita_utils::linalg::solve(&xtx, &x.t().dot(&y), "X'X")?;
+        let resid = &y - &x.dot(&ols);
+
+        assert!( (results.beta()? - &ols).iter().all(|d| d.abs() < 1e-6) );
+        assert!( (results.sigma()?.powi(2) - resid.dot(&resid) / 8.).abs() < 1e-6 );
+        assert_eq!(results.n_uncensored(), 8);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_censoring_counts_and_bounds() -> Result<(), AmitaError> {
+        let lower = array![f64::NAN, 1., 2., 0.5, 3., f64::NEG_INFINITY, 4.5, 2.5, 1.8, 2.6];
+        let upper = array![1., f64::INFINITY, 3., 0.5, 4., 2., f64::NAN, 3.5, 1.8, 2.6];
+        let x1 = array![0.2, 1.5, 1.1, 0.1, 2.4, 0.7, 3.0, 1.9, 0.6, 2.2];
+        let x = ndarray::stack![Axis(1), Array1::<f64>::ones(10), x1];
+
+        let results = IntervalSolver::new(&lower, &upper, &x)?
+            .with_se_type(SolverSEType::Robust)
+            .solve()?
+            .results();
+
+        assert_eq!(results.n_left_censored(), 2);
+        assert_eq!(results.n_right_censored(), 2);
+        assert_eq!(results.n_interval_censored(), 3);
+        assert_eq!(results.n_uncensored(), 3);
+        assert!( results.beta()?[1] > 0. );
+
+        let reversed = IntervalSolver::new(&array![2.], &array![1.], &array![[1.]]);
+        assert!( matches!(reversed, Err(AmitaError::InvalidOutcome { .. })) );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,6 @@
+mod normal;
+pub mod heckman;
+pub mod interval;
+pub mod results;
+pub mod tobit;
+pub mod truncated;
@@ -0,0 +1,198 @@
+//! Log-likelihood of a latent normal outcome y* = x'β + σε that is observed
+//! exactly, only up to an interval, or only when it falls inside an interval.
+//!
+//! Parameters are stacked as [β, ln σ]. Tail probabilities are evaluated on
+//! the log scale so that observations far into a censored tail keep finite
+//! log-likelihoods and scores.
+
+use std::f64::consts::{FRAC_1_SQRT_2, PI};
+
+use amita_error::AmitaError;
+use amita_utils::linalg::solve;
+use ndarray::prelude::*;
+use statrs::function::erf::erfc;
+
+/// ln φ(z)
+pub(crate) fn ln_pdf(z: f64) -> f64 {
+    - 0.5 * ( z * z + (2. * PI).ln() )
+}
+
+/// ln Φ(z), by its asymptotic series Φ(z) ≈ φ(z)/(-z) Σ_j (-1)^j (2j - 1)!! / z^{2j}
+/// once Φ(z) underflows, where z < -37
+pub(crate) fn ln_cdf(z: f64) -> f64 {
+    if z == f64::INFINITY {
+        return 0.;
+    }
+
+    let cdf = 0.5 * erfc(- z * FRAC_1_SQRT_2);
+    if cdf > 0. {
+        cdf.ln()
+    } else {
+        let z_sq = z * z;
+        let series = (1..6).rev().fold(1., |acc, j| 1. - (2 * j - 1) as f64 / z_sq * acc);
+        ln_pdf(z) - (- z).ln() + series.ln()
+    }
+}
+
+/// ln(Φ(b) - Φ(a)) for a < b, taking differences in the tail they share
+fn ln_prob(a: f64, b: f64) -> f64 {
+    if a == f64::NEG_INFINITY {
+        ln_cdf(b)
+    } else if b == f64::INFINITY {
+        ln_cdf(- a)
+    } else if a > 0. {
+        let upper = ln_cdf(- a);
+        upper + ( - (ln_cdf(- b) - upper).exp() ).ln_1p()
+    } else {
+        let upper = ln_cdf(b);
+        upper + ( - (ln_cdf(a) - upper).exp() ).ln_1p()
+    }
+}
+
+/// φ(z) / P and z φ(z) / P, both zero at infinite z
+fn density_ratios(z: f64, ln_p: f64) -> (f64, f64) {
+    if z.is_finite() {
+        let ratio = (ln_pdf(z) - ln_p).exp();
+        (ratio, z * ratio)
+    } else {
+        (0., 0.)
+    }
+}
+
+/// Contribution of y* ∈ [lower, upper] at index x'β, with ∂/∂x'β and ∂/∂ln σ.
+/// A degenerate interval is an exactly observed outcome.
+pub(crate) fn interval_density(lower: f64, upper: f64, xb: f64, ln_sigma: f64) -> (f64, f64, f64) {
+    let sigma = ln_sigma.exp();
+
+    if lower == upper {
+        let z = (upper - xb) / sigma;
+        return ( ln_pdf(z) - ln_sigma, z / sigma, z * z - 1. );
+    }
+
+    let a = (lower - xb) / sigma;
+    let b = (upper - xb) / sigma;
+    let ln_p = ln_prob(a, b);
+    let (ratio_a, tail_a) = density_ratios(a, ln_p);
+    let (ratio_b, tail_b) = density_ratios(b, ln_p);
+
+    ( ln_p, (ratio_a - ratio_b) / sigma, tail_a - tail_b )
+}
+
+/// Contribution of an outcome y observed only because lower < y* < upper
+pub(crate) fn truncated_density(y: f64, lower: f64, upper: f64, xb: f64, ln_sigma: f64) -> (f64, f64, f64) {
+    let sigma = ln_sigma.exp();
+
+    let z = (y - xb) / sigma;
+    let a = (lower - xb) / sigma;
+    let b = (upper - xb) / sigma;
+    let ln_p = ln_prob(a, b);
+    let (ratio_a, tail_a) = density_ratios(a, ln_p);
+    let (ratio_b, tail_b) = density_ratios(b, ln_p);
+
+    (
+        ln_pdf(z) - ln_sigma - ln_p,
+        z / sigma - (ratio_a - ratio_b) / sigma,
+        z * z - 1. - (tail_a - tail_b),
+    )
+}
+
+/// Per-observation log-likelihood and n×(k + 1) scores, given the
+/// contribution of each observation as a function of (i, x'β, ln σ)
+pub(crate) fn loglik_obs<F>(
+    x: &Array2<f64>,
+    param: ArrayView1<f64>,
+    density: F,
+) -> (Array1<f64>, Array2<f64>)
+where
+    F: Fn(usize, f64, f64) -> (f64, f64, f64),
+{
+    let k = x.shape()[1];
+    let beta = param.slice(s![..k]);
+    let ln_sigma = param[k];
+    let index = x.dot(&beta);
+
+    let mut loglik = Array1::zeros(index.len());
+    let mut scores = Array2::zeros((index.len(), k + 1));
+    for (i, xb) in index.iter().enumerate() {
+        let (ll, d_xb, d_ln_sigma) = density(i, *xb, ln_sigma);
+        loglik[i] = ll;
+        scores.row_mut(i).slice_mut(s![..k]).assign(&x.row(i).map(|x_ij| x_ij * d_xb));
+        scores[[i, k]] = d_ln_sigma;
+    }
+
+    (loglik, scores)
+}
+
+/// Starting values from least squares of a point inside each interval: its
+/// midpoint, or its finite bound when the interval is open on one side
+pub(crate) fn init_param(
+    lower: &Array1<f64>,
+    upper: &Array1<f64>,
+    x: &Array2<f64>,
+) -> Result<Array1<f64>, AmitaError> {
+    let response = lower.iter().zip(upper.iter())
+        .map(|(l, u)| match (l.is_finite(), u.is_finite()) {
+            (true, true) => (l + u) / 2.,
+            (true, false) => *l,
+            _ => *u,
+        })
+        .collect::<Array1<f64>>();
+
+    let beta = solve(&x.t().dot(x), &x.t().dot(&response), "X'X of the censored model")?;
+
+    let resid = &response - &x.dot(&beta);
+    let variance = resid.dot(&resid) / resid.len() as f64;
+
+    let mut param = beta.to_vec();
+    param.push( 0.5 * variance.max(1e-8).ln() );
+
+    Ok( Array1::from(param) )
+}
+
+#[cfg(test)]
+mod tests {
+    use amita_utils::math::numerical_jacobian;
+
+    use super::*;
+
+    #[test]
+    fn test_scores() {
+        let inf = f64::INFINITY;
+        let intervals = [
+            (1.3, 1.3),
+            (- inf, 0.),
+            (0., inf),
+            (- 0.5, 2.),
+            (3., 4.),
+            (- inf, - 40.),
+            (40., inf),
+        ];
+
+        for (lower, upper) in intervals {
+            for (xb, ln_sigma) in [(0.4, 0.2), (- 1.1, - 0.3)] {
+                let point = Array1::from(vec![xb, ln_sigma]);
+                let (ll, d_xb, d_ln_sigma) = interval_density(lower, upper, xb, ln_sigma);
+                let numerical = numerical_jacobian(
+                    |p| Array1::from(vec![interval_density(lower, upper, p[0], p[1]).0]),
+                    &point,
+                );
+
+                assert!( ll.is_finite() );
+                assert!( (d_xb - numerical[[0, 0]]).abs() < 1e-5 * d_xb.abs().max(1.) );
+                assert!( (d_ln_sigma - numerical[[0, 1]]).abs() < 1e-5 * d_ln_sigma.abs().max(1.) );
+            }
+        }
+
+        for (y, lower, upper) in [(0.5, 0., inf), (0.5, - inf, 1.), (2.5, 2., 3.)] {
+            let point = Array1::from(vec![0.3, - 0.2]);
+            let (_, d_xb, d_ln_sigma) = truncated_density(y, lower, upper, point[0], point[1]);
+            let numerical = numerical_jacobian(
+                |p| Array1::from(vec![truncated_density(y, lower, upper, p[0], p[1]).0]),
+                &point,
+            );
+
+            assert!( (d_xb - numerical[[0, 0]]).abs() < 1e-5 * d_xb.abs().max(1.) );
+            assert!( (d_ln_sigma - numerical[[0, 1]]).abs() < 1e-5 * d_ln_sigma.abs().max(1.) );
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! Results of the censored, interval and truncated normal regressions.
+//!
+//! Parameters are stacked as [β, ln σ].
+
+use amita_error::AmitaError;
+use amita_utils::iterations::{NewtonRaphsonState, TerminationCondition};
+use amita_utils::summary::coef_table;
+use amita_utils::traits::BaseResults;
+use ndarray::prelude::*;
+use statrs::distribution::{ContinuousCDF, Normal};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CensoredModel {
+    /// Type-I Tobit, censored at fixed lower and upper limits
+    Tobit,
+    /// Outcomes known to lie within observation-specific bounds
+    Interval,
+    /// Outcomes sampled only inside fixed truncation limits
+    Truncated,
+}
+
+#[derive(Debug, Clone)]
+pub struct CensoredResults {
+    model: CensoredModel,
+    n_obs: usize,
+    n_regressors: usize,
+
+    n_left_censored: usize,
+    n_right_censored: usize,
+    n_interval_censored: usize,
+
+    coef: Option<Array1<f64>>, // [β, ln σ]
+    se: Option<Array1<f64>>,
+    t: Option<Array1<f64>>, // z statistics
+    p_vals: Option<Array1<f64>>,
+    vcov: Option<Array2<f64>>,
+
+    loglik: Option<f64>,
+    n_iter: Option<u64>,
+    termination: Option<TerminationCondition>,
+}
+
+impl CensoredResults {
+    pub(crate) fn new(model: CensoredModel, n_obs: usize, n_regressors: usize) -> Self {
+        Self {
+            model,
+            n_obs,
+            n_regressors,
+
+            n_left_censored: 0,
+            n_right_censored: 0,
+            n_interval_censored: 0,
+
+            coef: None,
+            se: None,
+            t: None,
+            p_vals: None,
+            vcov: None,
+
+            loglik: None,
+            n_iter: None,
+            termination: None,
+        }
+    }
+
+    pub fn model(&self) -> CensoredModel {
+        self.model
+    }
+
+    pub fn n_obs(&self) -> usize {
+        self.n_obs
+    }
+
+    pub fn n_params(&self) -> usize {
+        self.n_regressors + 1
+    }
+
+    /// Observations only known to lie below their upper bound
+    pub fn n_left_censored(&self) -> usize {
+        self.n_left_censored
+    }
+
+    /// Observations only known to lie above their lower bound
+    pub fn n_right_censored(&self) -> usize {
+        self.n_right_censored
+    }
+
+    /// Observations only known to lie between two finite bounds
+    pub fn n_interval_censored(&self) -> usize {
+        self.n_interval_censored
+    }
+
+    pub fn n_uncensored(&self) -> usize {
+        self.n_obs - self.n_left_censored - self.n_right_censored - self.n_interval_censored
+    }
+
+    /// Coefficients β of the latent outcome
+    pub fn beta(&self) -> Result<Array1<f64>, AmitaError> {
+        Ok( self.coef()?.slice(s![..self.n_regressors]).to_owned() )
+    }
+
+    /// Standard deviation σ of the latent error
+    pub fn sigma(&self) -> Result<f64, AmitaError> {
+        Ok( self.coef()?[self.n_regressors].exp() )
+    }
+
+    /// Delta-method standard error of σ, σ se(ln σ)
+    pub fn sigma_se(&self) -> Result<f64, AmitaError> {
+        Ok( self.sigma()? * self.se()?[self.n_regressors] )
+    }
+
+    pub fn loglik(&self) -> Result<f64, AmitaError> {
+        self.loglik.ok_or(AmitaError::NotSolved)
+    }
+
+    pub fn aic(&self) -> Result<f64, AmitaError> {
+        Ok( - 2. * self.loglik()? + 2. * self.n_params() as f64 )
+    }
+
+    pub fn bic(&self) -> Result<f64, AmitaError> {
+        Ok( - 2. * self.loglik()? + (self.n_obs as f64).ln() * self.n_params() as f64 )
+    }
+
+    pub fn n_iter(&self) -> Result<u64, AmitaError> {
+        self.n_iter.ok_or(AmitaError::NotSolved)
+    }
+
+    pub fn termination(&self) -> Result<TerminationCondition, AmitaError> {
+        self.termination.ok_or(AmitaError::NotSolved)
+    }
+
+    pub(crate) fn set_censoring(&mut self, lower: &Array1<f64>, upper: &Array1<f64>) {
+        let (mut left, mut right, mut interval) = (0, 0, 0);
+        for (l, u) in lower.iter().zip(upper.iter()) {
+            match (l.is_finite(), u.is_finite()) {
+                (false, _) => left += 1,
+                (_, false) => right += 1,
+                _ if l < u => interval += 1,
+                _ => (),
+            }
+        }
+
+        self.n_left_censored = left;
+        self.n_right_censored = right;
+        self.n_interval_censored = interval;
+    }
+
+    pub(crate) fn set_fit(&mut self, state: NewtonRaphsonState) {
+        self.loglik = Some(- state.cost * self.n_obs as f64);
+        self.n_iter = Some(state.n_iter);
+        self.termination = Some(state.termination);
+        self.coef = Some(state.param);
+    }
+
+    pub(crate) fn set_vcov(&mut self, vcov: Array2<f64>) {
+        self.se = Some(vcov.diag().map(|x| x.sqrt()));
+        self.vcov = Some(vcov);
+    }
+
+    pub(crate) fn set_z_pvals(&mut self) -> Result<(), AmitaError> {
+        let z = self.coef()? / self.se()?;
+
+        let normal = Normal::new(0., 1.).unwrap();
+        let p_vals = z.map(|x| 2. * ( 1. - normal.cdf(x.abs()) ) );
+
+        self.t = Some(z);
+        self.p_vals = Some(p_vals);
+
+        Ok(())
+    }
+}
+
+impl BaseResults for CensoredResults {
+    fn coef(&self) -> Result<Array1<f64>, AmitaError> {
+        self.coef.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn se(&self) -> Result<Array1<f64>, AmitaError> {
+        self.se.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn t(&self) -> Result<Array1<f64>, AmitaError> {
+        self.t.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn p_vals(&self) -> Result<Array1<f64>, AmitaError> {
+        self.p_vals.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn vcov(&self) -> Result<Array2<f64>, AmitaError> {
+        self.vcov.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn summary(&self) -> Result<String, AmitaError> {
+        coef_table(self)
+    }
+}
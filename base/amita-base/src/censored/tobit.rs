@@ -0,0 +1,373 @@
+//! Type-I Tobit: the latent y* = x'β + σε, ε ~ N(0, 1), is observed when it
+//! falls between the censoring limits a < b, and recorded at the limit it
+//! crosses otherwise. The default a = 0, b = ∞ is the corner-solution model
+//! for expenditures and hours worked.
+
+use amita_error::AmitaError;
+use amita_utils::inference::SolverSEType;
+use amita_utils::math::numerical_jacobian;
+use amita_utils::traits::{BaseResults, BaseSolver};
+use ndarray::prelude::*;
+use statrs::distribution::{Continuous, ContinuousCDF, Normal};
+
+use crate::discrete::margins::MarginsAt;
+
+use super::interval::IntervalSolver;
+use super::results::{CensoredModel, CensoredResults};
+
+/// McDonald–Moffitt decomposition of the marginal effects of a Tobit.
+///
+/// With α = (a - x'β)/σ, γ = (b - x'β)/σ and P = Φ(γ) - Φ(α) the probability
+/// of an uncensored outcome, the effect on the unconditional mean,
+/// ∂E(y)/∂x = P β, splits into P ∂E(y | a < y < b)/∂x, the change in the
+/// mean of uncensored outcomes, and E(y | a < y < b) ∂P/∂x plus the limit
+/// terms, the change in the probability of being uncensored.
+#[derive(Debug, Clone)]
+pub struct McDonaldMoffitt {
+    at: MarginsAt,
+
+    unconditional: Array1<f64>,
+    unconditional_se: Array1<f64>,
+    conditional: Array1<f64>,
+    conditional_se: Array1<f64>,
+    probability: Array1<f64>,
+    probability_se: Array1<f64>,
+
+    fraction_conditional: f64,
+}
+
+impl McDonaldMoffitt {
+    pub fn at(&self) -> MarginsAt {
+        self.at.clone()
+    }
+
+    /// ∂E(y)/∂x
+    pub fn unconditional(&self) -> Array1<f64> {
+        self.unconditional.clone()
+    }
+
+    pub fn unconditional_se(&self) -> Array1<f64> {
+        self.unconditional_se.clone()
+    }
+
+    /// ∂E(y | a < y < b)/∂x
+    pub fn conditional(&self) -> Array1<f64> {
+        self.conditional.clone()
+    }
+
+    pub fn conditional_se(&self) -> Array1<f64> {
+        self.conditional_se.clone()
+    }
+
+    /// ∂Pr(a < y* < b)/∂x
+    pub fn probability(&self) -> Array1<f64> {
+        self.probability.clone()
+    }
+
+    pub fn probability_se(&self) -> Array1<f64> {
+        self.probability_se.clone()
+    }
+
+    /// Share of the unconditional effect due to the change in the mean of
+    /// the uncensored outcomes, the same for every regressor
+    pub fn fraction_conditional(&self) -> f64 {
+        self.fraction_conditional
+    }
+}
+
+/// Censoring probabilities and truncated moments at a given index
+struct Moments {
+    prob_uncensored: f64,
+    conditional_mean: f64,
+    unconditional_mean: f64,
+    /// ∂P/∂x'β
+    d_prob: f64,
+    /// ∂E(y | a < y < b)/∂x'β
+    d_conditional: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct TobitSolver {
+    y: Array1<f64>,
+    x: Array2<f64>,
+    lower_limit: f64,
+    upper_limit: f64,
+    se_type: SolverSEType,
+
+    max_iter: u64,
+    max_tolerance: f64,
+
+    results: CensoredResults,
+}
+
+impl TobitSolver {
+    pub fn new(
+        y: &Array1<f64>,
+        x: &Array2<f64>,
+    ) -> Result<Self, AmitaError> {
+        if y.len() != x.shape()[0] {
+            return Err(AmitaError::NotSameObservations);
+        }
+        if let Some(y_i) = y.iter().find(|y_i| !y_i.is_finite()) {
+            return Err(AmitaError::InvalidOutcome { outcome: y_i.to_string() });
+        }
+
+        Ok( Self {
+            y: y.to_owned(),
+            x: x.to_owned(),
+            lower_limit: 0.,
+            upper_limit: f64::INFINITY,
+            se_type: SolverSEType::NonRobust,
+
+            max_iter: 100,
+            max_tolerance: 1e-8,
+
+            results: CensoredResults::new(CensoredModel::Tobit, x.shape()[0], x.shape()[1]),
+        } )
+    }
+
+    /// Outcomes at or below `limit` are left-censored; -∞ for none
+    pub fn with_lower_limit(mut self, limit: f64) -> Self {
+        self.lower_limit = limit;
+        self
+    }
+
+    /// Outcomes at or above `limit` are right-censored; ∞ for none
+    pub fn with_upper_limit(mut self, limit: f64) -> Self {
+        self.upper_limit = limit;
+        self
+    }
+
+    pub fn with_se_type(mut self, se_type: SolverSEType) -> Self {
+        self.se_type = se_type;
+        self
+    }
+
+    pub fn with_max_iter(mut self, max_iter: u64) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    pub fn with_max_tolerance(mut self, max_tolerance: f64) -> Self {
+        self.max_tolerance = max_tolerance;
+        self
+    }
+}
+
+impl BaseSolver<CensoredResults> for TobitSolver {
+    fn results(&self) -> CensoredResults {
+        self.results.clone()
+    }
+
+    fn solve(self) -> Result<Self, AmitaError> {
+        self.run_solver()
+    }
+}
+
+impl TobitSolver {
+    /// Pr(a < y* < b | x)
+    pub fn prob_uncensored(&self, x: &Array2<f64>) -> Result<Array1<f64>, AmitaError> {
+        self.moments(x, &self.results.coef()?, |m| m.prob_uncensored)
+    }
+
+    /// E(y | x, a < y < b)
+    pub fn conditional_mean(&self, x: &Array2<f64>) -> Result<Array1<f64>, AmitaError> {
+        self.moments(x, &self.results.coef()?, |m| m.conditional_mean)
+    }
+
+    /// E(y | x) of the censored outcome
+    pub fn unconditional_mean(&self, x: &Array2<f64>) -> Result<Array1<f64>, AmitaError> {
+        self.moments(x, &self.results.coef()?, |m| m.unconditional_mean)
+    }
+
+    /// McDonald–Moffitt decomposition of the marginal effects, evaluated
+    /// `at` the sample, its means or representative values, with
+    /// delta-method standard errors
+    pub fn mcdonald_moffitt(&self, at: MarginsAt) -> Result<McDonaldMoffitt, AmitaError> {
+        let coef = self.results.coef()?;
+        let vcov = self.results.vcov()?;
+        let k = self.x.shape()[1];
+        let x = at.evaluation_points(&self.x)?;
+
+        let effects = |param: &Array1<f64>| -> Array1<f64> {
+            let beta = param.slice(s![..k]);
+            let prob = self.moments(&x, param, |m| m.prob_uncensored).unwrap().mean().unwrap();
+            let d_conditional = self.moments(&x, param, |m| m.d_conditional).unwrap().mean().unwrap();
+            let d_prob = self.moments(&x, param, |m| m.d_prob).unwrap().mean().unwrap();
+
+            ndarray::concatenate![Axis(0), &beta * prob, &beta * d_conditional, &beta * d_prob]
+        };
+
+        let estimates = effects(&coef);
+        let jacobian = numerical_jacobian(effects, &coef);
+        let se = jacobian.dot(&vcov).dot(&jacobian.t()).diag().map(|x| x.sqrt());
+
+        let prob = self.moments(&x, &coef, |m| m.prob_uncensored)?;
+        let d_conditional = self.moments(&x, &coef, |m| m.d_conditional)?;
+
+        Ok( McDonaldMoffitt {
+            at,
+
+            unconditional: estimates.slice(s![..k]).to_owned(),
+            unconditional_se: se.slice(s![..k]).to_owned(),
+            conditional: estimates.slice(s![k..2 * k]).to_owned(),
+            conditional_se: se.slice(s![k..2 * k]).to_owned(),
+            probability: estimates.slice(s![2 * k..]).to_owned(),
+            probability_se: se.slice(s![2 * k..]).to_owned(),
+
+            fraction_conditional: prob.dot(&d_conditional) / prob.sum(),
+        } )
+    }
+
+    fn run_solver(mut self) -> Result<Self, AmitaError> {
+        if self.lower_limit >= self.upper_limit || self.lower_limit.is_nan() || self.upper_limit.is_nan() {
+            return Err(AmitaError::InvalidOutcome {
+                outcome: format!("limits [{}, {}]", self.lower_limit, self.upper_limit),
+            });
+        }
+
+        let (lower, upper): (Vec<f64>, Vec<f64>) = self.y.iter()
+            .map(|y_i| {
+                if *y_i <= self.lower_limit {
+                    (f64::NEG_INFINITY, self.lower_limit)
+                } else if *y_i >= self.upper_limit {
+                    (self.upper_limit, f64::INFINITY)
+                } else {
+                    (*y_i, *y_i)
+                }
+            })
+            .unzip();
+        let (lower, upper) = (Array1::from(lower), Array1::from(upper));
+
+        self.results.set_censoring(&lower, &upper);
+
+        self.results = IntervalSolver::from_bounds(lower, upper, self.x.clone(), self.results.clone())
+            .with_se_type(self.se_type.clone())
+            .with_max_iter(self.max_iter)
+            .with_max_tolerance(self.max_tolerance)
+            .solve()?
+            .results();
+
+        Ok(self)
+    }
+
+    fn moments<F>(
+        &self,
+        x: &Array2<f64>,
+        param: &Array1<f64>,
+        moment: F,
+    ) -> Result<Array1<f64>, AmitaError>
+    where
+        F: Fn(&Moments) -> f64,
+    {
+        let k = self.x.shape()[1];
+        if x.shape()[1] != k {
+            return Err(AmitaError::NotSameObservations);
+        }
+
+        let sigma = param[k].exp();
+        let index = x.dot(&param.slice(s![..k]));
+
+        Ok( index.map(|xb| moment(&self.moments_at(*xb, sigma))) )
+    }
+
+    fn moments_at(&self, xb: f64, sigma: f64) -> Moments {
+        let normal = Normal::new(0., 1.).unwrap();
+        let cdf = |z: f64| if z.is_finite() { normal.cdf(z) } else if z > 0. { 1. } else { 0. };
+        let pdf = |z: f64| if z.is_finite() { normal.pdf(z) } else { 0. };
+        let tail = |z: f64| if z.is_finite() { z * normal.pdf(z) } else { 0. };
+
+        let (a, b) = (self.lower_limit, self.upper_limit);
+        let alpha = (a - xb) / sigma;
+        let gamma = (b - xb) / sigma;
+
+        let prob = cdf(gamma) - cdf(alpha);
+        let lambda = ( pdf(alpha) - pdf(gamma) ) / prob;
+        let conditional_mean = xb + sigma * lambda;
+
+        let lower_mass = if a.is_finite() { a * cdf(alpha) } else { 0. };
+        let upper_mass = if b.is_finite() { b * ( 1. - cdf(gamma) ) } else { 0. };
+
+        Moments {
+            prob_uncensored: prob,
+            conditional_mean,
+            unconditional_mean: lower_mass + upper_mass + prob * conditional_mean,
+            d_prob: ( pdf(alpha) - pdf(gamma) ) / sigma,
+            d_conditional: 1. + ( tail(alpha) - tail(gamma) ) / prob - lambda * lambda,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+
+    fn data() -> (Array1<f64>, Array2<f64>) {
+        let y = array![0., 1.3, 0., 2.8, 0.4, 0., 3.9, 2.2, 0., 5.1, 1.7, 0., 4.4, 0.9];
+        let x1 = array![-0.8, 0.4, -0.2, 1.1, 0.3, -1.4, 1.6, 0.2, 0.1, 2.1, 0.9, -0.6, 1.2, -0.1];
+        let x = ndarray::stack![Axis(1), Array1::<f64>::ones(14), x1];
+        (y, x)
+    }
+
+    #[test]
+    fn test_mcdonald_moffitt() -> Result<(), AmitaError> {
+        let (y, x) = data();
+        let solver = TobitSolver::new(&y, &x)?.solve()?;
+        let results = solver.results();
+
+        assert_eq!(results.n_left_censored(), 5);
+        assert_eq!(results.n_uncensored(), 9);
+
+        let decomposition = solver.mcdonald_moffitt(MarginsAt::Means)?;
+        let means = x.mean_axis(Axis(0)).unwrap().insert_axis(Axis(0));
+
+        // effects match differences of the predicted moments in x1
+        let h = 1e-6;
+        let mut shifted = means.clone();
+        shifted[[0, 1]] += h;
+        let unconditional = ( solver.unconditional_mean(&shifted)? - solver.unconditional_mean(&means)? ) / h;
+        let conditional = ( solver.conditional_mean(&shifted)? - solver.conditional_mean(&means)? ) / h;
+        let probability = ( solver.prob_uncensored(&shifted)? - solver.prob_uncensored(&means)? ) / h;
+        assert!( (decomposition.unconditional()[1] - unconditional[0]).abs() < 1e-4 );
+        assert!( (decomposition.conditional()[1] - conditional[0]).abs() < 1e-4 );
+        assert!( (decomposition.probability()[1] - probability[0]).abs() < 1e-4 );
+
+        // with a = 0, ∂E(y)/∂x = P ∂E(y | y > 0)/∂x + E(y | y > 0) ∂P/∂x
+        let prob = solver.prob_uncensored(&means)?[0];
+        let conditional_mean = solver.conditional_mean(&means)?[0];
+        let total = prob * decomposition.conditional()[1] + conditional_mean * decomposition.probability()[1];
+        assert!( (decomposition.unconditional()[1] - total).abs() < 1e-10 );
+        assert!( (decomposition.fraction_conditional() * decomposition.unconditional()[1]
+            - prob * decomposition.conditional()[1]).abs() < 1e-10 );
+        assert!( decomposition.unconditional_se()[1] > 0. );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_right_censoring_mirrors_left() -> Result<(), AmitaError> {
+        let (y, x) = data();
+        let left = TobitSolver::new(&y, &x)?.with_lower_limit(0.5).solve()?.results();
+        let right = TobitSolver::new(&y.map(|y| - y), &x)?
+            .with_lower_limit(f64::NEG_INFINITY)
+            .with_upper_limit(- 0.5)
+            .solve()?
+            .results();
+
+        assert!( (left.beta()? + right.beta()?).iter().all(|d| d.abs() < 1e-6) );
+        assert!( (left.sigma()? - right.sigma()?).abs() < 1e-6 );
+        assert!( (left.loglik()? - right.loglik()?).abs() < 1e-8 );
+        assert_eq!(right.n_right_censored(), 6);
+
+        let two_limit = TobitSolver::new(&y, &x)?.with_upper_limit(4.).solve()?;
+        let decomposition = two_limit.mcdonald_moffitt(MarginsAt::Average)?;
+        assert!( decomposition.unconditional()[1] < two_limit.results().beta()?[1] );
+
+        let invalid = TobitSolver::new(&y, &x)?.with_upper_limit(- 1.).solve();
+        assert!( matches!(invalid, Err(AmitaError::InvalidOutcome { .. })) );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,372 @@
+//! McFadden's conditional logit with alternative-specific regressors.
+//!
+//! Data come in long format: one row per alternative within each choice set,
+//! with `y` marking the chosen alternative. For choice set g,
+//! Pr(i chosen) = exp(x_i'β) / Σ_{l ∈ g} exp(x_l'β).
+
+use std::collections::{BTreeMap, HashSet};
+
+use amita_error::AmitaError;
+use amita_utils::hypothesis::{hausman, TestStatistic};
+use amita_utils::iterations::{NewtonRaphson, TerminationCondition};
+use amita_utils::linalg::inverse;
+use amita_utils::summary::coef_table;
+use amita_utils::traits::{BaseResults, BaseSolver};
+use ndarray::prelude::*;
+use statrs::distribution::{ContinuousCDF, Normal};
+
+#[derive(Debug, Clone)]
+pub struct ConditionalLogitResults {
+    n_obs: usize,
+    n_groups: usize,
+    n_regressors: usize,
+
+    coef: Option<Array1<f64>>,
+    se: Option<Array1<f64>>,
+    t: Option<Array1<f64>>, // z statistics
+    p_vals: Option<Array1<f64>>,
+    vcov: Option<Array2<f64>>,
+
+    loglik: Option<f64>,
+    n_iter: Option<u64>,
+    termination: Option<TerminationCondition>,
+}
+
+impl ConditionalLogitResults {
+    pub fn n_obs(&self) -> usize {
+        self.n_obs
+    }
+
+    pub fn n_groups(&self) -> usize {
+        self.n_groups
+    }
+
+    pub fn loglik(&self) -> Result<f64, AmitaError> {
+        self.loglik.ok_or(AmitaError::NotSolved)
+    }
+
+    pub fn n_iter(&self) -> Result<u64, AmitaError> {
+        self.n_iter.ok_or(AmitaError::NotSolved)
+    }
+
+    pub fn termination(&self) -> Result<TerminationCondition, AmitaError> {
+        self.termination.ok_or(AmitaError::NotSolved)
+    }
+}
+
+impl BaseResults for ConditionalLogitResults {
+    fn coef(&self) -> Result<Array1<f64>, AmitaError> {
+        self.coef.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn se(&self) -> Result<Array1<f64>, AmitaError> {
+        self.se.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn t(&self) -> Result<Array1<f64>, AmitaError> {
+        self.t.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn p_vals(&self) -> Result<Array1<f64>, AmitaError> {
+        self.p_vals.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn vcov(&self) -> Result<Array2<f64>, AmitaError> {
+        self.vcov.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn summary(&self) -> Result<String, AmitaError> {
+        coef_table(self)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConditionalLogitSolver {
+    y: Array1<f64>,
+    x: Array2<f64>,
+    groups: Array1<i32>,
+    alternatives: Option<Array1<i32>>,
+
+    group_rows: Vec<Vec<usize>>, // rows of each choice set
+    max_iter: u64,
+    max_tolerance: f64,
+
+    results: ConditionalLogitResults,
+}
+
+impl ConditionalLogitSolver {
+    /// `y` marks the chosen alternative (1) of each choice set identified by `groups`
+    pub fn new(
+        y: &Array1<i32>,
+        x: &Array2<f64>,
+        groups: &Array1<i32>,
+    ) -> Result<Self, AmitaError> {
+        let group_rows = ConditionalLogitSolver::validate_data(y, x, groups)?;
+
+        let results = ConditionalLogitResults {
+            n_obs: x.shape()[0],
+            n_groups: group_rows.len(),
+            n_regressors: x.shape()[1],
+
+            coef: None,
+            se: None,
+            t: None,
+            p_vals: None,
+            vcov: None,
+
+            loglik: None,
+            n_iter: None,
+            termination: None,
+        };
+
+        Ok( Self {
+            y: y.map(|x| *x as f64),
+            x: x.to_owned(),
+            groups: groups.to_owned(),
+            alternatives: None,
+
+            group_rows,
+            max_iter: 100,
+            max_tolerance: 1e-8,
+
+            results,
+        } )
+    }
+
+    /// Identifiers of the alternative on each row, required by the IIA test
+    pub fn with_alternatives(mut self, alternatives: &Array1<i32>) -> Result<Self, AmitaError> {
+        if alternatives.len() != self.results.n_obs {
+            return Err(AmitaError::NotSameObservations);
+        }
+        self.alternatives = Some(alternatives.to_owned());
+        Ok(self)
+    }
+
+    pub fn with_max_iter(mut self, max_iter: u64) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    pub fn with_max_tolerance(mut self, max_tolerance: f64) -> Self {
+        self.max_tolerance = max_tolerance;
+        self
+    }
+
+    fn validate_data(
+        y: &Array1<i32>,
+        x: &Array2<f64>,
+        groups: &Array1<i32>,
+    ) -> Result<Vec<Vec<usize>>, AmitaError> {
+        if x.shape()[0] != y.shape()[0] || groups.shape()[0] != y.shape()[0] {
+            return Err(AmitaError::NotSameObservations);
+        }
+
+        let y_allowed = HashSet::from([0_i32, 1_i32]);
+        if !y.iter().all(|x| y_allowed.contains(x)) {
+            return Err(AmitaError::NonBinary { matrix_name: "`y` of conditional logit model".to_string() });
+        }
+
+        let mut group_rows = BTreeMap::new();
+        for (i, g) in groups.iter().enumerate() {
+            group_rows.entry(*g).or_insert(vec![]).push(i);
+        }
+
+        for (g, rows) in group_rows.iter() {
+            let n_chosen = rows.iter().filter(|i| y[**i] == 1).count();
+            if n_chosen != 1 {
+                return Err(AmitaError::InvalidChoiceSet { group: g.to_string() });
+            }
+        }
+
+        Ok( group_rows.into_values().collect() )
+    }
+}
+
+impl BaseSolver<ConditionalLogitResults> for ConditionalLogitSolver {
+    fn results(&self) -> ConditionalLogitResults {
+        self.results.clone()
+    }
+
+    fn solve(self) -> Result<Self, AmitaError> {
+        self
+        .run_solver()?
+        .solve_se()?
+        .solve_z_pvals()
+    }
+}
+
+impl ConditionalLogitSolver {
+    /// Hausman–McFadden test of the independence of irrelevant alternatives,
+    /// dropping alternative `omit` from every choice set as well as the choice
+    /// sets in which it was chosen. Regressors that vanish in the restricted
+    /// sample, such as the constant of the omitted alternative, are excluded.
+    pub fn hausman_mcfadden(&self, omit: i32) -> Result<TestStatistic, AmitaError> {
+        let alternatives = self.alternatives.clone().ok_or(AmitaError::InvalidOutcome {
+            outcome: "alternatives are required, see `with_alternatives`".to_string()
+        })?;
+        if !alternatives.iter().any(|a| *a == omit) {
+            return Err(AmitaError::InvalidOutcome { outcome: omit.to_string() });
+        }
+
+        let coef_full = self.results.coef()?;
+        let vcov_full = self.results.vcov()?;
+
+        let kept = self.group_rows.iter()
+            .filter(|rows| rows.iter().all(|i| !(alternatives[*i] == omit && self.y[*i] > 0.5)))
+            .flat_map(|rows| rows.iter().copied().filter(|i| alternatives[*i] != omit))
+            .collect::<Vec<_>>();
+
+        let x = self.x.select(Axis(0), &kept);
+        let columns = (0..self.results.n_regressors)
+            .filter(|j| x.column(*j).iter().any(|x| *x != 0.))
+            .collect::<Vec<_>>();
+
+        let restricted = ConditionalLogitSolver::new(
+            &self.y.select(Axis(0), &kept).map(|y| *y as i32),
+            &x.select(Axis(1), &columns),
+            &self.groups.select(Axis(0), &kept),
+        )?
+            .with_max_iter(self.max_iter)
+            .with_max_tolerance(self.max_tolerance)
+            .solve()?
+            .results();
+
+        hausman(
+            "Hausman-McFadden IIA",
+            &restricted.coef()?,
+            &restricted.vcov()?,
+            &coef_full.select(Axis(0), &columns),
+            &vcov_full.select(Axis(0), &columns).select(Axis(1), &columns),
+        )
+    }
+}
+
+impl ConditionalLogitSolver {
+    fn run_solver(mut self) -> Result<Self, AmitaError> {
+        let init_param = Array1::zeros(self.results.n_regressors);
+
+        let state = NewtonRaphson::new(self.max_iter, self.max_tolerance)
+            .minimize(init_param, |param| Ok( self.mean_neg_loglik(param) ))?;
+
+        state.termination.check(state.n_iter)?;
+
+        self.results.loglik = Some(- state.cost * self.results.n_groups as f64);
+        self.results.n_iter = Some(state.n_iter);
+        self.results.termination = Some(state.termination);
+        self.results.coef = Some(state.param);
+
+        Ok(self)
+    }
+
+    fn solve_se(mut self) -> Result<Self, AmitaError> {
+        let coef = self.results.coef.clone().ok_or(AmitaError::NotSolved)?;
+        let (_, _, hessian) = self.mean_neg_loglik(&coef);
+
+        let vcov = inverse(&hessian, "Hessian of the conditional logit log-likelihood")?
+            / self.results.n_groups as f64;
+        let se = vcov.diag().map(|x| x.sqrt());
+
+        self.results.se = Some(se);
+        self.results.vcov = Some(vcov);
+
+        Ok(self)
+    }
+
+    fn solve_z_pvals(mut self) -> Result<Self, AmitaError> {
+        let coef = self.results.coef.clone().ok_or(AmitaError::NotSolved)?;
+        let se = self.results.se.clone().ok_or(AmitaError::NotSolved)?;
+
+        let z = coef / se;
+
+        let normal = Normal::new(0., 1.).unwrap();
+        let p_vals = z.map(|x| 2. * ( 1. - normal.cdf(x.abs()) ) );
+
+        self.results.t = Some(z);
+        self.results.p_vals = Some(p_vals);
+
+        Ok(self)
+    }
+
+    /// Mean negative log-likelihood over choice sets, with gradient
+    /// -Σ_g (x_chosen - x̄_g) / G and Hessian Σ_g Σ_i p_i (x_i - x̄_g)(x_i - x̄_g)' / G,
+    /// where x̄_g = Σ_i p_i x_i.
+    fn mean_neg_loglik(&self, param: &Array1<f64>) -> (f64, Array1<f64>, Array2<f64>) {
+        let k = self.results.n_regressors;
+        let n_groups = self.results.n_groups as f64;
+        let index = self.x.dot(param);
+
+        let mut loglik = 0.;
+        let mut gradient = Array1::zeros(k);
+        let mut hessian = Array2::zeros((k, k));
+
+        for rows in self.group_rows.iter() {
+            let max = rows.iter().fold(f64::NEG_INFINITY, |acc, i| acc.max(index[*i]));
+            let exp = rows.iter().map(|i| (index[*i] - max).exp()).collect::<Array1<f64>>();
+            let denom = exp.sum();
+            let p = exp / denom;
+
+            let x_g = self.x.select(Axis(0), rows);
+            let y_g = self.y.select(Axis(0), rows);
+            let x_bar = x_g.t().dot(&p);
+            let x_centered = &x_g - &x_bar.view().insert_axis(Axis(0));
+
+            loglik += y_g.dot(&index.select(Axis(0), rows)) - max - denom.ln();
+            gradient -= &x_centered.t().dot(&y_g);
+
+            let xp = &x_centered * &p.view().insert_axis(Axis(1));
+            hessian += &xp.t().dot(&x_centered);
+        }
+
+        (- loglik / n_groups, gradient / n_groups, hessian / n_groups)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+    use crate::discrete::logit::{LogitMethod, LogitSolver};
+
+    #[test]
+    fn test_two_alternatives_equal_logit() -> Result<(), AmitaError> {
+        // with two alternatives, clogit is a logit on the difference of regressors
+        let x_a = array![[0.5, 1.0], [1.2, -0.3], [-0.7, 0.8], [2.0, 0.1], [0.3, -1.2], [-1.1, 0.4], [0.9, 0.9], [0.0, -0.5]];
+        let x_b = array![[1.5, 0.2], [0.1, 0.4], [0.6, -0.2], [-0.5, 1.1], [0.8, 0.3], [0.2, -0.9], [-0.4, 0.6], [1.3, 0.7]];
+        let chose_a = array![1, 1, 0, 1, 0, 0, 1, 1];
+
+        let mut x = Array2::zeros((16, 2));
+        let mut y = Array1::zeros(16);
+        let mut groups = Array1::zeros(16);
+        for g in 0..8 {
+            x.row_mut(2 * g).assign(&x_a.row(g));
+            x.row_mut(2 * g + 1).assign(&x_b.row(g));
+            y[2 * g] = chose_a[g];
+            y[2 * g + 1] = 1 - chose_a[g];
+            groups[2 * g] = g as i32;
+            groups[2 * g + 1] = g as i32;
+        }
+
+        let clogit = ConditionalLogitSolver::new(&y, &x, &groups)?.solve()?.results();
+        let logit = LogitSolver::new(&chose_a, &(&x_a - &x_b))?
+            .with_method(LogitMethod::Newton)
+            .with_max_tolerance(1e-10)
+            .solve()?
+            .results();
+
+        assert!( (clogit.coef()? - logit.coef()?).iter().all(|d| d.abs() < 1e-6) );
+        assert!( (clogit.se()? - logit.se()?).iter().all(|d| d.abs() < 1e-6) );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_choice_set() {
+        let x = array![[1.], [2.], [3.], [4.]];
+        let y = array![1, 1, 0, 1];
+        let groups = array![0, 0, 1, 1];
+
+        let res = ConditionalLogitSolver::new(&y, &x, &groups);
+        assert!(matches!(res, Err(AmitaError::InvalidChoiceSet { group }) if group == "0"));
+    }
+}
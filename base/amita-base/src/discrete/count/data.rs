@@ -0,0 +1,55 @@
+//! Outcome, regressors and offset shared by the count-data solvers
+
+use amita_error::AmitaError;
+use amita_utils::linalg::solve;
+use ndarray::prelude::*;
+
+#[derive(Debug, Clone)]
+pub(crate) struct CountData {
+    pub(crate) y: Array1<f64>,
+    pub(crate) x: Array2<f64>,
+    pub(crate) offset: Array1<f64>,
+}
+
+impl CountData {
+    pub(crate) fn new(
+        y: &Array1<i32>,
+        x: &Array2<f64>,
+    ) -> Result<Self, AmitaError> {
+        if x.shape()[0] != y.shape()[0] {
+            return Err(AmitaError::NotSameObservations);
+        }
+        if let Some(y_i) = y.iter().find(|y_i| **y_i < 0) {
+            return Err(AmitaError::InvalidOutcome { outcome: y_i.to_string() });
+        }
+
+        Ok( Self {
+            y: y.map(|y_i| *y_i as f64),
+            x: x.to_owned(),
+            offset: Array1::zeros(y.len()),
+        } )
+    }
+
+    /// Adds a variable with coefficient constrained to 1 to the linear index
+    pub(crate) fn add_offset(&mut self, offset: &Array1<f64>) -> Result<(), AmitaError> {
+        if offset.len() != self.y.len() {
+            return Err(AmitaError::NotSameObservations);
+        }
+        self.offset += offset;
+        Ok(())
+    }
+
+    /// Exposure t enters as the offset ln t, so that μ is a rate per unit of t
+    pub(crate) fn add_exposure(&mut self, exposure: &Array1<f64>) -> Result<(), AmitaError> {
+        if exposure.iter().any(|t| *t <= 0.) {
+            return Err(AmitaError::NonPositive { matrix_name: "exposure".to_string() });
+        }
+        self.add_offset(&exposure.map(|t| t.ln()))
+    }
+
+    /// Starting values of β from least squares of ln(y + 0.5) - offset on x
+    pub(crate) fn init_coef(&self) -> Result<Array1<f64>, AmitaError> {
+        let z = self.y.map(|y| (y + 0.5).ln()) - &self.offset;
+        solve(&self.x.t().dot(&self.x), &self.x.t().dot(&z), "X'X")
+    }
+}
@@ -0,0 +1,147 @@
+//! Count distributions of y given the conditional mean μ = exp(x'β + offset).
+//!
+//! The negative binomials carry the overdispersion α as ln α, which keeps
+//! the parameter space unrestricted.
+
+use ndarray::prelude::*;
+use statrs::function::gamma::{digamma, ln_gamma};
+
+/// Counts up to which the gamma-function ratios are evaluated as finite sums
+const FINITE_SUM_MAX: f64 = 1000.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CountDistribution {
+    /// Var(y | x) = μ
+    Poisson,
+    /// Var(y | x) = μ (1 + α)
+    NB1,
+    /// Var(y | x) = μ + α μ²
+    NB2,
+}
+
+impl CountDistribution {
+    /// Number of ancillary parameters: none for Poisson, ln α otherwise
+    pub fn n_ancillary(&self) -> usize {
+        match self {
+            CountDistribution::Poisson => 0,
+            CountDistribution::NB1 | CountDistribution::NB2 => 1,
+        }
+    }
+
+    /// ln f(y) at the linear index η and ln α, with its derivatives with
+    /// respect to η and ln α
+    pub(crate) fn log_density(&self, y: f64, eta: f64, ln_alpha: f64) -> (f64, f64, f64) {
+        let mu = eta.exp();
+
+        match self {
+            CountDistribution::Poisson => {
+                let loglik = y * eta - mu - ln_gamma(y + 1.);
+                (loglik, y - mu, 0.)
+            },
+            CountDistribution::NB2 => {
+                let alpha = ln_alpha.exp();
+                let a = 1. / alpha;
+                let log_1p_alpha_mu = (alpha * mu).ln_1p();
+
+                // lnΓ(y + a) - lnΓ(a) + y ln α and ψ(y + a) - ψ(a), as finite
+                // sums for moderate y, which stay accurate as α → 0
+                let (log_ratio, digamma_diff) = if y <= FINITE_SUM_MAX {
+                    (0..y as usize).fold((0., 0.), |(l, d), j| {
+                        let j = j as f64;
+                        (l + (alpha * j).ln_1p(), d + 1. / (a + j))
+                    })
+                } else {
+                    (ln_gamma(y + a) - ln_gamma(a) + y * ln_alpha, digamma(y + a) - digamma(a))
+                };
+
+                let loglik = log_ratio - ln_gamma(y + 1.) - (a + y) * log_1p_alpha_mu + y * eta;
+                let d_eta = (y - mu) / (1. + alpha * mu);
+                let d_ln_alpha = - a * ( digamma_diff - log_1p_alpha_mu ) + d_eta;
+                (loglik, d_eta, d_ln_alpha)
+            },
+            CountDistribution::NB1 => {
+                let alpha = ln_alpha.exp();
+                let r = mu / alpha;
+                // r ln(1 + α), which tends to μ as α → 0
+                let r_log_1p_alpha = mu * alpha.ln_1p() / alpha;
+
+                // lnΓ(y + r) - lnΓ(r) + y ln α and r (ψ(y + r) - ψ(r))
+                let (log_ratio, r_digamma_diff) = if y <= FINITE_SUM_MAX {
+                    (0..y as usize).fold((0., 0.), |(l, d), j| {
+                        let j = j as f64;
+                        (l + (mu + alpha * j).ln(), d + mu / (mu + alpha * j))
+                    })
+                } else {
+                    (ln_gamma(y + r) - ln_gamma(r) + y * ln_alpha, r * ( digamma(y + r) - digamma(r) ))
+                };
+
+                let loglik = log_ratio - ln_gamma(y + 1.) - r_log_1p_alpha - y * alpha.ln_1p();
+                let d_eta = r_digamma_diff - r_log_1p_alpha;
+                let d_ln_alpha = - d_eta + (y - mu) / (1. + alpha);
+                (loglik, d_eta, d_ln_alpha)
+            },
+        }
+    }
+
+    /// Per-observation log-densities and scores of the count part, whose
+    /// parameter stacks β and the ancillary ln α
+    pub(crate) fn loglik_obs(
+        &self,
+        y: &Array1<f64>,
+        x: &Array2<f64>,
+        offset: &Array1<f64>,
+        theta: ArrayView1<f64>,
+    ) -> (Array1<f64>, Array2<f64>) {
+        let k = x.shape()[1];
+        let eta = x.dot(&theta.slice(s![..k])) + offset;
+        let ln_alpha = if self.n_ancillary() > 0 { theta[k] } else { 0. };
+
+        let mut loglik = Array1::zeros(y.len());
+        let mut scores = Array2::zeros((y.len(), k + self.n_ancillary()));
+        for i in 0..y.len() {
+            let (l, d_eta, d_ln_alpha) = self.log_density(y[i], eta[i], ln_alpha);
+            loglik[i] = l;
+            scores.slice_mut(s![i, ..k]).assign(&x.row(i).map(|x| x * d_eta));
+            if self.n_ancillary() > 0 {
+                scores[[i, k]] = d_ln_alpha;
+            }
+        }
+
+        (loglik, scores)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scores() {
+        let distributions = [CountDistribution::Poisson, CountDistribution::NB1, CountDistribution::NB2];
+        let h = 1e-6;
+
+        for distribution in distributions {
+            for (y, eta, ln_alpha) in [(0., 0.3, -0.5), (3., 1.1, 0.2), (12., 2.0, -1.5), (1500., 7.2, -0.8)] {
+                let (_, d_eta, d_ln_alpha) = distribution.log_density(y, eta, ln_alpha);
+
+                let numerical_eta = ( distribution.log_density(y, eta + h, ln_alpha).0
+                    - distribution.log_density(y, eta - h, ln_alpha).0 ) / (2. * h);
+                let numerical_ln_alpha = ( distribution.log_density(y, eta, ln_alpha + h).0
+                    - distribution.log_density(y, eta, ln_alpha - h).0 ) / (2. * h);
+
+                assert!( (d_eta - numerical_eta).abs() < 1e-6 * numerical_eta.abs().max(1.) );
+                assert!( (d_ln_alpha - numerical_ln_alpha).abs() < 1e-6 * numerical_ln_alpha.abs().max(1.) );
+            }
+        }
+    }
+
+    #[test]
+    fn test_densities_sum_to_one() {
+        for distribution in [CountDistribution::Poisson, CountDistribution::NB1, CountDistribution::NB2] {
+            let total = (0..200)
+                .map(|y| distribution.log_density(y as f64, 1.2, -0.3).0.exp())
+                .sum::<f64>();
+            assert!( (total - 1.).abs() < 1e-10 );
+        }
+    }
+}
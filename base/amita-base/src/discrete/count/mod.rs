@@ -0,0 +1,8 @@
+mod data;
+pub mod distribution;
+pub mod hurdle;
+pub mod negbin;
+pub mod poisson;
+pub mod ppml;
+pub mod results;
+pub mod zero_inflated;
@@ -0,0 +1,216 @@
+//! Negative binomial regression with mean μ = exp(x'β + offset), in the NB1
+//! (constant dispersion) and NB2 (mean dispersion) parametrizations
+
+use amita_error::AmitaError;
+use amita_utils::hypothesis::TestStatistic;
+use amita_utils::inference::SolverSEType;
+use amita_utils::likelihood::{maximize, ml_vcov};
+use amita_utils::traits::{BaseResults, BaseSolver};
+use ndarray::prelude::*;
+
+use super::data::CountData;
+use super::distribution::CountDistribution;
+use super::poisson::PoissonSolver;
+use super::results::{CountModel, CountResults};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NegBinType {
+    /// Var(y | x) = μ (1 + α)
+    NB1,
+    /// Var(y | x) = μ + α μ²
+    NB2,
+}
+
+impl NegBinType {
+    pub fn distribution(&self) -> CountDistribution {
+        match self {
+            NegBinType::NB1 => CountDistribution::NB1,
+            NegBinType::NB2 => CountDistribution::NB2,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NegativeBinomialSolver {
+    data: CountData,
+    distribution: CountDistribution,
+    se_type: SolverSEType,
+
+    max_iter: u64,
+    max_tolerance: f64,
+
+    results: CountResults,
+}
+
+impl NegativeBinomialSolver {
+    pub fn new(
+        y: &Array1<i32>,
+        x: &Array2<f64>,
+        dispersion: NegBinType,
+    ) -> Result<Self, AmitaError> {
+        let distribution = dispersion.distribution();
+        let results = CountResults::new(
+            CountModel::Count(distribution),
+            x.shape()[0],
+            x.shape()[1],
+            0,
+        );
+
+        Ok( Self {
+            data: CountData::new(y, x)?,
+            distribution,
+            se_type: SolverSEType::NonRobust,
+
+            max_iter: 100,
+            max_tolerance: 1e-8,
+
+            results,
+        } )
+    }
+
+    pub fn with_offset(mut self, offset: &Array1<f64>) -> Result<Self, AmitaError> {
+        self.data.add_offset(offset)?;
+        Ok(self)
+    }
+
+    pub fn with_exposure(mut self, exposure: &Array1<f64>) -> Result<Self, AmitaError> {
+        self.data.add_exposure(exposure)?;
+        Ok(self)
+    }
+
+    pub fn with_se_type(mut self, se_type: SolverSEType) -> Self {
+        self.se_type = se_type;
+        self
+    }
+
+    pub fn with_max_iter(mut self, max_iter: u64) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    pub fn with_max_tolerance(mut self, max_tolerance: f64) -> Self {
+        self.max_tolerance = max_tolerance;
+        self
+    }
+}
+
+impl BaseSolver<CountResults> for NegativeBinomialSolver {
+    fn results(&self) -> CountResults {
+        self.results.clone()
+    }
+
+    fn solve(self) -> Result<Self, AmitaError> {
+        self
+        .run_solver()?
+        .solve_se()?
+        .solve_z_pvals()
+    }
+}
+
+impl NegativeBinomialSolver {
+    /// Likelihood-ratio test of H0: α = 0 against the Poisson model. α lies
+    /// on the boundary under H0, so the statistic is referred to χ̄²(01).
+    pub fn lr_test_alpha(&self) -> Result<TestStatistic, AmitaError> {
+        let loglik = self.results.loglik()?;
+        let poisson = self.poisson()?.results();
+
+        let statistic = 2. * (loglik - poisson.loglik()?);
+        Ok( TestStatistic::chi_bar_squared_01("LR test of alpha = 0", statistic.max(0.)) )
+    }
+
+    fn poisson(&self) -> Result<PoissonSolver, AmitaError> {
+        PoissonSolver::from_data(self.data.clone())
+            .with_max_iter(self.max_iter)
+            .with_max_tolerance(self.max_tolerance)
+            .solve()
+    }
+
+    /// Starts from the Poisson estimates and α = 1
+    fn run_solver(mut self) -> Result<Self, AmitaError> {
+        let poisson = self.poisson()?.results();
+        let init = ndarray::concatenate![Axis(0), poisson.count_coef()?, array![0.]];
+
+        let state = maximize(init, self.max_iter, self.max_tolerance, |param| self.loglik_obs(param))?;
+
+        self.results.set_fit(state);
+        Ok(self)
+    }
+
+    fn solve_se(mut self) -> Result<Self, AmitaError> {
+        let coef = self.results.coef()?;
+        let vcov = ml_vcov(&coef, &self.se_type, |param| self.loglik_obs(param))?;
+
+        self.results.set_vcov(vcov);
+        Ok(self)
+    }
+
+    fn solve_z_pvals(mut self) -> Result<Self, AmitaError> {
+        self.results.set_z_pvals()?;
+        Ok(self)
+    }
+
+    fn loglik_obs(&self, param: &Array1<f64>) -> (Array1<f64>, Array2<f64>) {
+        self.distribution.loglik_obs(&self.data.y, &self.data.x, &self.data.offset, param.view())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data() -> (Array1<i32>, Array2<f64>) {
+        let y = array![0, 0, 9, 1, 0, 14, 2, 0, 7, 0, 11, 1, 3, 0, 5, 22];
+        let x1 = array![0.1, 0.4, 0.2, 0.3, -0.2, 1.5, 0.6, 0.0, -0.4, 1.1, 0.3, 0.2, 0.8, -0.1, 0.9, 0.5];
+        let x = ndarray::stack![Axis(1), Array1::<f64>::ones(16), x1];
+        (y, x)
+    }
+
+    #[test]
+    fn test_intercept_only_mean() -> Result<(), AmitaError> {
+        let (y, _) = data();
+        let x = Array2::<f64>::ones((16, 1));
+        let mean = y.iter().sum::<i32>() as f64 / 16.;
+
+        // the NB2 score of β equates the fitted mean with the sample mean
+        let results = NegativeBinomialSolver::new(&y, &x, NegBinType::NB2)?.solve()?.results();
+        assert!( (results.irr()?[0] - mean).abs() < 1e-6 );
+        assert!( results.alpha()?.unwrap() > 0. );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lr_test_alpha() -> Result<(), AmitaError> {
+        let (y, x) = data();
+
+        for dispersion in [NegBinType::NB1, NegBinType::NB2] {
+            let solver = NegativeBinomialSolver::new(&y, &x, dispersion)?.solve()?;
+            let results = solver.results();
+            let test = solver.lr_test_alpha()?;
+
+            assert_eq!(results.coef()?.len(), 3);
+            assert!( results.alpha_se()?.unwrap() > 0. );
+            assert!( test.statistic() > 0. );
+            assert!( test.p_val() < 0.05 );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_underdispersed_boundary() -> Result<(), AmitaError> {
+        let y = array![0, 0, 9, 1, 0, 14, 2, 0, 7, 0, 11, 1, 3, 0, 5, 22];
+        let x1 = array![0.1, 0.4, 1.2, 0.3, -0.2, 1.5, 0.6, 0.0, 1.1, -0.5, 1.3, 0.2, 0.8, -0.1, 0.9, 1.7];
+        let x = ndarray::stack![Axis(1), Array1::<f64>::ones(16), x1];
+
+        // α̂ heads to the Poisson boundary, where the finite-sum densities keep
+        // the iterations well defined
+        for dispersion in [NegBinType::NB1, NegBinType::NB2] {
+            let solver = NegativeBinomialSolver::new(&y, &x, dispersion)?.solve()?;
+            assert!( solver.results().alpha()?.unwrap() < 1e-6 );
+            assert!( solver.lr_test_alpha()?.p_val() > 0.99 );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,193 @@
+//! Poisson regression, E(y | x) = exp(x'β + offset)
+
+use amita_error::AmitaError;
+use amita_utils::hypothesis::TestStatistic;
+use amita_utils::inference::SolverSEType;
+use amita_utils::likelihood::{maximize, ml_vcov};
+use amita_utils::traits::{BaseResults, BaseSolver};
+use ndarray::prelude::*;
+
+use super::data::CountData;
+use super::distribution::CountDistribution;
+use super::results::{CountModel, CountResults};
+
+#[derive(Debug, Clone)]
+pub struct PoissonSolver {
+    data: CountData,
+    se_type: SolverSEType,
+
+    max_iter: u64,
+    max_tolerance: f64,
+
+    results: CountResults,
+}
+
+impl PoissonSolver {
+    pub fn new(
+        y: &Array1<i32>,
+        x: &Array2<f64>,
+    ) -> Result<Self, AmitaError> {
+        Ok( Self::from_data(CountData::new(y, x)?) )
+    }
+
+    pub(crate) fn from_data(data: CountData) -> Self {
+        let results = CountResults::new(
+            CountModel::Count(CountDistribution::Poisson),
+            data.x.shape()[0],
+            data.x.shape()[1],
+            0,
+        );
+
+        Self {
+            data,
+            se_type: SolverSEType::NonRobust,
+
+            max_iter: 100,
+            max_tolerance: 1e-8,
+
+            results,
+        }
+    }
+
+    pub fn with_offset(mut self, offset: &Array1<f64>) -> Result<Self, AmitaError> {
+        self.data.add_offset(offset)?;
+        Ok(self)
+    }
+
+    pub fn with_exposure(mut self, exposure: &Array1<f64>) -> Result<Self, AmitaError> {
+        self.data.add_exposure(exposure)?;
+        Ok(self)
+    }
+
+    pub fn with_se_type(mut self, se_type: SolverSEType) -> Self {
+        self.se_type = se_type;
+        self
+    }
+
+    pub fn with_max_iter(mut self, max_iter: u64) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    pub fn with_max_tolerance(mut self, max_tolerance: f64) -> Self {
+        self.max_tolerance = max_tolerance;
+        self
+    }
+}
+
+impl BaseSolver<CountResults> for PoissonSolver {
+    fn results(&self) -> CountResults {
+        self.results.clone()
+    }
+
+    fn solve(self) -> Result<Self, AmitaError> {
+        self
+        .run_solver()?
+        .solve_se()?
+        .solve_z_pvals()
+    }
+}
+
+impl PoissonSolver {
+    /// Fitted means exp(x'β + offset)
+    pub fn fitted(&self) -> Result<Array1<f64>, AmitaError> {
+        let coef = self.results.coef()?;
+        Ok( (self.data.x.dot(&coef) + &self.data.offset).map(|x| x.exp()) )
+    }
+
+    /// Cameron–Trivedi regression-based test of equidispersion against
+    /// Var(y | x) = μ + α μ², the t test of α in the auxiliary regression
+    /// ((y - μ)² - y) / μ = α μ + e, without a constant
+    pub fn overdispersion_test(&self) -> Result<TestStatistic, AmitaError> {
+        let mu = self.fitted()?;
+        let y = &self.data.y;
+        let n = y.len() as f64;
+
+        let z = ( (y - &mu).map(|e| e * e) - y ) / &mu;
+        let mu_sq = mu.dot(&mu);
+        let alpha = z.dot(&mu) / mu_sq;
+
+        let resid = z - &mu * alpha;
+        let s2 = resid.dot(&resid) / (n - 1.);
+        let se = (s2 / mu_sq).sqrt();
+
+        Ok( TestStatistic::students_t("Cameron-Trivedi overdispersion", alpha / se, n - 1.) )
+    }
+
+    fn run_solver(mut self) -> Result<Self, AmitaError> {
+        let init = self.data.init_coef()?;
+        let state = maximize(init, self.max_iter, self.max_tolerance, |param| self.loglik_obs(param))?;
+
+        self.results.set_fit(state);
+        Ok(self)
+    }
+
+    fn solve_se(mut self) -> Result<Self, AmitaError> {
+        let coef = self.results.coef()?;
+        let vcov = ml_vcov(&coef, &self.se_type, |param| self.loglik_obs(param))?;
+
+        self.results.set_vcov(vcov);
+        Ok(self)
+    }
+
+    fn solve_z_pvals(mut self) -> Result<Self, AmitaError> {
+        self.results.set_z_pvals()?;
+        Ok(self)
+    }
+
+    fn loglik_obs(&self, param: &Array1<f64>) -> (Array1<f64>, Array2<f64>) {
+        CountDistribution::Poisson.loglik_obs(&self.data.y, &self.data.x, &self.data.offset, param.view())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+
+    #[test]
+    fn test_intercept_and_exposure() -> Result<(), AmitaError> {
+        let y = array![0, 2, 1, 4, 3, 0, 1, 5];
+        let x = Array2::<f64>::ones((8, 1));
+        let exposure = array![1., 2., 1., 3., 2., 1., 1., 4.];
+
+        let results = PoissonSolver::new(&y, &x)?.solve()?.results();
+        assert!( (results.irr()?[0] - 2.).abs() < 1e-8 );
+
+        // the rate is total counts over total exposure
+        let results = PoissonSolver::new(&y, &x)?
+            .with_exposure(&exposure)?
+            .solve()?
+            .results();
+        assert!( (results.irr()?[0] - 16. / 15.).abs() < 1e-8 );
+        // se(ln rate) = 1 / sqrt(Σ y)
+        assert!( (results.se()?[0] - 0.25).abs() < 1e-6 );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_robust_se_and_overdispersion() -> Result<(), AmitaError> {
+        let y = array![0, 0, 9, 1, 0, 14, 2, 0, 7, 0, 11, 1];
+        let x1 = array![0.1, 0.4, 0.2, 0.3, -0.2, 1.5, 0.6, 0.0, -0.4, 1.1, 0.3, 0.2];
+        let x = ndarray::stack![Axis(1), Array1::<f64>::ones(12), x1];
+
+        let solver = PoissonSolver::new(&y, &x)?.solve()?;
+        let robust = PoissonSolver::new(&y, &x)?
+            .with_se_type(SolverSEType::Robust)
+            .solve()?
+            .results();
+
+        assert_eq!(solver.results().coef()?, robust.coef()?);
+        assert!( robust.se()?[1] > solver.results().se()?[1] );
+
+        let test = solver.overdispersion_test()?;
+        assert!( test.statistic() > 0. );
+
+        let hc3 = PoissonSolver::new(&y, &x)?.with_se_type(SolverSEType::HC3).solve();
+        assert!( matches!(hc3, Err(AmitaError::UnsupportedSEType { .. })) );
+
+        Ok(())
+    }
+}
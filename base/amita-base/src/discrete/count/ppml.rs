@@ -0,0 +1,600 @@
+//! Poisson pseudo-maximum likelihood with high-dimensional fixed effects.
+//!
+//! E(y | x) = exp(x'β + α_1 + ... + α_D + offset) for outcomes y ≥ 0 that
+//! need not be integer, such as trade flows. The fixed effects are absorbed
+//! inside IRLS by alternating projections, following ppmlhdfe (Correia,
+//! Guimarães and Zylkin, 2020). Observations whose mean is zero at the
+//! pseudo-MLE carry no information on β and are dropped beforehand: those
+//! in fixed-effect groups with only zero outcomes, and those separated by
+//! the regressors, found by the iterated rectifier (ReLU) method. Singleton
+//! groups are dropped as well, since they would understate the SEs.
+
+use amita_error::AmitaError;
+use amita_utils::fixed_effects::FixedEffects;
+use amita_utils::inference::{sandwich, SolverSEType};
+use amita_utils::iterations::TerminationCondition;
+use amita_utils::linalg::{inverse, pivoted_qr_with_rhs, solve, solve_upper_triangular};
+use amita_utils::summary::coef_table;
+use amita_utils::traits::{BaseResults, BaseSolver};
+use ndarray::prelude::*;
+use statrs::distribution::{ContinuousCDF, Normal};
+use statrs::function::gamma::ln_gamma;
+
+/// Weight of the positive outcomes, which must be fitted exactly at zero by
+/// the separating linear combination of the ReLU method
+const RELU_POSITIVE_WEIGHT: f64 = 1e8;
+const RELU_TOLERANCE: f64 = 1e-6;
+const RELU_MAX_ITER: usize = 1000;
+/// Columns whose norm orthogonal to the previous ones falls below this share
+/// of their own are dropped from the least squares steps of the ReLU method
+const RELU_COLLINEARITY_TOLERANCE: f64 = 1e-7;
+
+#[derive(Debug, Clone)]
+pub struct PPMLResults {
+    n_obs: usize,
+    dropped: Vec<usize>,
+    omitted: Vec<usize>,
+    separation_inconclusive: bool,
+
+    coef: Option<Array1<f64>>, // NaN for omitted regressors
+    se: Option<Array1<f64>>,
+    t: Option<Array1<f64>>, // z statistics
+    p_vals: Option<Array1<f64>>,
+    vcov: Option<Array2<f64>>,
+
+    deviance: Option<f64>,
+    pseudo_loglik: Option<f64>,
+    n_iter: Option<u64>,
+    termination: Option<TerminationCondition>,
+}
+
+impl PPMLResults {
+    /// Observations used in estimation
+    pub fn n_obs(&self) -> usize {
+        self.n_obs
+    }
+
+    /// Indices of the observations dropped as singletons or as separated
+    pub fn dropped(&self) -> Vec<usize> {
+        self.dropped.clone()
+    }
+
+    /// Indices of the regressors omitted as collinear with the fixed effects
+    /// on the estimation sample
+    pub fn omitted(&self) -> Vec<usize> {
+        self.omitted.clone()
+    }
+
+    /// Whether the ReLU check for separation by the regressors ended without
+    /// an answer, its least squares step failing or its iterations running
+    /// out. Separated observations may then remain in the sample, and the
+    /// coefficients that separate them are not identified.
+    pub fn separation_inconclusive(&self) -> bool {
+        self.separation_inconclusive
+    }
+
+    pub fn deviance(&self) -> Result<f64, AmitaError> {
+        self.deviance.ok_or(AmitaError::NotSolved)
+    }
+
+    pub fn pseudo_loglik(&self) -> Result<f64, AmitaError> {
+        self.pseudo_loglik.ok_or(AmitaError::NotSolved)
+    }
+
+    pub fn n_iter(&self) -> Result<u64, AmitaError> {
+        self.n_iter.ok_or(AmitaError::NotSolved)
+    }
+
+    pub fn termination(&self) -> Result<TerminationCondition, AmitaError> {
+        self.termination.ok_or(AmitaError::NotSolved)
+    }
+}
+
+impl BaseResults for PPMLResults {
+    fn coef(&self) -> Result<Array1<f64>, AmitaError> {
+        self.coef.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn se(&self) -> Result<Array1<f64>, AmitaError> {
+        self.se.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn t(&self) -> Result<Array1<f64>, AmitaError> {
+        self.t.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn p_vals(&self) -> Result<Array1<f64>, AmitaError> {
+        self.p_vals.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn vcov(&self) -> Result<Array2<f64>, AmitaError> {
+        self.vcov.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn summary(&self) -> Result<String, AmitaError> {
+        coef_table(self)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PPMLSolver {
+    y: Array1<f64>,
+    x: Array2<f64>,
+    offset: Array1<f64>,
+    fixed_effects: Option<FixedEffects>,
+    se_type: SolverSEType,
+
+    max_iter: u64,
+    max_tolerance: f64,
+    check_separation: bool,
+
+    kept: Vec<usize>,
+    mu: Option<Array1<f64>>,
+    results: PPMLResults,
+}
+
+impl PPMLSolver {
+    /// `x` should not contain a constant when fixed effects are absorbed.
+    /// Standard errors default to heteroskedasticity-robust, as the Poisson
+    /// variance is rarely tenable for the outcomes PPML is used on.
+    pub fn new(
+        y: &Array1<f64>,
+        x: &Array2<f64>,
+    ) -> Result<Self, AmitaError> {
+        if x.shape()[0] != y.shape()[0] {
+            return Err(AmitaError::NotSameObservations);
+        }
+        if let Some(y_i) = y.iter().find(|y_i| !y_i.is_finite() || **y_i < 0.) {
+            return Err(AmitaError::InvalidOutcome { outcome: y_i.to_string() });
+        }
+
+        let results = PPMLResults {
+            n_obs: y.len(),
+            dropped: vec![],
+            omitted: vec![],
+            separation_inconclusive: false,
+
+            coef: None,
+            se: None,
+            t: None,
+            p_vals: None,
+            vcov: None,
+
+            deviance: None,
+            pseudo_loglik: None,
+            n_iter: None,
+            termination: None,
+        };
+
+        Ok( Self {
+            y: y.to_owned(),
+            x: x.to_owned(),
+            offset: Array1::zeros(y.len()),
+            fixed_effects: None,
+            se_type: SolverSEType::Robust,
+
+            max_iter: 100,
+            max_tolerance: 1e-8,
+            check_separation: true,
+
+            kept: (0..y.len()).collect(),
+            mu: None,
+            results,
+        } )
+    }
+
+    /// One vector of group identifiers per set of fixed effects, e.g.
+    /// exporter-year, importer-year and pair
+    pub fn with_fixed_effects(mut self, ids: &[Array1<i32>]) -> Result<Self, AmitaError> {
+        let fixed_effects = FixedEffects::new(ids)?;
+        if fixed_effects.n_dims() > 0 && fixed_effects.n_obs() != self.y.len() {
+            return Err(AmitaError::NotSameObservations);
+        }
+
+        self.fixed_effects = Some(fixed_effects).filter(|fe| fe.n_dims() > 0);
+        Ok(self)
+    }
+
+    pub fn with_offset(mut self, offset: &Array1<f64>) -> Result<Self, AmitaError> {
+        if offset.len() != self.y.len() {
+            return Err(AmitaError::NotSameObservations);
+        }
+        self.offset += offset;
+        Ok(self)
+    }
+
+    pub fn with_exposure(self, exposure: &Array1<f64>) -> Result<Self, AmitaError> {
+        if exposure.iter().any(|t| *t <= 0.) {
+            return Err(AmitaError::NonPositive { matrix_name: "exposure".to_string() });
+        }
+        self.with_offset(&exposure.map(|t| t.ln()))
+    }
+
+    pub fn with_se_type(mut self, se_type: SolverSEType) -> Self {
+        self.se_type = se_type;
+        self
+    }
+
+    pub fn with_max_iter(mut self, max_iter: u64) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    pub fn with_max_tolerance(mut self, max_tolerance: f64) -> Self {
+        self.max_tolerance = max_tolerance;
+        self
+    }
+
+    pub fn with_separation_check(mut self, check_separation: bool) -> Self {
+        self.check_separation = check_separation;
+        self
+    }
+}
+
+impl BaseSolver<PPMLResults> for PPMLSolver {
+    fn results(&self) -> PPMLResults {
+        self.results.clone()
+    }
+
+    fn solve(self) -> Result<Self, AmitaError> {
+        self
+        .drop_observations()?
+        .run_solver()?
+        .solve_se()?
+        .solve_z_pvals()
+    }
+}
+
+impl PPMLSolver {
+    /// Drops singletons and separated observations until none remain
+    fn drop_observations(mut self) -> Result<Self, AmitaError> {
+        let mut kept = (0..self.y.len()).collect::<Vec<_>>();
+
+        loop {
+            let n_kept = kept.len();
+
+            if let Some(fe) = &self.fixed_effects {
+                let fe = fe.select(&kept);
+                let mut drop = vec![false; kept.len()];
+                fe.singletons().into_iter().for_each(|i| drop[i] = true);
+
+                if self.check_separation {
+                    for dim in 0..fe.n_dims() {
+                        let mut totals = vec![0.; fe.n_groups()[dim]];
+                        for (g, i) in fe.groups(dim).iter().zip(kept.iter()) {
+                            totals[*g] += self.y[*i];
+                        }
+                        for (r, g) in fe.groups(dim).iter().enumerate() {
+                            if totals[*g] == 0. {
+                                drop[r] = true;
+                            }
+                        }
+                    }
+                }
+
+                kept = kept.iter().zip(drop.iter()).filter(|(_, d)| !**d).map(|(i, _)| *i).collect();
+            }
+
+            if self.check_separation && !kept.is_empty() {
+                match self.relu_separated(&kept) {
+                    Some(separated) => {
+                        kept = kept.iter().zip(separated.iter())
+                            .filter(|(_, s)| !**s)
+                            .map(|(i, _)| *i)
+                            .collect();
+                    },
+                    None => self.results.separation_inconclusive = true,
+                }
+            }
+
+            if kept.len() == n_kept {
+                break;
+            }
+        }
+
+        if kept.iter().all(|i| self.y[*i] == 0.) {
+            return Err(AmitaError::InvalidOutcome { outcome: "no positive outcomes remain".to_string() });
+        }
+
+        self.results.dropped = (0..self.y.len()).filter(|i| kept.binary_search(i).is_err()).collect();
+        self.results.n_obs = kept.len();
+        self.kept = kept;
+
+        Ok(self)
+    }
+
+    /// Whether each observation in `kept` is separated by the regressors and
+    /// fixed effects: a linear combination z = x'γ + FE exists with z = 0
+    /// wherever y > 0, z ≥ 0 wherever y = 0, and z > 0 on them. None when the
+    /// check is inconclusive.
+    ///
+    /// The rectifier u, starting at 1{y = 0}, is replaced by max(ẑ, 0) of its
+    /// weighted least squares fit until the fit is non-negative everywhere.
+    fn relu_separated(&self, kept: &[usize]) -> Option<Vec<bool>> {
+        let y = self.y.select(Axis(0), kept);
+        if y.iter().all(|y_i| *y_i > 0.) {
+            return Some(vec![false; kept.len()]);
+        }
+
+        let x = self.x.select(Axis(0), kept);
+        let fe = self.fixed_effects.as_ref().map(|fe| fe.select(kept));
+        let weights = y.map(|y_i| if *y_i > 0. { RELU_POSITIVE_WEIGHT } else { 1. });
+
+        let mut u = y.map(|y_i| if *y_i > 0. { 0. } else { 1. });
+        for _ in 0..RELU_MAX_ITER {
+            let (u_tilde, x_tilde) = partial_out(fe.as_ref(), &u, &x, &weights).ok()?;
+            let resid = least_squares_resid(&u_tilde, &x_tilde, &weights).ok()?;
+
+            let fitted = &u - &resid;
+            let on_positives = fitted.iter().zip(y.iter())
+                .all(|(z, y_i)| *y_i == 0. || z.abs() < RELU_TOLERANCE);
+            let fitted = fitted.iter().zip(y.iter())
+                .map(|(z, y_i)| if *y_i > 0. || z.abs() < RELU_TOLERANCE { 0. } else { *z })
+                .collect::<Array1<f64>>();
+
+            if on_positives && fitted.iter().all(|z| *z >= 0.) {
+                return Some( fitted.iter().map(|z| *z > 0.).collect() );
+            }
+            u = fitted.map(|z| z.max(0.));
+        }
+
+        None
+    }
+
+    /// IRLS on the working outcome η + (y - μ) / μ with weights μ, absorbing
+    /// the fixed effects at every iteration, until the relative change in the
+    /// deviance falls below the tolerance
+    fn run_solver(mut self) -> Result<Self, AmitaError> {
+        let (y, x, offset, fe) = self.estimation_sample();
+        let included = self.included_regressors(&x, fe.as_ref())?;
+        let x = x.select(Axis(1), &included);
+
+        let mean_y = y.mean().unwrap();
+        let mut mu = y.map(|y_i| (y_i + mean_y) / 2.);
+        let mut eta = mu.map(|m| m.ln());
+        let mut deviance = poisson_deviance(&y, &mu);
+        let mut beta = Array1::zeros(x.shape()[1]);
+
+        let mut n_iter = 0;
+        let mut termination = TerminationCondition::MaxIterReached;
+        while n_iter < self.max_iter {
+            n_iter += 1;
+
+            let z = &eta - &offset + (&y - &mu) / &mu;
+            let (z_tilde, x_tilde) = partial_out(fe.as_ref(), &z, &x, &mu)?;
+            beta = least_squares(&z_tilde, &x_tilde, &mu)?;
+
+            let resid = &z_tilde - &x_tilde.dot(&beta);
+            eta = &z - &resid + &offset;
+            mu = eta.map(|e| e.exp());
+
+            let new_deviance = poisson_deviance(&y, &mu);
+            let change = (new_deviance - deviance).abs() / new_deviance.max(0.1);
+            deviance = new_deviance;
+            if change < self.max_tolerance {
+                termination = TerminationCondition::SolverConverged;
+                break;
+            }
+        }
+
+        if termination == TerminationCondition::MaxIterReached {
+            return Err(AmitaError::NotConverged { iterations: n_iter });
+        }
+
+        let pseudo_loglik = y.iter().zip(mu.iter())
+            .map(|(y_i, m)| y_i * m.ln() - m - ln_gamma(y_i + 1.))
+            .sum();
+
+        let mut coef = Array1::from_elem(self.x.shape()[1], f64::NAN);
+        included.iter().zip(beta.iter()).for_each(|(j, b)| coef[*j] = *b);
+
+        self.mu = Some(mu);
+        self.results.omitted = (0..self.x.shape()[1]).filter(|j| !included.contains(j)).collect();
+        self.results.coef = Some(coef);
+        self.results.deviance = Some(deviance);
+        self.results.pseudo_loglik = Some(pseudo_loglik);
+        self.results.n_iter = Some(n_iter);
+        self.results.termination = Some(termination);
+
+        Ok(self)
+    }
+
+    /// Sandwich of the partialled-out scores x̃ (y - μ) around the inverse
+    /// weighted cross-product of x̃ at the final weights μ
+    fn solve_se(mut self) -> Result<Self, AmitaError> {
+        let (y, x, _, fe) = self.estimation_sample();
+        let coef = self.results.coef()?;
+        let mu_fit = self.mu.clone().ok_or(AmitaError::NotSolved)?;
+        let included = (0..coef.len()).filter(|j| !self.results.omitted.contains(j)).collect::<Vec<_>>();
+        let x = x.select(Axis(1), &included);
+
+        let (_, x_tilde) = partial_out(fe.as_ref(), &Array1::zeros(y.len()), &x, &mu_fit)?;
+
+        let xw = &x_tilde * &mu_fit.view().insert_axis(Axis(1));
+        let bread = inverse(&x_tilde.t().dot(&xw), "X'WX of the partialled-out regressors")?;
+        let scores = &x_tilde * &(&y - &mu_fit).insert_axis(Axis(1));
+        let clusters = |by: &Array1<i32>| by.select(Axis(0), &self.kept);
+        let se_type = match &self.se_type {
+            SolverSEType::Clustered { by } => SolverSEType::Clustered { by: clusters(by) },
+            SolverSEType::MultiwayClustered { by } => {
+                SolverSEType::MultiwayClustered { by: by.iter().map(clusters).collect() }
+            },
+            se_type => se_type.clone(),
+        };
+        let vcov_included = sandwich(&bread, &scores, &se_type)?;
+
+        let k = coef.len();
+        let mut vcov = Array2::from_elem((k, k), f64::NAN);
+        for (a, j) in included.iter().enumerate() {
+            for (b, l) in included.iter().enumerate() {
+                vcov[[*j, *l]] = vcov_included[[a, b]];
+            }
+        }
+
+        self.results.se = Some(vcov.diag().map(|x| x.sqrt()));
+        self.results.vcov = Some(vcov);
+
+        Ok(self)
+    }
+
+    fn solve_z_pvals(mut self) -> Result<Self, AmitaError> {
+        let z = self.results.coef()? / self.results.se()?;
+
+        let normal = Normal::new(0., 1.).unwrap();
+        let p_vals = z.map(|x| 2. * ( 1. - normal.cdf(x.abs()) ) );
+
+        self.results.t = Some(z);
+        self.results.p_vals = Some(p_vals);
+
+        Ok(self)
+    }
+
+    fn estimation_sample(&self) -> (Array1<f64>, Array2<f64>, Array1<f64>, Option<FixedEffects>) {
+        (
+            self.y.select(Axis(0), &self.kept),
+            self.x.select(Axis(0), &self.kept),
+            self.offset.select(Axis(0), &self.kept),
+            self.fixed_effects.as_ref().map(|fe| fe.select(&self.kept)),
+        )
+    }
+
+    /// Regressors that do not vanish once the fixed effects are partialled out
+    fn included_regressors(
+        &self,
+        x: &Array2<f64>,
+        fe: Option<&FixedEffects>,
+    ) -> Result<Vec<usize>, AmitaError> {
+        let ones = Array1::ones(x.shape()[0]);
+        let (_, x_tilde) = partial_out(fe, &ones, x, &ones)?;
+
+        Ok( (0..x.shape()[1])
+            .filter(|j| {
+                let scale = x.column(*j).iter().fold(0., |acc: f64, x| acc.max(x.abs()));
+                x_tilde.column(*j).iter().any(|x| x.abs() > 1e-9 * scale.max(1.))
+            })
+            .collect() )
+    }
+}
+
+/// Weighted residuals of v and of the columns of x on the fixed effects
+fn partial_out(
+    fe: Option<&FixedEffects>,
+    v: &Array1<f64>,
+    x: &Array2<f64>,
+    weights: &Array1<f64>,
+) -> Result<(Array1<f64>, Array2<f64>), AmitaError> {
+    let Some(fe) = fe else {
+        return Ok( (v.to_owned(), x.to_owned()) );
+    };
+
+    let stacked = ndarray::concatenate![Axis(1), v.view().insert_axis(Axis(1)), x.view()];
+    let demeaned = fe.demean(&stacked, weights)?;
+
+    Ok( (demeaned.column(0).to_owned(), demeaned.slice(s![.., 1..]).to_owned()) )
+}
+
+/// Weighted least squares coefficients (X'WX)^{-1} X'Wv
+fn least_squares(
+    v: &Array1<f64>,
+    x: &Array2<f64>,
+    weights: &Array1<f64>,
+) -> Result<Array1<f64>, AmitaError> {
+    if x.shape()[1] == 0 {
+        return Ok( Array1::zeros(0) );
+    }
+
+    let xw = x * &weights.view().insert_axis(Axis(1));
+    solve(&xw.t().dot(x), &xw.t().dot(v), "X'WX")
+}
+
+/// Weighted least squares residuals of v on the columns of x, leaving out
+/// those collinear with the ones before
+fn least_squares_resid(
+    v: &Array1<f64>,
+    x: &Array2<f64>,
+    weights: &Array1<f64>,
+) -> Result<Array1<f64>, AmitaError> {
+    let root = weights.map(|w| w.sqrt());
+    let xw = x * &root.view().insert_axis(Axis(1));
+    let (qr, qty) = pivoted_qr_with_rhs(xw, v * &root, RELU_COLLINEARITY_TOLERANCE)?;
+    let beta = solve_upper_triangular(&qr.r, &qty, "R of the ReLU regressors")?;
+
+    Ok( v - &x.select(Axis(1), &qr.pivots[..qr.rank]).dot(&beta) )
+}
+
+fn poisson_deviance(y: &Array1<f64>, mu: &Array1<f64>) -> f64 {
+    2. * y.iter().zip(mu.iter())
+        .map(|(y_i, m)| if *y_i > 0. { y_i * (y_i / m).ln() } else { 0. } - (y_i - m))
+        .sum::<f64>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discrete::count::poisson::PoissonSolver;
+
+    fn data() -> (Array1<i32>, Array1<f64>, Array1<i32>) {
+        let y = array![0, 2, 5, 1, 0, 7, 3, 0, 4, 1, 6, 2, 0, 3, 9, 1, 2, 0];
+        let x1 = array![
+            0.1, 0.4, 1.2, 0.3, -0.2, 1.5, 0.6, 0.0, 1.1,
+            -0.5, 1.3, 0.2, 0.8, -0.1, 0.9, 0.5, 0.7, -0.4
+        ];
+        let group = array![0, 0, 0, 1, 1, 1, 2, 2, 2, 3, 3, 3, 4, 4, 4, 5, 5, 5];
+        (y, x1, group)
+    }
+
+    #[test]
+    fn test_matches_poisson_with_dummies() -> Result<(), AmitaError> {
+        let (y, x1, group) = data();
+
+        let mut dummies = Array2::<f64>::zeros((18, 6));
+        group.iter().enumerate().for_each(|(i, g)| dummies[[i, *g as usize]] = 1.);
+        let x_dummies = ndarray::concatenate![Axis(1), x1.clone().insert_axis(Axis(1)), dummies];
+
+        let poisson = PoissonSolver::new(&y, &x_dummies)?
+            .with_se_type(SolverSEType::Robust)
+            .solve()?
+            .results();
+        let ppml = PPMLSolver::new(&y.map(|y| *y as f64), &x1.insert_axis(Axis(1)))?
+            .with_fixed_effects(&[group])?
+            .solve()?
+            .results();
+
+        assert!( ppml.dropped().is_empty() && !ppml.separation_inconclusive() );
+        assert!( (ppml.coef()?[0] - poisson.coef()?[0]).abs() < 1e-6 );
+        assert!( (ppml.pseudo_loglik()? - poisson.loglik()?).abs() < 1e-6 );
+
+        // robust SEs agree up to the degrees of freedom of the sandwich
+        assert!( (ppml.se()?[0] - poisson.se()?[0]).abs() < 1e-5 );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_separation_drops() -> Result<(), AmitaError> {
+        let (y, x1, group) = data();
+        let mut y = y.map(|y| *y as f64);
+        // group 5 only has zero outcomes, and d separates observations 0 and 4
+        y[15] = 0.;
+        y[16] = 0.;
+        let mut d = Array1::<f64>::zeros(18);
+        d[0] = 1.;
+        d[4] = 2.;
+        let x = ndarray::stack![Axis(1), x1, d];
+
+        let two_way = group.map(|g| g % 2);
+        let results = PPMLSolver::new(&y, &x)?
+            .with_fixed_effects(&[group, two_way.clone()])?
+            .with_se_type(SolverSEType::MultiwayClustered { by: vec![two_way, Array1::from_iter(0..18)] })
+            .solve()?
+            .results();
+
+        assert_eq!(results.dropped(), vec![0, 4, 15, 16, 17]);
+        assert!( !results.separation_inconclusive() );
+        assert_eq!(results.omitted(), vec![1]);
+        assert!( results.coef()?[0].is_finite() );
+        assert!( results.coef()?[1].is_nan() );
+
+        Ok(())
+    }
+}
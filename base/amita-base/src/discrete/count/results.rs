@@ -0,0 +1,201 @@
+//! Results of the count-data models.
+//!
+//! Parameters are stacked as [β, ln α, γ]: the count-equation coefficients,
+//! the overdispersion of the negative binomials, and the coefficients of the
+//! zero-inflation or hurdle equation.
+
+use amita_error::AmitaError;
+use amita_utils::iterations::{NewtonRaphsonState, TerminationCondition};
+use amita_utils::summary::coef_table;
+use amita_utils::traits::BaseResults;
+use ndarray::prelude::*;
+use statrs::distribution::{ContinuousCDF, Normal};
+
+use super::distribution::CountDistribution;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CountModel {
+    /// Single-equation Poisson or negative binomial
+    Count(CountDistribution),
+    /// Mixture of a point mass at zero, with logit probability, and a count
+    ZeroInflated(CountDistribution),
+    /// Logit for y > 0, and a zero-truncated count for the positive outcomes
+    Hurdle(CountDistribution),
+}
+
+impl CountModel {
+    pub fn distribution(&self) -> CountDistribution {
+        match self {
+            CountModel::Count(d) | CountModel::ZeroInflated(d) | CountModel::Hurdle(d) => *d,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CountResults {
+    model: CountModel,
+    n_obs: usize,
+    n_regressors: usize,
+    n_inflate_regressors: usize,
+
+    coef: Option<Array1<f64>>, // [β, ln α, γ]
+    se: Option<Array1<f64>>,
+    t: Option<Array1<f64>>, // z statistics
+    p_vals: Option<Array1<f64>>,
+    vcov: Option<Array2<f64>>,
+
+    loglik: Option<f64>,
+    n_iter: Option<u64>,
+    termination: Option<TerminationCondition>,
+}
+
+impl CountResults {
+    pub(crate) fn new(
+        model: CountModel,
+        n_obs: usize,
+        n_regressors: usize,
+        n_inflate_regressors: usize,
+    ) -> Self {
+        Self {
+            model,
+            n_obs,
+            n_regressors,
+            n_inflate_regressors,
+
+            coef: None,
+            se: None,
+            t: None,
+            p_vals: None,
+            vcov: None,
+
+            loglik: None,
+            n_iter: None,
+            termination: None,
+        }
+    }
+
+    pub fn model(&self) -> CountModel {
+        self.model
+    }
+
+    pub fn n_obs(&self) -> usize {
+        self.n_obs
+    }
+
+    pub fn n_params(&self) -> usize {
+        self.n_regressors + self.model.distribution().n_ancillary() + self.n_inflate_regressors
+    }
+
+    /// Coefficients β of the count equation
+    pub fn count_coef(&self) -> Result<Array1<f64>, AmitaError> {
+        Ok( self.coef()?.slice(s![..self.n_regressors]).to_owned() )
+    }
+
+    /// Coefficients γ of the zero-inflation or hurdle equation, empty for
+    /// single-equation models
+    pub fn inflate_coef(&self) -> Result<Array1<f64>, AmitaError> {
+        let start = self.n_regressors + self.model.distribution().n_ancillary();
+        Ok( self.coef()?.slice(s![start..]).to_owned() )
+    }
+
+    /// Incidence-rate ratios exp(β) of the count equation
+    pub fn irr(&self) -> Result<Array1<f64>, AmitaError> {
+        Ok( self.count_coef()?.map(|x| x.exp()) )
+    }
+
+    /// Delta-method standard errors of the incidence-rate ratios, exp(β) se(β)
+    pub fn irr_se(&self) -> Result<Array1<f64>, AmitaError> {
+        let se = self.se()?.slice(s![..self.n_regressors]).to_owned();
+        Ok( self.irr()? * se )
+    }
+
+    /// Overdispersion α of the negative binomials, None for Poisson
+    pub fn alpha(&self) -> Result<Option<f64>, AmitaError> {
+        let coef = self.coef()?;
+        Ok( self.ln_alpha_index().map(|j| coef[j].exp()) )
+    }
+
+    /// Delta-method standard error of α, α se(ln α)
+    pub fn alpha_se(&self) -> Result<Option<f64>, AmitaError> {
+        let coef = self.coef()?;
+        let se = self.se()?;
+        Ok( self.ln_alpha_index().map(|j| coef[j].exp() * se[j]) )
+    }
+
+    pub fn loglik(&self) -> Result<f64, AmitaError> {
+        self.loglik.ok_or(AmitaError::NotSolved)
+    }
+
+    pub fn aic(&self) -> Result<f64, AmitaError> {
+        Ok( - 2. * self.loglik()? + 2. * self.n_params() as f64 )
+    }
+
+    pub fn bic(&self) -> Result<f64, AmitaError> {
+        Ok( - 2. * self.loglik()? + (self.n_obs as f64).ln() * self.n_params() as f64 )
+    }
+
+    pub fn n_iter(&self) -> Result<u64, AmitaError> {
+        self.n_iter.ok_or(AmitaError::NotSolved)
+    }
+
+    pub fn termination(&self) -> Result<TerminationCondition, AmitaError> {
+        self.termination.ok_or(AmitaError::NotSolved)
+    }
+
+    fn ln_alpha_index(&self) -> Option<usize> {
+        match self.model.distribution().n_ancillary() {
+            0 => None,
+            _ => Some(self.n_regressors),
+        }
+    }
+
+    pub(crate) fn set_fit(&mut self, state: NewtonRaphsonState) {
+        self.loglik = Some(- state.cost * self.n_obs as f64);
+        self.n_iter = Some(state.n_iter);
+        self.termination = Some(state.termination);
+        self.coef = Some(state.param);
+    }
+
+    pub(crate) fn set_vcov(&mut self, vcov: Array2<f64>) {
+        self.se = Some(vcov.diag().map(|x| x.sqrt()));
+        self.vcov = Some(vcov);
+    }
+
+    pub(crate) fn set_z_pvals(&mut self) -> Result<(), AmitaError> {
+        let z = self.coef()? / self.se()?;
+
+        let normal = Normal::new(0., 1.).unwrap();
+        let p_vals = z.map(|x| 2. * ( 1. - normal.cdf(x.abs()) ) );
+
+        self.t = Some(z);
+        self.p_vals = Some(p_vals);
+
+        Ok(())
+    }
+}
+
+impl BaseResults for CountResults {
+    fn coef(&self) -> Result<Array1<f64>, AmitaError> {
+        self.coef.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn se(&self) -> Result<Array1<f64>, AmitaError> {
+        self.se.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn t(&self) -> Result<Array1<f64>, AmitaError> {
+        self.t.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn p_vals(&self) -> Result<Array1<f64>, AmitaError> {
+        self.p_vals.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn vcov(&self) -> Result<Array2<f64>, AmitaError> {
+        self.vcov.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn summary(&self) -> Result<String, AmitaError> {
+        coef_table(self)
+    }
+}
@@ -0,0 +1,229 @@
+//! Zero-inflated count models.
+//!
+//! With probability π = Λ(z'γ) the outcome is an excess zero, otherwise it
+//! is drawn from the count distribution:
+//! Pr(y = 0) = π + (1 - π) f(0), Pr(y = j) = (1 - π) f(j) for j > 0.
+
+use amita_error::AmitaError;
+use amita_utils::inference::SolverSEType;
+use amita_utils::likelihood::{maximize, ml_vcov};
+use amita_utils::math::{sigmoid, softplus};
+use amita_utils::traits::{BaseResults, BaseSolver};
+use ndarray::prelude::*;
+
+use super::data::CountData;
+use super::distribution::CountDistribution;
+use super::poisson::PoissonSolver;
+use super::results::{CountModel, CountResults};
+
+#[derive(Debug, Clone)]
+pub struct ZeroInflatedSolver {
+    data: CountData,
+    z: Array2<f64>,
+    distribution: CountDistribution,
+    se_type: SolverSEType,
+
+    max_iter: u64,
+    max_tolerance: f64,
+
+    results: CountResults,
+}
+
+impl ZeroInflatedSolver {
+    /// `z` holds the regressors of the inflation logit, including its constant
+    pub fn new(
+        y: &Array1<i32>,
+        x: &Array2<f64>,
+        z: &Array2<f64>,
+        distribution: CountDistribution,
+    ) -> Result<Self, AmitaError> {
+        if z.shape()[0] != y.shape()[0] {
+            return Err(AmitaError::NotSameObservations);
+        }
+        if y.iter().all(|y_i| *y_i != 0) {
+            return Err(AmitaError::InvalidOutcome { outcome: "y has no zeros to inflate".to_string() });
+        }
+
+        let results = CountResults::new(
+            CountModel::ZeroInflated(distribution),
+            x.shape()[0],
+            x.shape()[1],
+            z.shape()[1],
+        );
+
+        Ok( Self {
+            data: CountData::new(y, x)?,
+            z: z.to_owned(),
+            distribution,
+            se_type: SolverSEType::NonRobust,
+
+            max_iter: 100,
+            max_tolerance: 1e-8,
+
+            results,
+        } )
+    }
+
+    pub fn with_offset(mut self, offset: &Array1<f64>) -> Result<Self, AmitaError> {
+        self.data.add_offset(offset)?;
+        Ok(self)
+    }
+
+    pub fn with_exposure(mut self, exposure: &Array1<f64>) -> Result<Self, AmitaError> {
+        self.data.add_exposure(exposure)?;
+        Ok(self)
+    }
+
+    pub fn with_se_type(mut self, se_type: SolverSEType) -> Self {
+        self.se_type = se_type;
+        self
+    }
+
+    pub fn with_max_iter(mut self, max_iter: u64) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    pub fn with_max_tolerance(mut self, max_tolerance: f64) -> Self {
+        self.max_tolerance = max_tolerance;
+        self
+    }
+}
+
+impl BaseSolver<CountResults> for ZeroInflatedSolver {
+    fn results(&self) -> CountResults {
+        self.results.clone()
+    }
+
+    fn solve(self) -> Result<Self, AmitaError> {
+        self
+        .run_solver()?
+        .solve_se()?
+        .solve_z_pvals()
+    }
+}
+
+impl ZeroInflatedSolver {
+    /// Starts from the Poisson estimates, α = 1 and π = 1/2
+    fn run_solver(mut self) -> Result<Self, AmitaError> {
+        let poisson = PoissonSolver::from_data(self.data.clone())
+            .with_max_iter(self.max_iter)
+            .with_max_tolerance(self.max_tolerance)
+            .solve()?
+            .results();
+        let init = ndarray::concatenate![
+            Axis(0),
+            poisson.count_coef()?,
+            Array1::zeros(self.distribution.n_ancillary() + self.z.shape()[1])
+        ];
+
+        let state = maximize(init, self.max_iter, self.max_tolerance, |param| self.loglik_obs(param))?;
+
+        self.results.set_fit(state);
+        Ok(self)
+    }
+
+    fn solve_se(mut self) -> Result<Self, AmitaError> {
+        let coef = self.results.coef()?;
+        let vcov = ml_vcov(&coef, &self.se_type, |param| self.loglik_obs(param))?;
+
+        self.results.set_vcov(vcov);
+        Ok(self)
+    }
+
+    fn solve_z_pvals(mut self) -> Result<Self, AmitaError> {
+        self.results.set_z_pvals()?;
+        Ok(self)
+    }
+
+    fn loglik_obs(&self, param: &Array1<f64>) -> (Array1<f64>, Array2<f64>) {
+        let n = self.data.y.len();
+        let k_count = self.data.x.shape()[1] + self.distribution.n_ancillary();
+        let theta = param.slice(s![..k_count]);
+        let index = self.z.dot(&param.slice(s![k_count..]));
+
+        let (count_loglik, count_scores) = self.distribution
+            .loglik_obs(&self.data.y, &self.data.x, &self.data.offset, theta);
+        let (zero_loglik, zero_scores) = self.distribution
+            .loglik_obs(&Array1::zeros(n), &self.data.x, &self.data.offset, theta);
+
+        let mut loglik = Array1::zeros(n);
+        let mut scores = Array2::zeros((n, param.len()));
+        for i in 0..n {
+            let pi = sigmoid(index[i]);
+            let z_i = self.z.row(i);
+
+            if self.data.y[i] == 0. {
+                let f0 = zero_loglik[i].exp();
+                let likelihood = pi + (1. - pi) * f0;
+
+                loglik[i] = likelihood.ln();
+                scores.slice_mut(s![i, ..k_count])
+                    .assign(&zero_scores.row(i).map(|s| (1. - pi) * f0 * s / likelihood));
+                scores.slice_mut(s![i, k_count..])
+                    .assign(&z_i.map(|z| (1. - f0) * pi * (1. - pi) * z / likelihood));
+            } else {
+                loglik[i] = - softplus(index[i]) + count_loglik[i];
+                scores.slice_mut(s![i, ..k_count]).assign(&count_scores.row(i));
+                scores.slice_mut(s![i, k_count..]).assign(&z_i.map(|z| - pi * z));
+            }
+        }
+
+        (loglik, scores)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use amita_utils::math::numerical_jacobian;
+
+    use super::*;
+
+    fn data() -> (Array1<i32>, Array2<f64>) {
+        let y = array![0, 0, 3, 0, 5, 0, 2, 0, 4, 0, 6, 1, 0, 3, 0, 2, 0, 7, 0, 4];
+        let x1 = array![
+            0.1, 0.4, 1.2, 0.3, -0.2, 1.5, 0.6, 0.0, 1.1, -0.5,
+            1.3, 0.2, 0.8, -0.1, 0.9, 1.7, -0.7, 1.4, 0.5, 0.3
+        ];
+        let x = ndarray::stack![Axis(1), Array1::<f64>::ones(20), x1];
+        (y, x)
+    }
+
+    #[test]
+    fn test_intercept_only_zip() -> Result<(), AmitaError> {
+        let (y, _) = data();
+        let ones = Array2::<f64>::ones((20, 1));
+
+        let results = ZeroInflatedSolver::new(&y, &ones, &ones, CountDistribution::Poisson)?
+            .solve()?
+            .results();
+        let lambda = results.irr()?[0];
+        let pi = sigmoid(results.inflate_coef()?[0]);
+
+        // the zeros are fitted exactly, and the positives by a truncated Poisson
+        let share_zeros = y.iter().filter(|y| **y == 0).count() as f64 / 20.;
+        let positives = y.iter().filter(|y| **y > 0).map(|y| *y as f64).collect::<Vec<_>>();
+        let mean_positives = positives.iter().sum::<f64>() / positives.len() as f64;
+
+        assert!( (pi + (1. - pi) * (-lambda).exp() - share_zeros).abs() < 1e-6 );
+        assert!( (lambda / (1. - (-lambda).exp()) - mean_positives).abs() < 1e-6 );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scores() -> Result<(), AmitaError> {
+        let (y, x) = data();
+        let solver = ZeroInflatedSolver::new(&y, &x, &x, CountDistribution::NB2)?;
+        let param = array![0.4, 0.3, -0.5, -0.2, 0.6];
+
+        let (_, scores) = solver.loglik_obs(&param);
+        let numerical = numerical_jacobian(|p| solver.loglik_obs(p).0, &param);
+        assert!( (scores - numerical).iter().all(|d| d.abs() < 1e-6) );
+
+        let results = solver.solve()?.results();
+        assert_eq!(results.inflate_coef()?.len(), 2);
+
+        Ok(())
+    }
+}
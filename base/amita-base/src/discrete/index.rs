@@ -0,0 +1,66 @@
+//! Index functions linking the linear index x'β to outcome probabilities
+//! of single-index discrete-choice models.
+
+use amita_utils::math::sigmoid;
+use statrs::distribution::{Continuous, ContinuousCDF, Normal};
+
+/// A distribution function F such that Pr(y = 1 | x) = F(x'β)
+pub trait IndexFunction: Clone {
+    /// F(z)
+    fn cdf(&self, z: f64) -> f64;
+
+    /// f(z) = F'(z)
+    fn pdf(&self, z: f64) -> f64;
+
+    /// f'(z) = F''(z), required by the delta method of marginal effects
+    fn pdf_derivative(&self, z: f64) -> f64;
+
+    /// F^{-1}(p)
+    fn inverse_cdf(&self, p: f64) -> f64;
+}
+
+/// Logistic distribution, the index function of logit models
+#[derive(Debug, Clone, Copy)]
+pub struct Logistic;
+
+impl IndexFunction for Logistic {
+    fn cdf(&self, z: f64) -> f64 {
+        sigmoid(z)
+    }
+
+    fn pdf(&self, z: f64) -> f64 {
+        let p = sigmoid(z);
+        p * (1. - p)
+    }
+
+    fn pdf_derivative(&self, z: f64) -> f64 {
+        let p = sigmoid(z);
+        p * (1. - p) * (1. - 2. * p)
+    }
+
+    fn inverse_cdf(&self, p: f64) -> f64 {
+        (p / (1. - p)).ln()
+    }
+}
+
+/// Standard normal distribution, the index function of probit models
+#[derive(Debug, Clone, Copy)]
+pub struct StandardNormal;
+
+impl IndexFunction for StandardNormal {
+    fn cdf(&self, z: f64) -> f64 {
+        Normal::new(0., 1.).unwrap().cdf(z)
+    }
+
+    fn pdf(&self, z: f64) -> f64 {
+        Normal::new(0., 1.).unwrap().pdf(z)
+    }
+
+    fn pdf_derivative(&self, z: f64) -> f64 {
+        - z * self.pdf(z)
+    }
+
+    fn inverse_cdf(&self, p: f64) -> f64 {
+        Normal::new(0., 1.).unwrap().inverse_cdf(p)
+    }
+}
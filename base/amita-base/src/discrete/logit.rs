@@ -1,16 +1,35 @@
 use std::collections::HashSet;
 
 use amita_error::AmitaError;
-use amita_utils::math::sigmoid;
+use amita_utils::iterations::{NewtonRaphson, TerminationCondition};
+use amita_utils::linalg::inverse;
+use amita_utils::math::{sigmoid, softplus};
+use amita_utils::parallel::weighted_gramian;
+use amita_utils::summary::coef_table;
 use amita_utils::traits::{BaseSolver, BaseResults};
-use linfa_linalg::qr::QR;
-use ndarray::prelude::*;
+use statrs::distribution::{ContinuousCDF, Normal};
 
-use argmin::core::{CostFunction, Error, Executor, Gradient, Operator, IterState, Hessian, State};
+use argmin::core::{CostFunction, Error, Executor, Gradient, Operator, Hessian, State, TerminationReason};
 use argmin::solver::{linesearch::MoreThuenteLineSearch, quasinewton::LBFGS};
 
 use ndarray::{Array1, Array2};
 
+use crate::glm::link::Link;
+use crate::prediction::Prediction;
+
+use super::index::Logistic;
+use super::margins::MarginsSolver;
+use super::separation::{diverging_columns, separating_columns};
+
+/// Optimization algorithm used to maximize the log-likelihood
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogitMethod {
+    /// Quasi-Newton L-BFGS with Moré–Thuente line search
+    LBFGS,
+    /// Newton–Raphson with the analytic Hessian, equivalent to IRLS
+    Newton,
+}
+
 #[derive(Debug, Clone)]
 pub struct LogitResults {
     n_obs: usize,
@@ -18,7 +37,34 @@ pub struct LogitResults {
 
     coef: Option<Array1<f64>>,
     se: Option<Array1<f64>>,
-    t: Option<Array1<f64>>,
+    t: Option<Array1<f64>>, // z statistics
+    p_vals: Option<Array1<f64>>,
+    vcov: Option<Array2<f64>>,
+
+    // convergence diagnostics
+    n_iter: Option<u64>,
+    grad_norm: Option<f64>, // max-norm of the mean log-likelihood gradient
+    termination: Option<TerminationCondition>,
+}
+
+impl LogitResults {
+    pub fn n_iter(&self) -> Result<u64, AmitaError> {
+        self.n_iter.ok_or(AmitaError::NotSolved)
+    }
+
+    pub fn grad_norm(&self) -> Result<f64, AmitaError> {
+        self.grad_norm.ok_or(AmitaError::NotSolved)
+    }
+
+    pub fn termination(&self) -> Result<TerminationCondition, AmitaError> {
+        self.termination.ok_or(AmitaError::NotSolved)
+    }
+
+    /// Predicted probabilities at the rows of `new_x`, with the columns of
+    /// the fitted x
+    pub fn predict(&self, new_x: &Array2<f64>) -> Result<Prediction, AmitaError> {
+        Prediction::new(new_x, &self.coef()?, &self.vcov()?, None, Link::Logit)
+    }
 }
 
 impl BaseResults for LogitResults {
@@ -27,19 +73,23 @@ impl BaseResults for LogitResults {
     }
 
     fn se(&self) -> Result<Array1<f64>, AmitaError> {
-        todo!()
+        self.se.clone().ok_or(AmitaError::NotSolved)
     }
 
     fn t(&self) -> Result<Array1<f64>, AmitaError> {
-        todo!()
+        self.t.clone().ok_or(AmitaError::NotSolved)
     }
 
     fn p_vals(&self) -> Result<Array1<f64>, AmitaError> {
-        todo!()
+        self.p_vals.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn vcov(&self) -> Result<Array2<f64>, AmitaError> {
+        self.vcov.clone().ok_or(AmitaError::NotSolved)
     }
 
     fn summary(&self) -> Result<String, AmitaError> {
-        todo!()
+        coef_table(self)
     }
 }
 
@@ -48,10 +98,12 @@ pub struct LogitSolver {
     y: Array1<f64>,
     x: Array2<f64>,
     hessian: Option<Array2<f64>>,
-    iter_state: Option<IterState<Array1<f64>, Array1<f64>, (), (), (), f64, >>,
 
+    method: LogitMethod,
     max_iter: u64,
     max_tolerance: f64,
+    check_separation: bool,
+    check_convergence: bool,
 
     results: LogitResults,
 }
@@ -61,7 +113,7 @@ impl LogitSolver {
         y: &Array1<i32>,
         x: &Array2<f64>,
     ) -> Result<Self, AmitaError> {
-        let _res = LogitSolver::validate_data(y, x)?;
+        LogitSolver::validate_data(y, x)?;
 
         let n_obs = x.shape()[0];
         let n_regressors = x.shape()[1];
@@ -73,23 +125,36 @@ impl LogitSolver {
             coef: None,
             se: None,
             t: None,
+            p_vals: None,
+            vcov: None,
+
+            n_iter: None,
+            grad_norm: None,
+            termination: None,
         };
 
         let y = y.clone().map(|x| *x as f64);
 
         Ok( Self {
-            y: y,
+            y,
             x: x.clone(),
             hessian: None,
-            iter_state: None,
 
+            method: LogitMethod::LBFGS,
             max_iter: 1_000,
             max_tolerance: 0.0001,
+            check_separation: true,
+            check_convergence: true,
 
-            results: results,
+            results,
         })
     }
 
+    pub fn with_method(mut self, method: LogitMethod) -> Self {
+        self.method = method;
+        self
+    }
+
     pub fn with_max_iter(mut self, max_iter: u64) -> Self {
         self.max_iter = max_iter;
         self
@@ -100,6 +165,22 @@ impl LogitSolver {
         self
     }
 
+    /// Whether to fail with `AmitaError::Separation` when the regressors
+    /// separate the outcome. Enabled by default.
+    pub fn with_separation_check(mut self, check_separation: bool) -> Self {
+        self.check_separation = check_separation;
+        self
+    }
+
+    /// Whether to fail with `AmitaError::NotConverged`, or
+    /// `AmitaError::SolverFailed` when step halving stalls, if the solver
+    /// does not converge. Enabled by default; when disabled, the last
+    /// iterate is kept and `termination` tells how the solver stopped.
+    pub fn with_convergence_check(mut self, check_convergence: bool) -> Self {
+        self.check_convergence = check_convergence;
+        self
+    }
+
     fn validate_data(
         y: &Array1<i32>,
         x: &Array2<f64>,
@@ -109,11 +190,11 @@ impl LogitSolver {
         }
 
         let mut y_allowed = HashSet::new();
-        y_allowed.insert(0 as i32);
-        y_allowed.insert(1 as i32);
+        y_allowed.insert(0_i32);
+        y_allowed.insert(1_i32);
 
-        let y_unique = y.into_iter()
-            .map(|x| *x)
+        let y_unique = y.iter()
+            .copied()
             .collect::<HashSet<i32>>();
 
         if y_unique != y_allowed {
@@ -126,27 +207,79 @@ impl LogitSolver {
 
 impl BaseSolver<LogitResults> for LogitSolver {
     fn results(&self) -> LogitResults {
-        todo!()
+        self.results.clone()
     }
 
     fn solve(self) -> Result<Self, AmitaError> {
         self
+        .validate_separation()?
         .run_solver()?
-        .solve_coef()?
         .solve_hessian()?
         .solve_se()?
-        .solve_t()
+        .solve_t()?
+        .solve_p_vals()
     }
 }
 
 impl LogitSolver {
-    fn run_solver(mut self) ->Result<Self, AmitaError> {
+    /// Marginal effects of the fitted model, evaluated on the estimation sample.
+    /// See [`MarginsSolver`] for the available evaluation points.
+    pub fn margins(&self) -> Result<MarginsSolver<Logistic>, AmitaError> {
+        let coef = self.results.coef()?;
+        let vcov = self.results.vcov()?;
+
+        MarginsSolver::new(&self.x, &coef, &vcov, Logistic)
+    }
+}
+
+impl LogitSolver {
+    fn validate_separation(self) -> Result<Self, AmitaError> {
+        if !self.check_separation {
+            return Ok(self);
+        }
+
+        let columns = separating_columns(&self.y, &self.x);
+        if !columns.is_empty() {
+            return Err(AmitaError::Separation { columns });
+        }
+
+        Ok(self)
+    }
+
+    fn run_solver(self) -> Result<Self, AmitaError> {
+        let solver = match self.method {
+            LogitMethod::LBFGS => self.run_lbfgs(),
+            LogitMethod::Newton => self.run_newton(),
+        }?;
+
+        solver
+        .check_divergence()?
+        .check_convergence()
+    }
+
+    /// Separation by a combination of regressors passes the pre-estimation
+    /// check but shows up as diverging coefficients
+    fn check_divergence(self) -> Result<Self, AmitaError> {
+        if !self.check_separation {
+            return Ok(self);
+        }
+
+        let coef = self.results.coef.clone().ok_or(AmitaError::NotSolved)?;
+        let columns = diverging_columns(&self.y, &self.x, &coef);
+        if !columns.is_empty() {
+            return Err(AmitaError::Separation { columns });
+        }
+
+        Ok(self)
+    }
+
+    fn run_lbfgs(mut self) -> Result<Self, AmitaError> {
         let init_param = Array1::zeros((self.results.n_regressors, ));
 
         let linesearch = MoreThuenteLineSearch::new();
         let solver = LBFGS::new(linesearch, 10)
             .with_tolerance_grad(self.max_tolerance)
-            .unwrap();
+            .map_err(|e| AmitaError::SolverFailed { message: e.to_string() })?;
 
         let res = Executor::new(self.clone(), solver)
             .configure(|state| 
@@ -156,32 +289,76 @@ impl LogitSolver {
                     .target_cost(0.0)
             )
             .run()
-            .unwrap();
+            .map_err(|e| AmitaError::SolverFailed { message: e.to_string() })?;
+
+        let state = res.state();
+        let coef = state.get_param().cloned().ok_or(AmitaError::NotSolved)?;
+        // the target cost of zero bounds the mean negative log-likelihood,
+        // so reaching it is convergence
+        let termination = match state.get_termination_reason() {
+            Some(TerminationReason::SolverConverged | TerminationReason::TargetCostReached) => {
+                TerminationCondition::SolverConverged
+            },
+            Some(TerminationReason::MaxItersReached) => TerminationCondition::MaxIterReached,
+            Some(TerminationReason::SolverExit(message)) => {
+                return Err(AmitaError::SolverFailed { message: message.clone() })
+            },
+            Some(reason @ (TerminationReason::Interrupt | TerminationReason::Timeout)) => {
+                return Err(AmitaError::SolverFailed { message: reason.text().to_string() })
+            },
+            None => {
+                return Err(AmitaError::SolverFailed { message: "L-BFGS did not terminate".to_string() })
+            },
+        };
+        let gradient = self.gradient(&coef)
+            .map_err(|e| AmitaError::SolverFailed { message: e.to_string() })?;
+
+        self.results.n_iter = Some(state.get_iter());
+        self.results.grad_norm = Some(gradient.iter().fold(0., |acc: f64, x| acc.max(x.abs())));
+        self.results.termination = Some(termination);
+        self.results.coef = Some(coef);
+
+        Ok(self)
+    }
 
-        self.iter_state = Some(res.state.clone());
+    /// Newton–Raphson on the mean negative log-likelihood. For the logit
+    /// model the Newton step coincides with an IRLS step, with working
+    /// weights p(1 - p).
+    fn run_newton(mut self) -> Result<Self, AmitaError> {
+        let init_param = Array1::zeros((self.results.n_regressors, ));
 
-        Ok(self)   
+        let state = NewtonRaphson::new(self.max_iter, self.max_tolerance)
+            .minimize(init_param, |param| {
+                let p = self.probabilities(param);
+                Ok( (
+                    self.mean_neg_loglik(param),
+                    self.mean_gradient(&p),
+                    self.mean_hessian(&p),
+                ) )
+            })?;
+
+        self.results.n_iter = Some(state.n_iter);
+        self.results.grad_norm = Some(state.grad_norm());
+        self.results.termination = Some(state.termination);
+        self.results.coef = Some(state.param);
+
+        Ok(self)
     }
 
-    fn solve_coef(mut self) -> Result<Self, AmitaError> {
-        let iter_state = self.iter_state.clone().ok_or(AmitaError::NotSolved)?;
-        self.results.coef = iter_state.get_param().map(|x| x.clone());
+    fn check_convergence(self) -> Result<Self, AmitaError> {
+        let termination = self.results.termination.ok_or(AmitaError::NotSolved)?;
+        if self.check_convergence {
+            termination.check(self.results.n_iter.unwrap_or(self.max_iter))?;
+        }
 
         Ok(self)
     }
 
     fn solve_hessian(mut self) -> Result<Self, AmitaError> {
         let coef = self.results.coef.clone().ok_or(AmitaError::NotSolved)?;
-        let n_regressors = self.results.n_regressors;
-        let mut hessian_elements = vec![];
-        for i in 0..n_regressors {
-            for j in 0..n_regressors {
-                hessian_elements.push(self.second_order_derivative(&coef, i, j)?);
-            }
-        }
+        let p = self.probabilities(&coef);
 
-        let hessian = Array2::from_shape_vec((n_regressors, n_regressors), hessian_elements).unwrap();
-        self.hessian = Some(hessian);
+        self.hessian = Some(self.mean_hessian(&p));
         Ok( self )
     }
 
@@ -193,40 +370,69 @@ impl LogitSolver {
         let mut coef = self.results.coef.clone().ok_or(AmitaError::NotSolved)?;
         let se = self.results.se.clone().ok_or(AmitaError::NotSolved)?;
 
-        coef.zip_mut_with(&se, |beta, se| *beta = *beta / *se);
+        coef.zip_mut_with(&se, |beta, se| *beta /= *se);
 
         self.results.t = Some(coef);
         Ok( self )
     }
 
+    fn solve_p_vals(mut self) -> Result<Self, AmitaError> {
+        let z = self.results.t.clone().ok_or(AmitaError::NotSolved)?;
+
+        let normal = Normal::new(0., 1.).unwrap();
+        let p_vals = z.map(|x| 2. * ( 1. - normal.cdf(x.abs()) ) );
+
+        self.results.p_vals = Some(p_vals);
+        Ok( self )
+    }
+
     fn solve_non_robust_se(mut self) -> Result<Self, AmitaError> {
         let n_obs = self.results.n_obs as f64;
         let hessian = self.hessian.clone().ok_or(AmitaError::NotSolved)?;
 
         let information_matrix = hessian;
-        let var_cov = information_matrix.qr().unwrap().inverse().unwrap();
+        let var_cov = inverse(&information_matrix, "Hessian of the logit log-likelihood")? / n_obs;
 
-        let se = var_cov.diag().map(|x| x.sqrt() / n_obs.sqrt());
+        let se = var_cov.diag().map(|x| x.sqrt());
         self.results.se = Some(se);
+        self.results.vcov = Some(var_cov);
 
         Ok(self)
     }
 }
 
+// Log-likelihood and its derivatives, all scaled by 1/n
+impl LogitSolver {
+    fn probabilities(&self, param: &Array1<f64>) -> Array1<f64> {
+        self.x.dot(param).map(|z| sigmoid(*z))
+    }
+
+    /// -mean(y z - ln(1 + e^z)) with z = x'β, which stays finite when the
+    /// fitted probabilities saturate at 0 or 1
+    fn mean_neg_loglik(&self, param: &Array1<f64>) -> f64 {
+        let z = self.x.dot(param);
+        let loglik = &self.y * &z - z.map(|z| softplus(*z));
+        - loglik.mean().unwrap()
+    }
+
+    /// X'(p - y) / n
+    fn mean_gradient(&self, p: &Array1<f64>) -> Array1<f64> {
+        self.x.t().dot(&(p - &self.y)) / self.results.n_obs as f64
+    }
+
+    /// X' diag(p(1 - p)) X / n
+    fn mean_hessian(&self, p: &Array1<f64>) -> Array2<f64> {
+        let w = p.map(|x| x * (1. - x));
+        weighted_gramian(self.x.view(), w.view()) / self.results.n_obs as f64
+    }
+}
+
 impl CostFunction for LogitSolver {
     type Param = Array1<f64>;
     type Output = f64;
 
     fn cost(&self, param: &Self::Param) -> Result<Self::Output, argmin::core::Error> {
-        let p = self.x.map_axis(Axis(1), 
-            |row| sigmoid(row.dot(param))
-        );
-
-        let pos = &self.y * p.map(|x| x.ln());
-        let neg = &self.y.map(|x| 1. - x) * p.map(|x| (1. - x).ln());
-        let cost = - (pos + neg).mean().unwrap();
-
-        Ok( cost )
+        Ok( self.mean_neg_loglik(param) )
     }
 }
 
@@ -235,17 +441,7 @@ impl Gradient for LogitSolver {
     type Gradient = Array1<f64>;
 
     fn gradient(&self, param: &Self::Param) -> Result<Self::Gradient, argmin::core::Error> {
-        let p = self.x.map_axis(Axis(1), |row| {
-            sigmoid(row.dot(param))
-        }).into_shape((self.results.n_obs, )).unwrap();
-
-        let px = (&p * &self.x.t())
-            .map_axis(Axis(1), |row| row.mean().unwrap());
-
-        let yx = (&self.y * &self.x.t())
-            .map_axis(Axis(1), |row| row.mean().unwrap());
-
-        Ok(px - yx)
+        Ok( self.mean_gradient(&self.probabilities(param)) )
     }
 }
 
@@ -254,16 +450,7 @@ impl Hessian for LogitSolver {
     type Hessian = Array2<f64>;
 
     fn hessian(&self, param: &Self::Param) -> Result<Self::Hessian, Error> {
-        let n_regressors = self.results.n_regressors;
-        let mut hessian_elements = vec![];
-        for i in 0..n_regressors {
-            for j in 0..n_regressors {
-                hessian_elements.push(self.second_order_derivative(param, i, j)?);
-            }
-        }
-        Ok(
-            Array2::from_shape_vec((n_regressors, n_regressors), hessian_elements).unwrap()
-        )
+        Ok( self.mean_hessian(&self.probabilities(param)) )
     }
 }
 
@@ -276,22 +463,6 @@ impl Operator for LogitSolver {
     }
 }
 
-impl LogitSolver {
-    /// i: the i-th regressor, or the i-th row of the hessian matrix
-    /// j: the j-th regressor, or the j-th row of the hessian matrix
-    fn second_order_derivative(
-        &self, param: &Array1<f64>, i: usize, j: usize
-    ) -> Result<f64, AmitaError>  {
-        let p = self.x.map_axis(Axis(1), 
-            |row| sigmoid(row.dot(param))
-        );
-        let x_i = self.x.slice(s![.., i]).clone();
-        let x_j = self.x.slice(s![.., j]).clone();
-
-        Ok( (&p * p.map(|x| (1. - x)) * x_i * x_j).mean().unwrap() )
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use ndarray::array;
@@ -325,13 +496,142 @@ mod tests {
         let y = array![0, 1, 1, 0, 1];
 
         let logit_solver = LogitSolver::new(&y, &x)?;
-        let logit_solver = logit_solver.solve_coef()?;
+        let logit_solver = logit_solver.run_solver()?;
         println!("{:#?}", logit_solver.results);
-        println!("{:#?}", logit_solver.second_order_derivative(&array![1., 2.], 1, 1));
+        println!("{:#?}", logit_solver.mean_hessian(&logit_solver.probabilities(&array![1., 2.])));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_newton_matches_lbfgs() -> Result<(), AmitaError> {
+        let x = array![
+            [1., 1., 1., 1., 1., 1., 1., 1.,],
+            [3.1, 13.2, -23.5, -4.4, 9.4, 0.7, -2.2, 5.1],
+        ].t().to_owned();
+
+        let y = array![0, 1, 1, 0, 1, 0, 0, 1];
+
+        let lbfgs = LogitSolver::new(&y, &x)?
+            .with_max_tolerance(1e-10)
+            .solve()?
+            .results();
+        let newton = LogitSolver::new(&y, &x)?
+            .with_method(LogitMethod::Newton)
+            .with_max_tolerance(1e-10)
+            .solve()?
+            .results();
+
+        assert_eq!(newton.termination()?, TerminationCondition::SolverConverged);
+        assert!(newton.grad_norm()? < 1e-10);
+        assert!( (lbfgs.coef()? - newton.coef()?).iter().all(|d| d.abs() < 1e-6) );
+        assert!( (lbfgs.se()? - newton.se()?).iter().all(|d| d.abs() < 1e-6) );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_vectorized_hessian() -> Result<(), AmitaError> {
+        let x = array![
+            [1., 1., 1., 1., 1.,],
+            [3.1, 13.2, -23.5, -4.4, 9.4],
+        ].t().to_owned();
+        let y = array![0, 1, 1, 0, 1];
+
+        let solver = LogitSolver::new(&y, &x)?;
+        let param = array![0.3, -0.1];
+        let p = solver.probabilities(&param);
+        let hessian = solver.mean_hessian(&p);
+
+        for i in 0..2 {
+            for j in 0..2 {
+                let expected = (&p * p.map(|x| 1. - x) * x.column(i) * x.column(j)).mean().unwrap();
+                assert!( (hessian[[i, j]] - expected).abs() < 1e-12 );
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_separation_by_combination() -> Result<(), AmitaError> {
+        // y = 1 exactly when x1 + x2 > 0, although neither regressor separates alone
+        let x = array![
+            [1., 1., 1., 1., 1., 1., 1., 1.,],
+            [1.0, -2.0, 0.5, -0.5, 2.0, -1.0, 1.5, -1.5],
+            [-0.5, 1.5, 0.5, -1.0, -1.0, 2.0, -2.0, 0.5],
+        ].t().to_owned();
+        let y = array![1, 0, 1, 0, 1, 1, 0, 0];
+
+        assert!(separating_columns(&y.map(|x| *x as f64), &x).is_empty());
+
+        for method in [LogitMethod::Newton, LogitMethod::LBFGS] {
+            let res = LogitSolver::new(&y, &x)?.with_method(method).solve();
+            assert!(
+                matches!(&res, Err(AmitaError::Separation { columns }) if columns.contains(&1) && columns.contains(&2)),
+                "{method:?}: {res:?}"
+            );
+        }
 
         Ok(())
     }
 
+    #[test]
+    fn test_large_scale_regressor() -> Result<(), AmitaError> {
+        // a steep but identified effect of the calendar year: the extreme
+        // years are perfectly predicted and the coefficient is large per
+        // standard deviation, but its standard error stays small
+        let years = (2000..=2020)
+            .flat_map(|year| std::iter::repeat_n(year as f64, 20))
+            .collect::<Array1<f64>>();
+        let y = years.iter().enumerate()
+            .map(|(i, year)| match *year as i32 {
+                ..=2008 => 0,
+                2009 => (i % 20 < 1) as i32,
+                2010 => (i % 20 < 10) as i32,
+                2011 => (i % 20 < 19) as i32,
+                _ => 1,
+            })
+            .collect::<Array1<i32>>();
+        let x = ndarray::stack![ndarray::Axis(1), Array1::ones(years.len()), years];
+
+        let results = LogitSolver::new(&y, &x)?
+            .with_method(LogitMethod::Newton)
+            .solve()?
+            .results();
+        assert!( (results.coef()?[1] - 2.94).abs() < 0.5 );
+        assert!( results.se()?[1] < 1. );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_not_converged() {
+        let x = array![
+            [1., 1., 1., 1., 1.,],
+            [3.1, 13.2, -23.5, -4.4, 9.4],
+        ].t().to_owned();
+        let y = array![0, 1, 1, 0, 1];
+
+        let res = LogitSolver::new(&y, &x).unwrap()
+            .with_method(LogitMethod::Newton)
+            .with_max_iter(1)
+            .solve();
+
+        assert!(matches!(res, Err(AmitaError::NotConverged { iterations: 1 })));
+
+        // without the check, the fit keeps the last iterate and reports why
+        let results = LogitSolver::new(&y, &x).unwrap()
+            .with_method(LogitMethod::Newton)
+            .with_max_iter(1)
+            .with_convergence_check(false)
+            .solve().unwrap()
+            .results();
+
+        assert_eq!(results.termination().unwrap(), TerminationCondition::MaxIterReached);
+        assert_eq!(results.n_iter().unwrap(), 1);
+    }
+
     #[test]
     fn test_hessian() -> Result<(), AmitaError> {
         let x = array![
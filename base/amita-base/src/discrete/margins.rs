@@ -0,0 +1,355 @@
+//! Marginal effects of single-index discrete-choice models, with
+//! delta-method standard errors.
+
+use amita_error::AmitaError;
+use amita_utils::summary::coef_table;
+use amita_utils::traits::{BaseResults, BaseSolver};
+use ndarray::prelude::*;
+use statrs::distribution::{ContinuousCDF, Normal};
+
+use super::index::IndexFunction;
+
+/// Where the marginal effects are evaluated
+#[derive(Debug, Clone)]
+pub enum MarginsAt {
+    /// Average marginal effects (AME): effects averaged over the sample
+    Average,
+    /// Marginal effects at means (MEM): effects at the sample means of x
+    Means,
+    /// Average marginal effects with the listed regressors `(column, value)`
+    /// fixed at representative values for every observation
+    Values(Vec<(usize, f64)>),
+}
+
+impl MarginsAt {
+    /// Observations at which the effects are evaluated and then averaged
+    pub(crate) fn evaluation_points(&self, x: &Array2<f64>) -> Result<Array2<f64>, AmitaError> {
+        match self {
+            MarginsAt::Average => Ok( x.clone() ),
+            MarginsAt::Means => {
+                let means = x.mean_axis(Axis(0))
+                    .ok_or(AmitaError::NoObservations { matrix_name: "x".to_string() })?;
+                Ok( means.insert_axis(Axis(0)) )
+            },
+            MarginsAt::Values(values) => {
+                let mut x = x.clone();
+                for (column, value) in values {
+                    if *column >= x.shape()[1] {
+                        return Err(AmitaError::ColumnNotFound { column: format!("x[{column}]") });
+                    }
+                    x.column_mut(*column).fill(*value);
+                }
+                Ok( x )
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MarginsResults {
+    n_regressors: usize,
+    at: MarginsAt,
+
+    effects: Option<Array1<f64>>, // dPr(y = 1)/dx, or discrete changes
+    se: Option<Array1<f64>>,
+    z: Option<Array1<f64>>,
+    p_vals: Option<Array1<f64>>,
+    vcov: Option<Array2<f64>>, // delta-method covariance of the effects
+}
+
+impl MarginsResults {
+    pub fn at(&self) -> MarginsAt {
+        self.at.clone()
+    }
+}
+
+impl BaseResults for MarginsResults {
+    fn coef(&self) -> Result<Array1<f64>, AmitaError> {
+        self.effects.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn se(&self) -> Result<Array1<f64>, AmitaError> {
+        self.se.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn t(&self) -> Result<Array1<f64>, AmitaError> {
+        self.z.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn p_vals(&self) -> Result<Array1<f64>, AmitaError> {
+        self.p_vals.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn vcov(&self) -> Result<Array2<f64>, AmitaError> {
+        self.vcov.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn summary(&self) -> Result<String, AmitaError> {
+        coef_table(self)
+    }
+}
+
+/// Computes marginal effects of Pr(y = 1 | x) = F(x'β) for every regressor.
+///
+/// Continuous regressors get the derivative f(x'β)β_j, regressors flagged by
+/// [`MarginsSolver::with_discrete`] get the discrete change
+/// F(x'β | x_j = 1) - F(x'β | x_j = 0). Standard errors follow from the delta
+/// method, V(me) = J V(β) J', with J the Jacobian of the effects w.r.t. β.
+/// The entry for an intercept column carries no meaning and should be ignored.
+#[derive(Debug, Clone)]
+pub struct MarginsSolver<F: IndexFunction> {
+    x: Array2<f64>,
+    coef: Array1<f64>,
+    coef_vcov: Array2<f64>,
+    index: F,
+
+    discrete: Vec<usize>,
+    jacobian: Option<Array2<f64>>,
+
+    results: MarginsResults,
+}
+
+impl<F: IndexFunction> MarginsSolver<F> {
+    pub fn new(
+        x: &Array2<f64>,
+        coef: &Array1<f64>,
+        coef_vcov: &Array2<f64>,
+        index: F,
+    ) -> Result<Self, AmitaError> {
+        let n_regressors = x.shape()[1];
+
+        if coef.len() != n_regressors
+            || coef_vcov.shape() != [n_regressors, n_regressors] {
+            return Err(AmitaError::NotSameObservations);
+        }
+
+        let results = MarginsResults {
+            n_regressors,
+            at: MarginsAt::Average,
+
+            effects: None,
+            se: None,
+            z: None,
+            p_vals: None,
+            vcov: None,
+        };
+
+        Ok( Self {
+            x: x.to_owned(),
+            coef: coef.to_owned(),
+            coef_vcov: coef_vcov.to_owned(),
+            index,
+
+            discrete: vec![],
+            jacobian: None,
+
+            results,
+        } )
+    }
+
+    pub fn with_at(mut self, at: MarginsAt) -> Self {
+        self.results.at = at;
+        self
+    }
+
+    /// Columns of dummy regressors, whose effects are computed as discrete
+    /// changes from 0 to 1 instead of derivatives
+    pub fn with_discrete(mut self, columns: Vec<usize>) -> Self {
+        self.discrete = columns;
+        self
+    }
+}
+
+impl<F: IndexFunction> BaseSolver<MarginsResults> for MarginsSolver<F> {
+    fn results(&self) -> MarginsResults {
+        self.results.clone()
+    }
+
+    fn solve(self) -> Result<Self, AmitaError> {
+        self
+        .solve_effects()?
+        .solve_se()?
+        .solve_z_pvals()
+    }
+}
+
+impl<F: IndexFunction> MarginsSolver<F> {
+    fn solve_effects(mut self) -> Result<Self, AmitaError> {
+        let k = self.results.n_regressors;
+        let x = self.results.at.evaluation_points(&self.x)?;
+        let n = x.shape()[0] as f64;
+
+        let z = x.dot(&self.coef);
+        let f = z.map(|z| self.index.pdf(*z));
+        let f_prime = z.map(|z| self.index.pdf_derivative(*z));
+
+        // continuous regressors:
+        // me_j = mean(f(z)) β_j
+        // d me_j / d β_l = mean(f'(z) x_l) β_j + 1{j = l} mean(f(z))
+        let mean_f = f.sum() / n;
+        let mean_f_prime_x = x.t().dot(&f_prime) / n;
+
+        let mut effects = &self.coef * mean_f;
+        let mut jacobian = self.coef.clone().insert_axis(Axis(1))
+            .dot(&mean_f_prime_x.insert_axis(Axis(0)))
+            + Array2::<f64>::eye(k) * mean_f;
+
+        // dummy regressors:
+        // me_j = mean(F(z1) - F(z0)), d me_j / d β = mean(f(z1) x1 - f(z0) x0)
+        for &j in self.discrete.iter() {
+            if j >= k {
+                return Err(AmitaError::ColumnNotFound { column: format!("x[{j}]") });
+            }
+
+            let mut x_one = x.clone();
+            x_one.column_mut(j).fill(1.);
+            let mut x_zero = x.clone();
+            x_zero.column_mut(j).fill(0.);
+
+            let z_one = x_one.dot(&self.coef);
+            let z_zero = x_zero.dot(&self.coef);
+
+            let change = z_one.iter().zip(z_zero.iter())
+                .map(|(z1, z0)| self.index.cdf(*z1) - self.index.cdf(*z0))
+                .sum::<f64>() / n;
+
+            let f_one = z_one.map(|z| self.index.pdf(*z));
+            let f_zero = z_zero.map(|z| self.index.pdf(*z));
+            let gradient = ( x_one.t().dot(&f_one) - x_zero.t().dot(&f_zero) ) / n;
+
+            effects[j] = change;
+            jacobian.row_mut(j).assign(&gradient);
+        }
+
+        self.results.effects = Some(effects);
+        self.jacobian = Some(jacobian);
+
+        Ok(self)
+    }
+
+    fn solve_se(mut self) -> Result<Self, AmitaError> {
+        let jacobian = self.jacobian.clone().ok_or(AmitaError::NotSolved)?;
+
+        let vcov = jacobian.dot(&self.coef_vcov).dot(&jacobian.t());
+        let se = vcov.diag().map(|x| x.sqrt());
+
+        self.results.se = Some(se);
+        self.results.vcov = Some(vcov);
+
+        Ok(self)
+    }
+
+    fn solve_z_pvals(mut self) -> Result<Self, AmitaError> {
+        let effects = self.results.effects.clone().ok_or(AmitaError::NotSolved)?;
+        let se = self.results.se.clone().ok_or(AmitaError::NotSolved)?;
+
+        let z = effects / se;
+
+        let normal = Normal::new(0., 1.).unwrap();
+        let p_vals = z.map(|x| 2. * ( 1. - normal.cdf(x.abs()) ) );
+
+        self.results.z = Some(z);
+        self.results.p_vals = Some(p_vals);
+
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+    use crate::discrete::index::Logistic;
+
+    fn data() -> (Array2<f64>, Array1<f64>, Array2<f64>) {
+        let x = array![
+            [1., 0.5, 0.],
+            [1., -1.2, 1.],
+            [1., 2.3, 1.],
+            [1., 0.1, 0.],
+            [1., -0.7, 1.],
+            [1., 1.4, 0.],
+        ];
+        let coef = array![0.2, 0.8, -0.5];
+        let vcov = array![
+            [0.30, 0.02, -0.05],
+            [0.02, 0.10, 0.01],
+            [-0.05, 0.01, 0.40],
+        ];
+        (x, coef, vcov)
+    }
+
+    /// effects as a plain function of β, used to check the analytic Jacobian
+    fn average_effects(x: &Array2<f64>, coef: &Array1<f64>) -> Array1<f64> {
+        let n = x.shape()[0] as f64;
+        let z = x.dot(coef);
+        let mean_f = z.map(|z| Logistic.pdf(*z)).sum() / n;
+
+        let mut effects = coef * mean_f;
+
+        let mut x_one = x.clone();
+        x_one.column_mut(2).fill(1.);
+        let mut x_zero = x.clone();
+        x_zero.column_mut(2).fill(0.);
+        effects[2] = ( x_one.dot(coef).map(|z| Logistic.cdf(*z))
+            - x_zero.dot(coef).map(|z| Logistic.cdf(*z)) ).sum() / n;
+
+        effects
+    }
+
+    #[test]
+    fn test_average_effects_delta_method() -> Result<(), AmitaError> {
+        let (x, coef, vcov) = data();
+
+        let results = MarginsSolver::new(&x, &coef, &vcov, Logistic)?
+            .with_discrete(vec![2])
+            .solve()?
+            .results();
+
+        let effects = results.coef()?;
+        let expected = average_effects(&x, &coef);
+        assert!( (&effects - &expected).iter().all(|d| d.abs() < 1e-12) );
+
+        // numerical Jacobian
+        let h = 1e-6;
+        let mut jacobian = Array2::<f64>::zeros((3, 3));
+        for l in 0..3 {
+            let mut up = coef.clone();
+            up[l] += h;
+            let mut down = coef.clone();
+            down[l] -= h;
+            let column = ( average_effects(&x, &up) - average_effects(&x, &down) ) / (2. * h);
+            jacobian.column_mut(l).assign(&column);
+        }
+        let expected_vcov = jacobian.dot(&vcov).dot(&jacobian.t());
+
+        assert!( (results.vcov()? - expected_vcov).iter().all(|d| d.abs() < 1e-8) );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_effects_at_means() -> Result<(), AmitaError> {
+        let (x, coef, vcov) = data();
+
+        let results = MarginsSolver::new(&x, &coef, &vcov, Logistic)?
+            .with_at(MarginsAt::Means)
+            .solve()?
+            .results();
+
+        let means = x.mean_axis(Axis(0)).unwrap();
+        let f = Logistic.pdf(means.dot(&coef));
+
+        let effects = results.coef()?;
+        assert!( (effects[1] - f * coef[1]).abs() < 1e-12 );
+
+        // there are no means of an empty sample
+        let empty = MarginsSolver::new(&Array2::zeros((0, coef.len())), &coef, &vcov, Logistic)?
+            .with_at(MarginsAt::Means)
+            .solve();
+        assert!( matches!(empty, Err(AmitaError::NoObservations { .. })) );
+
+        Ok(())
+    }
+}
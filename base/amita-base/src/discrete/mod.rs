@@ -1 +1,9 @@
-pub mod logit;
\ No newline at end of file
+pub mod clogit;
+pub mod count;
+pub mod firth;
+pub mod index;
+pub mod logit;
+pub mod margins;
+pub mod mlogit;
+pub mod ordered;
+pub mod separation;
@@ -0,0 +1,623 @@
+//! Ordered logit and ordered probit for ordinal outcomes.
+//!
+//! With categories 1, ..., J and cutpoints κ_1 < ... < κ_{J-1},
+//! Pr(y = j | x) = F(κ_j - x'β) - F(κ_{j-1} - x'β), where κ_0 = -∞ and κ_J = ∞.
+//! The regressors must not include a constant, which is absorbed by the
+//! cutpoints. Cutpoints are estimated as κ_1 and log-increments
+//! ln(κ_j - κ_{j-1}), so that their ordering holds at every iteration.
+
+use std::collections::BTreeSet;
+
+use amita_error::AmitaError;
+use amita_utils::hypothesis::TestStatistic;
+use amita_utils::iterations::{NewtonRaphson, TerminationCondition};
+use amita_utils::linalg::inverse;
+use amita_utils::math::{numerical_hessian, numerical_jacobian};
+use amita_utils::summary::coef_table;
+use amita_utils::traits::{BaseResults, BaseSolver};
+use ndarray::prelude::*;
+use statrs::distribution::{ContinuousCDF, Normal};
+
+use super::index::{IndexFunction, Logistic, StandardNormal};
+use super::logit::{LogitMethod, LogitSolver};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderedLink {
+    Logit,
+    Probit,
+}
+
+impl IndexFunction for OrderedLink {
+    fn cdf(&self, z: f64) -> f64 {
+        match self {
+            OrderedLink::Logit => Logistic.cdf(z),
+            OrderedLink::Probit => StandardNormal.cdf(z),
+        }
+    }
+
+    fn pdf(&self, z: f64) -> f64 {
+        match self {
+            OrderedLink::Logit => Logistic.pdf(z),
+            OrderedLink::Probit => StandardNormal.pdf(z),
+        }
+    }
+
+    fn pdf_derivative(&self, z: f64) -> f64 {
+        match self {
+            OrderedLink::Logit => Logistic.pdf_derivative(z),
+            OrderedLink::Probit => StandardNormal.pdf_derivative(z),
+        }
+    }
+
+    fn inverse_cdf(&self, p: f64) -> f64 {
+        match self {
+            OrderedLink::Logit => Logistic.inverse_cdf(p),
+            OrderedLink::Probit => StandardNormal.inverse_cdf(p),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OrderedResults {
+    n_obs: usize,
+    n_regressors: usize,
+    link: OrderedLink,
+    categories: Vec<i32>, // sorted outcome values
+
+    coef: Option<Array1<f64>>, // slopes β followed by cutpoints κ
+    se: Option<Array1<f64>>,
+    t: Option<Array1<f64>>, // z statistics
+    p_vals: Option<Array1<f64>>,
+    vcov: Option<Array2<f64>>,
+
+    loglik: Option<f64>,
+    n_iter: Option<u64>,
+    termination: Option<TerminationCondition>,
+}
+
+impl OrderedResults {
+    pub fn link(&self) -> OrderedLink {
+        self.link
+    }
+
+    pub fn categories(&self) -> Vec<i32> {
+        self.categories.clone()
+    }
+
+    pub fn slopes(&self) -> Result<Array1<f64>, AmitaError> {
+        Ok( self.coef()?.slice(s![..self.n_regressors]).to_owned() )
+    }
+
+    pub fn cutpoints(&self) -> Result<Array1<f64>, AmitaError> {
+        Ok( self.coef()?.slice(s![self.n_regressors..]).to_owned() )
+    }
+
+    /// Predicted probabilities of each category, n×J
+    pub fn predicted_probabilities(&self, x: &Array2<f64>) -> Result<Array2<f64>, AmitaError> {
+        if x.shape()[1] != self.n_regressors {
+            return Err(AmitaError::NotSameObservations);
+        }
+
+        let index = x.dot(&self.slopes()?);
+        Ok( category_probabilities(self.link, &index, &self.cutpoints()?) )
+    }
+
+    pub fn loglik(&self) -> Result<f64, AmitaError> {
+        self.loglik.ok_or(AmitaError::NotSolved)
+    }
+
+    pub fn n_iter(&self) -> Result<u64, AmitaError> {
+        self.n_iter.ok_or(AmitaError::NotSolved)
+    }
+
+    pub fn termination(&self) -> Result<TerminationCondition, AmitaError> {
+        self.termination.ok_or(AmitaError::NotSolved)
+    }
+}
+
+impl BaseResults for OrderedResults {
+    fn coef(&self) -> Result<Array1<f64>, AmitaError> {
+        self.coef.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn se(&self) -> Result<Array1<f64>, AmitaError> {
+        self.se.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn t(&self) -> Result<Array1<f64>, AmitaError> {
+        self.t.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn p_vals(&self) -> Result<Array1<f64>, AmitaError> {
+        self.p_vals.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn vcov(&self) -> Result<Array2<f64>, AmitaError> {
+        self.vcov.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn summary(&self) -> Result<String, AmitaError> {
+        coef_table(self)
+    }
+}
+
+/// Average marginal effects on the probability of each category
+#[derive(Debug, Clone)]
+pub struct OrderedMargins {
+    effects: Array2<f64>, // J×k, dPr(y = j)/dx_k averaged over the sample
+    se: Array2<f64>,
+}
+
+impl OrderedMargins {
+    pub fn effects(&self) -> Array2<f64> {
+        self.effects.clone()
+    }
+
+    pub fn se(&self) -> Array2<f64> {
+        self.se.clone()
+    }
+}
+
+/// Brant test of the parallel-lines assumption
+#[derive(Debug, Clone)]
+pub struct BrantTest {
+    all: TestStatistic,
+    by_regressor: Vec<TestStatistic>,
+}
+
+impl BrantTest {
+    /// Joint test that all slopes are equal across the cumulative logits
+    pub fn all(&self) -> TestStatistic {
+        self.all.clone()
+    }
+
+    /// Tests of equal slopes, regressor by regressor
+    pub fn by_regressor(&self) -> Vec<TestStatistic> {
+        self.by_regressor.clone()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OrderedSolver {
+    y: Array1<usize>, // category index 0, ..., J - 1
+    x: Array2<f64>,
+
+    max_iter: u64,
+    max_tolerance: f64,
+
+    results: OrderedResults,
+}
+
+impl OrderedSolver {
+    pub fn new(
+        y: &Array1<i32>,
+        x: &Array2<f64>,
+        link: OrderedLink,
+    ) -> Result<Self, AmitaError> {
+        if x.shape()[0] != y.shape()[0] {
+            return Err(AmitaError::NotSameObservations);
+        }
+
+        let categories = y.iter().copied().collect::<BTreeSet<i32>>().into_iter().collect::<Vec<_>>();
+        if categories.len() < 2 {
+            return Err(AmitaError::InvalidOutcome { outcome: "y takes a single value".to_string() });
+        }
+
+        let y = y.map(|y_i| categories.iter().position(|c| c == y_i).unwrap());
+
+        let results = OrderedResults {
+            n_obs: x.shape()[0],
+            n_regressors: x.shape()[1],
+            link,
+            categories,
+
+            coef: None,
+            se: None,
+            t: None,
+            p_vals: None,
+            vcov: None,
+
+            loglik: None,
+            n_iter: None,
+            termination: None,
+        };
+
+        Ok( Self {
+            y,
+            x: x.to_owned(),
+
+            max_iter: 100,
+            max_tolerance: 1e-8,
+
+            results,
+        } )
+    }
+
+    pub fn with_max_iter(mut self, max_iter: u64) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    pub fn with_max_tolerance(mut self, max_tolerance: f64) -> Self {
+        self.max_tolerance = max_tolerance;
+        self
+    }
+}
+
+impl BaseSolver<OrderedResults> for OrderedSolver {
+    fn results(&self) -> OrderedResults {
+        self.results.clone()
+    }
+
+    fn solve(self) -> Result<Self, AmitaError> {
+        self
+        .run_solver()?
+        .solve_se()?
+        .solve_z_pvals()
+    }
+}
+
+impl OrderedSolver {
+    fn n_categories(&self) -> usize {
+        self.results.categories.len()
+    }
+
+    fn run_solver(mut self) -> Result<Self, AmitaError> {
+        let k = self.results.n_regressors;
+        let n = self.results.n_obs as f64;
+
+        // start at β = 0 with cutpoints matching the cumulative sample shares
+        let mut init_param = Array1::zeros(k + self.n_categories() - 1);
+        let mut cumulative = 0.;
+        let mut previous_kappa = 0.;
+        for j in 0..self.n_categories() - 1 {
+            cumulative += self.y.iter().filter(|y| **y == j).count() as f64 / n;
+            let kappa = self.results.link.inverse_cdf(cumulative);
+            init_param[k + j] = if j == 0 { kappa } else { (kappa - previous_kappa).ln() };
+            previous_kappa = kappa;
+        }
+
+        let state = NewtonRaphson::new(self.max_iter, self.max_tolerance)
+            .minimize(init_param, |theta| {
+                let (loglik, gradient) = self.theta_loglik(theta);
+                let hessian = numerical_hessian(|theta| -self.theta_loglik(theta).1 / n, theta);
+                Ok( (-loglik / n, -gradient / n, hessian) )
+            })?;
+
+        state.termination.check(state.n_iter)?;
+
+        let (beta, kappa) = self.theta_to_params(&state.param);
+        let mut coef = beta.to_vec();
+        coef.extend(kappa.iter());
+
+        self.results.loglik = Some(- state.cost * n);
+        self.results.n_iter = Some(state.n_iter);
+        self.results.termination = Some(state.termination);
+        self.results.coef = Some(Array1::from(coef));
+
+        Ok(self)
+    }
+
+    fn solve_se(mut self) -> Result<Self, AmitaError> {
+        let coef = self.results.coef.clone().ok_or(AmitaError::NotSolved)?;
+
+        let hessian = numerical_hessian(|param| -self.params_loglik(param).1, &coef);
+        let vcov = inverse(&hessian, "Hessian of the ordered model log-likelihood")?;
+        let se = vcov.diag().map(|x| x.sqrt());
+
+        self.results.se = Some(se);
+        self.results.vcov = Some(vcov);
+
+        Ok(self)
+    }
+
+    fn solve_z_pvals(mut self) -> Result<Self, AmitaError> {
+        let coef = self.results.coef.clone().ok_or(AmitaError::NotSolved)?;
+        let se = self.results.se.clone().ok_or(AmitaError::NotSolved)?;
+
+        let z = coef / se;
+
+        let normal = Normal::new(0., 1.).unwrap();
+        let p_vals = z.map(|x| 2. * ( 1. - normal.cdf(x.abs()) ) );
+
+        self.results.t = Some(z);
+        self.results.p_vals = Some(p_vals);
+
+        Ok(self)
+    }
+}
+
+impl OrderedSolver {
+    /// Average marginal effects of each regressor on the probability of each
+    /// category, dPr(y = j)/dx = -(f(κ_j - x'β) - f(κ_{j-1} - x'β)) β, with
+    /// delta-method standard errors
+    pub fn margins(&self) -> Result<OrderedMargins, AmitaError> {
+        let coef = self.results.coef()?;
+        let vcov = self.results.vcov()?;
+        let k = self.results.n_regressors;
+        let n_categories = self.n_categories();
+
+        let effects = |param: &Array1<f64>| -> Array1<f64> {
+            let beta = param.slice(s![..k]);
+            let kappa = param.slice(s![k..]);
+            let index = self.x.dot(&beta);
+
+            let mut effects = Array2::zeros((n_categories, k));
+            for j in 0..n_categories {
+                let density = index.map(|z| {
+                    let upper = if j < n_categories - 1 { self.results.link.pdf(kappa[j] - z) } else { 0. };
+                    let lower = if j > 0 { self.results.link.pdf(kappa[j - 1] - z) } else { 0. };
+                    upper - lower
+                });
+                let mean_density = density.mean().unwrap();
+                effects.row_mut(j).assign(&beta.map(|b| -mean_density * b));
+            }
+            effects.into_shape(n_categories * k).unwrap()
+        };
+
+        let jacobian = numerical_jacobian(effects, &coef);
+        let se = jacobian.dot(&vcov).dot(&jacobian.t()).diag().map(|x| x.sqrt());
+
+        Ok( OrderedMargins {
+            effects: effects(&coef).into_shape((n_categories, k)).unwrap(),
+            se: se.into_shape((n_categories, k)).unwrap(),
+        } )
+    }
+
+    /// Brant test of parallel lines for the ordered logit.
+    ///
+    /// Separate binary logits of 1{y > j} are fitted for j = 1, ..., J - 1 and
+    /// the equality of their slopes is tested by a Wald test, using the
+    /// covariance between binary estimates derived by Brant (1990).
+    pub fn brant(&self) -> Result<BrantTest, AmitaError> {
+        if self.results.link != OrderedLink::Logit {
+            return Err(AmitaError::InvalidOutcome { outcome: "the Brant test requires an ordered logit".to_string() });
+        }
+        if self.n_categories() < 3 {
+            return Err(AmitaError::InvalidOutcome { outcome: "the Brant test requires at least 3 categories".to_string() });
+        }
+
+        let k = self.results.n_regressors;
+        let n_equations = self.n_categories() - 1;
+        let x = ndarray::concatenate![Axis(1), Array2::ones((self.results.n_obs, 1)), self.x];
+
+        let mut slopes = vec![];
+        let mut fitted = vec![];
+        let mut information_inverse = vec![];
+        for j in 0..n_equations {
+            let y_j = self.y.map(|y| if *y > j { 1 } else { 0 });
+            let results = LogitSolver::new(&y_j, &x)?
+                .with_method(LogitMethod::Newton)
+                .with_max_tolerance(1e-10)
+                .solve()?
+                .results();
+
+            let coef = results.coef()?;
+            let pi = x.dot(&coef).map(|z| Logistic.cdf(*z));
+
+            slopes.extend(coef.slice(s![1..]).iter().copied());
+            information_inverse.push(results.vcov()?);
+            fitted.push(pi);
+        }
+
+        // covariance of the stacked slopes
+        let mut vcov = Array2::zeros((n_equations * k, n_equations * k));
+        for m in 0..n_equations {
+            for l in m..n_equations {
+                let block = if m == l {
+                    information_inverse[m].clone()
+                } else {
+                    let w = &fitted[l] * &fitted[m].map(|p| 1. - p);
+                    let xw = &x * &w.insert_axis(Axis(1));
+                    information_inverse[m].dot(&xw.t().dot(&x)).dot(&information_inverse[l])
+                };
+                let block = block.slice(s![1.., 1..]).to_owned();
+
+                vcov.slice_mut(s![m * k..(m + 1) * k, l * k..(l + 1) * k]).assign(&block);
+                vcov.slice_mut(s![l * k..(l + 1) * k, m * k..(m + 1) * k]).assign(&block.t());
+            }
+        }
+        let slopes = Array1::from(slopes);
+
+        // H0: β_1 = β_m for m = 2, ..., J - 1, on the given regressors
+        let wald = |regressors: &[usize], name: &str| -> Result<TestStatistic, AmitaError> {
+            let q = regressors.len();
+            let mut d = Array2::zeros(((n_equations - 1) * q, n_equations * k));
+            for m in 1..n_equations {
+                for (r, v) in regressors.iter().enumerate() {
+                    d[[(m - 1) * q + r, *v]] = 1.;
+                    d[[(m - 1) * q + r, m * k + v]] = -1.;
+                }
+            }
+            let diff = d.dot(&slopes);
+            let statistic = diff.dot(&inverse(&d.dot(&vcov).dot(&d.t()), "covariance of slope differences")?.dot(&diff));
+            Ok( TestStatistic::chi_squared(name, statistic, diff.len() as f64) )
+        };
+
+        let all = wald(&(0..k).collect::<Vec<_>>(), "Brant: all")?;
+        let by_regressor = (0..k)
+            .map(|v| wald(&[v], &format!("Brant: x[{v}]")))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok( BrantTest { all, by_regressor } )
+    }
+}
+
+// Log-likelihood in the parameters (β, κ) and in the optimization parameters
+// (β, κ_1, ln(κ_2 - κ_1), ...)
+impl OrderedSolver {
+    fn theta_to_params(&self, theta: &Array1<f64>) -> (Array1<f64>, Array1<f64>) {
+        let k = self.results.n_regressors;
+        let beta = theta.slice(s![..k]).to_owned();
+
+        let mut kappa = Array1::zeros(self.n_categories() - 1);
+        for j in 0..kappa.len() {
+            kappa[j] = if j == 0 { theta[k] } else { kappa[j - 1] + theta[k + j].exp() };
+        }
+
+        (beta, kappa)
+    }
+
+    /// Log-likelihood and its gradient w.r.t. (β, κ)
+    fn loglik(&self, beta: ArrayView1<f64>, kappa: ArrayView1<f64>) -> (f64, Array1<f64>) {
+        let k = self.results.n_regressors;
+        let n_cutpoints = kappa.len();
+        let link = self.results.link;
+        let index = self.x.dot(&beta);
+
+        let mut loglik = 0.;
+        let mut gradient = Array1::zeros(k + n_cutpoints);
+        for (i, y_i) in self.y.iter().enumerate() {
+            let (upper_cdf, upper_pdf) = if *y_i < n_cutpoints {
+                let a = kappa[*y_i] - index[i];
+                (link.cdf(a), link.pdf(a))
+            } else {
+                (1., 0.)
+            };
+            let (lower_cdf, lower_pdf) = if *y_i > 0 {
+                let b = kappa[*y_i - 1] - index[i];
+                (link.cdf(b), link.pdf(b))
+            } else {
+                (0., 0.)
+            };
+
+            let p = (upper_cdf - lower_cdf).max(f64::MIN_POSITIVE);
+            loglik += p.ln();
+
+            let d_index = -(upper_pdf - lower_pdf) / p;
+            gradient.slice_mut(s![..k]).scaled_add(d_index, &self.x.row(i));
+            if *y_i < n_cutpoints {
+                gradient[k + *y_i] += upper_pdf / p;
+            }
+            if *y_i > 0 {
+                gradient[k + *y_i - 1] -= lower_pdf / p;
+            }
+        }
+
+        (loglik, gradient)
+    }
+
+    fn params_loglik(&self, param: &Array1<f64>) -> (f64, Array1<f64>) {
+        let k = self.results.n_regressors;
+        self.loglik(param.slice(s![..k]), param.slice(s![k..]))
+    }
+
+    /// Log-likelihood and its gradient w.r.t. the optimization parameters,
+    /// by the chain rule dκ_m/dθ_{k+l} = exp(θ_{k+l}) for 1 <= l <= m
+    fn theta_loglik(&self, theta: &Array1<f64>) -> (f64, Array1<f64>) {
+        let k = self.results.n_regressors;
+        let (beta, kappa) = self.theta_to_params(theta);
+        let (loglik, gradient) = self.loglik(beta.view(), kappa.view());
+
+        let mut theta_gradient = gradient.clone();
+        let n_cutpoints = kappa.len();
+        for l in 0..n_cutpoints {
+            let tail = gradient.slice(s![k + l..]).sum();
+            theta_gradient[k + l] = if l == 0 { tail } else { theta[k + l].exp() * tail };
+        }
+
+        (loglik, theta_gradient)
+    }
+}
+
+/// Pr(y = j) = F(κ_j - z) - F(κ_{j-1} - z) for each index z, n×J
+fn category_probabilities(
+    link: OrderedLink,
+    index: &Array1<f64>,
+    cutpoints: &Array1<f64>,
+) -> Array2<f64> {
+    let n_categories = cutpoints.len() + 1;
+    let mut probabilities = Array2::zeros((index.len(), n_categories));
+
+    for (i, z) in index.iter().enumerate() {
+        let mut lower = 0.;
+        for j in 0..n_categories {
+            let upper = if j < n_categories - 1 { link.cdf(cutpoints[j] - z) } else { 1. };
+            probabilities[[i, j]] = upper - lower;
+            lower = upper;
+        }
+    }
+
+    probabilities
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+
+    fn data() -> (Array1<i32>, Array2<f64>) {
+        let n = 60;
+        let mut x = Array2::zeros((n, 2));
+        let mut y = Array1::zeros(n);
+        for i in 0..n {
+            let t = i as f64;
+            x[[i, 0]] = (t * 0.37).sin() * 2.;
+            x[[i, 1]] = (t * 0.91).cos();
+            let latent = 0.8 * x[[i, 0]] - 0.5 * x[[i, 1]] + (t * 2.3).sin() * 1.5;
+            y[i] = if latent < -1. { 1 } else if latent < 0.3 { 2 } else if latent < 1.4 { 3 } else { 4 };
+        }
+        (y, x)
+    }
+
+    #[test]
+    fn test_two_categories_equal_logit() -> Result<(), AmitaError> {
+        let (y, x) = data();
+        let y = y.map(|y| if *y > 2 { 1 } else { 0 });
+
+        let ordered = OrderedSolver::new(&y, &x, OrderedLink::Logit)?.solve()?.results();
+
+        let x_const = ndarray::concatenate![Axis(1), Array2::ones((60, 1)), x];
+        let logit = LogitSolver::new(&y, &x_const)?
+            .with_method(LogitMethod::Newton)
+            .with_max_tolerance(1e-10)
+            .solve()?
+            .results();
+
+        // the cutpoint is minus the logit intercept
+        let expected = array![logit.coef()?[1], logit.coef()?[2], -logit.coef()?[0]];
+        assert!( (ordered.coef()? - &expected).iter().all(|d| d.abs() < 1e-5) );
+        assert!( (ordered.se()? - logit.se()?.select(Axis(0), &[1, 2, 0])).iter().all(|d| d.abs() < 1e-4) );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ordered_probit() -> Result<(), AmitaError> {
+        let (y, x) = data();
+
+        let solver = OrderedSolver::new(&y, &x, OrderedLink::Probit)?.solve()?;
+        let results = solver.results();
+
+        let cutpoints = results.cutpoints()?;
+        assert_eq!(cutpoints.len(), 3);
+        assert!(cutpoints[0] < cutpoints[1] && cutpoints[1] < cutpoints[2]);
+
+        let (_, gradient) = solver.params_loglik(&results.coef()?);
+        assert!(gradient.iter().all(|g| g.abs() < 1e-5));
+
+        let probabilities = results.predicted_probabilities(&x)?;
+        assert!(probabilities.rows().into_iter().all(|row| (row.sum() - 1.).abs() < 1e-12));
+
+        // effects on the category probabilities sum to zero
+        let margins = solver.margins()?;
+        assert!(margins.effects().sum_axis(Axis(0)).iter().all(|e| e.abs() < 1e-12));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_brant() -> Result<(), AmitaError> {
+        let (y, x) = data();
+
+        let solver = OrderedSolver::new(&y, &x, OrderedLink::Logit)?.solve()?;
+        let brant = solver.brant()?;
+
+        assert_eq!(brant.all().df(), vec![4.]);
+        assert_eq!(brant.by_regressor().len(), 2);
+        assert!(brant.by_regressor().iter().all(|test| test.df() == vec![2.]));
+
+        Ok(())
+    }
+}
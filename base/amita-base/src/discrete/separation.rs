@@ -0,0 +1,140 @@
+//! Detection of perfect and quasi-complete separation in binary outcome models.
+//!
+//! With separation the maximum likelihood estimates do not exist: the
+//! log-likelihood keeps increasing as some coefficients diverge. Two checks
+//! are provided. [`separating_columns`] is exact for separation by a single
+//! regressor and runs before estimation. [`diverging_columns`] inspects a
+//! fitted coefficient vector for the blow-up that separation by a linear
+//! combination of regressors produces.
+
+use ndarray::{Array1, Array2};
+
+use amita_utils::linalg::inverse;
+use amita_utils::math::sigmoid;
+use amita_utils::parallel::weighted_gramian;
+
+/// Standardized log-odds |β_j| s_j, with s_j the standard deviation of x_j,
+/// above which a coefficient is considered to be diverging. The intercept,
+/// with s_j = 0, never is: its size only reflects the location of the other
+/// regressors, such as a calendar year, or how rare the outcome is.
+const DIVERGENCE_THRESHOLD: f64 = 10.;
+
+/// Standardized standard error se_j s_j above which the information about a
+/// coefficient is considered to have vanished
+const SE_THRESHOLD: f64 = 10.;
+
+/// Fitted probabilities this close to 0 or 1 are considered perfectly predicted
+const SATURATION_TOLERANCE: f64 = 1e-8;
+
+/// Non-constant columns of `x` whose values alone perfectly or
+/// quasi-perfectly separate `y`, i.e. there is a threshold c such that
+/// x_j <= c for all y = 0 and x_j >= c for all y = 1, or vice versa.
+pub fn separating_columns(
+    y: &Array1<f64>,
+    x: &Array2<f64>,
+) -> Vec<usize> {
+    let mut columns = vec![];
+
+    for (j, x_j) in x.columns().into_iter().enumerate() {
+        let (mut min_0, mut max_0) = (f64::INFINITY, f64::NEG_INFINITY);
+        let (mut min_1, mut max_1) = (f64::INFINITY, f64::NEG_INFINITY);
+
+        for (y_i, x_ij) in y.iter().zip(x_j.iter()) {
+            if *y_i > 0.5 {
+                min_1 = min_1.min(*x_ij);
+                max_1 = max_1.max(*x_ij);
+            } else {
+                min_0 = min_0.min(*x_ij);
+                max_0 = max_0.max(*x_ij);
+            }
+        }
+
+        let is_constant = min_0.min(min_1) == max_0.max(max_1);
+        if is_constant {
+            continue;
+        }
+
+        if max_0 <= min_1 || max_1 <= min_0 {
+            columns.push(j);
+        }
+    }
+
+    columns
+}
+
+/// Columns whose fitted coefficients diverge while some observations are
+/// perfectly predicted, the signature of separation by a combination of
+/// regressors: both the coefficient and its standard error are large on the
+/// scale of the regressor. A large but well-identified coefficient, such as
+/// one on a calendar year, keeps a small standard error. Returns an empty
+/// vector when no observation is saturated.
+pub fn diverging_columns(
+    y: &Array1<f64>,
+    x: &Array2<f64>,
+    coef: &Array1<f64>,
+) -> Vec<usize> {
+    let p = x.dot(coef).mapv(sigmoid);
+    let is_saturated = p.iter().zip(y.iter())
+        .any(|(p, y)| {
+            ( *y > 0.5 && 1. - p < SATURATION_TOLERANCE )
+                || ( *y < 0.5 && *p < SATURATION_TOLERANCE )
+        });
+
+    if !is_saturated {
+        return vec![];
+    }
+
+    // without information in some direction the inverse does not exist,
+    // and every standard error is taken to have blown up
+    let information = weighted_gramian(x.view(), p.mapv(|p| p * (1. - p)).view());
+    let variances = inverse(&information, "logit information matrix")
+        .map(|inverse| inverse.diag().to_owned())
+        .unwrap_or_else(|_| Array1::from_elem(coef.len(), f64::INFINITY));
+
+    x.columns().into_iter().zip(coef.iter()).zip(variances.iter())
+        .enumerate()
+        .filter(|(_, ((x_j, beta), variance))| {
+            let scale = x_j.std(0.);
+            let se = variance.sqrt();
+            beta.abs() * scale > DIVERGENCE_THRESHOLD && (se.is_nan() || se * scale > SE_THRESHOLD)
+        })
+        .map(|(j, _)| j)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+
+    #[test]
+    fn test_separating_columns() {
+        let y = array![0., 0., 1., 1., 0., 1.];
+        let x = array![
+            [1., -2.0, 0., 0.3],
+            [1., -1.0, 0., -0.4],
+            [1., 0.5, 1., 0.1],
+            [1., 1.5, 0., 0.9],
+            [1., -0.5, 0., -0.2],
+            [1., 0.5, 1., -0.6],
+        ];
+
+        // column 1 separates completely, column 2 quasi-completely (x = 1 => y = 1)
+        assert_eq!(separating_columns(&y, &x), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_diverging_columns() {
+        let y = array![0., 1., 1., 0.];
+        let x = array![
+            [1., -1.],
+            [1., 1.],
+            [1., 2.],
+            [1., -2.],
+        ];
+
+        assert_eq!(diverging_columns(&y, &x, &array![0., 40.]), vec![1]);
+        assert!(diverging_columns(&y, &x, &array![0., 0.5]).is_empty());
+    }
+}
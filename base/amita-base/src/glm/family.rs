@@ -0,0 +1,175 @@
+//! Exponential dispersion families of generalized linear models, described
+//! by their variance function V(μ) and unit deviance d(y, μ)
+
+use statrs::function::gamma::ln_gamma;
+
+use super::link::Link;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Family {
+    Gaussian,
+    /// Proportions y ∈ [0, 1], with the number of trials as prior weights
+    Binomial,
+    Poisson,
+    Gamma,
+    InverseGaussian,
+    /// V(μ) = μ^p, for p = 0 or p ≥ 1. Compound Poisson–gamma for 1 < p < 2.
+    Tweedie { power: f64 },
+}
+
+impl Family {
+    pub fn canonical_link(&self) -> Link {
+        match self {
+            Family::Gaussian => Link::Identity,
+            Family::Binomial => Link::Logit,
+            Family::Poisson => Link::Log,
+            Family::Gamma => Link::Inverse,
+            Family::InverseGaussian => Link::InverseSquared,
+            Family::Tweedie { power } => Link::Power(1. - power),
+        }
+    }
+
+    /// V(μ)
+    pub fn variance(&self, mu: f64) -> f64 {
+        match self {
+            Family::Gaussian => 1.,
+            Family::Binomial => mu * (1. - mu),
+            Family::Poisson => mu,
+            Family::Gamma => mu * mu,
+            Family::InverseGaussian => mu.powi(3),
+            Family::Tweedie { power } => mu.powf(*power),
+        }
+    }
+
+    /// d(y, μ) = 2 ∫_μ^y (y - t) / V(t) dt
+    pub fn unit_deviance(&self, y: f64, mu: f64) -> f64 {
+        match self {
+            Family::Gaussian => (y - mu).powi(2),
+            Family::Binomial => 2. * ( xlogy(y, y / mu) + xlogy(1. - y, (1. - y) / (1. - mu)) ),
+            Family::Poisson => 2. * ( xlogy(y, y / mu) - (y - mu) ),
+            Family::Gamma => 2. * ( - (y / mu).ln() + (y - mu) / mu ),
+            Family::InverseGaussian => (y - mu).powi(2) / (mu * mu * y),
+            Family::Tweedie { power } => match self.tweedie_special_case() {
+                Some(family) => family.unit_deviance(y, mu),
+                None => {
+                    let p = *power;
+                    let y_term = if y > 0. { y.powf(2. - p) / ( (1. - p) * (2. - p) ) } else { 0. };
+                    2. * ( y_term - y * mu.powf(1. - p) / (1. - p) + mu.powf(2. - p) / (2. - p) )
+                },
+            },
+        }
+    }
+
+    /// Binomial and Poisson fix the dispersion at 1
+    pub fn has_unit_dispersion(&self) -> bool {
+        match self {
+            Family::Binomial | Family::Poisson => true,
+            Family::Tweedie { .. } => self.tweedie_special_case() == Some(Family::Poisson),
+            _ => false,
+        }
+    }
+
+    /// Log-likelihood of y with prior weight w at dispersion φ, NaN for the
+    /// Tweedie densities that have no closed form
+    pub fn loglik(&self, y: f64, mu: f64, weight: f64, dispersion: f64) -> f64 {
+        match self {
+            Family::Gaussian => {
+                - 0.5 * ( (2. * std::f64::consts::PI * dispersion / weight).ln() + weight * (y - mu).powi(2) / dispersion )
+            },
+            Family::Binomial => {
+                let successes = weight * y;
+                ln_gamma(weight + 1.) - ln_gamma(successes + 1.) - ln_gamma(weight - successes + 1.)
+                    + weight * ( xlogy(y, mu) + xlogy(1. - y, 1. - mu) )
+            },
+            Family::Poisson => weight * ( xlogy(y, mu) - mu - ln_gamma(y + 1.) ),
+            Family::Gamma => {
+                let shape = weight / dispersion;
+                shape * (shape * y / mu).ln() - shape * y / mu - y.ln() - ln_gamma(shape)
+            },
+            Family::InverseGaussian => {
+                - 0.5 * ( (2. * std::f64::consts::PI * dispersion * y.powi(3) / weight).ln()
+                    + weight * (y - mu).powi(2) / (dispersion * y * mu * mu) )
+            },
+            Family::Tweedie { .. } => match self.tweedie_special_case() {
+                Some(family) => family.loglik(y, mu, weight, dispersion),
+                None => f64::NAN,
+            },
+        }
+    }
+
+    pub(crate) fn valid_outcome(&self, y: f64) -> bool {
+        match self {
+            Family::Gaussian => y.is_finite(),
+            Family::Binomial => (0. ..=1.).contains(&y),
+            Family::Poisson => y >= 0. && y.is_finite(),
+            Family::Gamma | Family::InverseGaussian => y > 0. && y.is_finite(),
+            Family::Tweedie { power } if *power < 2. => y >= 0. && y.is_finite(),
+            Family::Tweedie { .. } => y > 0. && y.is_finite(),
+        }
+    }
+
+    pub(crate) fn valid_mean(&self, mu: f64) -> bool {
+        match self {
+            Family::Gaussian => mu.is_finite(),
+            Family::Binomial => mu > 0. && mu < 1.,
+            Family::Tweedie { power } if *power == 0. => mu.is_finite(),
+            _ => mu > 0. && mu.is_finite(),
+        }
+    }
+
+    /// Starting mean, shrunk towards the sample mean to stay inside the
+    /// parameter space when y sits on its boundary
+    pub(crate) fn init_mean(&self, y: f64, mean_y: f64) -> f64 {
+        match self {
+            Family::Binomial => (y + 0.5) / 2.,
+            _ => (y + mean_y) / 2.,
+        }
+    }
+
+    fn tweedie_special_case(&self) -> Option<Family> {
+        match self {
+            Family::Tweedie { power } if *power == 0. => Some(Family::Gaussian),
+            Family::Tweedie { power } if *power == 1. => Some(Family::Poisson),
+            Family::Tweedie { power } if *power == 2. => Some(Family::Gamma),
+            Family::Tweedie { power } if *power == 3. => Some(Family::InverseGaussian),
+            _ => None,
+        }
+    }
+}
+
+/// x ln y, taken as 0 at x = 0
+fn xlogy(x: f64, y: f64) -> f64 {
+    if x == 0. { 0. } else { x * y.ln() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unit_deviance() {
+        let families = [
+            (Family::Gaussian, 1.3),
+            (Family::Binomial, 0.3),
+            (Family::Poisson, 2.),
+            (Family::Gamma, 1.3),
+            (Family::InverseGaussian, 1.3),
+            (Family::Tweedie { power: 1.5 }, 2.),
+        ];
+
+        for (family, y) in families {
+            // zero at the outcome, with ∂²d/∂μ² = 2 / V(μ)
+            assert!( family.unit_deviance(y, y).abs() < 1e-12 );
+
+            let h = 1e-4;
+            let second = ( family.unit_deviance(y, y + h) - 2. * family.unit_deviance(y, y)
+                + family.unit_deviance(y, y - h) ) / (h * h);
+            assert!( (second - 2. / family.variance(y)).abs() < 1e-4 * second.abs() );
+        }
+
+        // Tweedie reduces to the Poisson and gamma deviances
+        let tweedie = Family::Tweedie { power: 1.000001 };
+        assert!( (tweedie.unit_deviance(3., 2.) - Family::Poisson.unit_deviance(3., 2.)).abs() < 1e-4 );
+        assert_eq!(Family::Tweedie { power: 2. }.unit_deviance(3., 2.), Family::Gamma.unit_deviance(3., 2.));
+    }
+}
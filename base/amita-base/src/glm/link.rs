@@ -0,0 +1,107 @@
+//! Link functions η = g(μ) of generalized linear models
+
+use amita_utils::math::sigmoid;
+use statrs::distribution::{Continuous, ContinuousCDF, Normal};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Link {
+    Identity,
+    Log,
+    Logit,
+    Probit,
+    /// Complementary log-log, ln(-ln(1 - μ))
+    CLogLog,
+    /// 1 / μ
+    Inverse,
+    /// 1 / μ²
+    InverseSquared,
+    Sqrt,
+    /// μ^λ, or ln μ for λ = 0
+    Power(f64),
+}
+
+impl Link {
+    /// η = g(μ)
+    pub fn link(&self, mu: f64) -> f64 {
+        match self {
+            Link::Identity => mu,
+            Link::Log => mu.ln(),
+            Link::Logit => (mu / (1. - mu)).ln(),
+            Link::Probit => standard_normal().inverse_cdf(mu),
+            Link::CLogLog => ( - (- mu).ln_1p() ).ln(),
+            Link::Inverse => 1. / mu,
+            Link::InverseSquared => 1. / (mu * mu),
+            Link::Sqrt => mu.sqrt(),
+            Link::Power(lambda) if *lambda == 0. => mu.ln(),
+            Link::Power(lambda) => mu.powf(*lambda),
+        }
+    }
+
+    /// μ = g^{-1}(η)
+    pub fn inverse(&self, eta: f64) -> f64 {
+        match self {
+            Link::Identity => eta,
+            Link::Log => eta.exp(),
+            Link::Logit => sigmoid(eta),
+            Link::Probit => standard_normal().cdf(eta),
+            Link::CLogLog => - ( - eta.exp() ).exp_m1(),
+            Link::Inverse => 1. / eta,
+            Link::InverseSquared => 1. / eta.sqrt(),
+            Link::Sqrt => eta * eta,
+            Link::Power(lambda) if *lambda == 0. => eta.exp(),
+            Link::Power(lambda) => eta.powf(1. / lambda),
+        }
+    }
+
+    /// dη/dμ = g'(μ)
+    pub fn derivative(&self, mu: f64) -> f64 {
+        match self {
+            Link::Identity => 1.,
+            Link::Log => 1. / mu,
+            Link::Logit => 1. / ( mu * (1. - mu) ),
+            Link::Probit => {
+                let normal = standard_normal();
+                1. / normal.pdf(normal.inverse_cdf(mu))
+            },
+            Link::CLogLog => 1. / ( (mu - 1.) * (- mu).ln_1p() ),
+            Link::Inverse => - 1. / (mu * mu),
+            Link::InverseSquared => - 2. / mu.powi(3),
+            Link::Sqrt => 0.5 / mu.sqrt(),
+            Link::Power(lambda) if *lambda == 0. => 1. / mu,
+            Link::Power(lambda) => lambda * mu.powf(lambda - 1.),
+        }
+    }
+}
+
+fn standard_normal() -> Normal {
+    Normal::new(0., 1.).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inverse_and_derivative() {
+        let links = [
+            (Link::Identity, 0.3),
+            (Link::Log, 2.5),
+            (Link::Logit, 0.3),
+            (Link::Probit, 0.7),
+            (Link::CLogLog, 0.4),
+            (Link::Inverse, 1.7),
+            (Link::InverseSquared, 0.8),
+            (Link::Sqrt, 3.2),
+            (Link::Power(0.), 1.4),
+            (Link::Power(-0.5), 1.4),
+        ];
+        let h = 1e-6;
+
+        for (link, mu) in links {
+            assert!( (link.inverse(link.link(mu)) - mu).abs() < 1e-10 );
+
+            let numerical = ( link.link(mu + h) - link.link(mu - h) ) / (2. * h);
+            assert!( (link.derivative(mu) - numerical).abs() < 1e-6 * numerical.abs().max(1.) );
+        }
+    }
+}
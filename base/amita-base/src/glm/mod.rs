@@ -0,0 +1,3 @@
+pub mod family;
+pub mod link;
+pub mod solver;
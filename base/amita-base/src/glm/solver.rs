@@ -0,0 +1,687 @@
+//! Generalized linear models fitted by iteratively reweighted least squares.
+//!
+//! E(y | x) = μ = g^{-1}(x'β + offset) and Var(y | x) = φ V(μ) / w, for a
+//! family with variance function V, a link g, prior weights w and a
+//! dispersion φ. Each iteration regresses the working outcome
+//! z = η - offset + (y - μ) g'(μ) on x with weights w / (V(μ) g'(μ)²).
+
+use amita_error::AmitaError;
+use amita_utils::hypothesis::students_t_p_vals;
+use amita_utils::inference::{sandwich, SolverSEType};
+use amita_utils::iterations::TerminationCondition;
+use amita_utils::linalg::{inverse, solve};
+use amita_utils::parallel::weighted_gramian;
+use amita_utils::summary::coef_table;
+use amita_utils::traits::{BaseResults, BaseSolver};
+use ndarray::prelude::*;
+use statrs::distribution::{ContinuousCDF, Normal};
+
+use super::family::Family;
+use super::link::Link;
+use crate::prediction::Prediction;
+
+/// Bound keeping binomial means off 0 and 1, where the logit-type links and
+/// the variance function degenerate
+const BINOMIAL_MEAN_EPS: f64 = 1e-10;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dispersion {
+    /// Pearson χ² / (n - k)
+    Pearson,
+    /// Deviance / (n - k)
+    Deviance,
+    Fixed(f64),
+}
+
+#[derive(Debug, Clone)]
+pub struct GLMResults {
+    family: Family,
+    link: Link,
+    n_obs: usize,
+    n_regressors: usize,
+
+    y: Array1<f64>,
+    weights: Array1<f64>,
+    fitted: Option<Array1<f64>>,
+
+    coef: Option<Array1<f64>>,
+    se: Option<Array1<f64>>,
+    t: Option<Array1<f64>>, // z statistics when the dispersion is fixed
+    p_vals: Option<Array1<f64>>,
+    vcov: Option<Array2<f64>>,
+
+    deviance: Option<f64>,
+    null_deviance: Option<f64>,
+    pearson_chi2: Option<f64>,
+    dispersion: Option<f64>,
+    dispersion_estimated: bool,
+    loglik: Option<f64>,
+    n_iter: Option<u64>,
+    termination: Option<TerminationCondition>,
+}
+
+impl GLMResults {
+    pub fn family(&self) -> Family {
+        self.family
+    }
+
+    pub fn link(&self) -> Link {
+        self.link
+    }
+
+    pub fn n_obs(&self) -> usize {
+        self.n_obs
+    }
+
+    /// Residual degrees of freedom n - k
+    pub fn df_resid(&self) -> usize {
+        self.n_obs - self.n_regressors
+    }
+
+    /// Fitted means μ
+    pub fn fitted(&self) -> Result<Array1<f64>, AmitaError> {
+        self.fitted.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    /// y - μ
+    pub fn response_residuals(&self) -> Result<Array1<f64>, AmitaError> {
+        Ok( &self.y - &self.fitted()? )
+    }
+
+    /// (y - μ) sqrt(w / V(μ))
+    pub fn pearson_residuals(&self) -> Result<Array1<f64>, AmitaError> {
+        let mu = self.fitted()?;
+        Ok( Array1::from_iter((0..self.n_obs).map(|i| {
+            (self.y[i] - mu[i]) * ( self.weights[i] / self.family.variance(mu[i]) ).sqrt()
+        })) )
+    }
+
+    /// sign(y - μ) sqrt(w d(y, μ)), whose squares sum to the deviance
+    pub fn deviance_residuals(&self) -> Result<Array1<f64>, AmitaError> {
+        let mu = self.fitted()?;
+        Ok( Array1::from_iter((0..self.n_obs).map(|i| {
+            let d = self.weights[i] * self.family.unit_deviance(self.y[i], mu[i]);
+            (self.y[i] - mu[i]).signum() * d.max(0.).sqrt()
+        })) )
+    }
+
+    /// (y - μ) g'(μ), the residuals of the final IRLS regression
+    pub fn working_residuals(&self) -> Result<Array1<f64>, AmitaError> {
+        let mu = self.fitted()?;
+        Ok( Array1::from_iter((0..self.n_obs).map(|i| {
+            (self.y[i] - mu[i]) * self.link.derivative(mu[i])
+        })) )
+    }
+
+    pub fn deviance(&self) -> Result<f64, AmitaError> {
+        self.deviance.ok_or(AmitaError::NotSolved)
+    }
+
+    /// Deviance of the intercept-only model with the same offset and weights,
+    /// not available when that model fails to converge
+    pub fn null_deviance(&self) -> Result<f64, AmitaError> {
+        self.deviance()?;
+        self.null_deviance.ok_or(AmitaError::NotAvailable { feature: "Null deviance".to_string() })
+    }
+
+    pub fn pearson_chi2(&self) -> Result<f64, AmitaError> {
+        self.pearson_chi2.ok_or(AmitaError::NotSolved)
+    }
+
+    /// Dispersion φ, fixed at 1 for binomial and Poisson unless overridden
+    pub fn dispersion(&self) -> Result<f64, AmitaError> {
+        self.dispersion.ok_or(AmitaError::NotSolved)
+    }
+
+    /// Log-likelihood at the estimated dispersion, or at the ML dispersion
+    /// Σ w (y - μ)² / n for the Gaussian family. NaN for Tweedie families
+    /// without a closed-form density.
+    pub fn loglik(&self) -> Result<f64, AmitaError> {
+        self.loglik.ok_or(AmitaError::NotSolved)
+    }
+
+    pub fn aic(&self) -> Result<f64, AmitaError> {
+        Ok( - 2. * self.loglik()? + 2. * self.n_params() as f64 )
+    }
+
+    pub fn bic(&self) -> Result<f64, AmitaError> {
+        Ok( - 2. * self.loglik()? + (self.n_obs as f64).ln() * self.n_params() as f64 )
+    }
+
+    pub fn n_iter(&self) -> Result<u64, AmitaError> {
+        self.n_iter.ok_or(AmitaError::NotSolved)
+    }
+
+    pub fn termination(&self) -> Result<TerminationCondition, AmitaError> {
+        self.termination.ok_or(AmitaError::NotSolved)
+    }
+
+    fn n_params(&self) -> usize {
+        self.n_regressors + usize::from(self.dispersion_estimated)
+    }
+
+    /// Predicted means at the rows of `new_x`, with the columns of the
+    /// fitted x
+    pub fn predict(&self, new_x: &Array2<f64>) -> Result<Prediction, AmitaError> {
+        self.predict_with_offset(new_x, &Array1::zeros(new_x.shape()[0]))
+    }
+
+    /// Predicted means with an offset added to the linear predictor, such
+    /// as the log exposure of a count model
+    pub fn predict_with_offset(
+        &self,
+        new_x: &Array2<f64>,
+        offset: &Array1<f64>,
+    ) -> Result<Prediction, AmitaError> {
+        let prediction = Prediction::new(new_x, &self.coef()?, &self.vcov()?, Some(offset), self.link)?
+            .with_df(self.small_sample_df());
+        Ok(prediction)
+    }
+}
+
+impl BaseResults for GLMResults {
+    fn coef(&self) -> Result<Array1<f64>, AmitaError> {
+        self.coef.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn se(&self) -> Result<Array1<f64>, AmitaError> {
+        self.se.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn t(&self) -> Result<Array1<f64>, AmitaError> {
+        self.t.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn p_vals(&self) -> Result<Array1<f64>, AmitaError> {
+        self.p_vals.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn vcov(&self) -> Result<Array2<f64>, AmitaError> {
+        self.vcov.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn summary(&self) -> Result<String, AmitaError> {
+        coef_table(self)
+    }
+
+    fn small_sample_df(&self) -> Option<f64> {
+        self.dispersion_estimated.then_some(self.df_resid() as f64)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct IrlsFit {
+    coef: Array1<f64>,
+    mu: Array1<f64>,
+    deviance: f64,
+    n_iter: u64,
+    termination: TerminationCondition,
+}
+
+#[derive(Debug, Clone)]
+pub struct GLMSolver {
+    y: Array1<f64>,
+    x: Array2<f64>,
+    weights: Array1<f64>,
+    offset: Array1<f64>,
+
+    family: Family,
+    link: Link,
+    dispersion: Option<Dispersion>,
+    se_type: SolverSEType,
+
+    max_iter: u64,
+    max_tolerance: f64,
+
+    results: GLMResults,
+}
+
+impl GLMSolver {
+    /// Uses the canonical link of `family` unless `with_link` overrides it
+    pub fn new(
+        y: &Array1<f64>,
+        x: &Array2<f64>,
+        family: Family,
+    ) -> Result<Self, AmitaError> {
+        if x.shape()[0] != y.shape()[0] {
+            return Err(AmitaError::NotSameObservations);
+        }
+        if let Family::Tweedie { power } = family {
+            if power > 0. && power < 1. {
+                return Err(AmitaError::SolverFailed { message: format!("no Tweedie distribution has power {power}") });
+            }
+        }
+        if let Some(y_i) = y.iter().find(|y_i| !family.valid_outcome(**y_i)) {
+            return Err(AmitaError::InvalidOutcome { outcome: y_i.to_string() });
+        }
+
+        let n_obs = x.shape()[0];
+        let link = family.canonical_link();
+        let results = GLMResults {
+            family,
+            link,
+            n_obs,
+            n_regressors: x.shape()[1],
+
+            y: y.to_owned(),
+            weights: Array1::ones(n_obs),
+            fitted: None,
+
+            coef: None,
+            se: None,
+            t: None,
+            p_vals: None,
+            vcov: None,
+
+            deviance: None,
+            null_deviance: None,
+            pearson_chi2: None,
+            dispersion: None,
+            dispersion_estimated: false,
+            loglik: None,
+            n_iter: None,
+            termination: None,
+        };
+
+        Ok( Self {
+            y: y.to_owned(),
+            x: x.to_owned(),
+            weights: Array1::ones(n_obs),
+            offset: Array1::zeros(n_obs),
+
+            family,
+            link,
+            dispersion: None,
+            se_type: SolverSEType::NonRobust,
+
+            max_iter: 100,
+            max_tolerance: 1e-8,
+
+            results,
+        } )
+    }
+
+    pub fn with_link(mut self, link: Link) -> Self {
+        self.link = link;
+        self.results.link = link;
+        self
+    }
+
+    /// Prior weights, the number of trials for binomial proportions
+    pub fn with_weights(mut self, weights: &Array1<f64>) -> Result<Self, AmitaError> {
+        if weights.len() != self.y.len() {
+            return Err(AmitaError::NotSameObservations);
+        }
+        if weights.iter().any(|w| *w <= 0. || !w.is_finite()) {
+            return Err(AmitaError::NonPositive { matrix_name: "weights".to_string() });
+        }
+
+        self.weights = weights.to_owned();
+        self.results.weights = weights.to_owned();
+        Ok(self)
+    }
+
+    pub fn with_offset(mut self, offset: &Array1<f64>) -> Result<Self, AmitaError> {
+        if offset.len() != self.y.len() {
+            return Err(AmitaError::NotSameObservations);
+        }
+        self.offset += offset;
+        Ok(self)
+    }
+
+    /// Exposure t enters as the offset ln t, for log-link rate models
+    pub fn with_exposure(self, exposure: &Array1<f64>) -> Result<Self, AmitaError> {
+        if exposure.iter().any(|t| *t <= 0.) {
+            return Err(AmitaError::NonPositive { matrix_name: "exposure".to_string() });
+        }
+        self.with_offset(&exposure.map(|t| t.ln()))
+    }
+
+    pub fn with_dispersion(mut self, dispersion: Dispersion) -> Self {
+        self.dispersion = Some(dispersion);
+        self
+    }
+
+    pub fn with_se_type(mut self, se_type: SolverSEType) -> Self {
+        self.se_type = se_type;
+        self
+    }
+
+    pub fn with_max_iter(mut self, max_iter: u64) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    pub fn with_max_tolerance(mut self, max_tolerance: f64) -> Self {
+        self.max_tolerance = max_tolerance;
+        self
+    }
+}
+
+impl BaseSolver<GLMResults> for GLMSolver {
+    fn results(&self) -> GLMResults {
+        self.results.clone()
+    }
+
+    fn solve(self) -> Result<Self, AmitaError> {
+        self
+        .run_solver()?
+        .solve_dispersion()?
+        .solve_se()?
+        .solve_t_pvals()
+    }
+}
+
+impl GLMSolver {
+    fn run_solver(mut self) -> Result<Self, AmitaError> {
+        let fit = self.irls(&self.x)?;
+        if fit.termination == TerminationCondition::MaxIterReached {
+            return Err(AmitaError::NotConverged { iterations: fit.n_iter });
+        }
+
+        // the null model only serves comparisons, so its failure is not the fit's
+        let null_deviance = match self.irls(&Array2::ones((self.y.len(), 1))) {
+            Ok(null_fit) if null_fit.termination == TerminationCondition::SolverConverged => Some(null_fit.deviance),
+            _ => None,
+        };
+
+        self.results.coef = Some(fit.coef);
+        self.results.fitted = Some(fit.mu);
+        self.results.deviance = Some(fit.deviance);
+        self.results.null_deviance = null_deviance;
+        self.results.n_iter = Some(fit.n_iter);
+        self.results.termination = Some(fit.termination);
+
+        Ok(self)
+    }
+
+    fn solve_dispersion(mut self) -> Result<Self, AmitaError> {
+        let mu = self.results.fitted()?;
+        let df_resid = self.results.df_resid() as f64;
+
+        let pearson_chi2 = (0..self.y.len())
+            .map(|i| self.weights[i] * (self.y[i] - mu[i]).powi(2) / self.family.variance(mu[i]))
+            .sum::<f64>();
+
+        let method = self.dispersion.unwrap_or(match self.family.has_unit_dispersion() {
+            true => Dispersion::Fixed(1.),
+            false => Dispersion::Pearson,
+        });
+        let dispersion = match method {
+            Dispersion::Pearson => pearson_chi2 / df_resid,
+            Dispersion::Deviance => self.results.deviance()? / df_resid,
+            Dispersion::Fixed(phi) => phi,
+        };
+
+        let loglik_dispersion = match self.family {
+            Family::Gaussian => pearson_chi2 / self.y.len() as f64,
+            _ => dispersion,
+        };
+        let loglik = (0..self.y.len())
+            .map(|i| self.family.loglik(self.y[i], mu[i], self.weights[i], loglik_dispersion))
+            .sum::<f64>();
+
+        self.results.pearson_chi2 = Some(pearson_chi2);
+        self.results.dispersion = Some(dispersion);
+        self.results.dispersion_estimated = !matches!(method, Dispersion::Fixed(_));
+        self.results.loglik = Some(loglik);
+
+        Ok(self)
+    }
+
+    /// φ (X'WX)^{-1} with the IRLS weights at convergence, or the sandwich
+    /// around (X'WX)^{-1} of the scores x w (y - μ) / (V(μ) g'(μ))
+    fn solve_se(mut self) -> Result<Self, AmitaError> {
+        let mu = self.results.fitted()?;
+        let dispersion = self.results.dispersion()?;
+
+        let g_prime = mu.map(|m| self.link.derivative(*m));
+        let variance = mu.map(|m| self.family.variance(*m));
+        let w = &self.weights / &(&variance * &g_prime * &g_prime);
+
+        let bread = inverse(&weighted_gramian(self.x.view(), w.view()), "X'WX")?;
+
+        let vcov = match self.se_type {
+            SolverSEType::Homoscedastic | SolverSEType::NonRobust => bread * dispersion,
+            _ => {
+                let u = &self.weights * &(&self.y - &mu) / &(&variance * &g_prime);
+                let scores = &self.x * &u.insert_axis(Axis(1));
+                sandwich(&bread, &scores, &self.se_type)?
+            },
+        };
+
+        self.results.se = Some(vcov.diag().map(|x| x.sqrt()));
+        self.results.vcov = Some(vcov);
+
+        Ok(self)
+    }
+
+    /// t(n - k) statistics when the dispersion is estimated, z otherwise
+    fn solve_t_pvals(mut self) -> Result<Self, AmitaError> {
+        let t = self.results.coef()? / self.results.se()?;
+
+        let p_vals = if self.results.dispersion_estimated {
+            students_t_p_vals(&t, self.results.df_resid() as f64)
+        } else {
+            let dist = Normal::new(0., 1.).unwrap();
+            t.map(|x| 2. * ( 1. - dist.cdf(x.abs()) ) )
+        };
+
+        self.results.t = Some(t);
+        self.results.p_vals = Some(p_vals);
+
+        Ok(self)
+    }
+
+    /// IRLS on the regressors `x`. Steps leaving the parameter space of μ are
+    /// halved towards the previous coefficients.
+    fn irls(
+        &self,
+        x: &Array2<f64>,
+    ) -> Result<IrlsFit, AmitaError> {
+        let mean_y = self.y.mean().unwrap();
+        let mut mu = self.y.map(|y| self.family.init_mean(*y, mean_y));
+        let mut eta = mu.map(|m| self.link.link(*m));
+        let mut deviance = self.deviance(&mu);
+        let mut coef: Option<Array1<f64>> = None;
+
+        let mut n_iter = 0;
+        let mut termination = TerminationCondition::MaxIterReached;
+        while n_iter < self.max_iter {
+            n_iter += 1;
+
+            let g_prime = mu.map(|m| self.link.derivative(*m));
+            let variance = mu.map(|m| self.family.variance(*m));
+            let z = &eta - &self.offset + (&self.y - &mu) * &g_prime;
+            let w = &self.weights / &(&variance * &g_prime * &g_prime);
+
+            let xw = x * &w.view().insert_axis(Axis(1));
+            let mut candidate = solve(&x.t().dot(&xw), &xw.t().dot(&z), "X'WX")?;
+
+            let mut halvings = 0;
+            let (new_eta, new_mu, new_deviance) = loop {
+                let new_eta = x.dot(&candidate) + &self.offset;
+                let new_mu = new_eta.map(|e| self.mean(*e));
+                let new_deviance = self.deviance(&new_mu);
+
+                if new_deviance.is_finite() && new_mu.iter().all(|m| self.family.valid_mean(*m)) {
+                    break (new_eta, new_mu, new_deviance);
+                }
+
+                halvings += 1;
+                match &coef {
+                    Some(previous) if halvings <= 40 => candidate = (&candidate + previous) / 2.,
+                    _ => return Err(AmitaError::SolverFailed {
+                        message: "fitted means left the parameter space of the family".to_string()
+                    }),
+                }
+            };
+
+            let change = (new_deviance - deviance).abs() / (new_deviance.abs() + 0.1);
+            eta = new_eta;
+            mu = new_mu;
+            deviance = new_deviance;
+            coef = Some(candidate);
+
+            if change < self.max_tolerance {
+                termination = TerminationCondition::SolverConverged;
+                break;
+            }
+        }
+
+        Ok( IrlsFit {
+            coef: coef.unwrap_or_else(|| Array1::zeros(x.shape()[1])),
+            mu,
+            deviance,
+            n_iter,
+            termination,
+        } )
+    }
+
+    fn mean(&self, eta: f64) -> f64 {
+        let mu = self.link.inverse(eta);
+        match self.family {
+            Family::Binomial => mu.clamp(BINOMIAL_MEAN_EPS, 1. - BINOMIAL_MEAN_EPS),
+            _ => mu,
+        }
+    }
+
+    fn deviance(&self, mu: &Array1<f64>) -> f64 {
+        (0..self.y.len())
+            .map(|i| self.weights[i] * self.family.unit_deviance(self.y[i], mu[i]))
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discrete::count::poisson::PoissonSolver;
+    use crate::discrete::logit::{LogitMethod, LogitSolver};
+    use crate::linear::ols::OLSSolver;
+
+    fn data() -> (Array1<f64>, Array2<f64>) {
+        let y = array![1., 0., 3., 2., 0., 5., 2., 1., 4., 0., 6., 2., 1., 3., 7., 1.];
+        let x1 = array![
+            0.1, 0.4, 1.2, 0.3, -0.2, 1.5, 0.6, 0.0,
+            1.1, -0.5, 1.3, 0.2, 0.8, -0.1, 0.9, 0.5
+        ];
+        let x = ndarray::stack![Axis(1), Array1::<f64>::ones(16), x1];
+        (y, x)
+    }
+
+    #[test]
+    fn test_gaussian_is_ols() -> Result<(), AmitaError> {
+        let (y, x) = data();
+
+        let glm = GLMSolver::new(&y, &x, Family::Gaussian)?.solve()?.results();
+        let ols = OLSSolver::new(&y, &x)?.solve()?.results();
+        assert!( (glm.coef()? - ols.coef()?).iter().all(|d| d.abs() < 1e-10) );
+
+        // dispersion is RSS / (n - k)
+        let resid = glm.response_residuals()?;
+        assert!( (glm.dispersion()? - resid.dot(&resid) / 14.).abs() < 1e-10 );
+        assert!( (glm.deviance()? - resid.dot(&resid)).abs() < 1e-10 );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_binomial_is_logit() -> Result<(), AmitaError> {
+        let (y, x) = data();
+        let y_binary = y.map(|y| if *y >= 2. { 1 } else { 0 });
+
+        let glm = GLMSolver::new(&y_binary.map(|y| *y as f64), &x, Family::Binomial)?.solve()?.results();
+        let logit = LogitSolver::new(&y_binary, &x)?
+            .with_method(LogitMethod::Newton)
+            .with_max_tolerance(1e-10)
+            .solve()?
+            .results();
+
+        assert!( (glm.coef()? - logit.coef()?).iter().all(|d| d.abs() < 1e-6) );
+        assert!( (glm.se()? - logit.se()?).iter().all(|d| d.abs() < 1e-6) );
+        assert_eq!(glm.dispersion()?, 1.);
+        assert_eq!(glm.summary()?, logit.summary()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_poisson_weights_and_exposure() -> Result<(), AmitaError> {
+        let (y, x) = data();
+        let exposure = Array1::from_iter((0..16).map(|i| 1. + (i % 3) as f64));
+
+        let glm = GLMSolver::new(&y, &x, Family::Poisson)?
+            .with_exposure(&exposure)?
+            .solve()?
+            .results();
+        let poisson = PoissonSolver::new(&y.map(|y| *y as i32), &x)?
+            .with_exposure(&exposure)?
+            .solve()?
+            .results();
+        assert!( (glm.coef()? - poisson.coef()?).iter().all(|d| d.abs() < 1e-6) );
+        assert!( (glm.loglik()? - poisson.loglik()?).abs() < 1e-6 );
+
+        // a prior weight of 2 is a duplicated observation
+        let mut weights = Array1::ones(16);
+        weights[3] = 2.;
+        let weighted = GLMSolver::new(&y, &x, Family::Poisson)?.with_weights(&weights)?.solve()?.results();
+
+        let rows = (0..16).chain(std::iter::once(3)).collect::<Vec<_>>();
+        let duplicated = GLMSolver::new(&y.select(Axis(0), &rows), &x.select(Axis(0), &rows), Family::Poisson)?
+            .solve()?
+            .results();
+        assert!( (weighted.coef()? - duplicated.coef()?).iter().all(|d| d.abs() < 1e-8) );
+        assert!( (weighted.se()? - duplicated.se()?).iter().all(|d| d.abs() < 1e-8) );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_gamma_and_tweedie_residuals() -> Result<(), AmitaError> {
+        let (y, x) = data();
+        let y_positive = y.map(|y| y + 0.5);
+
+        let gamma = GLMSolver::new(&y_positive, &x, Family::Gamma)?
+            .with_link(Link::Log)
+            .solve()?
+            .results();
+        let deviance_resid = gamma.deviance_residuals()?;
+        let pearson_resid = gamma.pearson_residuals()?;
+        assert!( (deviance_resid.dot(&deviance_resid) - gamma.deviance()?).abs() < 1e-8 );
+        assert!( (pearson_resid.dot(&pearson_resid) - gamma.pearson_chi2()?).abs() < 1e-8 );
+        assert!( gamma.deviance()? < gamma.null_deviance()? );
+
+        let tweedie = GLMSolver::new(&y, &x, Family::Tweedie { power: 1.5 })?
+            .with_link(Link::Log)
+            .with_se_type(SolverSEType::Robust)
+            .solve()?
+            .results();
+        assert_eq!(tweedie.termination()?, TerminationCondition::SolverConverged);
+        assert!( tweedie.loglik()?.is_nan() );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_null_model_fails() -> Result<(), AmitaError> {
+        // with an identity link, no constant mean plus the offset stays
+        // positive, while the regressor cancels the offset
+        let n = 12;
+        let offset = Array1::from_iter( (0..n).map(|i| i as f64) );
+        let y = Array1::from_iter( (0..n).map(|i| 1. + 0.1 * ((i * 7) % 5) as f64) );
+        let x = ndarray::stack![Axis(1), Array1::<f64>::ones(n), offset.clone()];
+
+        let gamma = GLMSolver::new(&y, &x, Family::Gamma)?
+            .with_link(Link::Identity)
+            .with_offset(&offset)?
+            .solve()?
+            .results();
+
+        assert!( (gamma.coef()?[1] + 1.).abs() < 0.1 );
+        assert!( gamma.deviance()?.is_finite() );
+        assert!( matches!(gamma.null_deviance(), Err(AmitaError::NotAvailable { .. })) );
+
+        Ok(())
+    }
+}
@@ -1,2 +1,5 @@
+pub mod censored;
 pub mod discrete;
-pub mod linear;
\ No newline at end of file
+pub mod glm;
+pub mod linear;
+pub mod prediction;
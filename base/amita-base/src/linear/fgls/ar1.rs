@@ -0,0 +1,208 @@
+//! Feasible GLS with AR(1) errors u_t = ρ u_{t-1} + ε_t in time-ordered
+//! data, by Cochrane–Orcutt or Prais–Winsten iterations: ρ from the
+//! regression of the residuals on their lag, then least squares on the
+//! quasi-differenced data y_t - ρ y_{t-1}, x_t - ρ x_{t-1}.
+
+use amita_error::AmitaError;
+use amita_utils::inference::SolverSEType;
+use amita_utils::traits::{BaseResults, BaseSolver};
+use ndarray::concatenate;
+use ndarray::prelude::*;
+
+use super::results::{iterate, FGLSResults, VarianceStructure};
+use crate::linear::ols::{OLSResults, OLSSolver};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AR1Method {
+    /// Drops the first observation, which has no lag
+    CochraneOrcutt,
+    /// Keeps the first observation, scaled by (1 - ρ^2)^{1/2}
+    PraisWinsten,
+}
+
+#[derive(Debug, Clone)]
+pub struct AR1Solver {
+    y: Array1<f64>,
+    x: Array2<f64>,
+
+    method: AR1Method,
+    se_type: SolverSEType,
+    max_iter: u64,
+    max_tolerance: f64,
+
+    results: FGLSResults,
+}
+
+impl AR1Solver {
+    /// Observations must be in time order
+    pub fn new(
+        y: &Array1<f64>,
+        x: &Array2<f64>,
+    ) -> Result<Self, AmitaError> {
+        if x.shape()[0] != y.len() {
+            return Err(AmitaError::NotSameObservations);
+        }
+
+        Ok( Self {
+            y: y.to_owned(),
+            x: x.to_owned(),
+
+            method: AR1Method::PraisWinsten,
+            se_type: SolverSEType::NonRobust,
+            max_iter: 100,
+            max_tolerance: 1e-8,
+
+            results: FGLSResults::new(y.len()),
+        } )
+    }
+
+    pub fn with_method(mut self, method: AR1Method) -> Self {
+        self.method = method;
+        self
+    }
+
+    /// Standard errors of the final regression on the transformed data.
+    /// Cochrane–Orcutt drops the first observation from cluster
+    /// identifiers too.
+    pub fn with_se_type(mut self, se_type: SolverSEType) -> Self {
+        self.se_type = se_type;
+        self
+    }
+
+    /// One iteration is two-step feasible GLS
+    pub fn with_max_iter(mut self, max_iter: u64) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    pub fn with_max_tolerance(mut self, max_tolerance: f64) -> Self {
+        self.max_tolerance = max_tolerance;
+        self
+    }
+}
+
+impl BaseSolver<FGLSResults> for AR1Solver {
+    fn results(&self) -> FGLSResults {
+        self.results.clone()
+    }
+
+    fn solve(mut self) -> Result<Self, AmitaError> {
+        let ols = OLSSolver::new(&self.y, &self.x)?.solve()?.results();
+
+        let fit = iterate(&self.y, &self.x, ols.coef()?, self.max_iter, self.max_tolerance, |resid| {
+            let rho = Self::rho(resid)?;
+            let regression = self.transformed_regression(rho)?;
+
+            let innovations = regression.resid()?;
+            let innovation_variance = innovations.dot(&innovations) / regression.df_resid();
+
+            Ok( (regression, VarianceStructure::AR1 { rho, innovation_variance }) )
+        })?;
+
+        self.results.set_fit(fit);
+
+        Ok(self)
+    }
+}
+
+impl AR1Solver {
+    /// ρ = Σ e_t e_{t-1} / Σ e_{t-1}^2
+    fn rho(resid: &Array1<f64>) -> Result<f64, AmitaError> {
+        let n = resid.len();
+        let current = resid.slice(s![1..]);
+        let lagged = resid.slice(s![..n - 1]);
+        let rho = current.dot(&lagged) / lagged.dot(&lagged);
+
+        if rho.is_nan() || rho.abs() >= 1. {
+            return Err(AmitaError::SolverFailed { message: format!("AR(1) coefficient {rho} is not stationary") });
+        }
+
+        Ok(rho)
+    }
+
+    fn transformed_regression(&self, rho: f64) -> Result<OLSResults, AmitaError> {
+        let n = self.y.len();
+        let quasi_difference = |v: ArrayView2<f64>| &v.slice(s![1.., ..]) - &(&v.slice(s![..n - 1, ..]) * rho);
+
+        let mut y = quasi_difference(self.y.view().insert_axis(Axis(1))).remove_axis(Axis(1));
+        let mut x = quasi_difference(self.x.view());
+        let mut se_type = self.se_type.clone();
+
+        match self.method {
+            AR1Method::CochraneOrcutt => {
+                let drop_first = |by: &Array1<i32>| by.slice(s![1..]).to_owned();
+                se_type = match se_type {
+                    SolverSEType::Clustered { by } => SolverSEType::Clustered { by: drop_first(&by) },
+                    SolverSEType::MultiwayClustered { by } => SolverSEType::MultiwayClustered { by: by.iter().map(drop_first).collect() },
+                    se_type => se_type,
+                };
+            },
+            AR1Method::PraisWinsten => {
+                let scale = (1. - rho * rho).sqrt();
+                y = concatenate![Axis(0), array![self.y[0] * scale], y];
+                x = concatenate![Axis(0), (&self.x.slice(s![..1, ..]) * scale), x];
+            },
+        }
+
+        let results = OLSSolver::from_owned(y, x)?
+            .with_se_type(se_type)
+            .solve()?
+            .results();
+
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use statrs::distribution::Normal;
+
+    use super::*;
+
+    #[test]
+    fn test_ar1() -> Result<(), AmitaError> {
+        // u_t = 0.7 u_{t-1} + ε_t with Var(ε) = 1
+        let mut rng = StdRng::seed_from_u64(11);
+        let normal = Normal::new(0., 1.).unwrap();
+
+        let n = 1000;
+        let x1 = Array1::from_iter( (0..n).map(|t| (t as f64 / 25.).sin() + rng.sample(normal)) );
+        let mut u = Array1::<f64>::zeros(n);
+        u[0] = rng.sample(normal) / (1. - 0.49_f64).sqrt();
+        for t in 1..n {
+            u[t] = 0.7 * u[t - 1] + rng.sample(normal);
+        }
+        let y = 1. + 2. * &x1 + u;
+        let x = ndarray::stack![Axis(1), Array1::<f64>::ones(n), x1];
+
+        let prais = AR1Solver::new(&y, &x)?.solve()?.results();
+        let cochrane = AR1Solver::new(&y, &x)?
+            .with_method(AR1Method::CochraneOrcutt)
+            .with_se_type(SolverSEType::Clustered { by: Array1::from_iter( (0..n).map(|t| (t / 50) as i32) ) })
+            .solve()?
+            .results();
+
+        for results in [&prais, &cochrane] {
+            let VarianceStructure::AR1 { rho, innovation_variance } = results.variance()? else { panic!() };
+            assert!( (rho - 0.7).abs() < 0.06 );
+            assert!( (innovation_variance - 1.).abs() < 0.15 );
+            assert!( (results.coef()?[1] - 2.).abs() < 0.1 );
+        }
+        assert_eq!(prais.regression()?.n_obs(), n);
+        assert_eq!(cochrane.regression()?.n_obs(), n - 1);
+        assert!( (prais.coef()? - cochrane.coef()?).iter().all(|d| d.abs() < 0.05) );
+
+        // the history records ρ at every step
+        assert!( prais.history().iter().all(|step| step.variance_param.len() == 2) );
+
+        // a collinear regressor is omitted rather than making every residual NaN
+        let collinear = ndarray::concatenate![Axis(1), x, (2. * &x.column(1)).insert_axis(Axis(1))];
+        let omitted = AR1Solver::new(&y, &collinear)?.solve()?.results();
+        assert!( omitted.coef()?[2].is_nan() );
+        assert!( (omitted.coef()?.slice(s![..2]).to_owned() - prais.coef()?).iter().all(|d| d.abs() < 1e-8) );
+
+        Ok(())
+    }
+}
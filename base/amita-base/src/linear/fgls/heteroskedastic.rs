@@ -0,0 +1,235 @@
+//! Feasible GLS under heteroskedasticity: weighted least squares with
+//! weights 1 / σ_i^2 estimated from the least-squares residuals.
+
+use std::collections::BTreeMap;
+
+use amita_error::AmitaError;
+use amita_utils::inference::SolverSEType;
+use amita_utils::traits::{BaseResults, BaseSolver};
+use ndarray::prelude::*;
+
+use super::results::{iterate, FGLSResults, VarianceStructure};
+use crate::linear::ols::{OLSResults, OLSSolver, WeightType};
+
+#[derive(Debug, Clone)]
+pub enum HeteroskedasticityModel {
+    /// Harvey's multiplicative heteroskedasticity σ_i^2 = exp(z_i'α), with
+    /// α from the regression of ln e_i^2 on z_i, which should include a
+    /// constant
+    Multiplicative { z: Array2<f64> },
+    /// A separate variance for each group, the mean squared residual
+    Groupwise { groups: Array1<i32> },
+}
+
+#[derive(Debug, Clone)]
+pub struct HeteroskedasticFGLSSolver {
+    y: Array1<f64>,
+    x: Array2<f64>,
+    model: HeteroskedasticityModel,
+
+    se_type: SolverSEType,
+    max_iter: u64,
+    max_tolerance: f64,
+
+    results: FGLSResults,
+}
+
+impl HeteroskedasticFGLSSolver {
+    pub fn new(
+        y: &Array1<f64>,
+        x: &Array2<f64>,
+        model: HeteroskedasticityModel,
+    ) -> Result<Self, AmitaError> {
+        let n_model = match &model {
+            HeteroskedasticityModel::Multiplicative { z } => z.shape()[0],
+            HeteroskedasticityModel::Groupwise { groups } => groups.len(),
+        };
+        if x.shape()[0] != y.len() || n_model != y.len() {
+            return Err(AmitaError::NotSameObservations);
+        }
+
+        Ok( Self {
+            y: y.to_owned(),
+            x: x.to_owned(),
+            model,
+
+            se_type: SolverSEType::NonRobust,
+            max_iter: 100,
+            max_tolerance: 1e-8,
+
+            results: FGLSResults::new(y.len()),
+        } )
+    }
+
+    /// Standard errors of the final weighted regression
+    pub fn with_se_type(mut self, se_type: SolverSEType) -> Self {
+        self.se_type = se_type;
+        self
+    }
+
+    /// One iteration is two-step feasible GLS
+    pub fn with_max_iter(mut self, max_iter: u64) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    pub fn with_max_tolerance(mut self, max_tolerance: f64) -> Self {
+        self.max_tolerance = max_tolerance;
+        self
+    }
+}
+
+impl BaseSolver<FGLSResults> for HeteroskedasticFGLSSolver {
+    fn results(&self) -> FGLSResults {
+        self.results.clone()
+    }
+
+    fn solve(mut self) -> Result<Self, AmitaError> {
+        let ols = OLSSolver::new(&self.y, &self.x)?.solve()?.results();
+
+        let fit = iterate(&self.y, &self.x, ols.coef()?, self.max_iter, self.max_tolerance, |resid| {
+            let (variance, sigma_sq) = self.variance(resid)?;
+            Ok( (self.weighted_regression(&sigma_sq)?, variance) )
+        })?;
+
+        self.results.set_fit(fit);
+
+        Ok(self)
+    }
+}
+
+impl HeteroskedasticFGLSSolver {
+    /// Variance structure and the fitted σ_i^2 from residuals
+    fn variance(&self, resid: &Array1<f64>) -> Result<(VarianceStructure, Array1<f64>), AmitaError> {
+        match &self.model {
+            HeteroskedasticityModel::Multiplicative { z } => {
+                if resid.iter().any(|e| *e == 0.) {
+                    return Err(AmitaError::NonPositive { matrix_name: "squared residuals".to_string() });
+                }
+
+                let auxiliary = OLSSolver::new(&resid.map(|e| (e * e).ln()), z)?.solve()?.results();
+                let alpha = auxiliary.coef()?;
+                let sigma_sq = z.dot(&alpha.map(|a| if a.is_nan() { 0. } else { *a })).map(|x| x.exp());
+
+                Ok( (VarianceStructure::Multiplicative { alpha, alpha_se: auxiliary.se()? }, sigma_sq) )
+            },
+            HeteroskedasticityModel::Groupwise { groups } => {
+                let mut sums = BTreeMap::<i32, (f64, f64)>::new();
+                for (g, e) in groups.iter().zip(resid.iter()) {
+                    let entry = sums.entry(*g).or_insert((0., 0.));
+                    entry.0 += e * e;
+                    entry.1 += 1.;
+                }
+                let variances = sums.iter().map(|(g, (ss, n))| (*g, ss / n)).collect::<BTreeMap<_, _>>();
+                if variances.values().any(|v| *v <= 0.) {
+                    return Err(AmitaError::NonPositive { matrix_name: "group variances".to_string() });
+                }
+
+                let sigma_sq = groups.map(|g| variances[g]);
+                let structure = VarianceStructure::Groupwise {
+                    groups: variances.keys().copied().collect(),
+                    variances: variances.values().copied().collect(),
+                };
+
+                Ok( (structure, sigma_sq) )
+            },
+        }
+    }
+
+    fn weighted_regression(&self, sigma_sq: &Array1<f64>) -> Result<OLSResults, AmitaError> {
+        let results = OLSSolver::new(&self.y, &self.x)?
+            .with_weights(&sigma_sq.map(|s| 1. / s), WeightType::Analytic)?
+            .with_se_type(self.se_type.clone())
+            .solve()?
+            .results();
+
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use amita_utils::iterations::TerminationCondition;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use statrs::distribution::Normal;
+
+    use super::*;
+
+    fn data(sigma: impl Fn(f64, usize) -> f64) -> (Array1<f64>, Array2<f64>) {
+        let mut rng = StdRng::seed_from_u64(7);
+        let normal = Normal::new(0., 1.).unwrap();
+
+        let n = 2000;
+        let x1 = Array1::from_iter( (0..n).map(|_| rng.gen_range(0. ..2.)) );
+        let e = Array1::from_iter( (0..n).map(|i| sigma(x1[i], i) * rng.sample(normal)) );
+        let y = 1. + 2. * &x1 + e;
+        (y, ndarray::stack![Axis(1), Array1::<f64>::ones(n), x1])
+    }
+
+    #[test]
+    fn test_multiplicative() -> Result<(), AmitaError> {
+        // σ_i^2 = exp(0.5 + 1.5 x_i)
+        let (y, x) = data(|x1, _| (0.25 + 0.75 * x1).exp());
+
+        let model = HeteroskedasticityModel::Multiplicative { z: x.clone() };
+        let results = HeteroskedasticFGLSSolver::new(&y, &x, model)?.solve()?.results();
+
+        let VarianceStructure::Multiplicative { alpha, alpha_se } = results.variance()? else { panic!() };
+        assert!( (alpha[1] - 1.5).abs() < 3. * alpha_se[1] );
+        assert!( (results.coef()? - array![1., 2.]).iter().all(|d| d.abs() < 0.15) );
+        assert_eq!(results.termination()?, TerminationCondition::SolverConverged);
+
+        // FGLS is more precise than least squares when the model is right
+        let ols = OLSSolver::new(&y, &x)?.with_robust_se().solve()?.results();
+        assert!( results.se()?[1] < ols.se()?[1] );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_groupwise() -> Result<(), AmitaError> {
+        // standard deviations 1 and 3 in alternating groups
+        let (y, x) = data(|_, i| if i % 2 == 0 { 1. } else { 3. });
+        let groups = Array1::from_iter( (0..2000).map(|i| 10 * (i % 2)) );
+
+        let two_step = HeteroskedasticFGLSSolver::new(&y, &x, HeteroskedasticityModel::Groupwise { groups: groups.clone() })?
+            .with_max_iter(1)
+            .solve()?
+            .results();
+        assert_eq!(two_step.n_iter(), 1);
+        assert_eq!(two_step.termination()?, TerminationCondition::MaxIterReached);
+
+        let iterated = HeteroskedasticFGLSSolver::new(&y, &x, HeteroskedasticityModel::Groupwise { groups })?
+            .solve()?
+            .results();
+        let VarianceStructure::Groupwise { groups, variances } = iterated.variance()? else { panic!() };
+        assert_eq!(groups, vec![0, 10]);
+        assert!( (variances[0] - 1.).abs() < 0.15 && (variances[1] - 9.).abs() < 1. );
+
+        // the history ends at the reported estimates
+        let history = iterated.history();
+        assert!( history.len() > 1 && history.last().unwrap().change < 1e-8 );
+        assert_eq!(history.last().unwrap().coef, iterated.coef()?);
+        assert_eq!(history.last().unwrap().variance_param, variances);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_collinear_regressors() -> Result<(), AmitaError> {
+        let (y, x) = data(|x1, _| (0.25 + 0.75 * x1).exp());
+        let collinear = ndarray::concatenate![Axis(1), x, (2. * &x.column(1)).insert_axis(Axis(1))];
+
+        // the doubled column is omitted from the regression and the variance model
+        let model = |z: &Array2<f64>| HeteroskedasticityModel::Multiplicative { z: z.clone() };
+        let full_rank = HeteroskedasticFGLSSolver::new(&y, &x, model(&x))?.solve()?.results();
+        let omitted = HeteroskedasticFGLSSolver::new(&y, &collinear, model(&collinear))?.solve()?.results();
+
+        let coef = omitted.coef()?;
+        assert!( coef[2].is_nan() );
+        assert!( (coef.slice(s![..2]).to_owned() - full_rank.coef()?).iter().all(|d| d.abs() < 1e-8) );
+
+        Ok(())
+    }
+}
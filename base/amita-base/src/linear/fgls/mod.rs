@@ -0,0 +1,3 @@
+pub mod ar1;
+pub mod heteroskedastic;
+pub mod results;
@@ -0,0 +1,173 @@
+//! Results of the feasible GLS estimators.
+//!
+//! Coefficients, standard errors and the covariance are those of the final
+//! least-squares regression on the reweighted or transformed data.
+
+use amita_error::AmitaError;
+use amita_utils::iterations::TerminationCondition;
+use amita_utils::summary::coef_table;
+use amita_utils::traits::BaseResults;
+use ndarray::prelude::*;
+
+use crate::linear::ols::OLSResults;
+
+/// Estimated structure of the error variance
+#[derive(Debug, Clone)]
+pub enum VarianceStructure {
+    /// σ_i^2 = exp(z_i'α), with the intercept of α biased by E[ln χ²(1)]
+    /// ≈ -1.27, which rescales all weights alike and leaves β unaffected
+    Multiplicative { alpha: Array1<f64>, alpha_se: Array1<f64> },
+    /// σ_g^2 of each group, in sorted order of the group identifiers
+    Groupwise { groups: Vec<i32>, variances: Array1<f64> },
+    /// u_t = ρ u_{t-1} + ε_t, with Var(ε_t) the innovation variance
+    AR1 { rho: f64, innovation_variance: f64 },
+}
+
+impl VarianceStructure {
+    /// Parameters of the structure as a vector: α, the group variances, or
+    /// ρ and the innovation variance
+    pub fn param(&self) -> Array1<f64> {
+        match self {
+            VarianceStructure::Multiplicative { alpha, .. } => alpha.clone(),
+            VarianceStructure::Groupwise { variances, .. } => variances.clone(),
+            VarianceStructure::AR1 { rho, innovation_variance } => array![*rho, *innovation_variance],
+        }
+    }
+}
+
+/// One feasible GLS step: the coefficients of the reweighted regression,
+/// the variance parameters it used, as in [`VarianceStructure::param`], and the largest change in the
+/// coefficients from the previous step
+#[derive(Debug, Clone)]
+pub struct FGLSIteration {
+    pub coef: Array1<f64>,
+    pub variance_param: Array1<f64>,
+    pub change: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct FGLSResults {
+    n_obs: usize,
+
+    regression: Option<OLSResults>,
+    variance: Option<VarianceStructure>,
+    history: Vec<FGLSIteration>,
+    termination: Option<TerminationCondition>,
+}
+
+impl FGLSResults {
+    pub(crate) fn new(n_obs: usize) -> Self {
+        Self {
+            n_obs,
+
+            regression: None,
+            variance: None,
+            history: vec![],
+            termination: None,
+        }
+    }
+
+    pub(crate) fn set_fit(&mut self, fit: FGLSFit) {
+        self.regression = Some(fit.regression);
+        self.variance = Some(fit.variance);
+        self.history = fit.history;
+        self.termination = Some(fit.termination);
+    }
+
+    pub fn n_obs(&self) -> usize {
+        self.n_obs
+    }
+
+    /// Final least-squares regression on the reweighted or transformed data
+    pub fn regression(&self) -> Result<OLSResults, AmitaError> {
+        self.regression.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    pub fn variance(&self) -> Result<VarianceStructure, AmitaError> {
+        self.variance.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    pub fn history(&self) -> Vec<FGLSIteration> {
+        self.history.clone()
+    }
+
+    pub fn n_iter(&self) -> usize {
+        self.history.len()
+    }
+
+    pub fn termination(&self) -> Result<TerminationCondition, AmitaError> {
+        self.termination.ok_or(AmitaError::NotSolved)
+    }
+}
+
+impl BaseResults for FGLSResults {
+    fn coef(&self) -> Result<Array1<f64>, AmitaError> {
+        self.regression()?.coef()
+    }
+
+    fn se(&self) -> Result<Array1<f64>, AmitaError> {
+        self.regression()?.se()
+    }
+
+    fn t(&self) -> Result<Array1<f64>, AmitaError> {
+        self.regression()?.t()
+    }
+
+    fn p_vals(&self) -> Result<Array1<f64>, AmitaError> {
+        self.regression()?.p_vals()
+    }
+
+    fn vcov(&self) -> Result<Array2<f64>, AmitaError> {
+        self.regression()?.vcov()
+    }
+
+    fn summary(&self) -> Result<String, AmitaError> {
+        coef_table(self)
+    }
+}
+
+pub(crate) struct FGLSFit {
+    pub(crate) regression: OLSResults,
+    pub(crate) variance: VarianceStructure,
+    pub(crate) history: Vec<FGLSIteration>,
+    pub(crate) termination: TerminationCondition,
+}
+
+/// Alternates between estimating the variance parameters from the residuals
+/// y - Xβ and re-estimating β by least squares under them, starting from
+/// `coef`, until β changes by less than the tolerance. One iteration is
+/// two-step feasible GLS. Coefficients omitted for collinearity, which are
+/// NaN, count as zero in the residuals.
+pub(crate) fn iterate<F>(
+    y: &Array1<f64>,
+    x: &Array2<f64>,
+    coef: Array1<f64>,
+    max_iter: u64,
+    max_tolerance: f64,
+    step: F,
+) -> Result<FGLSFit, AmitaError>
+where
+    F: Fn(&Array1<f64>) -> Result<(OLSResults, VarianceStructure), AmitaError>,
+{
+    let mut coef = coef;
+    let mut history = vec![];
+
+    loop {
+        let (regression, variance) = step(&(y - &x.dot(&coef.map(|b| if b.is_nan() { 0. } else { *b }))))?;
+        let new_coef = regression.coef()?;
+        let change = (&new_coef - &coef).iter().fold(0., |acc: f64, d| acc.max(d.abs()));
+
+        history.push( FGLSIteration { coef: new_coef.clone(), variance_param: variance.param(), change } );
+        coef = new_coef;
+
+        let termination = if change < max_tolerance {
+            TerminationCondition::SolverConverged
+        } else if history.len() as u64 >= max_iter {
+            TerminationCondition::MaxIterReached
+        } else {
+            continue;
+        };
+
+        return Ok( FGLSFit { regression, variance, history, termination } );
+    }
+}
@@ -1 +1,4 @@
-pub mod ols;
\ No newline at end of file
+pub mod fgls;
+pub mod ols;
+pub mod quantile;
+pub mod system;
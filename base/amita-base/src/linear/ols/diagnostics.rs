@@ -0,0 +1,420 @@
+//! Specification tests on a solved OLS model. With weights, they apply to
+//! the transformed model √w y = √w X β + √w e, and rows are taken to be in
+//! time order by the serial correlation and structural break tests.
+
+use amita_error::AmitaError;
+use amita_utils::hypothesis::TestStatistic;
+use amita_utils::linalg::inverse;
+use amita_utils::traits::{BaseResults, BaseSolver};
+use ndarray::{concatenate, prelude::*};
+
+use super::OLSSolver;
+
+/// Sum of squared residuals of y on x
+fn rss(y: &Array1<f64>, x: &Array2<f64>) -> Result<f64, AmitaError> {
+    let resid = OLSSolver::new(y, x)?.solve()?.results().resid()?;
+    Ok( resid.dot(&resid) )
+}
+
+/// LM statistic n R^2 of the auxiliary regression of `dependent` on a
+/// constant and `z`
+fn auxiliary_lm(dependent: &Array1<f64>, z: &Array2<f64>) -> Result<f64, AmitaError> {
+    let n = dependent.len();
+    let z = concatenate![Axis(1), Array2::<f64>::ones((n, 1)), z.view()];
+    let r_sq = OLSSolver::new(dependent, &z)?.solve()?.results().r_sq()?;
+    Ok( n as f64 * r_sq )
+}
+
+/// √w y, √w X and √w e of the transformed model
+struct Transformed {
+    y: Array1<f64>,
+    x: Array2<f64>,
+    resid: Array1<f64>,
+}
+
+impl OLSSolver {
+    fn transformed(&self) -> Result<Transformed, AmitaError> {
+        let root = self.weights.map(|w| w.sqrt());
+        let resid = self.results.resid()?;
+
+        Ok( Transformed {
+            y: &self.y * &root,
+            x: &self.x_kept() * &root.view().insert_axis(Axis(1)),
+            resid: resid * &root,
+        } )
+    }
+
+    /// Regressors other than the intercept and the omitted ones
+    fn varying_regressors(&self) -> Array2<f64> {
+        let columns = (0..self.results.n_regressors)
+            .filter(|j| Some(*j) != self.results.intercept && !self.results.omitted.contains(j))
+            .collect::<Vec<_>>();
+        self.x.select(Axis(1), &columns)
+    }
+
+    /// Breusch–Pagan test of homoskedasticity against variances depending
+    /// on `z`, in Koenker's studentized form: n R^2 of e^2 on a constant
+    /// and z, χ²(columns of z). `None` uses the regressors of the model.
+    pub fn breusch_pagan(&self, z: Option<&Array2<f64>>) -> Result<TestStatistic, AmitaError> {
+        let Transformed { resid, .. } = self.transformed()?;
+        let z = match z {
+            Some(z) if z.shape()[0] != resid.len() => return Err(AmitaError::NotSameObservations),
+            Some(z) => z.to_owned(),
+            None => self.varying_regressors(),
+        };
+
+        let statistic = auxiliary_lm(&resid.map(|e| e.powi(2)), &z)?;
+        Ok( TestStatistic::chi_squared("Breusch-Pagan test", statistic, z.shape()[1] as f64) )
+    }
+
+    /// White's test of homoskedasticity: n R^2 of e^2 on the regressors,
+    /// their squares and cross products, dropping terms that duplicate
+    /// others such as squared dummies
+    pub fn white(&self) -> Result<TestStatistic, AmitaError> {
+        let Transformed { resid, .. } = self.transformed()?;
+        let regressors = self.varying_regressors();
+        let k = regressors.shape()[1];
+
+        let mut terms: Vec<Array1<f64>> = regressors.columns().into_iter().map(|x| x.to_owned()).collect();
+        for i in 0..k {
+            for j in i..k {
+                let product = &regressors.column(i) * &regressors.column(j);
+                let constant = product.iter().all(|x| *x == product[0]);
+                if !constant && !terms.contains(&product) {
+                    terms.push(product);
+                }
+            }
+        }
+
+        let views = terms.iter().map(|x| x.view()).collect::<Vec<_>>();
+        let z = ndarray::stack(Axis(1), &views).map_err(|_| AmitaError::NotSameObservations)?;
+
+        let statistic = auxiliary_lm(&resid.map(|e| e.powi(2)), &z)?;
+        Ok( TestStatistic::chi_squared("White test", statistic, z.shape()[1] as f64) )
+    }
+
+    /// Ramsey's RESET: F test of the powers 2, ..., `max_power` of the
+    /// fitted values added to the regressors
+    pub fn reset(&self, max_power: usize) -> Result<TestStatistic, AmitaError> {
+        let Transformed { y, x, resid } = self.transformed()?;
+        if max_power < 2 {
+            return Err(AmitaError::SolverFailed { message: "RESET needs powers of at least 2".to_string() });
+        }
+
+        // powers of ŷ scaled to at most 1 span the same space, and are
+        // better conditioned
+        let y_pred = &y - &resid;
+        let scale = y_pred.iter().fold(0., |acc: f64, y| acc.max(y.abs()));
+        let y_pred = y_pred / scale;
+        let powers = (2..=max_power)
+            .map(|p| y_pred.map(|y| y.powi(p as i32)).insert_axis(Axis(1)))
+            .collect::<Vec<_>>();
+        let powers = powers.iter().map(|p| p.view()).collect::<Vec<_>>();
+        let augmented = concatenate(Axis(1), &[&[x.view()], powers.as_slice()].concat())
+            .map_err(|_| AmitaError::NotSameObservations)?;
+
+        let rss_restricted = resid.dot(&resid);
+        let rss_unrestricted = rss(&y, &augmented)?;
+
+        let df_num = (max_power - 1) as f64;
+        let df_denom = y.len().checked_sub(augmented.shape()[1])
+            .filter(|df| *df > 0)
+            .ok_or_else(|| AmitaError::SolverFailed {
+                message: format!("RESET with powers up to {max_power} needs more observations than regressors")
+            })? as f64;
+        let statistic = ( (rss_restricted - rss_unrestricted) / df_num ) / ( rss_unrestricted / df_denom );
+
+        Ok( TestStatistic::f("Ramsey RESET test", statistic, df_num, df_denom) )
+    }
+
+    /// Jarque–Bera test of normal residuals, n/6 (S^2 + (K - 3)^2 / 4)
+    /// from their skewness S and kurtosis K, χ²(2)
+    pub fn jarque_bera(&self) -> Result<TestStatistic, AmitaError> {
+        let Transformed { resid, .. } = self.transformed()?;
+        let n = resid.len() as f64;
+
+        let mean = resid.mean().ok_or(AmitaError::NotSolved)?;
+        let moment = |power: i32| resid.map(|e| (e - mean).powi(power)).sum() / n;
+        let skewness = moment(3) / moment(2).powf(1.5);
+        let kurtosis = moment(4) / moment(2).powi(2);
+
+        let statistic = n / 6. * ( skewness.powi(2) + (kurtosis - 3.).powi(2) / 4. );
+        Ok( TestStatistic::chi_squared("Jarque-Bera test", statistic, 2.) )
+    }
+
+    /// Durbin–Watson statistic d = Σ (e_t - e_{t-1})^2 / Σ e_t^2, with the
+    /// p-value of a normal approximation from the exact mean and variance
+    /// of d under normal, serially independent errors
+    pub fn durbin_watson(&self) -> Result<TestStatistic, AmitaError> {
+        let Transformed { x, resid, .. } = self.transformed()?;
+        let n = resid.len();
+        let k = x.shape()[1];
+        if n <= k + 1 {
+            return Err(AmitaError::SolverFailed { message: "Durbin-Watson needs more than k + 1 observations".to_string() });
+        }
+
+        let diff = |v: ArrayView1<f64>| &v.slice(s![1..]) - &v.slice(s![..n - 1]);
+        let statistic = diff(resid.view()).map(|d| d.powi(2)).sum() / resid.dot(&resid);
+
+        // with A = D'D from the differences D and M the residual maker,
+        // P = tr(MA) and Q = tr((MA)^2)
+        let x_diff = Array2::from_shape_fn((n - 1, k), |(t, j)| x[[t + 1, j]] - x[[t, j]]);
+        let mut ax = Array2::zeros((n, k));
+        for t in 0..n {
+            for j in 0..k {
+                let up = if t > 0 { x_diff[[t - 1, j]] } else { 0. };
+                let down = if t < n - 1 { x_diff[[t, j]] } else { 0. };
+                ax[[t, j]] = up - down;
+            }
+        }
+
+        let gramian_inverse = inverse(&x.t().dot(&x), "X'X")?;
+        let xax = gramian_inverse.dot(&x_diff.t().dot(&x_diff));
+        let p = 2. * (n - 1) as f64 - xax.diag().sum();
+        let q = (6 * n - 8) as f64
+            - 2. * gramian_inverse.dot(&ax.t().dot(&ax)).diag().sum()
+            + xax.dot(&xax).diag().sum();
+
+        let df = (n - k) as f64;
+        let mean = p / df;
+        let variance = 2. * (q - p * mean) / (df * (df + 2.));
+
+        Ok( TestStatistic::normal_approximation("Durbin-Watson test", statistic, mean, variance.sqrt()) )
+    }
+
+    /// Breusch–Godfrey LM test of no serial correlation up to `lags`: n R^2
+    /// of e on the regressors and e_{t-1}, ..., e_{t-lags}, with presample
+    /// residuals set to zero, χ²(lags)
+    pub fn breusch_godfrey(&self, lags: usize) -> Result<TestStatistic, AmitaError> {
+        let Transformed { x, resid, .. } = self.transformed()?;
+        let n = resid.len();
+        if lags == 0 || n <= x.shape()[1] + lags {
+            return Err(AmitaError::SolverFailed { message: format!("cannot test {lags} lags") });
+        }
+
+        let lagged = Array2::from_shape_fn((n, lags), |(t, l)| if t > l { resid[t - l - 1] } else { 0. });
+        let augmented = concatenate![Axis(1), x, lagged];
+
+        // uncentered, as e need not have mean zero without an intercept
+        let statistic = n as f64 * ( 1. - rss(&resid, &augmented)? / resid.dot(&resid) );
+        Ok( TestStatistic::chi_squared("Breusch-Godfrey test", statistic, lags as f64) )
+    }
+
+    /// Wald form of the Chow test, k F, at the break before row `index`
+    fn chow_wald(&self, index: usize) -> Result<f64, AmitaError> {
+        let Transformed { y, x, resid } = self.transformed()?;
+        let (n, k) = (y.len(), x.shape()[1]);
+        if index <= k || index >= n || n - index <= k {
+            return Err(AmitaError::InvalidBreakPoint { index });
+        }
+
+        let rss_first = rss(&y.slice(s![..index]).to_owned(), &x.slice(s![..index, ..]).to_owned())?;
+        let rss_second = rss(&y.slice(s![index..]).to_owned(), &x.slice(s![index.., ..]).to_owned())?;
+        let rss_split = rss_first + rss_second;
+
+        Ok( (resid.dot(&resid) - rss_split) / ( rss_split / (n - 2 * k) as f64 ) )
+    }
+
+    /// Chow test that all coefficients are the same before and from row
+    /// `index`, F(k, n - 2k)
+    pub fn chow(&self, index: usize) -> Result<TestStatistic, AmitaError> {
+        let (n, k) = (self.results.n_obs, self.results.rank());
+        let statistic = self.chow_wald(index)? / k as f64;
+        Ok( TestStatistic::f("Chow test", statistic, k as f64, (n - 2 * k) as f64) )
+    }
+
+    /// Quandt–Andrews test of a break at an unknown row: the largest Chow
+    /// Wald statistic over breaks within the middle 1 - 2 `trim` of the
+    /// sample, against Andrews' limiting distribution. Returns the test and
+    /// the row of the most likely break.
+    pub fn quandt_andrews(&self, trim: f64) -> Result<(TestStatistic, usize), AmitaError> {
+        if trim.is_nan() || trim <= 0. || trim >= 0.5 {
+            return Err(AmitaError::InvalidTrimming { trim });
+        }
+
+        let (n, k) = (self.results.n_obs, self.results.rank());
+        let first = (trim * n as f64).ceil() as usize;
+        let last = ((1. - trim) * n as f64).floor() as usize;
+
+        let mut sup = (f64::NEG_INFINITY, first);
+        for index in first.max(k + 1)..=last.min(n.saturating_sub(k + 1)) {
+            let wald = self.chow_wald(index)?;
+            if wald > sup.0 {
+                sup = (wald, index);
+            }
+        }
+        if !sup.0.is_finite() {
+            return Err(AmitaError::InvalidBreakPoint { index: first });
+        }
+
+        Ok( (TestStatistic::sup_wald("Quandt-Andrews test", sup.0, k as f64, trim), sup.1) )
+    }
+
+    /// Variance inflation factors 1 / (1 - R_j^2) of the estimated
+    /// regressors other than the intercept, from regressing each on all the
+    /// others, by name
+    pub fn vif(&self) -> Result<Vec<(String, f64)>, AmitaError> {
+        let names = self.results.coef_names()?;
+        let kept = &self.decomposed()?.kept;
+
+        kept.iter()
+            .copied()
+            .filter(|j| Some(*j) != self.results.intercept)
+            .map(|j| {
+                let others = kept.iter().copied().filter(|l| *l != j).collect::<Vec<_>>();
+                let column = self.x.column(j).to_owned();
+                let r_sq = match self.results.intercept {
+                    Some(_) => OLSSolver::new(&column, &self.x.select(Axis(1), &others))?.solve()?.results().r_sq()?,
+                    // uncentered without an intercept
+                    None => 1. - rss(&column, &self.x.select(Axis(1), &others))? / column.dot(&column),
+                };
+                Ok( (names[j].clone(), 1. / (1. - r_sq)) )
+            })
+            .collect()
+    }
+
+    /// The tests needing no further choices: Breusch–Pagan on the
+    /// regressors, White, RESET with powers up to 4, Jarque–Bera and
+    /// Durbin–Watson. [`OLSSolver::with_diagnostics`] reports them in the
+    /// summary of the results.
+    pub fn diagnostics(&self) -> Result<Vec<TestStatistic>, AmitaError> {
+        Ok( vec![
+            self.breusch_pagan(None)?,
+            self.white()?,
+            self.reset(4)?,
+            self.jarque_bera()?,
+            self.durbin_watson()?,
+        ] )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use amita_utils::hypothesis::LinearHypothesis;
+    use amita_utils::restrictions::LinearRestrictions;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use statrs::distribution::Normal;
+
+    use super::*;
+
+    fn draws(rng: &mut StdRng, n: usize) -> Array1<f64> {
+        let normal = Normal::new(0., 1.).unwrap();
+        Array1::from_iter( (0..n).map(|_| rng.sample(normal)) )
+    }
+
+    fn fit(y: &Array1<f64>, x1: &Array1<f64>, x2: &Array1<f64>) -> Result<OLSSolver, AmitaError> {
+        let x = ndarray::stack![Axis(1), Array1::<f64>::ones(y.len()), *x1, *x2];
+        OLSSolver::new(y, &x)?
+            .with_coef_names(["const", "x1", "x2"].map(String::from).to_vec())?
+            .solve()
+    }
+
+    #[test]
+    fn test_well_specified() -> Result<(), AmitaError> {
+        let mut rng = StdRng::seed_from_u64(11);
+        let n = 200;
+        let (x1, x2, e) = (draws(&mut rng, n), draws(&mut rng, n), draws(&mut rng, n));
+        let y = 1. + &x1 - 0.5 * &x2 + e;
+
+        let solver = fit(&y, &x1, &x2)?;
+        for test in solver.diagnostics()? {
+            assert!( test.p_val() > 0.01, "{test}" );
+        }
+        assert!( solver.breusch_godfrey(2)?.p_val() > 0.01 );
+        assert!( solver.chow(n / 2)?.p_val() > 0.01 );
+
+        let dw = solver.durbin_watson()?;
+        assert!( (dw.statistic() - 2.).abs() < 0.4 );
+        let vif = solver.vif()?;
+        assert_eq!(vif.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>(), vec!["x1", "x2"]);
+        assert!( vif.iter().all(|(_, v)| *v >= 1. && *v < 1.2) );
+
+        assert!( matches!(solver.chow(2), Err(AmitaError::InvalidBreakPoint { .. })) );
+        assert!( matches!(solver.chow(n + 5), Err(AmitaError::InvalidBreakPoint { .. })) );
+        assert!( matches!(solver.quandt_andrews(0.6), Err(AmitaError::InvalidTrimming { .. })) );
+
+        // the summary reports them when asked to
+        let x = ndarray::stack![Axis(1), Array1::<f64>::ones(n), x1, x2];
+        let summary = OLSSolver::new(&y, &x)?.with_diagnostics().solve()?.results().summary()?;
+        for test in solver.diagnostics()? {
+            assert!( summary.contains(&test.to_string()) );
+        }
+        assert!( !solver.results().summary()?.contains("Jarque-Bera") );
+
+        let rows = [0, 1, 2, 3, 4];
+        let small = fit(&y.select(Axis(0), &rows), &x1.select(Axis(0), &rows), &x2.select(Axis(0), &rows))?;
+        assert!( matches!(small.reset(4), Err(AmitaError::SolverFailed { .. })) );
+
+        let unsolved = OLSSolver::new(&y, &x.slice(ndarray::s![.., 1..]).to_owned())?;
+        assert!( matches!(unsolved.jarque_bera(), Err(AmitaError::NotSolved)) );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_misspecified() -> Result<(), AmitaError> {
+        let mut rng = StdRng::seed_from_u64(12);
+        let n = 200;
+        let (x1, x2, v) = (draws(&mut rng, n), draws(&mut rng, n), draws(&mut rng, n));
+
+        // variance growing with x1
+        let y = 1. + &x1 + &x2 + &v * &x1.map(|x| 0.2 + 2. * x.abs());
+        let solver = fit(&y, &x1, &x2)?;
+        assert!( solver.breusch_pagan(None)?.p_val() < 0.01 );
+        assert!( solver.white()?.p_val() < 0.01 );
+        assert_eq!(solver.white()?.df(), vec![5.]);
+
+        // an omitted square
+        let y = 1. + &x1 + x1.map(|x| x.powi(2)) + &v;
+        assert!( fit(&y, &x1, &x2)?.reset(3)?.p_val() < 0.01 );
+
+        // AR(1) errors
+        let mut e = v.clone();
+        for t in 1..n {
+            e[t] = 0.7 * e[t - 1] + v[t];
+        }
+        let solver = fit(&(1. + &x1 + &e), &x1, &x2)?;
+        let dw = solver.durbin_watson()?;
+        assert!( dw.statistic() < 1. && dw.p_val() < 0.01 );
+        assert!( solver.breusch_godfrey(1)?.p_val() < 0.01 );
+
+        // nearly collinear regressors
+        let x3 = &x1 + 0.1 * &x2;
+        let vif = fit(&y, &x1, &x3)?.vif()?;
+        assert!( vif.iter().all(|(_, v)| *v > 50.) );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_structural_break() -> Result<(), AmitaError> {
+        let mut rng = StdRng::seed_from_u64(13);
+        let n = 200;
+        let (x1, x2, v) = (draws(&mut rng, n), draws(&mut rng, n), draws(&mut rng, n));
+        let after = Array1::from_iter( (0..n).map(|t| if t >= 120 { 1. } else { 0. }) );
+        let y = 1. + &x1 + &x2 + &after * &x1 + v;
+
+        let solver = fit(&y, &x1, &x2)?;
+        let chow = solver.chow(120)?;
+        assert!( chow.p_val() < 1e-4 );
+
+        // the Chow test is the F test of the interactions with the regime
+        let x = ndarray::stack![
+            Axis(1), Array1::<f64>::ones(n), x1, x2, after, &after * &x1, &after * &x2
+        ];
+        let interacted = OLSSolver::new(&y, &x)?.solve()?.results();
+        let mut r = Array2::zeros((3, 6));
+        for row in 0..3 {
+            r[[row, row + 3]] = 1.;
+        }
+        let test = interacted.test_linear(&LinearRestrictions::new(&r, &Array1::zeros(3))?)?;
+        assert!( (test.f().unwrap().statistic() - chow.statistic()).abs() < 1e-8 );
+
+        let (quandt_andrews, index) = solver.quandt_andrews(0.15)?;
+        assert!( quandt_andrews.p_val() < 0.01 );
+        assert!( (index as i64 - 120).abs() <= 10 );
+
+        Ok(())
+    }
+}
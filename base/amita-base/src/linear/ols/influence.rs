@@ -0,0 +1,205 @@
+//! Influence and outlier statistics of a solved OLS model, from the hat
+//! values h_i, the diagonal of the projection onto the columns of √W X.
+//! With weights, they are those of the transformed model
+//! √w y = √w X β + √w e.
+
+use amita_error::AmitaError;
+use amita_utils::traits::BaseResults;
+use ndarray::prelude::*;
+use polars::prelude::*;
+
+use super::{OLSSolver, WeightType};
+
+impl OLSSolver {
+    /// Hat values h_i, the diagonal of √W X (X'WX)^{-1} X'√W, computed
+    /// in blocks of rows rather than from Q, which is never formed. An
+    /// unsolved model is decomposed first.
+    pub fn leverage(&self) -> Result<Array1<f64>, AmitaError> {
+        const BLOCK_ROWS: usize = 4096;
+
+        let solver = self.decomposed()?;
+        let x = solver.x_kept();
+        let mut leverage = Array1::zeros(x.shape()[0]);
+        for ((x, weights), mut leverage) in x.axis_chunks_iter(Axis(0), BLOCK_ROWS)
+            .zip(solver.weights.axis_chunks_iter(Axis(0), BLOCK_ROWS))
+            .zip(leverage.axis_chunks_iter_mut(Axis(0), BLOCK_ROWS))
+        {
+            let quadratic_forms = ( x.dot(&solver.x_gramian_inverse) * x ).sum_axis(Axis(1));
+            leverage.assign(&(quadratic_forms * weights));
+        }
+        Ok( leverage )
+    }
+
+    /// One row per observation, in the order of the data, with columns
+    /// - `leverage`: hat values h_i
+    /// - `rstandard`: internally studentized residuals e_i / (s √(1 - h_i))
+    /// - `rstudent`: externally studentized residuals, with s_(i) estimated
+    ///   without observation i
+    /// - `cooks_d`: Cook's distance r_i^2 h_i / (k (1 - h_i))
+    /// - `dffits`: t_i √(h_i / (1 - h_i))
+    /// - `dfbeta_<name>`: change in each coefficient when dropping
+    ///   observation i, scaled by its standard error with s_(i), NaN for
+    ///   omitted regressors
+    ///
+    /// s has the model's residual degrees of freedom. Under frequency
+    /// weights, dropping row i drops the w_i observations it stands for.
+    pub fn influence(&self) -> Result<DataFrame, AmitaError> {
+        let n = self.results.n_obs;
+        let k = self.results.rank();
+        let df = self.results.df_resid();
+        if df <= 1. {
+            return Err(AmitaError::SolverFailed {
+                message: "influence statistics need more than k + 1 observations".to_string()
+            });
+        }
+
+        let root = self.weights.map(|w| w.sqrt());
+        let resid = self.results.resid()? * &root;
+        let leverage = self.leverage()?;
+        let dropped_obs = match self.results.weight_type {
+            Some(WeightType::Frequency) => self.weights.clone(),
+            _ => Array1::ones(n),
+        };
+
+        let sigma_sq = resid.dot(&resid) / df;
+        let sigma_sq_dropped = Array1::from_iter(
+            resid.iter().zip(leverage.iter()).zip(dropped_obs.iter())
+                .map(|((e, h), d)| ( df * sigma_sq - e.powi(2) / (1. - h) ) / (df - d))
+        );
+
+        let one_minus_h = leverage.map(|h| 1. - h);
+        let rstandard = &resid / &(one_minus_h.map(|m| m.sqrt()) * sigma_sq.sqrt());
+        let rstudent = &resid / &(&one_minus_h * &sigma_sq_dropped).map(|v| v.sqrt());
+        let cooks_d = rstandard.map(|r| r.powi(2)) * &leverage / &(&one_minus_h * k as f64);
+        let dffits = &rstudent * &(&leverage / &one_minus_h).map(|v| v.sqrt());
+
+        // β - β_(i) = (X'WX)^{-1} x_i √w_i e_i / (1 - h_i)
+        let x_gramian_inverse = &self.x_gramian_inverse;
+        let x_weighted = &self.x_kept() * &root.view().insert_axis(Axis(1));
+        let change = x_weighted.dot(x_gramian_inverse) * &(&resid / &one_minus_h).insert_axis(Axis(1));
+        let dfbeta = change
+            / &x_gramian_inverse.diag().map(|v| v.sqrt()).insert_axis(Axis(0))
+            / &sigma_sq_dropped.map(|v| v.sqrt()).insert_axis(Axis(1));
+
+        let mut columns = vec![
+            Series::new("leverage", leverage.to_vec()),
+            Series::new("rstandard", rstandard.to_vec()),
+            Series::new("rstudent", rstudent.to_vec()),
+            Series::new("cooks_d", cooks_d.to_vec()),
+            Series::new("dffits", dffits.to_vec()),
+        ];
+        for (j, name) in self.results.coef_names()?.iter().enumerate() {
+            let column = match self.kept.iter().position(|kept| *kept == j) {
+                Some(position) => dfbeta.column(position).to_vec(),
+                None => vec![f64::NAN; n],
+            };
+            columns.push(Series::new(&format!("dfbeta_{name}"), column));
+        }
+
+        DataFrame::new(columns).map_err(|e| AmitaError::SolverFailed { message: e.to_string() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use amita_utils::frame::column_to_array1;
+    use amita_utils::linalg::inverse;
+    use amita_utils::traits::BaseSolver;
+    use ndarray::{array, Axis};
+
+    use super::*;
+
+    #[test]
+    fn test_influence_matches_deletion() -> Result<(), AmitaError> {
+        let x1 = array![0.5, 1.7, 2.2, 3.1, 0.9, 2.8, 1.4, 3.6, 2.0, 9.0];
+        let y = array![1.9, 4.1, 4.0, 7.2, 2.4, 5.9, 3.8, 8.1, 4.4, 11.0];
+        let x = ndarray::stack![Axis(1), Array1::<f64>::ones(10), x1];
+        let n = y.len();
+
+        let solver = OLSSolver::new(&y, &x)?
+            .with_coef_names(vec!["const".to_string(), "x1".to_string()])?
+            .solve()?;
+        let full = solver.results();
+        let influence = solver.influence()?;
+        assert_eq!(influence.height(), n);
+        assert!( (solver.leverage()?.sum() - 2.).abs() < 1e-10 );
+
+        // an unsolved model is decomposed for its hat values
+        let unsolved = OLSSolver::new(&y, &x)?.leverage()?;
+        assert!( (unsolved - solver.leverage()?).iter().all(|d| d.abs() < 1e-12) );
+
+        let cooks_d = column_to_array1(&influence, "cooks_d")?;
+        let rstudent = column_to_array1(&influence, "rstudent")?;
+        let dfbeta = column_to_array1(&influence, "dfbeta_x1")?;
+
+        let vcov = full.vcov()?;
+        let vcov_inverse = inverse(&vcov, "vcov")?;
+        let sigma_sq = full.resid()?.dot(&full.resid()?) / (n - 2) as f64;
+
+        // every statistic against refitting without the observation
+        for i in 0..n {
+            let kept = (0..n).filter(|j| *j != i).collect::<Vec<_>>();
+            let dropped = OLSSolver::new(&y.select(Axis(0), &kept), &x.select(Axis(0), &kept))?
+                .solve()?
+                .results();
+
+            let change = full.coef()? - dropped.coef()?;
+            let expected_cooks_d = change.dot(&vcov_inverse.dot(&change)) / 2.;
+            assert!( (cooks_d[i] - expected_cooks_d).abs() < 1e-8 );
+
+            // the dropped fit's error variance and prediction of y_i
+            let resid = dropped.resid()?;
+            let sigma_dropped = (resid.dot(&resid) / (n - 3) as f64).sqrt();
+            let prediction_error = y[i] - x.row(i).dot(&dropped.coef()?);
+            let h = solver.leverage()?[i];
+            assert!( (rstudent[i] - prediction_error * (1. - h).sqrt() / sigma_dropped).abs() < 1e-8 );
+
+            let se_dropped = sigma_dropped * (vcov[[1, 1]] / sigma_sq).sqrt();
+            assert!( (dfbeta[i] - change[1] / se_dropped).abs() < 1e-8 );
+        }
+
+        // the high-leverage point stands out
+        let leverage = column_to_array1(&influence, "leverage")?;
+        assert!( leverage[9] > 0.5 );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_frequency_weights() -> Result<(), AmitaError> {
+        let x1 = array![0.5, 1.7, 2.2, 3.1, 0.9, 2.8, 1.4, 3.6, 2.0, 9.0];
+        let y = array![1.9, 4.1, 4.0, 7.2, 2.4, 5.9, 3.8, 8.1, 4.4, 11.0];
+        let weights = array![1., 3., 2., 1., 4., 2., 1., 3., 2., 1.];
+        let x = ndarray::stack![Axis(1), Array1::<f64>::ones(10), x1];
+        let n = y.len();
+
+        let solver = OLSSolver::new(&y, &x)?
+            .with_weights(&weights, WeightType::Frequency)?
+            .solve()?;
+        let full = solver.results();
+        let influence = solver.influence()?;
+        let rstandard = column_to_array1(&influence, "rstandard")?;
+        let rstudent = column_to_array1(&influence, "rstudent")?;
+        let leverage = solver.leverage()?;
+
+        // s is the model's, on N - k = 17 degrees of freedom
+        let sigma = full.sigma_sq()?.sqrt();
+        let resid = full.resid()? * &weights.map(|w| w.sqrt());
+        for i in 0..n {
+            assert!( (rstandard[i] - resid[i] / (sigma * (1. - leverage[i]).sqrt())).abs() < 1e-10 );
+        }
+
+        // s_(i) is that of the model without the w_i copies of row i
+        for i in 0..n {
+            let kept = (0..n).filter(|j| *j != i).collect::<Vec<_>>();
+            let dropped = OLSSolver::new(&y.select(Axis(0), &kept), &x.select(Axis(0), &kept))?
+                .with_weights(&weights.select(Axis(0), &kept), WeightType::Frequency)?
+                .solve()?
+                .results();
+            let expected = resid[i] / (dropped.sigma_sq()? * (1. - leverage[i])).sqrt();
+            assert!( (rstudent[i] - expected).abs() < 1e-8 );
+        }
+
+        Ok(())
+    }
+}
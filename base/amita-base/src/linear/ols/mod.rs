@@ -0,0 +1,927 @@
+use std::borrow::Cow;
+
+use amita_error::AmitaError;
+use amita_utils::hypothesis::{students_t_p_vals, LinearHypothesis, TestStatistic};
+use amita_utils::inference::{multiway_cluster_meat, SolverSEType};
+use amita_utils::linalg::{pivoted_qr_with_rhs, solve_upper_triangular, upper_triangular_inverse};
+use amita_utils::parallel::weighted_gramian;
+use amita_utils::restrictions::LinearRestrictions;
+use amita_utils::summary::coef_table;
+use amita_utils::traits::{BaseSolver, BaseResults};
+use ndarray::{Array1, Array2, Axis, CowArray, Ix2, ShapeBuilder, Zip};
+
+use crate::glm::link::Link;
+use crate::prediction::Prediction;
+
+mod diagnostics;
+mod influence;
+pub mod streaming;
+pub mod wild_cluster;
+
+/// Interpretation of observation weights, following Stata's conventions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WeightType {
+    /// Inversely proportional to the variance of each observation, such as
+    /// the cell sizes of averaged data
+    Analytic,
+    /// Integer number of identical observations each row stands for
+    Frequency,
+    /// Inverse probabilities of sampling, which only robust or clustered
+    /// standard errors account for
+    Probability,
+}
+
+#[derive(Debug, Clone)]
+pub struct OLSResults {
+    n_obs: usize,
+    n_regressors: usize,
+    se_type: SolverSEType,
+    weight_type: Option<WeightType>,
+    sum_weights: f64,
+    coef_names: Option<Vec<String>>,
+    intercept: Option<usize>, // column of x that is constant
+    omitted: Vec<usize>, // columns of x collinear with the others
+    n_clusters: Option<usize>, // fewest clusters of any dimension
+
+    // estimates
+    coef: Option<Array1<f64>>, // beta
+    se: Option<Array1<f64>>, // standard error of beta
+    y_pred: Option<Array1<f64>>, // predicted y, or fitted y
+    resid: Option<Array1<f64>>, // residuals, or the error term
+    t: Option<Array1<f64>>, // t statistics
+    p_vals: Option<Array1<f64>>, // p values of H_0: \beta = 0
+    vcov: Option<Array2<f64>>, // variance-covariance matrix of beta
+    df_t: Option<f64>, // degrees of freedom of the t statistics
+    sigma_sq: Option<f64>, // error variance, for unit weight
+
+    // goodness of fit
+    r_sq: Option<f64>,
+    r_sq_adj: Option<f64>,
+
+    diagnostics: Vec<TestStatistic>, // reported in the summary
+}
+
+impl OLSResults {
+    /// Results before solving, whose estimates are all `NotSolved`
+    fn unsolved(n_obs: usize, n_regressors: usize) -> Self {
+        OLSResults {
+            n_obs,
+            n_regressors,
+            se_type: SolverSEType::NonRobust,
+            weight_type: None,
+            sum_weights: n_obs as f64,
+            coef_names: None,
+            intercept: None,
+            omitted: vec![],
+            n_clusters: None,
+
+            coef: None,
+            se: None,
+            y_pred: None,
+            resid: None,
+            t: None,
+            p_vals: None,
+            vcov: None,
+            df_t: None,
+            sigma_sq: None,
+
+            r_sq: None,
+            r_sq_adj: None,
+
+            diagnostics: vec![],
+        }
+    }
+
+    /// Number of rows of the data
+    pub fn n_obs(&self) -> usize {
+        self.n_obs
+    }
+
+    pub fn se_type(&self) -> SolverSEType {
+        self.se_type.clone()
+    }
+
+    pub fn weight_type(&self) -> Option<WeightType> {
+        self.weight_type
+    }
+
+    /// Number of observations the data represent: the sum of the weights
+    /// with frequency weights, and the number of rows otherwise
+    pub fn n_effective(&self) -> f64 {
+        match self.weight_type {
+            Some(WeightType::Frequency) => self.sum_weights,
+            _ => self.n_obs as f64,
+        }
+    }
+
+    /// Number of linearly independent regressors, which are estimated
+    pub fn rank(&self) -> usize {
+        self.n_regressors - self.omitted.len()
+    }
+
+    /// Names of the regressors omitted because of collinearity with the
+    /// regressors before them, whose coefficients are NaN
+    pub fn omitted(&self) -> Result<Vec<String>, AmitaError> {
+        let names = self.coef_names()?;
+        Ok( self.omitted.iter().map(|j| names[*j].clone()).collect() )
+    }
+
+    /// Number of clusters with clustered standard errors, the fewest of any
+    /// dimension with multiway clustering
+    pub fn n_clusters(&self) -> Result<usize, AmitaError> {
+        self.n_clusters.ok_or(AmitaError::NotSolved)
+    }
+
+    /// Residual degrees of freedom
+    pub fn df_resid(&self) -> f64 {
+        self.n_effective() - self.rank() as f64
+    }
+
+    /// Degrees of freedom of the t statistics: the residual degrees of
+    /// freedom, or one less than the fewest clusters
+    pub fn df_t(&self) -> Result<f64, AmitaError> {
+        self.df_t.ok_or(AmitaError::NotSolved)
+    }
+
+    pub fn y_pred(&self) -> Result<Array1<f64>, AmitaError> {
+        self.y_pred.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    pub fn resid(&self) -> Result<Array1<f64>, AmitaError> {
+        self.resid.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    pub fn r_sq(&self) -> Result<f64, AmitaError> {
+        self.r_sq.ok_or(AmitaError::NotSolved)
+    }
+
+    pub fn r_sq_adj(&self) -> Result<f64, AmitaError> {
+        self.r_sq_adj.ok_or(AmitaError::NotSolved)
+    }
+
+    /// Estimated error variance Σ w_i e_i^2 / (N - k), for unit weight
+    pub fn sigma_sq(&self) -> Result<f64, AmitaError> {
+        self.sigma_sq.ok_or(AmitaError::NotSolved)
+    }
+
+    /// Predictions at the rows of `new_x`, with the columns of the fitted
+    /// x, using the covariance the model was fitted with for the mean and
+    /// the error variance for prediction intervals: σ^2 / w_i with
+    /// analytic weights, and none with probability weights, which do not
+    /// describe the errors
+    pub fn predict(&self, new_x: &Array2<f64>) -> Result<Prediction, AmitaError> {
+        let prediction = Prediction::new(new_x, &self.coef()?, &self.vcov()?, None, Link::Identity)?
+            .with_df(self.df_t);
+        Ok( match self.weight_type {
+            None | Some(WeightType::Frequency) => prediction.with_error_variance(self.sigma_sq()?),
+            Some(WeightType::Analytic) => prediction.with_weighted_error_variance(self.sigma_sq()?),
+            Some(WeightType::Probability) => prediction,
+        } )
+    }
+
+    /// Specification tests run with [`OLSSolver::with_diagnostics`], empty
+    /// otherwise
+    pub fn diagnostics(&self) -> Vec<TestStatistic> {
+        self.diagnostics.clone()
+    }
+
+    /// F test that every estimated coefficient but the intercept is zero, with the
+    /// covariance the model was fitted with
+    pub fn overall_f_test(&self) -> Result<TestStatistic, AmitaError> {
+        let tested = (0..self.n_regressors)
+            .filter(|j| Some(*j) != self.intercept && !self.omitted.contains(j))
+            .collect::<Vec<_>>();
+
+        let mut r = Array2::zeros((tested.len(), self.n_regressors));
+        for (row, j) in tested.iter().enumerate() {
+            r[[row, *j]] = 1.;
+        }
+        let restrictions = LinearRestrictions::new(&r, &Array1::zeros(tested.len()))?;
+
+        let test = self.test_linear(&restrictions)?;
+        test.f().cloned().ok_or(AmitaError::NotSolved)
+    }
+}
+
+
+impl BaseResults for OLSResults {
+    fn coef(&self) -> Result<Array1<f64>, AmitaError> {
+        let coef = self.coef.clone()
+            .ok_or(AmitaError::NotSolved)?;
+        Ok(coef)
+
+    }
+
+    fn se(&self) -> Result<Array1<f64>, AmitaError> {
+        self.se.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn t(&self) -> Result<Array1<f64>, AmitaError> {
+        self.t.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn p_vals(&self) -> Result<Array1<f64>, AmitaError> {
+        self.p_vals.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn vcov(&self) -> Result<Array2<f64>, AmitaError> {
+        self.vcov.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    /// Fit statistics, the coefficient table, the omitted regressors and
+    /// any diagnostics
+    fn summary(&self) -> Result<String, AmitaError> {
+        let mut summary = format!(
+            "Number of obs = {}\nR-squared = {:.4}\nAdj R-squared = {:.4}\n",
+            self.n_obs, self.r_sq()?, self.r_sq_adj()?,
+        );
+        if let Ok(f) = self.overall_f_test() {
+            summary += &format!("{f}\n");
+        }
+
+        summary += "\n";
+        summary += &coef_table(self)?;
+
+        let omitted = self.omitted()?;
+        if !omitted.is_empty() {
+            summary += &format!("Omitted because of collinearity: {}\n", omitted.join(", "));
+        }
+        if !self.diagnostics.is_empty() {
+            summary += "\n";
+            for test in self.diagnostics.iter() {
+                summary += &format!("{test}\n");
+            }
+        }
+
+        Ok(summary)
+    }
+
+    fn coef_names(&self) -> Result<Vec<String>, AmitaError> {
+        match &self.coef_names {
+            Some(names) => Ok( names.clone() ),
+            None => Ok( (0..self.n_regressors).map(|j| format!("x{j}")).collect() ),
+        }
+    }
+
+    fn small_sample_df(&self) -> Option<f64> {
+        self.df_t
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct OLSSolver {
+    y: Array1<f64>,
+    x: Array2<f64>,
+    weights: Array1<f64>,
+    collinearity_tolerance: f64,
+
+    // linearly independent columns of x, and the QR decomposition of
+    // W^{1/2} X restricted to them, without Q, found when solving
+    kept: Vec<usize>,
+    r: Array2<f64>,
+    qty: Array1<f64>, // Q^{\transpose} W^{1/2} y
+    x_gramian_inverse: Array2<f64>, // (X^{\transpose} W X)^{-1} = R^{-1} R^{-\transpose}
+    report_diagnostics: bool,
+
+    results: OLSResults
+}
+
+impl BaseSolver<OLSResults> for OLSSolver {
+    fn results(&self) -> OLSResults {
+        self.results.clone()
+    }
+
+    fn solve(self) -> Result<Self, AmitaError> {
+        self
+        .decompose()?
+        .solve_coef()?
+        .solve_se()?
+        .solve_r_sq()?
+        .solve_diagnostics()
+    }
+}
+
+// initializers
+impl OLSSolver {
+    pub fn new(
+        y: &Array1<f64>,
+        x: &Array2<f64>,
+    ) -> Result<Self, AmitaError> {
+        OLSSolver::from_owned(y.to_owned(), x.to_owned())
+    }
+
+    /// As [`OLSSolver::new`], taking ownership of data built for the
+    /// regression rather than copying it
+    pub fn from_owned(
+        y: Array1<f64>,
+        x: Array2<f64>,
+    ) -> Result<Self, AmitaError> {
+        OLSSolver::validate_data(&y, &x)?;
+
+        let weights = Array1::ones(y.len());
+
+        let n_obs = x.shape()[0];
+        let n_regressors = x.shape()[1];
+        let intercept = x.columns().into_iter()
+            .position(|column| column.first().is_some_and(|c| *c != 0.) && column.iter().all(|x| *x == column[0]));
+
+        let mut results = OLSResults::unsolved(n_obs, n_regressors);
+        results.intercept = intercept;
+
+        Ok( OLSSolver {
+            y,
+            x,
+            weights,
+            collinearity_tolerance: 1e-7,
+            kept: vec![],
+            r: Array2::zeros((0, 0)),
+            qty: Array1::zeros(0),
+            x_gramian_inverse: Array2::zeros((0, 0)),
+            report_diagnostics: false,
+            results,
+        } )
+    }
+
+    /// Weighted least squares, minimizing Σ w_i (y_i - x_i'β)^2. Weights
+    /// must be positive, and integers when they are frequencies.
+    pub fn with_weights(
+        mut self,
+        weights: &Array1<f64>,
+        weight_type: WeightType,
+    ) -> Result<Self, AmitaError> {
+        if weights.len() != self.y.len() {
+            return Err(AmitaError::NotSameObservations);
+        }
+        if weights.iter().any(|w| *w <= 0. || !w.is_finite()) {
+            return Err(AmitaError::NonPositive { matrix_name: "weights".to_string() });
+        }
+        if weight_type == WeightType::Frequency && weights.iter().any(|w| w.fract() != 0.) {
+            return Err(AmitaError::NonInteger { matrix_name: "frequency weights".to_string() });
+        }
+
+        self.weights = weights.to_owned();
+        self.results.weight_type = Some(weight_type);
+        self.results.sum_weights = weights.sum();
+
+        Ok(self)
+    }
+
+    /// Tolerance below which a column's norm orthogonal to the columns
+    /// before it, relative to its own norm, makes it collinear with them.
+    /// Collinear columns are omitted, with NaN coefficients.
+    pub fn with_collinearity_tolerance(mut self, tolerance: f64) -> Result<Self, AmitaError> {
+        validate_collinearity_tolerance(tolerance)?;
+        self.collinearity_tolerance = tolerance;
+        Ok(self)
+    }
+
+    /// Names of the columns of x, which hypotheses on the results refer to
+    pub fn with_coef_names(mut self, names: Vec<String>) -> Result<Self, AmitaError> {
+        if names.len() != self.results.n_regressors {
+            return Err(AmitaError::NotSameObservations);
+        }
+
+        self.results.coef_names = Some(names);
+        Ok(self)
+    }
+
+    pub fn with_se_type(mut self, se_type: SolverSEType) -> Self {
+        self.results.se_type = se_type;
+        self
+    }
+
+    pub fn with_robust_se(mut self) -> Self {
+        self.results.se_type = SolverSEType::Robust;
+        self
+    }
+
+    pub fn with_nonrobust_se(mut self) -> Self {
+        self.results.se_type = SolverSEType::NonRobust;
+        self
+    }
+
+    /// Runs [`OLSSolver::diagnostics`] when solving, for the summary
+    pub fn with_diagnostics(mut self) -> Self {
+        self.report_diagnostics = true;
+        self
+    }
+
+    fn validate_data(
+        y: &Array1<f64>,
+        x: &Array2<f64>,
+    ) -> Result<(), AmitaError> {
+
+        if x.shape()[0] != y.shape()[0] {
+            return Err(AmitaError::NotSameObservations)
+        }
+
+        Ok(())
+    }
+
+    /// Finds the linearly independent columns of W^{1/2} X by pivoted QR,
+    /// which keeps them in their original order, and decomposes them in
+    /// the same pass, reflecting W^{1/2} y along instead of forming Q
+    fn decompose(mut self) -> Result<Self, AmitaError> {
+        // column-major, which the reflections work through, and weighted
+        // unless every weight is one
+        let mut x_weighted = Array2::zeros(self.x.raw_dim().f());
+        let y_weighted = if self.weights.iter().all(|w| *w == 1.) {
+            x_weighted.assign(&self.x);
+            self.y.clone()
+        } else {
+            let root = self.weights.map(|w| w.sqrt());
+            Zip::from(&mut x_weighted)
+                .and(&self.x)
+                .and_broadcast(&root.view().insert_axis(Axis(1)))
+                .for_each(|weighted, x, w| *weighted = x * w);
+            &self.y * &root
+        };
+
+        let (pivoted, qty) = pivoted_qr_with_rhs(x_weighted, y_weighted, self.collinearity_tolerance)?;
+        let kept = pivoted.pivots[..pivoted.rank].to_vec();
+
+        let r_inverse = upper_triangular_inverse(&pivoted.r, "R matrix from QR-decomposed X")?;
+        self.x_gramian_inverse = r_inverse.dot(&r_inverse.t());
+
+        self.results.omitted = (0..self.results.n_regressors).filter(|j| !kept.contains(j)).collect();
+        self.kept = kept;
+        self.r = pivoted.r;
+        self.qty = qty;
+
+        Ok(self)
+    }
+
+    /// The solver with its decomposition, borrowed once solved and
+    /// decomposed afresh otherwise
+    fn decomposed(&self) -> Result<Cow<'_, OLSSolver>, AmitaError> {
+        if self.results.coef.is_some() {
+            Ok( Cow::Borrowed(self) )
+        } else {
+            Ok( Cow::Owned(self.clone().decompose()?) )
+        }
+    }
+
+    /// Columns of x that are estimated, borrowed unless some are omitted
+    fn x_kept(&self) -> CowArray<'_, f64, Ix2> {
+        if self.kept.len() == self.results.n_regressors {
+            CowArray::from(self.x.view())
+        } else {
+            CowArray::from(self.x.select(Axis(1), &self.kept))
+        }
+    }
+
+    fn expand_coef(&self, coef: &Array1<f64>) -> Array1<f64> {
+        expand_coef(coef, &self.kept, self.results.n_regressors)
+    }
+
+    fn expand_vcov(&self, vcov: &Array2<f64>) -> Array2<f64> {
+        expand_vcov(vcov, &self.kept, self.results.n_regressors)
+    }
+}
+
+
+impl OLSSolver {
+    /// Estimate OLS' coefficients using QR-decomposed W^{1/2} X, solving
+    /// the triangular system R \beta = Q^{\transpose} W^{1/2} y
+    fn solve_coef(mut self) -> Result<Self, AmitaError> {
+        let coef = solve_upper_triangular(&self.r, &self.qty, "R matrix from QR-decomposed X")?;
+        let y_pred = self.x_kept().dot(&coef);
+        let resid = &self.y - &y_pred;
+
+        self.results.coef = Some(self.expand_coef(&coef));
+        self.results.y_pred = Some(y_pred);
+        self.results.resid = Some(resid);
+
+        Ok(self)
+    }
+
+    fn solve_se(self) -> Result<Self, AmitaError> {
+        match self.results.se_type {
+            SolverSEType::Homoscedastic | SolverSEType::NonRobust => {
+                self
+                .solve_non_robust_se()?
+                .solve_t_pvals()
+            },
+            SolverSEType::HC1 | SolverSEType::HC2 | SolverSEType::HC3 | SolverSEType::Robust => {
+                self
+                .solve_robust_se()?
+                .solve_t_pvals()
+            },
+            SolverSEType::Clustered { .. } | SolverSEType::MultiwayClustered { .. } => {
+                self
+                .solve_clustered_se()?
+                .solve_t_pvals()
+            },
+            SolverSEType::ClusterSums { .. } => {
+                Err(AmitaError::UnsupportedSEType { se_type: format!("{:?}", self.results.se_type) })
+            },
+        }
+    }
+
+    fn solve_non_robust_se(mut self) -> Result<Self, AmitaError> {
+        if self.results.weight_type == Some(WeightType::Probability) {
+            return Err(AmitaError::UnsupportedSEType { se_type: format!("{:?} with probability weights", self.results.se_type) });
+        }
+
+        let resid = self.results.resid.as_ref().ok_or(AmitaError::NotSolved)?;
+
+        let sigma = weighted_sum_of_squares(&self.weights, resid) / self.results.df_resid();
+        let vcov = self.expand_vcov(&(&self.x_gramian_inverse * sigma));
+        let se = vcov.diag().map(|x| x.sqrt());
+
+        self.results.se = Some(se);
+        self.results.vcov = Some(vcov);
+        self.results.df_t = Some(self.results.df_resid());
+
+        Ok(self)
+    }
+
+    /// Heteroskedasticity-robust standard errors: HC1 (`Robust`) scales
+    /// the meat Σ w_i^2 e_i^2 x_i x_i^{\transpose} by N / (N - k), HC2 and
+    /// HC3 divide each term by (1 - h_i) and (1 - h_i)^2. A row of frequency
+    /// weight w_i counts as w_i observations of leverage h_i / w_i.
+    fn solve_robust_se(mut self) -> Result<Self, AmitaError> {
+        let n_effective = self.results.n_effective();
+        let n_regressors = self.results.rank() as f64;
+        let resid = self.results.resid.as_ref().ok_or(AmitaError::NotSolved)?;
+
+        let is_frequency = self.results.weight_type == Some(WeightType::Frequency);
+        let multiplicity = if is_frequency { self.weights.clone() } else { self.weights.map(|w| w.powi(2)) };
+
+        let (power, scale) = match self.results.se_type {
+            SolverSEType::HC2 => (1, 1.),
+            SolverSEType::HC3 => (2, 1.),
+            _ => (0, n_effective / (n_effective - n_regressors)),
+        };
+        let mut resid_weights = multiplicity * &resid.map(|e| e.powi(2));
+        if power > 0 {
+            let leverage = self.leverage()?;
+            let leverage = if is_frequency { leverage / &self.weights } else { leverage };
+            resid_weights /= &leverage.map(|h| (1. - h).powi(power));
+        }
+
+        let meat = weighted_gramian(self.x_kept().view(), resid_weights.view());
+
+        let vcov = self.expand_vcov(&(self.x_gramian_inverse.dot(&meat).dot(&self.x_gramian_inverse) * scale));
+        let se = vcov.diag().map(|x| x.sqrt());
+
+        self.results.se = Some(se);
+        self.results.vcov = Some(vcov);
+        self.results.df_t = Some(self.results.df_resid());
+
+        Ok(self)
+    }
+
+    /// Cluster-robust standard errors from the scores w_i e_i x_i, with the
+    /// small-sample correction G / (G - 1) (N - 1) / (N - k)
+    fn solve_clustered_se(mut self) -> Result<Self, AmitaError> {
+        let n_effective = self.results.n_effective();
+        let n_regressors = self.results.rank() as f64;
+        let resid = self.results.resid.as_ref().ok_or(AmitaError::NotSolved)?;
+
+        let clusters = match &self.results.se_type {
+            SolverSEType::Clustered { by } => vec![by.clone()],
+            SolverSEType::MultiwayClustered { by } => by.clone(),
+            _ => unreachable!(),
+        };
+
+        let scores = &self.x_kept() * &(&self.weights * resid).insert_axis(Axis(1));
+        let meat = multiway_cluster_meat(&scores, &clusters)?;
+
+        let vcov = self.expand_vcov(&(
+            self.x_gramian_inverse.dot(&meat).dot(&self.x_gramian_inverse) * (n_effective - 1.) / (n_effective - n_regressors)
+        ));
+        let se = vcov.diag().map(|x| x.sqrt());
+
+        let fewest_clusters = clusters.iter()
+            .map(|by| {
+                let mut ids = by.to_vec();
+                ids.sort_unstable();
+                ids.dedup();
+                ids.len()
+            })
+            .min()
+            .unwrap_or(0);
+
+        self.results.se = Some(se);
+        self.results.vcov = Some(vcov);
+        self.results.df_t = Some(fewest_clusters as f64 - 1.);
+        self.results.n_clusters = Some(fewest_clusters);
+
+        Ok(self)
+    }
+
+    fn solve_t_pvals(mut self) -> Result<Self, AmitaError> {
+        let df = self.results.df_t()?;
+        let coef = self.results.coef.as_ref().ok_or(AmitaError::NotSolved)?;
+        let se = self.results.se.as_ref().ok_or(AmitaError::NotSolved)?;
+
+        let t = coef / se;
+
+        let p_vals = students_t_p_vals(&t, df);
+
+        self.results.t = Some(t);
+        self.results.p_vals = Some(p_vals);
+
+        Ok(self)
+    }
+}
+
+// Goodness of fit
+impl OLSSolver {
+    /// Weighted R^2 around the weighted mean of y
+    fn solve_r_sq(mut self) -> Result<Self, AmitaError> {
+        let resid = self.results.resid.as_ref().ok_or(AmitaError::NotSolved)?;
+        let weights = &self.weights;
+        let y_mean = weights.dot(&self.y) / weights.sum();
+
+        let df_rss = self.results.df_resid();
+        let df_tss = self.results.n_effective() - 1.;
+
+        let rss = weighted_sum_of_squares(weights, resid);
+        let tss = Zip::from(weights).and(&self.y).fold(0., |sum, w, y| sum + w * (y - y_mean).powi(2));
+
+        let r_sq = 1. - rss / tss;
+        let r_sq_adj = 1. - ( rss / df_rss ) / ( tss / df_tss );
+
+        self.results.r_sq = Some(r_sq);
+        self.results.r_sq_adj = Some(r_sq_adj);
+        self.results.sigma_sq = Some(rss / df_rss);
+
+        Ok(self)
+    }
+}
+
+// Specification tests
+impl OLSSolver {
+    fn solve_diagnostics(mut self) -> Result<Self, AmitaError> {
+        if self.report_diagnostics {
+            self.results.diagnostics = self.diagnostics()?;
+        }
+        Ok(self)
+    }
+}
+
+/// A collinearity tolerance is relative, so positive, and finite
+fn validate_collinearity_tolerance(tolerance: f64) -> Result<(), AmitaError> {
+    if !tolerance.is_finite() || tolerance <= 0. {
+        return Err(AmitaError::InvalidTolerance { tolerance });
+    }
+    Ok(())
+}
+
+/// Coefficients of all `n_regressors` columns of x from those of the kept
+/// columns, with NaN for the omitted ones
+fn expand_coef(coef: &Array1<f64>, kept: &[usize], n_regressors: usize) -> Array1<f64> {
+    let mut expanded = Array1::from_elem(n_regressors, f64::NAN);
+    for (value, j) in coef.iter().zip(kept.iter()) {
+        expanded[*j] = *value;
+    }
+    expanded
+}
+
+/// Covariance of all coefficients from that of the kept ones, with NaN in
+/// the rows and columns of the omitted ones
+fn expand_vcov(vcov: &Array2<f64>, kept: &[usize], n_regressors: usize) -> Array2<f64> {
+    let mut expanded = Array2::from_elem((n_regressors, n_regressors), f64::NAN);
+    for (a, i) in kept.iter().enumerate() {
+        for (b, j) in kept.iter().enumerate() {
+            expanded[[*i, *j]] = vcov[[a, b]];
+        }
+    }
+    expanded
+}
+
+/// Σ w_i e_i^2 without an intermediate vector
+fn weighted_sum_of_squares(weights: &Array1<f64>, resid: &Array1<f64>) -> f64 {
+    Zip::from(weights).and(resid).fold(0., |sum, w, e| sum + w * e.powi(2))
+}
+
+#[cfg(test)]
+mod tests {
+    use amita_utils::delta::DeltaMethod;
+    use ndarray::{array, stack};
+
+    use super::*;
+
+    fn data() -> (Array1<f64>, Array2<f64>, Array1<f64>) {
+        let x1 = array![0.5, 1.7, 2.2, 3.1, 0.9, 2.8, 1.4, 3.6, 2.0, 0.2];
+        let y = array![1.9, 4.1, 4.0, 7.2, 2.4, 5.9, 3.8, 8.1, 4.4, 1.1];
+        let weights = array![1., 3., 2., 1., 4., 2., 1., 3., 2., 1.];
+        let x = stack![Axis(1), Array1::<f64>::ones(10), x1];
+        (y, x, weights)
+    }
+
+    #[test]
+    fn test_frequency_weights_expand_rows() -> Result<(), AmitaError> {
+        let (y, x, weights) = data();
+
+        let rows = weights.iter().enumerate()
+            .flat_map(|(i, w)| std::iter::repeat_n(i, *w as usize))
+            .collect::<Vec<_>>();
+        let y_expanded = y.select(Axis(0), &rows);
+        let x_expanded = x.select(Axis(0), &rows);
+
+        for se_type in [SolverSEType::NonRobust, SolverSEType::HC1, SolverSEType::HC3] {
+            let weighted = OLSSolver::new(&y, &x)?
+                .with_weights(&weights, WeightType::Frequency)?
+                .with_se_type(se_type.clone())
+                .solve()?
+                .results();
+            let expanded = OLSSolver::new(&y_expanded, &x_expanded)?
+                .with_se_type(se_type)
+                .solve()?
+                .results();
+
+            assert!( (weighted.coef()? - expanded.coef()?).iter().all(|d| d.abs() < 1e-10) );
+            assert!( (weighted.se()? - expanded.se()?).iter().all(|d| d.abs() < 1e-10) );
+            assert!( (weighted.r_sq_adj()? - expanded.r_sq_adj()?).abs() < 1e-10 );
+            assert_eq!(weighted.df_t()?, expanded.df_t()?);
+        }
+
+        let clusters = array![0, 0, 1, 1, 2, 2, 3, 3, 4, 4];
+        let weighted = OLSSolver::new(&y, &x)?
+            .with_weights(&weights, WeightType::Frequency)?
+            .with_se_type(SolverSEType::Clustered { by: clusters.clone() })
+            .solve()?
+            .results();
+        let expanded = OLSSolver::new(&y_expanded, &x_expanded)?
+            .with_se_type(SolverSEType::Clustered { by: clusters.select(Axis(0), &rows) })
+            .solve()?
+            .results();
+        assert!( (weighted.se()? - expanded.se()?).iter().all(|d| d.abs() < 1e-10) );
+        assert_eq!(weighted.df_t()?, 4.);
+
+        let fractional = OLSSolver::new(&y, &x)?.with_weights(&(&weights * 0.5), WeightType::Frequency);
+        assert!( matches!(fractional, Err(AmitaError::NonInteger { .. })) );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_analytic_and_probability_weights() -> Result<(), AmitaError> {
+        let (y, x, weights) = data();
+
+        // least squares on the data scaled by √w
+        let root = weights.map(|w| w.sqrt());
+        let transformed = OLSSolver::new(&(&y * &root), &(&x * &root.view().insert_axis(Axis(1))))?
+            .solve()?
+            .results();
+
+        let analytic = OLSSolver::new(&y, &x)?
+            .with_weights(&weights, WeightType::Analytic)?
+            .solve()?
+            .results();
+        assert!( (analytic.coef()? - transformed.coef()?).iter().all(|d| d.abs() < 1e-10) );
+        assert!( (analytic.se()? - transformed.se()?).iter().all(|d| d.abs() < 1e-10) );
+        assert_eq!(analytic.df_resid(), 8.);
+
+        // rescaling analytic weights changes nothing
+        let rescaled = OLSSolver::new(&y, &x)?
+            .with_weights(&(&weights * 10.), WeightType::Analytic)?
+            .solve()?
+            .results();
+        assert!( (analytic.se()? - rescaled.se()?).iter().all(|d| d.abs() < 1e-10) );
+        assert!( (analytic.r_sq()? - rescaled.r_sq()?).abs() < 1e-12 );
+
+        // probability weights give the analytic estimates and robust errors
+        let probability = OLSSolver::new(&y, &x)?
+            .with_weights(&weights, WeightType::Probability)?
+            .with_robust_se()
+            .solve()?
+            .results();
+        let analytic_robust = OLSSolver::new(&y, &x)?
+            .with_weights(&weights, WeightType::Analytic)?
+            .with_robust_se()
+            .solve()?
+            .results();
+        assert!( (probability.se()? - analytic_robust.se()?).iter().all(|d| d.abs() < 1e-10) );
+
+        let non_robust = OLSSolver::new(&y, &x)?
+            .with_weights(&weights, WeightType::Probability)?
+            .solve();
+        assert!( matches!(non_robust, Err(AmitaError::UnsupportedSEType { .. })) );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_degrees_of_freedom() -> Result<(), AmitaError> {
+        let (y, x, weights) = data();
+
+        // as many observations as regressors leave nothing for the t tests
+        let rows = [0, 1];
+        let saturated = OLSSolver::new(&y.select(Axis(0), &rows), &x.select(Axis(0), &rows))?
+            .with_weights(&weights.select(Axis(0), &rows), WeightType::Analytic)?
+            .solve()?
+            .results();
+        assert_eq!(saturated.df_t()?, 0.);
+        assert!( saturated.p_vals()?.iter().all(|p| p.is_nan()) );
+
+        let single_cluster = OLSSolver::new(&y, &x)?
+            .with_se_type(SolverSEType::Clustered { by: Array1::zeros(10) })
+            .solve();
+        assert!( matches!(single_cluster, Err(AmitaError::TooFewClusters { n_clusters: 1 })) );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_linear_restrictions() -> Result<(), AmitaError> {
+        let (y, x, _) = data();
+        let x2 = array![0.3, -1.2, 0.8, 0.1, -0.4, 1.5, -0.7, 0.2, 0.9, -1.1];
+        let x = ndarray::concatenate![Axis(1), x, x2.insert_axis(Axis(1))];
+        let names = ["const", "x1", "x2"].map(String::from).to_vec();
+
+        let results = OLSSolver::new(&y, &x)?
+            .with_coef_names(names)?
+            .solve()?
+            .results();
+
+        // under homoscedastic errors the overall F test is the classic one
+        // from the R^2
+        let r_sq = results.r_sq()?;
+        let f = results.overall_f_test()?;
+        assert!( (f.statistic() - (r_sq / 2.) / ((1. - r_sq) / 7.)).abs() < 1e-8 );
+        assert_eq!(f.df(), vec![2., 7.]);
+
+        let parsed = results.test_hypothesis("x1 - 2 = -x2")?;
+        let restrictions = LinearRestrictions::new(&array![[0., 1., 1.]], &array![2.])?;
+        let matrix = results.test_linear(&restrictions)?;
+        assert!( (parsed.wald().statistic() - matrix.wald().statistic()).abs() < 1e-10 );
+        assert_eq!(matrix.wald().df(), vec![1.]);
+
+        // a single restriction's F is the squared t statistic
+        let t = results.test_hypothesis("x2 = 0")?;
+        assert!( (t.f().unwrap().statistic() - results.t()?[2].powi(2)).abs() < 1e-8 );
+
+        // robust fits test with the robust covariance
+        let robust = OLSSolver::new(&y, &x)?
+            .with_robust_se()
+            .solve()?
+            .results();
+        let test = robust.test_hypothesis("x2 = 0")?;
+        assert!( (test.wald().statistic() - robust.t()?[2].powi(2)).abs() < 1e-8 );
+
+        assert!( matches!(results.test_hypothesis("x3 = 0"), Err(AmitaError::ColumnNotFound { .. })) );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_collinear_columns_are_omitted() -> Result<(), AmitaError> {
+        let (y, x, _) = data();
+        let d1 = array![1., 0., 1., 0., 1., 0., 1., 0., 1., 1.];
+        let d2 = d1.map(|d| 1. - d);
+        let x1 = x.column(1).to_owned();
+
+        // a dummy trap and a rescaled duplicate of x1
+        let trapped = stack![Axis(1), x.column(0), x1, d1, d2, &x1 * 2.];
+        let names = ["const", "x1", "d1", "d2", "x1_doubled"].map(String::from).to_vec();
+        let results = OLSSolver::new(&y, &trapped)?
+            .with_coef_names(names)?
+            .with_robust_se()
+            .solve()?
+            .results();
+
+        assert_eq!(results.omitted()?, vec!["d2".to_string(), "x1_doubled".to_string()]);
+        assert_eq!(results.rank(), 3);
+        assert_eq!(results.df_resid(), 7.);
+
+        let reduced = OLSSolver::from_owned(y.clone(), stack![Axis(1), x.column(0), x1, d1])?
+            .with_robust_se()
+            .solve()?
+            .results();
+        let coef = results.coef()?;
+        assert!( coef[3].is_nan() && coef[4].is_nan() && results.se()?[3].is_nan() );
+        assert!( (coef.slice(ndarray::s![..3]).to_owned() - reduced.coef()?).iter().all(|d| d.abs() < 1e-10) );
+        assert!( (results.se()?.slice(ndarray::s![..3]).to_owned() - reduced.se()?).iter().all(|d| d.abs() < 1e-10) );
+        assert!( results.p_vals()?[4].is_nan() );
+
+        // tests on the estimated coefficients ignore the omitted ones
+        let f = results.overall_f_test()?;
+        assert!( (f.statistic() - reduced.overall_f_test()?.statistic()).abs() < 1e-8 );
+        assert!( results.test_hypothesis("x1 = 2")?.wald().statistic().is_finite() );
+        assert!( matches!(results.test_hypothesis("x1 = x1_doubled"), Err(AmitaError::InvalidHypothesis { .. })) );
+
+        // and so do nonlinear combinations, unless they depend on one
+        let ratio = results.nlcom(|b| array![b[1] / b[2]])?;
+        let reduced_ratio = reduced.nlcom(|b| array![b[1] / b[2]])?;
+        assert!( (ratio.se()[0] - reduced_ratio.se()[0]).abs() < 1e-8 );
+        assert!( matches!(results.nlcom(|b| array![b[1] * b[4]]), Err(AmitaError::InvalidHypothesis { .. })) );
+
+        // a smaller tolerance keeps the nearly dependent column in
+        let nearly = stack![Axis(1), x.column(0), x1, &x1 + &(&d1 * 1e-9)];
+        assert_eq!(OLSSolver::new(&y, &nearly)?.solve()?.results().rank(), 2);
+        let kept = OLSSolver::new(&y, &nearly)?.with_collinearity_tolerance(1e-14)?.solve()?.results();
+        assert_eq!(kept.rank(), 3);
+
+        for invalid in [0., -1e-7, f64::NAN, f64::INFINITY] {
+            let solver = OLSSolver::new(&y, &nearly)?.with_collinearity_tolerance(invalid);
+            assert!( matches!(solver, Err(AmitaError::InvalidTolerance { .. })) );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,465 @@
+//! OLS over data streamed in chunks, from the sufficient statistics X'X,
+//! X'y and y'y, so memory grows with the number of regressors (and of
+//! clusters) rather than of observations. They are accumulated as means
+//! and cross-products of deviations from them, merged chunk by chunk, so
+//! that the residual sum of squares does not cancel when y has a large
+//! mean.
+//!
+//! Coefficients and homoskedastic standard errors need one pass over the
+//! data with `update`. Robust and clustered standard errors need the
+//! residuals, so a second pass with `update_scores` once every chunk has
+//! been seen.
+
+use std::collections::BTreeMap;
+
+use amita_error::AmitaError;
+use amita_utils::hypothesis::students_t_p_vals;
+use amita_utils::inference::SolverSEType;
+use amita_utils::linalg::{pivoted_qr_from_gramian, upper_triangular_inverse};
+use amita_utils::parallel::weighted_gramian;
+use amita_utils::traits::BaseSolver;
+use ndarray::prelude::*;
+use ndarray::CowArray;
+
+use super::{expand_coef, expand_vcov, validate_collinearity_tolerance, OLSResults};
+
+/// Standard errors available to streaming OLS
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamingSEType {
+    NonRobust,
+    HC1,
+    HC2,
+    HC3,
+    /// Clustered by the ids passed to `update_scores`, which must identify
+    /// the same cluster in every chunk
+    Clustered,
+}
+
+#[derive(Debug, Clone)]
+pub struct StreamingOLSSolver {
+    n_regressors: usize,
+    se_type: StreamingSEType,
+    collinearity_tolerance: f64,
+
+    // first pass, as means and cross-products of deviations from them
+    n_obs: usize,
+    x_mean: Array1<f64>,
+    y_mean: f64,
+    x_comoment: Array2<f64>,
+    x_y_comoment: Array1<f64>,
+    y_comoment: f64,
+    constant: Vec<Option<f64>>, // common value of each column, if any
+
+    // second pass, with the coefficients of the first
+    fit: Option<Fit>,
+    n_scored: usize,
+    meat: Array2<f64>,
+    cluster_scores: BTreeMap<i32, Array1<f64>>,
+
+    results: OLSResults,
+}
+
+/// Coefficients of the linearly independent columns of x, from the first
+/// pass
+#[derive(Debug, Clone)]
+struct Fit {
+    kept: Vec<usize>,
+    coef: Array1<f64>,
+    x_gramian_inverse: Array2<f64>, // of the kept columns
+}
+
+impl StreamingOLSSolver {
+    pub fn new(n_regressors: usize) -> Self {
+        Self {
+            n_regressors,
+            se_type: StreamingSEType::NonRobust,
+            collinearity_tolerance: 1e-7,
+
+            n_obs: 0,
+            x_mean: Array1::zeros(n_regressors),
+            y_mean: 0.,
+            x_comoment: Array2::zeros((n_regressors, n_regressors)),
+            x_y_comoment: Array1::zeros(n_regressors),
+            y_comoment: 0.,
+            constant: vec![None; n_regressors],
+
+            fit: None,
+            n_scored: 0,
+            meat: Array2::zeros((0, 0)),
+            cluster_scores: BTreeMap::new(),
+
+            results: OLSResults::unsolved(0, n_regressors),
+        }
+    }
+
+    pub fn with_se_type(mut self, se_type: StreamingSEType) -> Self {
+        self.se_type = se_type;
+        self
+    }
+
+    /// Tolerance below which a column's norm orthogonal to the columns
+    /// before it, relative to its own norm, makes it collinear with them,
+    /// as in [`super::OLSSolver::with_collinearity_tolerance`]. Collinear
+    /// columns are omitted, with NaN coefficients.
+    pub fn with_collinearity_tolerance(mut self, tolerance: f64) -> Result<Self, AmitaError> {
+        validate_collinearity_tolerance(tolerance)?;
+        self.collinearity_tolerance = tolerance;
+        Ok(self)
+    }
+
+    /// Names of the columns of x, which hypotheses on the results refer to
+    pub fn with_coef_names(mut self, names: Vec<String>) -> Result<Self, AmitaError> {
+        if names.len() != self.n_regressors {
+            return Err(AmitaError::NotSameObservations);
+        }
+
+        self.results.coef_names = Some(names);
+        Ok(self)
+    }
+
+    /// Whether the standard errors need a second pass with `update_scores`
+    pub fn needs_scores(&self) -> bool {
+        self.se_type != StreamingSEType::NonRobust
+    }
+
+    /// First pass: adds a chunk of observations to X'X, X'y and y'y
+    pub fn update(&mut self, y: &Array1<f64>, x: &Array2<f64>) -> Result<(), AmitaError> {
+        self.validate_chunk(y, x)?;
+        if self.fit.is_some() {
+            return Err(AmitaError::SolverFailed { message: "the first pass is over once scores are updated".to_string() });
+        }
+        if y.is_empty() {
+            return Ok(());
+        }
+
+        if self.n_obs == 0 {
+            self.constant = x.row(0).iter().map(|x| Some(*x)).collect();
+        }
+        for (constant, column) in self.constant.iter_mut().zip(x.columns()) {
+            if constant.is_some_and(|c| column.iter().any(|x| *x != c)) {
+                *constant = None;
+            }
+        }
+
+        // merges the chunk's means and cross-products of deviations with
+        // those so far, as in Chan, Golub and LeVeque (1979)
+        let n_chunk = y.len() as f64;
+        let x_mean = x.mean_axis(Axis(0)).unwrap();
+        let y_mean = y.mean().unwrap();
+        let x_centered = x - &x_mean;
+        let y_centered = y - y_mean;
+
+        let n_before = self.n_obs as f64;
+        let n = n_before + n_chunk;
+        let x_delta = &x_mean - &self.x_mean;
+        let y_delta = y_mean - self.y_mean;
+        let scale = n_before * n_chunk / n;
+
+        let x_delta_column = x_delta.view().insert_axis(Axis(1));
+        self.x_comoment += &(x_centered.t().dot(&x_centered) + x_delta_column.dot(&x_delta_column.t()) * scale);
+        self.x_y_comoment += &(x_centered.t().dot(&y_centered) + &x_delta * (y_delta * scale));
+        self.y_comoment += y_centered.dot(&y_centered) + y_delta.powi(2) * scale;
+        self.x_mean.scaled_add(n_chunk / n, &x_delta);
+        self.y_mean += y_delta * n_chunk / n;
+        self.n_obs += y.len();
+
+        Ok(())
+    }
+
+    /// Second pass: adds the scores e_i x_i of a chunk to the meat of the
+    /// robust covariance, or to the sums of their clusters. The chunks must
+    /// be those of the first pass, in any order.
+    pub fn update_scores(
+        &mut self,
+        y: &Array1<f64>,
+        x: &Array2<f64>,
+        clusters: Option<&Array1<i32>>,
+    ) -> Result<(), AmitaError> {
+        self.validate_chunk(y, x)?;
+        if clusters.is_some_and(|clusters| clusters.len() != y.len()) {
+            return Err(AmitaError::NotSameObservations);
+        }
+
+        if self.fit.is_none() {
+            let fit = self.fit()?;
+            self.meat = Array2::zeros((fit.kept.len(), fit.kept.len()));
+            self.fit = Some(fit);
+        }
+        let Fit { kept, coef, x_gramian_inverse } = self.fit.as_ref().unwrap();
+
+        let x = if kept.len() == self.n_regressors {
+            CowArray::from(x.view())
+        } else {
+            CowArray::from(x.select(Axis(1), kept))
+        };
+        let resid = y - &x.dot(coef);
+        self.n_scored += y.len();
+
+        match self.se_type {
+            StreamingSEType::NonRobust => {},
+            StreamingSEType::HC1 | StreamingSEType::HC2 | StreamingSEType::HC3 => {
+                let leverage = ( x.dot(x_gramian_inverse) * &x ).sum_axis(Axis(1));
+                let power = match self.se_type {
+                    StreamingSEType::HC2 => 1,
+                    StreamingSEType::HC3 => 2,
+                    _ => 0,
+                };
+                let resid_weights = resid.map(|e| e.powi(2)) / leverage.map(|h| (1. - h).powi(power));
+                self.meat += &weighted_gramian(x.view(), resid_weights.view());
+            },
+            StreamingSEType::Clustered => {
+                let clusters = clusters.ok_or(AmitaError::UnsupportedSEType {
+                    se_type: "clustered standard errors without cluster ids".to_string()
+                })?;
+                for ((id, e), row) in clusters.iter().zip(resid.iter()).zip(x.rows()) {
+                    self.cluster_scores.entry(*id)
+                        .or_insert_with(|| Array1::zeros(kept.len()))
+                        .scaled_add(*e, &row);
+                }
+            },
+        }
+
+        Ok(())
+    }
+
+    fn validate_chunk(&self, y: &Array1<f64>, x: &Array2<f64>) -> Result<(), AmitaError> {
+        if x.shape()[0] != y.len() || x.shape()[1] != self.n_regressors {
+            return Err(AmitaError::NotSameObservations);
+        }
+        Ok(())
+    }
+
+    /// Finds the linearly independent columns of x from X'X, as the pivoted
+    /// QR of `OLSSolver` does from x, and regresses y on them
+    fn fit(&self) -> Result<Fit, AmitaError> {
+        let pivoted = pivoted_qr_from_gramian(&self.x_gramian(), self.collinearity_tolerance);
+        let kept = pivoted.pivots[..pivoted.rank].to_vec();
+
+        let r_inverse = upper_triangular_inverse(&pivoted.r, "R matrix from X'X accumulated over chunks")?;
+        let x_gramian_inverse = r_inverse.dot(&r_inverse.t());
+        let coef = x_gramian_inverse.dot(&self.x_y().select(Axis(0), &kept));
+
+        Ok( Fit { kept, coef, x_gramian_inverse } )
+    }
+
+    /// X'X = Σ (x_i - x̄)(x_i - x̄)' + n x̄ x̄'
+    fn x_gramian(&self) -> Array2<f64> {
+        let x_mean = self.x_mean.view().insert_axis(Axis(1));
+        &self.x_comoment + &(x_mean.dot(&x_mean.t()) * self.n_obs as f64)
+    }
+
+    /// X'y = Σ (x_i - x̄)(y_i - ȳ) + n x̄ ȳ
+    fn x_y(&self) -> Array1<f64> {
+        &self.x_y_comoment + &(&self.x_mean * (self.y_mean * self.n_obs as f64))
+    }
+}
+
+impl BaseSolver<OLSResults> for StreamingOLSSolver {
+    fn results(&self) -> OLSResults {
+        self.results.clone()
+    }
+
+    /// Coefficients and standard errors from the statistics accumulated,
+    /// as `OLSSolver` would give on all the chunks stacked
+    fn solve(mut self) -> Result<Self, AmitaError> {
+        if self.needs_scores() && self.n_scored != self.n_obs {
+            return Err(AmitaError::SolverFailed {
+                message: format!("scores cover {} of {} observations", self.n_scored, self.n_obs)
+            });
+        }
+
+        let Fit { kept, coef, x_gramian_inverse } = match &self.fit {
+            Some(fit) => fit.clone(),
+            None => self.fit()?,
+        };
+        if self.n_obs <= kept.len() {
+            return Err(AmitaError::NotSameObservations);
+        }
+
+        let n = self.n_obs as f64;
+        let k = kept.len() as f64;
+
+        // e'e from the deviations from the means, over the kept columns: the
+        // residuals' sum of squares about their mean ē = ȳ - x̄'β, plus n ē²
+        let x_y_comoment = self.x_y_comoment.select(Axis(0), &kept);
+        let x_comoment = self.x_comoment.select(Axis(0), &kept).select(Axis(1), &kept);
+        let resid_mean = self.y_mean - self.x_mean.select(Axis(0), &kept).dot(&coef);
+        let rss = self.y_comoment - 2. * coef.dot(&x_y_comoment) + coef.dot(&x_comoment.dot(&coef))
+            + n * resid_mean.powi(2);
+        let tss = self.y_comoment;
+        let sigma_sq = rss / (n - k);
+
+        let sandwich = |meat: &Array2<f64>| x_gramian_inverse.dot(meat).dot(&x_gramian_inverse);
+        let (se_type, vcov, df_t, n_clusters) = match self.se_type {
+            StreamingSEType::NonRobust => (SolverSEType::NonRobust, &x_gramian_inverse * sigma_sq, n - k, None),
+            StreamingSEType::HC1 => (SolverSEType::HC1, sandwich(&self.meat) * n / (n - k), n - k, None),
+            StreamingSEType::HC2 => (SolverSEType::HC2, sandwich(&self.meat), n - k, None),
+            StreamingSEType::HC3 => (SolverSEType::HC3, sandwich(&self.meat), n - k, None),
+            StreamingSEType::Clustered => {
+                let n_clusters = self.cluster_scores.len();
+                if n_clusters < 2 {
+                    return Err(AmitaError::TooFewClusters { n_clusters });
+                }
+                let g = n_clusters as f64;
+
+                let mut meat = Array2::zeros((kept.len(), kept.len()));
+                for score in self.cluster_scores.values() {
+                    let score = score.view().insert_axis(Axis(1));
+                    meat += &score.dot(&score.t());
+                }
+                let vcov = sandwich(&meat) * g / (g - 1.) * (n - 1.) / (n - k);
+
+                (SolverSEType::ClusterSums { n_clusters }, vcov, g - 1., Some(n_clusters))
+            },
+        };
+
+        let coef = expand_coef(&coef, &kept, self.n_regressors);
+        let vcov = expand_vcov(&vcov, &kept, self.n_regressors);
+        let se = vcov.diag().map(|v| v.sqrt());
+        let t = &coef / &se;
+        let p_vals = students_t_p_vals(&t, df_t);
+
+        let results = &mut self.results;
+        results.n_obs = self.n_obs;
+        results.se_type = se_type;
+        results.sum_weights = n;
+        results.intercept = self.constant.iter().position(|c| c.is_some_and(|c| c != 0.));
+        results.omitted = (0..self.n_regressors).filter(|j| !kept.contains(j)).collect();
+        results.n_clusters = n_clusters;
+
+        results.coef = Some(coef);
+        results.se = Some(se);
+        results.t = Some(t);
+        results.p_vals = Some(p_vals);
+        results.vcov = Some(vcov);
+        results.df_t = Some(df_t);
+        results.sigma_sq = Some(sigma_sq);
+
+        results.r_sq = Some(1. - rss / tss);
+        results.r_sq_adj = Some(1. - (rss / (n - k)) / (tss / (n - 1.)));
+
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use amita_utils::traits::BaseResults;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use statrs::distribution::Normal;
+
+    use super::*;
+    use crate::linear::ols::OLSSolver;
+
+    #[test]
+    fn test_chunks_match_full_data() -> Result<(), AmitaError> {
+        let mut rng = StdRng::seed_from_u64(21);
+        let normal = Normal::new(0., 1.).unwrap();
+
+        let n = 1000;
+        let x1 = Array1::from_iter( (0..n).map(|_| rng.sample(normal)) );
+        let x2 = Array1::from_iter( (0..n).map(|_| rng.gen_range(0. .. 5.)) );
+        let clusters = Array1::from_iter( (0..n).map(|i| (i % 37) as i32) );
+        let e = Array1::from_iter( (0..n).map(|i| rng.sample(normal) * (1. + x2[i])) );
+        let y = 2. + 0.5 * &x1 - &x2 + e;
+        let x = ndarray::stack![Axis(1), Array1::<f64>::ones(n), x1, x2];
+
+        let chunks = (0..n).step_by(128).map(|start| start..(start + 128).min(n)).collect::<Vec<_>>();
+
+        let cases = [
+            (StreamingSEType::NonRobust, SolverSEType::NonRobust),
+            (StreamingSEType::HC1, SolverSEType::HC1),
+            (StreamingSEType::HC2, SolverSEType::HC2),
+            (StreamingSEType::HC3, SolverSEType::HC3),
+            (StreamingSEType::Clustered, SolverSEType::Clustered { by: clusters.clone() }),
+        ];
+        for (streaming_se_type, se_type) in cases {
+            let mut solver = StreamingOLSSolver::new(3).with_se_type(streaming_se_type);
+            for chunk in chunks.iter() {
+                solver.update(&y.slice(s![chunk.clone()]).to_owned(), &x.slice(s![chunk.clone(), ..]).to_owned())?;
+            }
+            if solver.needs_scores() {
+                for chunk in chunks.iter().rev() {
+                    solver.update_scores(
+                        &y.slice(s![chunk.clone()]).to_owned(),
+                        &x.slice(s![chunk.clone(), ..]).to_owned(),
+                        Some(&clusters.slice(s![chunk.clone()]).to_owned()),
+                    )?;
+                }
+            }
+            let streamed = solver.solve()?.results();
+            let full = OLSSolver::new(&y, &x)?.with_se_type(se_type).solve()?.results();
+
+            assert!( (streamed.coef()? - full.coef()?).iter().all(|d| d.abs() < 1e-10) );
+            assert!( (streamed.se()? - full.se()?).iter().all(|d| d.abs() < 1e-10) );
+            assert!( (streamed.p_vals()? - full.p_vals()?).iter().all(|d| d.abs() < 1e-10) );
+            assert!( (streamed.r_sq()? - full.r_sq()?).abs() < 1e-10 );
+            assert!( (streamed.overall_f_test()?.statistic() - full.overall_f_test()?.statistic()).abs() < 1e-8 );
+            assert_eq!(streamed.df_t()?, full.df_t()?);
+            assert_eq!(streamed.n_clusters().ok(), full.n_clusters().ok());
+        }
+
+        // the cluster ids are summed away, leaving their number
+        let mut solver = StreamingOLSSolver::new(3).with_se_type(StreamingSEType::Clustered);
+        solver.update(&y, &x)?;
+        solver.update_scores(&y, &x, Some(&clusters))?;
+        assert!( matches!(solver.solve()?.results().se_type(), SolverSEType::ClusterSums { n_clusters: 37 }) );
+
+        // a collinear column is omitted as it is from the stacked data
+        let collinear = ndarray::concatenate![Axis(1), x, (2. * &x.column(1)).insert_axis(Axis(1))];
+        let mut solver = StreamingOLSSolver::new(4).with_se_type(StreamingSEType::HC1);
+        assert!( matches!(solver.results().coef(), Err(AmitaError::NotSolved)) );
+        for chunk in chunks.iter() {
+            solver.update(&y.slice(s![chunk.clone()]).to_owned(), &collinear.slice(s![chunk.clone(), ..]).to_owned())?;
+        }
+        for chunk in chunks.iter() {
+            solver.update_scores(&y.slice(s![chunk.clone()]).to_owned(), &collinear.slice(s![chunk.clone(), ..]).to_owned(), None)?;
+        }
+        let streamed = solver.solve()?.results();
+        let full = OLSSolver::new(&y, &collinear)?.with_se_type(SolverSEType::HC1).solve()?.results();
+        assert!( matches!(StreamingOLSSolver::new(4).with_collinearity_tolerance(-1.), Err(AmitaError::InvalidTolerance { .. })) );
+        assert_eq!(streamed.omitted()?, vec!["x3".to_string()]);
+        assert_eq!(streamed.omitted()?, full.omitted()?);
+        assert!( streamed.coef()?[3].is_nan() && streamed.se()?[3].is_nan() );
+        assert!( (streamed.coef()? - full.coef()?).slice(s![..3]).iter().all(|d| d.abs() < 1e-10) );
+        assert!( (streamed.se()? - full.se()?).slice(s![..3]).iter().all(|d| d.abs() < 1e-10) );
+        assert_eq!(streamed.df_t()?, full.df_t()?);
+
+        // robust errors need every chunk scored
+        let mut solver = StreamingOLSSolver::new(3).with_se_type(StreamingSEType::HC1);
+        solver.update(&y, &x)?;
+        solver.update_scores(&y.slice(s![..10]).to_owned(), &x.slice(s![..10, ..]).to_owned(), None)?;
+        assert!( matches!(solver.clone().solve(), Err(AmitaError::SolverFailed { .. })) );
+        assert!( matches!(solver.update(&y, &x), Err(AmitaError::SolverFailed { .. })) );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_large_mean_of_y() -> Result<(), AmitaError> {
+        let mut rng = StdRng::seed_from_u64(8);
+        let normal = Normal::new(0., 1.).unwrap();
+
+        // y'y and the sums of squares about the mean differ by 18 orders of
+        // magnitude, which the raw sums y'y - 2β'X'y + β'X'Xβ lose
+        let n = 500;
+        let x1 = Array1::from_iter( (0..n).map(|_| rng.sample(normal)) );
+        let y = 1e9 + 0.5 * &x1 + Array1::from_iter( (0..n).map(|_| rng.sample(normal)) );
+        let x = ndarray::stack![Axis(1), Array1::<f64>::ones(n), x1];
+
+        let mut solver = StreamingOLSSolver::new(2);
+        for start in (0..n).step_by(64) {
+            let rows = start..(start + 64).min(n);
+            solver.update(&y.slice(s![rows.clone()]).to_owned(), &x.slice(s![rows, ..]).to_owned())?;
+        }
+        let streamed = solver.solve()?.results();
+        let full = OLSSolver::new(&y, &x)?.solve()?.results();
+
+        assert!( (streamed.sigma_sq()? / full.sigma_sq()? - 1.).abs() < 1e-6 );
+        assert!( (streamed.se()?[1] / full.se()?[1] - 1.).abs() < 1e-6 );
+        assert!( (streamed.r_sq()? - full.r_sq()?).abs() < 1e-6 );
+
+        Ok(())
+    }
+}
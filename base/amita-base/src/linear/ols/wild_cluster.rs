@@ -0,0 +1,430 @@
+//! Wild cluster restricted (WCR) bootstrap of a single OLS coefficient, for
+//! inference with few clusters, following the fast algorithm of Roodman,
+//! MacKinnon, Nielsen and Webb (2019), "Fast and wild: Bootstrap inference
+//! in Stata using boottest".
+//!
+//! Bootstrap samples y* = Xβ̃ + v_g ũ impose H_0: β_j = β_0 through the
+//! restricted fit β̃ and its residuals ũ, and flip or scale the residuals of
+//! each cluster g by a common v_g. The numerator and the cluster scores of
+//! every bootstrap t statistic are linear in v, and in β_0, so each draw
+//! costs O(G^2) without re-fitting, and each β_0 tried when inverting the
+//! test into a confidence interval costs O(1) per draw.
+
+use amita_error::AmitaError;
+use amita_utils::bootstrap::{cluster_rows, WildWeights};
+use amita_utils::linalg::inverse;
+use amita_utils::parallel::gramian;
+use amita_utils::traits::BaseResults;
+use ndarray::{Array1, Array2, Axis};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use super::OLSSolver;
+
+/// Settings of the wild cluster restricted bootstrap
+#[derive(Debug, Clone)]
+pub struct WildClusterBootstrap {
+    weights: WildWeights,
+    replications: usize,
+    seed: u64,
+    null: f64,
+}
+
+impl Default for WildClusterBootstrap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WildClusterBootstrap {
+    /// Rademacher weights, 999 replications, seed 0 and H_0: β_j = 0
+    pub fn new() -> Self {
+        WildClusterBootstrap { weights: WildWeights::Rademacher, replications: 999, seed: 0, null: 0. }
+    }
+
+    /// Rademacher, or Webb's six-point weights with fewer than about 12
+    /// clusters, which Rademacher weights can only combine 2^G ways
+    pub fn with_weights(mut self, weights: WildWeights) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    /// Number of draws of the weights. When the weights have at most that
+    /// many combinations across clusters, every combination is enumerated
+    /// instead.
+    pub fn with_replications(mut self, replications: usize) -> Result<Self, AmitaError> {
+        if replications < 2 {
+            return Err(AmitaError::InvalidBootstrap { message: "fewer than 2 replications".to_string() });
+        }
+
+        self.replications = replications;
+        Ok(self)
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Value of the coefficient under the null hypothesis, 0 by default
+    pub fn with_null(mut self, null: f64) -> Self {
+        self.null = null;
+        self
+    }
+
+    /// Weights of each cluster, one column per draw, and whether they
+    /// enumerate every combination
+    fn draws(&self, n_clusters: usize) -> (Array2<f64>, bool) {
+        let values = match self.weights {
+            WildWeights::Rademacher => Some(vec![-1., 1.]),
+            WildWeights::Webb => {
+                let (a, b) = (0.5_f64.sqrt(), 1.5_f64.sqrt());
+                Some(vec![-b, -1., -a, a, 1., b])
+            },
+            WildWeights::Mammen => None,
+        };
+
+        let combinations = values.as_ref()
+            .and_then(|values| u32::try_from(n_clusters).ok().and_then(|g| values.len().checked_pow(g)));
+        match (values, combinations) {
+            (Some(values), Some(combinations)) if combinations <= self.replications => {
+                let mut draws = Array2::zeros((n_clusters, combinations));
+                for (b, mut draw) in draws.columns_mut().into_iter().enumerate() {
+                    let mut index = b;
+                    for v in draw.iter_mut() {
+                        *v = values[index % values.len()];
+                        index /= values.len();
+                    }
+                }
+                (draws, true)
+            },
+            _ => {
+                let mut rng = StdRng::seed_from_u64(self.seed);
+                let mut draws = Array2::zeros((n_clusters, self.replications));
+                for mut draw in draws.columns_mut() {
+                    for v in draw.iter_mut() {
+                        *v = self.weights.draw(&mut rng);
+                    }
+                }
+                (draws, false)
+            },
+        }
+    }
+}
+
+/// Bootstrap test of H_0: β_j = β_0 with CR1 cluster-robust t statistics.
+/// Each bootstrap statistic is stored as a function of β_0, so p values
+/// and confidence intervals are available for any null.
+#[derive(Debug, Clone)]
+pub struct WildClusterTest {
+    estimate: f64,
+    se: f64, // CR1 standard error of the unrestricted fit
+    null: f64,
+    n_clusters: usize,
+    enumerated: bool,
+
+    // numerators N_0 - β_0 N_1 and sums of squared scores
+    // P_00 - 2 β_0 P_01 + β_0^2 P_11 of each draw, with the CR1 scaling
+    numerator: (Array1<f64>, Array1<f64>),
+    squares: (Array1<f64>, Array1<f64>, Array1<f64>),
+}
+
+impl WildClusterTest {
+    /// Unrestricted estimate of the coefficient
+    pub fn estimate(&self) -> f64 {
+        self.estimate
+    }
+
+    /// CR1 standard error of the estimate
+    pub fn se(&self) -> f64 {
+        self.se
+    }
+
+    pub fn null(&self) -> f64 {
+        self.null
+    }
+
+    pub fn n_clusters(&self) -> usize {
+        self.n_clusters
+    }
+
+    pub fn n_draws(&self) -> usize {
+        self.numerator.0.len()
+    }
+
+    /// Whether the draws enumerate every combination of the weights
+    pub fn enumerated(&self) -> bool {
+        self.enumerated
+    }
+
+    /// t statistic (β - β_0) / se
+    pub fn statistic(&self) -> f64 {
+        (self.estimate - self.null) / self.se
+    }
+
+    /// Symmetric bootstrap p value of H_0: β_j = β_0, the share of draws
+    /// with |t*| at least |t|
+    pub fn p_value(&self) -> f64 {
+        self.p_value_at(self.null)
+    }
+
+    /// Bootstrap t statistics of H_0: β_j = `null`
+    pub fn bootstrap_statistics(&self, null: f64) -> Array1<f64> {
+        let (n_0, n_1) = &self.numerator;
+        let (p_00, p_01, p_11) = &self.squares;
+
+        let numerator = n_0 - &(n_1 * null);
+        let variance = p_00 - &(p_01 * (2. * null)) + &(p_11 * null.powi(2));
+        numerator / variance.map(|v| v.max(0.).sqrt())
+    }
+
+    /// Symmetric bootstrap p value of H_0: β_j = `null`
+    pub fn p_value_at(&self, null: f64) -> f64 {
+        // ties, such as the draw v = 1 that reproduces the data, count
+        let t = ((self.estimate - null) / self.se).abs() * (1. - 1e-10);
+        let statistics = self.bootstrap_statistics(null);
+        statistics.iter().filter(|t_star| t_star.abs() >= t).count() as f64 / statistics.len() as f64
+    }
+
+    /// Confidence interval at `level` as the nulls the test does not reject
+    /// at 1 - `level`, found by bisection on each side of the estimate
+    pub fn conf_int(&self, level: f64) -> Result<(f64, f64), AmitaError> {
+        if level.is_nan() || level <= 0. || level >= 1. {
+            return Err(AmitaError::InvalidConfidenceLevel { level });
+        }
+        let alpha = 1. - level;
+        let scale = if self.se > 0. { self.se } else { self.estimate.abs().max(1.) };
+
+        let bound = |direction: f64| -> Result<f64, AmitaError> {
+            let mut inside = self.estimate;
+            let mut step = scale;
+            let mut outside = self.estimate + direction * step;
+            let mut expansions = 0;
+            while self.p_value_at(outside) > alpha {
+                inside = outside;
+                step *= 2.;
+                outside = self.estimate + direction * step;
+                expansions += 1;
+                if expansions > 60 {
+                    return Err(AmitaError::NotAvailable {
+                        feature: format!("A bounded {level} confidence interval with these draws")
+                    });
+                }
+            }
+
+            while (outside - inside).abs() > 1e-10 * scale {
+                let middle = (inside + outside) / 2.;
+                if self.p_value_at(middle) > alpha {
+                    inside = middle;
+                } else {
+                    outside = middle;
+                }
+            }
+            Ok( (inside + outside) / 2. )
+        };
+
+        Ok( (bound(-1.)?, bound(1.)?) )
+    }
+}
+
+impl OLSSolver {
+    /// Wild cluster restricted bootstrap of H_0: β = β_0 for the
+    /// coefficient named `coefficient`, clustered by `clusters`. With
+    /// weights, the model is the transformed √w y = √w X β + √w e.
+    pub fn wild_cluster_bootstrap(
+        &self,
+        coefficient: &str,
+        clusters: &Array1<i32>,
+        bootstrap: &WildClusterBootstrap,
+    ) -> Result<WildClusterTest, AmitaError> {
+        let n = self.y.len();
+        if clusters.len() != n {
+            return Err(AmitaError::NotSameObservations);
+        }
+
+        // decomposed as when solving, unless it already is
+        let solver = self.decomposed()?;
+
+        let invalid = |message: &str| AmitaError::InvalidHypothesis {
+            hypothesis: coefficient.to_string(),
+            message: message.to_string(),
+        };
+        let column = solver.results.coef_names()?.iter()
+            .position(|name| name == coefficient)
+            .ok_or(invalid("no coefficient has this name"))?;
+        let j = solver.kept.iter()
+            .position(|kept| *kept == column)
+            .ok_or(invalid("the coefficient is omitted for collinearity"))?;
+
+        let groups = cluster_rows(clusters);
+        let n_clusters = groups.len();
+        if n_clusters < 2 {
+            return Err(AmitaError::InvalidBootstrap { message: "fewer than 2 clusters".to_string() });
+        }
+
+        let root = solver.weights.map(|w| w.sqrt());
+        let x = &solver.x_kept() * &root.view().insert_axis(Axis(1));
+        let y = &solver.y * &root;
+        let a = &solver.x_gramian_inverse;
+        let a_j = a.column(j).to_owned();
+        let k = solver.kept.len();
+
+        // restricted residuals u_0 - β_0 u_1, from y - β_0 x_j on the other columns
+        let x_j = x.column(j).to_owned();
+        let others = (0..k).filter(|l| *l != j).collect::<Vec<_>>();
+        let (u_0, u_1) = if others.is_empty() {
+            (y.clone(), x_j)
+        } else {
+            let x_others = x.select(Axis(1), &others);
+            let m = inverse(&gramian(x_others.view()), "Regressors other than the tested one")?;
+            let fit = |v: &Array1<f64>| x_others.dot(&m.dot(&x_others.t().dot(v)));
+            (&y - &fit(&y), &x_j - &fit(&x_j))
+        };
+
+        // unrestricted estimate and its CR1 standard error
+        let n_effective = solver.results.n_effective();
+        let scale = n_clusters as f64 / (n_clusters as f64 - 1.) * (n_effective - 1.) / (n_effective - k as f64);
+        let coef = a.dot(&x.t().dot(&y));
+        let resid = &y - &x.dot(&coef);
+        let z = x.dot(&a_j);
+        let variance = groups.iter()
+            .map(|rows| z.select(Axis(0), rows).dot(&resid.select(Axis(0), rows)).powi(2))
+            .sum::<f64>() * scale;
+
+        // cluster sums s_g = X_g' u_g of both parts, and w_g = A X_g'X_g A e_j
+        let mut s_0 = Array2::zeros((n_clusters, k));
+        let mut s_1 = Array2::zeros((n_clusters, k));
+        let mut w = Array2::zeros((n_clusters, k));
+        for (g, rows) in groups.iter().enumerate() {
+            let x_g = x.select(Axis(0), rows);
+            s_0.row_mut(g).assign(&x_g.t().dot(&u_0.select(Axis(0), rows)));
+            s_1.row_mut(g).assign(&x_g.t().dot(&u_1.select(Axis(0), rows)));
+            w.row_mut(g).assign(&a.dot(&x_g.t().dot(&z.select(Axis(0), rows))));
+        }
+
+        // numerator Σ_g v_g c_g and scores v_g c_g - Σ_h D_gh v_h of each
+        // draw, with c_g = e_j' A s_g and D_gh = w_g' s_h
+        let (draws, enumerated) = bootstrap.draws(n_clusters);
+        let parts = [&s_0, &s_1].map(|s| {
+            let c = s.dot(&a_j);
+            let d = w.dot(&s.t());
+            let numerator = draws.t().dot(&c);
+            let scores = &draws * &c.insert_axis(Axis(1)) - d.dot(&draws);
+            (numerator, scores)
+        });
+        let [(n_0, scores_0), (n_1, scores_1)] = parts;
+
+        let squares = (
+            (&scores_0 * &scores_0).sum_axis(Axis(0)) * scale,
+            (&scores_0 * &scores_1).sum_axis(Axis(0)) * scale,
+            (&scores_1 * &scores_1).sum_axis(Axis(0)) * scale,
+        );
+
+        Ok( WildClusterTest {
+            estimate: coef[j],
+            se: variance.sqrt(),
+            null: bootstrap.null,
+            n_clusters,
+            enumerated,
+            numerator: (n_0, n_1),
+            squares,
+        } )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use amita_utils::inference::SolverSEType;
+    use amita_utils::traits::BaseSolver;
+    use rand::Rng;
+
+    use super::*;
+
+    fn data(n_clusters: usize, effect: f64) -> (Array1<f64>, Array2<f64>, Array1<i32>) {
+        let mut rng = StdRng::seed_from_u64(50);
+        let per_cluster = 20;
+        let n = n_clusters * per_cluster;
+
+        let clusters = Array1::from_shape_fn(n, |i| (i / per_cluster) as i32);
+        let shocks = Array1::from_shape_fn(n_clusters, |_| rng.gen_range(-1. .. 1.));
+        let treated = Array1::from_shape_fn(n, |i| if (i / per_cluster) % 3 == 0 { 1. } else { 0. });
+        let x1 = Array1::from_shape_fn(n, |_| rng.gen_range(0. .. 2.));
+        let y = Array1::from_shape_fn(n, |i| {
+            1. + effect * treated[i] + 0.5 * x1[i] + shocks[i / per_cluster] + rng.gen_range(-1. .. 1.)
+        });
+        let x = ndarray::stack![Axis(1), treated, x1, Array1::<f64>::ones(n)];
+        (y, x, clusters)
+    }
+
+    fn solver(y: &Array1<f64>, x: &Array2<f64>) -> Result<OLSSolver, AmitaError> {
+        OLSSolver::new(y, x)?.with_coef_names(vec!["treated".to_string(), "x1".to_string(), "const".to_string()])
+    }
+
+    #[test]
+    fn test_fast_statistics_match_refitting() -> Result<(), AmitaError> {
+        let (y, x, clusters) = data(8, 0.3);
+        let bootstrap = WildClusterBootstrap::new().with_replications(100)?.with_seed(3).with_null(0.2);
+        let test = solver(&y, &x)?.wild_cluster_bootstrap("treated", &clusters, &bootstrap)?;
+
+        // the t statistic is the CR1 one of OLS
+        let clustered = solver(&y, &x)?
+            .with_se_type(SolverSEType::Clustered { by: clusters.clone() })
+            .solve()?
+            .results();
+        assert!( (test.estimate() - clustered.coef()?[0]).abs() < 1e-10 );
+        assert!( (test.se() - clustered.se()?[0]).abs() < 1e-10 );
+        assert!( !test.enumerated() && test.n_draws() == 100 );
+
+        // each bootstrap statistic against re-fitting y* = Xβ̃ + v_g ũ
+        let x_others = x.select(Axis(1), &[1, 2]);
+        let restricted = OLSSolver::new(&(&y - &(x.column(0).to_owned() * 0.2)), &x_others)?.solve()?.results();
+        let resid = restricted.resid()?;
+        let linear = &y - &resid;
+        let (draws, _) = bootstrap.draws(8);
+        let statistics = test.bootstrap_statistics(0.2);
+        for b in 0..5 {
+            let v = clusters.map(|g| draws[[*g as usize, b]]);
+            let y_star = &linear + &(&resid * &v);
+            let refit = solver(&y_star, &x)?
+                .with_se_type(SolverSEType::Clustered { by: clusters.clone() })
+                .solve()?
+                .results();
+            let t_star = (refit.coef()?[0] - 0.2) / refit.se()?[0];
+            assert!( (statistics[b] - t_star).abs() < 1e-8 );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_enumeration_and_inversion() -> Result<(), AmitaError> {
+        let (y, x, clusters) = data(9, 1.5);
+        let solver = solver(&y, &x)?;
+
+        // 2^9 sign patterns, each reproducing the data's t for v = ±1
+        let enumerated = solver.wild_cluster_bootstrap("treated", &clusters, &WildClusterBootstrap::new())?;
+        assert!( enumerated.enumerated() && enumerated.n_draws() == 512 );
+        assert!( (enumerated.p_value() * 512.).fract().abs() < 1e-9 );
+        assert!( enumerated.p_value() >= 2. / 512. );
+
+        let webb = WildClusterBootstrap::new().with_weights(WildWeights::Webb).with_replications(1999)?;
+        let test = solver.wild_cluster_bootstrap("treated", &clusters, &webb)?;
+        assert!( !test.enumerated() );
+        assert!( test.p_value() < 0.05 );
+        assert_eq!(test.p_value(), solver.wild_cluster_bootstrap("treated", &clusters, &webb)?.p_value());
+
+        // the interval's bounds are where the p value crosses 5%
+        let (lower, upper) = test.conf_int(0.95)?;
+        assert!( lower < test.estimate() && test.estimate() < upper );
+        let margin = 1e-6 * test.se();
+        assert!( test.p_value_at(lower + margin) > 0.05 && test.p_value_at(lower - margin) <= 0.05 );
+        assert!( test.p_value_at(upper - margin) > 0.05 && test.p_value_at(upper + margin) <= 0.05 );
+        let (narrow_lower, narrow_upper) = test.conf_int(0.8)?;
+        assert!( lower < narrow_lower && narrow_upper < upper );
+
+        let missing = solver.wild_cluster_bootstrap("post", &clusters, &WildClusterBootstrap::new());
+        assert!( matches!(missing, Err(AmitaError::InvalidHypothesis { .. })) );
+
+        Ok(())
+    }
+}
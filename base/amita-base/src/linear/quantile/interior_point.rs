@@ -0,0 +1,127 @@
+//! Frisch–Newton interior-point method of Portnoy and Koenker (1997).
+//!
+//! Solves the dual of the quantile regression linear program,
+//! max y'a s.t. X'a = (1 - τ) X'1, 0 ≤ a ≤ 1, by Mehrotra's
+//! predictor-corrector steps. The multipliers of the equality constraints
+//! are the coefficients β, and the dual slacks z, w ≥ 0 with
+//! Xβ - z + w = y are the negative and positive parts of the residuals.
+
+use amita_error::AmitaError;
+use amita_utils::linalg::solve;
+use ndarray::prelude::*;
+
+/// Largest share of the distance to the boundary taken by a step
+const STEP_SHARE: f64 = 0.99995;
+
+/// Relative duality gap accepted when the Newton system turns singular
+const NEAR_OPTIMAL: f64 = 1e-6;
+
+pub(crate) fn interior_point(
+    x: &Array2<f64>,
+    y: &Array1<f64>,
+    tau: f64,
+    max_iter: u64,
+    tolerance: f64,
+) -> Result<(Array1<f64>, u64), AmitaError> {
+    let n = y.len();
+    let b = x.t().dot(&Array1::from_elem(n, 1. - tau));
+
+    // a = 1 - τ is feasible, and least squares gives interior residual parts
+    let mut a = Array1::from_elem(n, 1. - tau);
+    let mut s = Array1::from_elem(n, tau);
+    let mut beta = solve(&x.t().dot(x), &x.t().dot(y), "X'X of the quantile regression")?;
+    let resid = y - &x.dot(&beta);
+    let shift = resid.iter().map(|r| r.abs()).sum::<f64>() / n as f64 + 1e-8;
+    let mut z = resid.map(|r| (- r).max(0.) + shift);
+    let mut w = resid.map(|r| r.max(0.) + shift);
+
+    let scale = 1. + y.iter().map(|y_i| y_i.abs()).sum::<f64>();
+
+    for n_iter in 0..max_iter {
+        let gap = a.dot(&z) + s.dot(&w);
+        let r_primal = &b - &x.t().dot(&a);
+        let r_dual = y - &x.dot(&beta) + &z - &w;
+        if gap < tolerance * scale
+            && r_dual.iter().all(|r| r.abs() < tolerance * scale)
+            && r_primal.iter().all(|r| r.abs() < tolerance * scale) {
+            return Ok( (beta, n_iter) );
+        }
+
+        let q = 1. / ( &z / &a + &w / &s );
+
+        // affine-scaling predictor, aiming at complementarity a z = s w = 0.
+        // X'QX loses rank as the weights of observations off the fit vanish,
+        // which on degenerate problems, with repeated rows, happens before
+        // the gap closes to the tolerance: a small gap is then the best
+        // attainable
+        let predictor = match newton_step(x, &q, &r_primal, &r_dual, (&a, &s, &z, &w), &(- &a * &z), &(- &s * &w)) {
+            Err(AmitaError::NotInvertible { .. }) if gap < NEAR_OPTIMAL * scale => return Ok( (beta, n_iter) ),
+            predictor => predictor?,
+        };
+        let (alpha_p, alpha_d) = step_lengths(&predictor, (&a, &s, &z, &w));
+        let gap_affine = (&a + &(&predictor.da * alpha_p)).dot(&(&z + &(&predictor.dz * alpha_d)))
+            + (&s - &(&predictor.da * alpha_p)).dot(&(&w + &(&predictor.dw * alpha_d)));
+
+        // centred corrector with Mehrotra's second-order terms
+        let mu = (gap_affine / gap).powi(3) * gap / (2 * n) as f64;
+        let c_a = - &a * &z - &predictor.da * &predictor.dz + mu;
+        let c_s = - &s * &w + &predictor.da * &predictor.dw + mu;
+        let corrector = newton_step(x, &q, &r_primal, &r_dual, (&a, &s, &z, &w), &c_a, &c_s)?;
+        let (alpha_p, alpha_d) = step_lengths(&corrector, (&a, &s, &z, &w));
+
+        a = a + &corrector.da * alpha_p;
+        s = 1. - &a;
+        beta = beta + &corrector.dbeta * alpha_d;
+        z = z + &corrector.dz * alpha_d;
+        w = w + &corrector.dw * alpha_d;
+    }
+
+    Err(AmitaError::NotConverged { iterations: max_iter })
+}
+
+struct Direction {
+    da: Array1<f64>,
+    dbeta: Array1<f64>,
+    dz: Array1<f64>,
+    dw: Array1<f64>,
+}
+
+/// Newton direction for the linearized conditions X'da = r_p,
+/// X dβ - dz + dw = r_d, z da + a dz = c_a and -w da + s dw = c_s, reduced to
+/// the normal equations X'QX dβ = X'Q r - r_p with Q = (z/a + w/s)^{-1}
+fn newton_step(
+    x: &Array2<f64>,
+    q: &Array1<f64>,
+    r_primal: &Array1<f64>,
+    r_dual: &Array1<f64>,
+    (a, s, z, w): (&Array1<f64>, &Array1<f64>, &Array1<f64>, &Array1<f64>),
+    c_a: &Array1<f64>,
+    c_s: &Array1<f64>,
+) -> Result<Direction, AmitaError> {
+    let r = r_dual + &(c_a / a) - &(c_s / s);
+    let xq = x * &q.view().insert_axis(Axis(1));
+
+    let dbeta = solve(&x.t().dot(&xq), &(xq.t().dot(&r) - r_primal), "X'QX of the quantile regression")?;
+    let da = q * &(&r - &x.dot(&dbeta));
+    let dz = ( c_a - &(z * &da) ) / a;
+    let dw = ( c_s + &(w * &da) ) / s;
+
+    Ok( Direction { da, dbeta, dz, dw } )
+}
+
+/// Primal and dual step lengths keeping 0 < a < 1 and z, w > 0
+fn step_lengths(
+    direction: &Direction,
+    (a, s, z, w): (&Array1<f64>, &Array1<f64>, &Array1<f64>, &Array1<f64>),
+) -> (f64, f64) {
+    let longest = |value: &Array1<f64>, change: &Array1<f64>| {
+        value.iter().zip(change.iter())
+            .filter(|(_, c)| **c < 0.)
+            .fold(f64::INFINITY, |acc, (v, c)| acc.min(- v / c))
+    };
+
+    let alpha_primal = longest(a, &direction.da).min(longest(s, &(- &direction.da)));
+    let alpha_dual = longest(z, &direction.dz).min(longest(w, &direction.dw));
+
+    ( (STEP_SHARE * alpha_primal).min(1.), (STEP_SHARE * alpha_dual).min(1.) )
+}
@@ -0,0 +1,5 @@
+mod interior_point;
+pub mod mss;
+pub mod process;
+mod simplex;
+pub mod solver;
@@ -0,0 +1,404 @@
+//! Quantile regression with fixed effects by the method of moments of
+//! Machado and Santos Silva (2019).
+//!
+//! The location-scale model y_it = α_i + x_it'β + (δ_i + x_it'γ) U_it has
+//! conditional quantiles α_i(τ) + x_it'β(τ), with β(τ) = β + q(τ)γ and q(τ)
+//! the τ-quantile of U. The location β comes from the within regression of
+//! y, the scale γ from the within regression of its absolute residuals, and
+//! q(τ) from the standardized residuals, so the incidental parameters never
+//! enter a quantile regression.
+//!
+//! Coefficients are stacked τ by τ, [β(τ_1), ..., β(τ_T)].
+
+use amita_error::AmitaError;
+use amita_utils::fixed_effects::FixedEffects;
+use amita_utils::inference::{multiway_cluster_meat, SolverSEType};
+use amita_utils::linalg::{inverse, solve};
+use amita_utils::summary::coef_table;
+use amita_utils::traits::{BaseResults, BaseSolver};
+use ndarray::prelude::*;
+use statrs::distribution::{Continuous, ContinuousCDF, Normal};
+
+use super::solver::{sample_quantile, validate_data};
+
+#[derive(Debug, Clone)]
+pub struct MSSResults {
+    taus: Vec<f64>,
+    n_obs: usize,
+    n_regressors: usize,
+
+    location: Option<Array1<f64>>,
+    location_se: Option<Array1<f64>>,
+    scale: Option<Array1<f64>>,
+    scale_se: Option<Array1<f64>>,
+    quantiles: Option<Array1<f64>>,
+
+    coef: Option<Array1<f64>>,
+    se: Option<Array1<f64>>,
+    z: Option<Array1<f64>>,
+    p_vals: Option<Array1<f64>>,
+    vcov: Option<Array2<f64>>,
+}
+
+impl MSSResults {
+    pub fn taus(&self) -> Vec<f64> {
+        self.taus.clone()
+    }
+
+    pub fn n_obs(&self) -> usize {
+        self.n_obs
+    }
+
+    /// Location coefficients β
+    pub fn location(&self) -> Result<Array1<f64>, AmitaError> {
+        self.location.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    pub fn location_se(&self) -> Result<Array1<f64>, AmitaError> {
+        self.location_se.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    /// Scale coefficients γ
+    pub fn scale(&self) -> Result<Array1<f64>, AmitaError> {
+        self.scale.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    pub fn scale_se(&self) -> Result<Array1<f64>, AmitaError> {
+        self.scale_se.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    /// Quantiles q(τ) of the standardized residuals, one per τ
+    pub fn quantiles(&self) -> Result<Array1<f64>, AmitaError> {
+        self.quantiles.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    /// β(τ_j), the coefficients at the j-th quantile
+    pub fn coef_at(&self, j: usize) -> Result<Array1<f64>, AmitaError> {
+        let k = self.n_regressors;
+        Ok( self.coef()?.slice(s![j * k..(j + 1) * k]).to_owned() )
+    }
+
+    pub fn se_at(&self, j: usize) -> Result<Array1<f64>, AmitaError> {
+        let k = self.n_regressors;
+        Ok( self.se()?.slice(s![j * k..(j + 1) * k]).to_owned() )
+    }
+}
+
+impl BaseResults for MSSResults {
+    fn coef(&self) -> Result<Array1<f64>, AmitaError> {
+        self.coef.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn se(&self) -> Result<Array1<f64>, AmitaError> {
+        self.se.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn t(&self) -> Result<Array1<f64>, AmitaError> {
+        self.z.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn p_vals(&self) -> Result<Array1<f64>, AmitaError> {
+        self.p_vals.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn vcov(&self) -> Result<Array2<f64>, AmitaError> {
+        self.vcov.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn summary(&self) -> Result<String, AmitaError> {
+        coef_table(self)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MSSSolver {
+    y: Array1<f64>,
+    x: Array2<f64>,
+    fixed_effects: FixedEffects,
+
+    se_type: SolverSEType,
+
+    // within-transformed data, residuals and fitted scale
+    x_within: Option<Array2<f64>>,
+    resid: Option<Array1<f64>>,
+    abs_resid_within: Option<Array1<f64>>,
+    fitted_scale: Option<Array1<f64>>,
+
+    results: MSSResults,
+}
+
+impl MSSSolver {
+    /// `x` excludes the constant, which the fixed effects absorb. Standard
+    /// errors are clustered by the first fixed-effects dimension unless set
+    /// otherwise.
+    pub fn new(
+        y: &Array1<f64>,
+        x: &Array2<f64>,
+        fixed_effects: &[Array1<i32>],
+        taus: &[f64],
+    ) -> Result<Self, AmitaError> {
+        validate_data(y, x, taus)?;
+        let fe = FixedEffects::new(fixed_effects)?;
+        if fe.n_obs() != y.len() {
+            return Err(AmitaError::NotSameObservations);
+        }
+
+        let results = MSSResults {
+            taus: taus.to_vec(),
+            n_obs: x.shape()[0],
+            n_regressors: x.shape()[1],
+
+            location: None,
+            location_se: None,
+            scale: None,
+            scale_se: None,
+            quantiles: None,
+
+            coef: None,
+            se: None,
+            z: None,
+            p_vals: None,
+            vcov: None,
+        };
+
+        Ok( Self {
+            y: y.to_owned(),
+            x: x.to_owned(),
+            fixed_effects: fe,
+
+            se_type: SolverSEType::Clustered { by: fixed_effects[0].to_owned() },
+
+            x_within: None,
+            resid: None,
+            abs_resid_within: None,
+            fitted_scale: None,
+
+            results,
+        } )
+    }
+
+    /// Robust, Clustered or MultiwayClustered
+    pub fn with_se_type(mut self, se_type: SolverSEType) -> Self {
+        self.se_type = se_type;
+        self
+    }
+}
+
+impl BaseSolver<MSSResults> for MSSSolver {
+    fn results(&self) -> MSSResults {
+        self.results.clone()
+    }
+
+    fn solve(self) -> Result<Self, AmitaError> {
+        self
+        .solve_location()?
+        .solve_scale()?
+        .solve_quantiles()?
+        .solve_se()?
+        .solve_z_pvals()
+    }
+}
+
+impl MSSSolver {
+    fn within(&self, columns: &Array2<f64>) -> Result<Array2<f64>, AmitaError> {
+        self.fixed_effects.demean(columns, &Array1::ones(self.y.len()))
+    }
+
+    fn solve_location(mut self) -> Result<Self, AmitaError> {
+        let y_within = self.within(&self.y.view().insert_axis(Axis(1)).to_owned())?.column(0).to_owned();
+        let x_within = self.within(&self.x)?;
+
+        let beta = solve(&x_within.t().dot(&x_within), &x_within.t().dot(&y_within), "within X'X")?;
+
+        self.resid = Some(&y_within - &x_within.dot(&beta));
+        self.x_within = Some(x_within);
+        self.results.location = Some(beta);
+
+        Ok(self)
+    }
+
+    /// Within regression of |R| on X, whose fitted values δ_i + x_it'γ are
+    /// the scale of each observation
+    fn solve_scale(mut self) -> Result<Self, AmitaError> {
+        let x_within = self.x_within.as_ref().ok_or(AmitaError::NotSolved)?;
+        let abs_resid = self.resid.as_ref().ok_or(AmitaError::NotSolved)?.map(|r| r.abs());
+        let abs_resid_within = self.within(&abs_resid.view().insert_axis(Axis(1)).to_owned())?.column(0).to_owned();
+
+        let gamma = solve(&x_within.t().dot(x_within), &x_within.t().dot(&abs_resid_within), "within X'X")?;
+
+        let fitted_scale = &abs_resid - &(&abs_resid_within - &x_within.dot(&gamma));
+        if fitted_scale.iter().any(|s| *s <= 0.) {
+            return Err(AmitaError::NonPositive { matrix_name: "fitted scale".to_string() });
+        }
+
+        self.abs_resid_within = Some(abs_resid_within);
+        self.fitted_scale = Some(fitted_scale);
+        self.results.scale = Some(gamma);
+
+        Ok(self)
+    }
+
+    fn solve_quantiles(mut self) -> Result<Self, AmitaError> {
+        let beta = self.results.location()?;
+        let gamma = self.results.scale()?;
+        let standardized = self.standardized()?;
+
+        let quantiles = self.results.taus.iter()
+            .map(|tau| sample_quantile(&standardized, *tau))
+            .collect::<Array1<f64>>();
+
+        let coef = quantiles.iter()
+            .flat_map(|q| (&beta + &(&gamma * *q)).to_vec())
+            .collect::<Array1<f64>>();
+
+        self.results.quantiles = Some(quantiles);
+        self.results.coef = Some(coef);
+
+        Ok(self)
+    }
+
+    fn standardized(&self) -> Result<Array1<f64>, AmitaError> {
+        let resid = self.resid.as_ref().ok_or(AmitaError::NotSolved)?;
+        let fitted_scale = self.fitted_scale.as_ref().ok_or(AmitaError::NotSolved)?;
+        Ok( resid / fitted_scale )
+    }
+
+    /// GMM sandwich J^{-1} M J^{-T} for θ = (β, γ, q_1, ..., q_T), with the
+    /// moments x̃R, x̃(Ã - x̃'γ) and τ_t - 1{U ≤ q_t}, then the delta method
+    /// for β(τ_t) = β + q_t γ
+    fn solve_se(mut self) -> Result<Self, AmitaError> {
+        let x_within = self.x_within.as_ref().ok_or(AmitaError::NotSolved)?;
+        let resid = self.resid.as_ref().ok_or(AmitaError::NotSolved)?;
+        let abs_resid_within = self.abs_resid_within.as_ref().ok_or(AmitaError::NotSolved)?;
+        let fitted_scale = self.fitted_scale.as_ref().ok_or(AmitaError::NotSolved)?;
+        let standardized = self.standardized()?;
+
+        let gamma = self.results.scale()?;
+        let quantiles = self.results.quantiles()?;
+        let taus = self.results.taus.clone();
+        let (n, k, n_taus) = (self.results.n_obs, self.results.n_regressors, taus.len());
+        let dim = 2 * k + n_taus;
+
+        let scale_resid = abs_resid_within - &x_within.dot(&gamma);
+        let mut scores = Array2::zeros((n, dim));
+        scores.slice_mut(s![.., ..k]).assign(&(x_within * &resid.view().insert_axis(Axis(1))));
+        scores.slice_mut(s![.., k..2 * k]).assign(&(x_within * &scale_resid.view().insert_axis(Axis(1))));
+        for (t, (tau, q)) in taus.iter().zip(quantiles.iter()).enumerate() {
+            scores.column_mut(2 * k + t).assign(&standardized.map(|u| tau - if u <= q { 1. } else { 0. }));
+        }
+
+        let gram = x_within.t().dot(x_within);
+        let signed = x_within * &resid.map(|r| r.signum()).insert_axis(Axis(1));
+        let x_over_scale = (x_within / &fitted_scale.view().insert_axis(Axis(1))).sum_axis(Axis(0));
+
+        let mut jacobian = Array2::zeros((dim, dim));
+        jacobian.slice_mut(s![..k, ..k]).assign(&(- &gram));
+        jacobian.slice_mut(s![k..2 * k, ..k]).assign(&(- signed.t().dot(x_within)));
+        jacobian.slice_mut(s![k..2 * k, k..2 * k]).assign(&(- &gram));
+        for (t, q) in quantiles.iter().enumerate() {
+            let density = kernel_density(&standardized, *q);
+            jacobian.slice_mut(s![2 * k + t, ..k]).assign(&(&x_over_scale * (- density)));
+            jacobian.slice_mut(s![2 * k + t, k..2 * k]).assign(&(&x_over_scale * (- density * q)));
+            jacobian[[2 * k + t, 2 * k + t]] = - density * n as f64;
+        }
+
+        let meat = match &self.se_type {
+            SolverSEType::Robust | SolverSEType::HC1 => scores.t().dot(&scores) * n as f64 / (n as f64 - 1.),
+            SolverSEType::Clustered { by } => multiway_cluster_meat(&scores, std::slice::from_ref(by))?,
+            SolverSEType::MultiwayClustered { by } => multiway_cluster_meat(&scores, by)?,
+            se_type => return Err(AmitaError::UnsupportedSEType { se_type: format!("{se_type:?}") }),
+        };
+        let jacobian_inv = inverse(&jacobian, "MSS moment Jacobian")?;
+        let gmm_vcov = jacobian_inv.dot(&meat).dot(&jacobian_inv.t());
+
+        let mut delta = Array2::zeros((k * n_taus, dim));
+        for (t, q) in quantiles.iter().enumerate() {
+            for j in 0..k {
+                delta[[t * k + j, j]] = 1.;
+                delta[[t * k + j, k + j]] = *q;
+                delta[[t * k + j, 2 * k + t]] = gamma[j];
+            }
+        }
+        let vcov = delta.dot(&gmm_vcov).dot(&delta.t());
+
+        let gmm_se = gmm_vcov.diag().map(|x| x.sqrt());
+        self.results.location_se = Some(gmm_se.slice(s![..k]).to_owned());
+        self.results.scale_se = Some(gmm_se.slice(s![k..2 * k]).to_owned());
+        self.results.se = Some(vcov.diag().map(|x| x.sqrt()));
+        self.results.vcov = Some(vcov);
+
+        Ok(self)
+    }
+
+    fn solve_z_pvals(mut self) -> Result<Self, AmitaError> {
+        let z = self.results.coef()? / self.results.se()?;
+
+        let normal = Normal::new(0., 1.).unwrap();
+        let p_vals = z.map(|x| 2. * ( 1. - normal.cdf(x.abs()) ) );
+
+        self.results.z = Some(z);
+        self.results.p_vals = Some(p_vals);
+
+        Ok(self)
+    }
+}
+
+/// Gaussian kernel density of `values` at `point`, with Silverman's
+/// rule-of-thumb bandwidth
+fn kernel_density(values: &Array1<f64>, point: f64) -> f64 {
+    let normal = Normal::new(0., 1.).unwrap();
+    let n = values.len() as f64;
+
+    let sd = values.std(1.);
+    let iqr = sample_quantile(values, 0.75) - sample_quantile(values, 0.25);
+    let spread = if iqr > 0. { sd.min(iqr / 1.34) } else { sd };
+    let h = 1.06 * spread * n.powf(-0.2);
+
+    values.iter().map(|v| normal.pdf((point - v) / h)).sum::<f64>() / (n * h)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mss() -> Result<(), AmitaError> {
+        // 500 units of 10: y = α_i + x + (δ_i + 0.5 x) e, so that
+        // β(τ) = 1 + 0.5 Φ^{-1}(τ)
+        let normal = Normal::new(0., 1.).unwrap();
+        let n = 5000;
+        let unit = Array1::from_iter( (0..n).map(|i| i / 10) );
+        let alpha = unit.map(|g| (*g as f64 * 3_f64.sqrt()).fract() * 4.);
+        let delta = unit.map(|g| 0.5 + (*g as f64 * 7_f64.sqrt()).fract());
+        let x1 = Array1::from_iter( (0..n).map(|i| ((i as f64 + 0.5) * 5_f64.sqrt()).fract() * 2.) ) + &alpha * 0.25;
+        let e = Array1::from_iter( (0..n).map(|i| normal.inverse_cdf(((i as f64 + 0.5) * 2_f64.sqrt()).fract())) );
+        let y = &alpha + &x1 + &e * &(&delta + &(&x1 * 0.5));
+        let x = x1.insert_axis(Axis(1));
+
+        let taus = [0.25, 0.5, 0.75];
+        let results = MSSSolver::new(&y, &x, std::slice::from_ref(&unit), &taus)?.solve()?.results();
+
+        assert!( (results.location()?[0] - 1.).abs() < 0.1 );
+        for (j, tau) in taus.iter().enumerate() {
+            let truth = 1. + 0.5 * normal.inverse_cdf(*tau);
+            assert!( (results.coef_at(j)?[0] - truth).abs() < 0.1 );
+            assert!( results.se_at(j)?[0] > 0. && results.se_at(j)?[0] < 0.1 );
+        }
+
+        // clustering by unit and heteroskedasticity-robust errors are close
+        // with independent draws
+        let robust = MSSSolver::new(&y, &x, std::slice::from_ref(&unit), &taus)?
+            .with_se_type(SolverSEType::Robust)
+            .solve()?
+            .results();
+        assert_eq!(robust.coef()?, results.coef()?);
+        assert!( (robust.se()? / results.se()?).iter().all(|r| (r - 1.).abs() < 0.3) );
+
+        let homoscedastic = MSSSolver::new(&y, &x, &[unit], &taus)?
+            .with_se_type(SolverSEType::NonRobust)
+            .solve();
+        assert!( matches!(homoscedastic, Err(AmitaError::UnsupportedSEType { .. })) );
+
+        Ok(())
+    }
+}
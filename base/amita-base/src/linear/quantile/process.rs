@@ -0,0 +1,298 @@
+//! Quantile regression process: β(τ) over a grid of quantiles, with the
+//! joint covariance of the estimates across τ.
+//!
+//! Coefficients are stacked τ by τ, [β(τ_1), ..., β(τ_T)].
+
+use amita_error::AmitaError;
+use amita_utils::hypothesis::{students_t_p_vals, TestStatistic};
+use amita_utils::linalg::inverse;
+use amita_utils::summary::coef_table;
+use amita_utils::traits::{BaseResults, BaseSolver};
+use ndarray::prelude::*;
+
+use super::solver::{
+    bootstrap_coef, draws_vcov, fit, powell_bread, validate_data,
+    FitOptions, QuantileMethod, QuantileSEType,
+};
+
+#[derive(Debug, Clone)]
+pub struct QuantileProcessResults {
+    taus: Vec<f64>,
+    n_obs: usize,
+    n_regressors: usize,
+
+    coef: Option<Array1<f64>>,
+    se: Option<Array1<f64>>,
+    t: Option<Array1<f64>>,
+    p_vals: Option<Array1<f64>>,
+    vcov: Option<Array2<f64>>,
+}
+
+impl QuantileProcessResults {
+    pub fn taus(&self) -> Vec<f64> {
+        self.taus.clone()
+    }
+
+    pub fn n_obs(&self) -> usize {
+        self.n_obs
+    }
+
+    /// β(τ_j), the coefficients at the j-th quantile of the grid
+    pub fn coef_at(&self, j: usize) -> Result<Array1<f64>, AmitaError> {
+        let k = self.n_regressors;
+        Ok( self.coef()?.slice(s![j * k..(j + 1) * k]).to_owned() )
+    }
+
+    pub fn se_at(&self, j: usize) -> Result<Array1<f64>, AmitaError> {
+        let k = self.n_regressors;
+        Ok( self.se()?.slice(s![j * k..(j + 1) * k]).to_owned() )
+    }
+
+    /// Path τ ↦ β_column(τ) of one coefficient over the grid
+    pub fn coef_path(&self, column: usize) -> Result<Array1<f64>, AmitaError> {
+        let coef = self.coef()?;
+        Ok( (0..self.taus.len()).map(|j| coef[j * self.n_regressors + column]).collect() )
+    }
+
+    /// Wald test that the coefficient of `column` is the same at every
+    /// quantile of the grid, χ²(T - 1). For a slope, this is the
+    /// Koenker–Bassett test of location shift against heteroskedasticity.
+    pub fn equality_test(&self, column: usize) -> Result<TestStatistic, AmitaError> {
+        if column >= self.n_regressors {
+            return Err(AmitaError::ColumnNotFound { column: format!("x[{column}]") });
+        }
+
+        let k = self.n_regressors;
+        let n_taus = self.taus.len();
+        let mut restrictions = Array2::zeros((n_taus - 1, k * n_taus));
+        for j in 0..n_taus - 1 {
+            restrictions[[j, j * k + column]] = 1.;
+            restrictions[[j, (j + 1) * k + column]] = -1.;
+        }
+
+        let diff = restrictions.dot(&self.coef()?);
+        let vcov_diff = restrictions.dot(&self.vcov()?).dot(&restrictions.t());
+        let statistic = diff.dot(&inverse(&vcov_diff, "covariance of quantile differences")?.dot(&diff));
+
+        Ok( TestStatistic::chi_squared("Equality of quantile coefficients", statistic, (n_taus - 1) as f64) )
+    }
+}
+
+impl BaseResults for QuantileProcessResults {
+    fn coef(&self) -> Result<Array1<f64>, AmitaError> {
+        self.coef.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn se(&self) -> Result<Array1<f64>, AmitaError> {
+        self.se.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn t(&self) -> Result<Array1<f64>, AmitaError> {
+        self.t.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn p_vals(&self) -> Result<Array1<f64>, AmitaError> {
+        self.p_vals.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn vcov(&self) -> Result<Array2<f64>, AmitaError> {
+        self.vcov.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn summary(&self) -> Result<String, AmitaError> {
+        coef_table(self)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct QuantileProcessSolver {
+    y: Array1<f64>,
+    x: Array2<f64>,
+
+    method: QuantileMethod,
+    se_type: QuantileSEType,
+    max_iter: u64,
+    max_tolerance: f64,
+
+    results: QuantileProcessResults,
+}
+
+impl QuantileProcessSolver {
+    pub fn new(
+        y: &Array1<f64>,
+        x: &Array2<f64>,
+        taus: &[f64],
+    ) -> Result<Self, AmitaError> {
+        validate_data(y, x, taus)?;
+
+        let results = QuantileProcessResults {
+            taus: taus.to_vec(),
+            n_obs: x.shape()[0],
+            n_regressors: x.shape()[1],
+
+            coef: None,
+            se: None,
+            t: None,
+            p_vals: None,
+            vcov: None,
+        };
+
+        Ok( Self {
+            y: y.to_owned(),
+            x: x.to_owned(),
+
+            method: QuantileMethod::InteriorPoint,
+            se_type: QuantileSEType::Powell,
+            max_iter: 1_000,
+            max_tolerance: 1e-10,
+
+            results,
+        } )
+    }
+
+    /// Equally spaced grid τ = 1/(m + 1), ..., m/(m + 1)
+    pub fn with_grid(
+        y: &Array1<f64>,
+        x: &Array2<f64>,
+        m: usize,
+    ) -> Result<Self, AmitaError> {
+        let taus = (1..=m).map(|j| j as f64 / (m + 1) as f64).collect::<Vec<_>>();
+        Self::new(y, x, &taus)
+    }
+
+    pub fn with_method(mut self, method: QuantileMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    pub fn with_se_type(mut self, se_type: QuantileSEType) -> Self {
+        self.se_type = se_type;
+        self
+    }
+
+    pub fn with_max_iter(mut self, max_iter: u64) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    pub fn with_max_tolerance(mut self, max_tolerance: f64) -> Self {
+        self.max_tolerance = max_tolerance;
+        self
+    }
+}
+
+impl BaseSolver<QuantileProcessResults> for QuantileProcessSolver {
+    fn results(&self) -> QuantileProcessResults {
+        self.results.clone()
+    }
+
+    fn solve(self) -> Result<Self, AmitaError> {
+        self
+        .solve_coef()?
+        .solve_se()?
+        .solve_t_pvals()
+    }
+}
+
+impl QuantileProcessSolver {
+    fn fit_options(&self) -> FitOptions {
+        FitOptions { method: self.method, max_iter: self.max_iter, max_tolerance: self.max_tolerance }
+    }
+
+    fn solve_coef(mut self) -> Result<Self, AmitaError> {
+        let coef = self.results.taus.iter()
+            .map(|tau| Ok( fit(&self.x, &self.y, *tau, self.fit_options())?.0 ))
+            .collect::<Result<Vec<_>, AmitaError>>()?;
+
+        self.results.coef = Some( ndarray::concatenate(Axis(0), &coef.iter().map(|c| c.view()).collect::<Vec<_>>()).unwrap() );
+
+        Ok(self)
+    }
+
+    /// Powell's joint covariance has blocks
+    /// (min(τ_s, τ_t) - τ_s τ_t) H_s^{-1} J H_t^{-1} / n across quantiles
+    fn solve_se(mut self) -> Result<Self, AmitaError> {
+        let k = self.results.n_regressors;
+        let n = self.results.n_obs as f64;
+        let taus = self.results.taus.clone();
+
+        let vcov = match self.se_type {
+            QuantileSEType::Powell => {
+                let breads = (0..taus.len())
+                    .map(|j| {
+                        let resid = &self.y - &self.x.dot(&self.results.coef_at(j)?);
+                        powell_bread(&self.x, &resid, taus[j])
+                    })
+                    .collect::<Result<Vec<_>, AmitaError>>()?;
+                let gram = self.x.t().dot(&self.x) / n;
+
+                let mut vcov = Array2::zeros((k * taus.len(), k * taus.len()));
+                for (s, tau_s) in taus.iter().enumerate() {
+                    for (t, tau_t) in taus.iter().enumerate() {
+                        let block = breads[s].dot(&gram).dot(&breads[t]) * ( tau_s.min(*tau_t) - tau_s * tau_t ) / n;
+                        vcov.slice_mut(s![s * k..(s + 1) * k, t * k..(t + 1) * k]).assign(&block);
+                    }
+                }
+                vcov
+            },
+            QuantileSEType::Bootstrap { replications, seed } => {
+                let draws = bootstrap_coef(&self.x, &self.y, &taus, self.fit_options(), replications, seed)?;
+                draws_vcov(&draws)
+            },
+        };
+
+        self.results.se = Some(vcov.diag().map(|x| x.sqrt()));
+        self.results.vcov = Some(vcov);
+
+        Ok(self)
+    }
+
+    fn solve_t_pvals(mut self) -> Result<Self, AmitaError> {
+        let df = self.results.n_obs as f64 - self.results.n_regressors as f64;
+        let t = self.results.coef()? / self.results.se()?;
+        let p_vals = students_t_p_vals(&t, df);
+
+        self.results.t = Some(t);
+        self.results.p_vals = Some(p_vals);
+
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use statrs::distribution::{ContinuousCDF, Normal};
+
+    use super::*;
+    use crate::linear::quantile::solver::QuantileSolver;
+
+    #[test]
+    fn test_process() -> Result<(), AmitaError> {
+        // y = 1 + 2 x1 + (0.5 + x1) e: quantile slopes 2 + Φ^{-1}(τ)
+        let normal = Normal::new(0., 1.).unwrap();
+        let n = 400;
+        let x1 = Array1::from_iter( (0..n).map(|i| ((i as f64 + 0.5) * 5_f64.sqrt()).fract() * 2.) );
+        let e = Array1::from_iter( (0..n).map(|i| normal.inverse_cdf(((i as f64 + 0.5) * 2_f64.sqrt()).fract())) );
+        let y = 1. + 2. * &x1 + &e * &x1.map(|x| 0.5 + x);
+        let x = ndarray::stack![Axis(1), Array1::<f64>::ones(n), x1];
+
+        let results = QuantileProcessSolver::with_grid(&y, &x, 3)?.solve()?.results();
+        assert_eq!(results.taus(), vec![0.25, 0.5, 0.75]);
+
+        // each τ matches the single-quantile solver, standard errors included
+        let median = QuantileSolver::new(&y, &x, 0.5)?.solve()?.results();
+        assert_eq!(results.coef_at(1)?, median.coef()?);
+        assert!( (results.se_at(1)? - median.se()?).iter().all(|d| d.abs() < 1e-12) );
+
+        let slopes = results.coef_path(1)?;
+        for (slope, tau) in slopes.iter().zip([0.25, 0.5, 0.75]) {
+            assert!( (slope - 2. - normal.inverse_cdf(tau)).abs() < 0.25 );
+        }
+
+        // the fanning slopes reject equality, the intercepts Φ^{-1}(τ)/2 too
+        assert!( results.equality_test(1)?.p_val() < 0.01 );
+        assert!( matches!(results.equality_test(2), Err(AmitaError::ColumnNotFound { .. })) );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+//! Exterior-point simplex method of Barrodale and Roberts (1974) for
+//! quantile regression.
+//!
+//! Every vertex of the linear program interpolates k observations h, with
+//! β = X_h^{-1} y_h. From a vertex, releasing one interpolated observation
+//! above or below the fit moves β along an edge; the method takes the edge
+//! of steepest descent of Σ ρ_τ(y - x'β) and follows it to the minimum along
+//! the ray, the weighted median of the points where residuals change sign,
+//! whose observation then enters the basis.
+
+use amita_error::AmitaError;
+use amita_utils::linalg::{inverse, solve};
+use ndarray::prelude::*;
+
+pub(crate) fn simplex(
+    x: &Array2<f64>,
+    y: &Array1<f64>,
+    tau: f64,
+    max_iter: u64,
+    tolerance: f64,
+) -> Result<(Array1<f64>, u64), AmitaError> {
+    let (n, k) = (x.shape()[0], x.shape()[1]);
+    let scale = y.iter().fold(1., |acc: f64, y_i| acc.max(y_i.abs()));
+
+    let ols = solve(&x.t().dot(x), &x.t().dot(y), "X'X of the quantile regression")?;
+    let mut basis = initial_basis(x, &(y - &x.dot(&ols)))?;
+
+    for n_iter in 0..max_iter {
+        let x_basis_inv = inverse(&x.select(Axis(0), &basis), "basis of the quantile regression simplex")?;
+        let beta = x_basis_inv.dot(&y.select(Axis(0), &basis));
+        let resid = y - &x.dot(&beta);
+
+        let mut in_basis = vec![false; n];
+        basis.iter().for_each(|i| in_basis[*i] = true);
+        let zero = |i: usize| in_basis[i] || resid[i].abs() <= tolerance * scale;
+
+        // Σ ψ_τ(r_i) x_i over observations off the fit
+        let mut score = Array1::<f64>::zeros(k);
+        for i in (0..n).filter(|i| !zero(*i)) {
+            let psi = if resid[i] > 0. { tau } else { tau - 1. };
+            score.scaled_add(psi, &x.row(i));
+        }
+        let score = score.dot(&x_basis_inv);
+
+        // directional derivative along d = ±X_h^{-1} e_j, with observations
+        // on the fit contributing (1 - τ)|x'd| or τ|x'd| as they move below
+        // or above it
+        let mut steepest: Option<(f64, usize, Array1<f64>)> = None;
+        for j in 0..k {
+            for sign in [1., -1.] {
+                let direction = x_basis_inv.column(j).map(|d| sign * d);
+                let mut slope = - sign * score[j];
+                for i in (0..n).filter(|i| zero(*i)) {
+                    let change = if in_basis[i] {
+                        if basis[j] == i { sign } else { 0. }
+                    } else {
+                        x.row(i).dot(&direction)
+                    };
+                    slope += if change > 0. { (1. - tau) * change } else { - tau * change };
+                }
+
+                if slope < - tolerance && steepest.as_ref().is_none_or(|(best, _, _)| slope < *best) {
+                    steepest = Some( (slope, j, direction) );
+                }
+            }
+        }
+
+        let Some( (mut slope, leaving, direction) ) = steepest else {
+            return Ok( (beta, n_iter) );
+        };
+
+        // residuals r_i - t x_i'd cross zero at t_i = r_i / x_i'd, each
+        // raising the slope by |x_i'd|
+        let change = x.dot(&direction);
+        let mut crossings = (0..n)
+            .filter(|i| !zero(*i) && change[*i] != 0. && resid[*i] / change[*i] > 0.)
+            .map(|i| (resid[i] / change[i], i))
+            .collect::<Vec<_>>();
+        crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let entering = crossings.iter()
+            .find(|(_, i)| {
+                slope += change[*i].abs();
+                slope >= 0.
+            })
+            .map(|(_, i)| *i)
+            .ok_or(AmitaError::SolverFailed { message: "quantile regression objective is unbounded".to_string() })?;
+
+        basis[leaving] = entering;
+    }
+
+    Err(AmitaError::NotConverged { iterations: max_iter })
+}
+
+/// k linearly independent observations, taken greedily in order of the
+/// absolute least-squares residual
+fn initial_basis(x: &Array2<f64>, resid: &Array1<f64>) -> Result<Vec<usize>, AmitaError> {
+    let k = x.shape()[1];
+
+    let mut order = (0..x.shape()[0]).collect::<Vec<_>>();
+    order.sort_by(|a, b| resid[*a].abs().total_cmp(&resid[*b].abs()));
+
+    let mut basis = vec![];
+    let mut orthonormal: Vec<Array1<f64>> = vec![];
+    for i in order {
+        let row = x.row(i).to_owned();
+        let mut projected = row.clone();
+        for e in orthonormal.iter() {
+            projected.scaled_add(- e.dot(&row), e);
+        }
+
+        let norm = projected.dot(&projected).sqrt();
+        if norm > 1e-8 * row.dot(&row).sqrt() {
+            orthonormal.push(projected / norm);
+            basis.push(i);
+            if basis.len() == k {
+                return Ok(basis);
+            }
+        }
+    }
+
+    Err(AmitaError::NotInvertible { matrix_name: "X of the quantile regression".to_string() })
+}
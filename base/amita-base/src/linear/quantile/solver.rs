@@ -0,0 +1,456 @@
+//! Linear quantile regression, β(τ) = argmin Σ ρ_τ(y - x'β) with the check
+//! function ρ_τ(u) = u (τ - 1{u < 0}).
+
+use amita_error::AmitaError;
+use amita_utils::hypothesis::students_t_p_vals;
+use amita_utils::linalg::inverse;
+use amita_utils::parallel::map_indices;
+use amita_utils::summary::coef_table;
+use amita_utils::traits::{BaseResults, BaseSolver};
+use ndarray::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use statrs::distribution::{Continuous, ContinuousCDF, Normal};
+
+use super::interior_point::interior_point;
+use super::simplex::simplex;
+
+/// Algorithm solving the quantile regression linear program
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuantileMethod {
+    /// Frisch–Newton interior point, for large samples
+    InteriorPoint,
+    /// Barrodale–Roberts simplex, exact vertex solutions for small samples
+    Simplex,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QuantileSEType {
+    /// Powell's kernel sandwich τ(1 - τ) H^{-1} J H^{-1}, robust to
+    /// heteroskedasticity, with the uniform kernel and Hall–Sheather bandwidth
+    Powell,
+    /// Pairs bootstrap with `replications` resamples drawn from `seed`
+    Bootstrap { replications: usize, seed: u64 },
+}
+
+#[derive(Debug, Clone)]
+pub struct QuantileResults {
+    tau: f64,
+    n_obs: usize,
+    n_regressors: usize,
+
+    coef: Option<Array1<f64>>,
+    se: Option<Array1<f64>>,
+    t: Option<Array1<f64>>,
+    p_vals: Option<Array1<f64>>,
+    vcov: Option<Array2<f64>>,
+
+    resid: Option<Array1<f64>>,
+    objective: Option<f64>, // Σ ρ_τ(y - x'β)
+    pseudo_r_sq: Option<f64>,
+    n_iter: Option<u64>,
+}
+
+impl QuantileResults {
+    pub fn tau(&self) -> f64 {
+        self.tau
+    }
+
+    pub fn n_obs(&self) -> usize {
+        self.n_obs
+    }
+
+    pub fn resid(&self) -> Result<Array1<f64>, AmitaError> {
+        self.resid.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    /// Minimized sum of check-function losses
+    pub fn objective(&self) -> Result<f64, AmitaError> {
+        self.objective.ok_or(AmitaError::NotSolved)
+    }
+
+    /// Koenker–Machado goodness of fit, one minus the ratio of the objective
+    /// to that of the unconditional τ-quantile
+    pub fn pseudo_r_sq(&self) -> Result<f64, AmitaError> {
+        self.pseudo_r_sq.ok_or(AmitaError::NotSolved)
+    }
+
+    pub fn n_iter(&self) -> Result<u64, AmitaError> {
+        self.n_iter.ok_or(AmitaError::NotSolved)
+    }
+}
+
+impl BaseResults for QuantileResults {
+    fn coef(&self) -> Result<Array1<f64>, AmitaError> {
+        self.coef.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn se(&self) -> Result<Array1<f64>, AmitaError> {
+        self.se.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn t(&self) -> Result<Array1<f64>, AmitaError> {
+        self.t.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn p_vals(&self) -> Result<Array1<f64>, AmitaError> {
+        self.p_vals.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn vcov(&self) -> Result<Array2<f64>, AmitaError> {
+        self.vcov.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn summary(&self) -> Result<String, AmitaError> {
+        coef_table(self)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct QuantileSolver {
+    y: Array1<f64>,
+    x: Array2<f64>,
+
+    method: QuantileMethod,
+    se_type: QuantileSEType,
+    max_iter: u64,
+    max_tolerance: f64,
+
+    results: QuantileResults,
+}
+
+impl QuantileSolver {
+    pub fn new(
+        y: &Array1<f64>,
+        x: &Array2<f64>,
+        tau: f64,
+    ) -> Result<Self, AmitaError> {
+        validate_data(y, x, &[tau])?;
+
+        let results = QuantileResults {
+            tau,
+            n_obs: x.shape()[0],
+            n_regressors: x.shape()[1],
+
+            coef: None,
+            se: None,
+            t: None,
+            p_vals: None,
+            vcov: None,
+
+            resid: None,
+            objective: None,
+            pseudo_r_sq: None,
+            n_iter: None,
+        };
+
+        Ok( Self {
+            y: y.to_owned(),
+            x: x.to_owned(),
+
+            method: QuantileMethod::InteriorPoint,
+            se_type: QuantileSEType::Powell,
+            max_iter: 1_000,
+            max_tolerance: 1e-10,
+
+            results,
+        } )
+    }
+
+    pub fn with_method(mut self, method: QuantileMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    pub fn with_se_type(mut self, se_type: QuantileSEType) -> Self {
+        self.se_type = se_type;
+        self
+    }
+
+    /// Iterations of the interior point, or pivots of the simplex
+    pub fn with_max_iter(mut self, max_iter: u64) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    pub fn with_max_tolerance(mut self, max_tolerance: f64) -> Self {
+        self.max_tolerance = max_tolerance;
+        self
+    }
+}
+
+impl BaseSolver<QuantileResults> for QuantileSolver {
+    fn results(&self) -> QuantileResults {
+        self.results.clone()
+    }
+
+    fn solve(self) -> Result<Self, AmitaError> {
+        self
+        .solve_coef()?
+        .solve_se()?
+        .solve_t_pvals()
+    }
+}
+
+impl QuantileSolver {
+    fn fit_options(&self) -> FitOptions {
+        FitOptions { method: self.method, max_iter: self.max_iter, max_tolerance: self.max_tolerance }
+    }
+
+    fn solve_coef(mut self) -> Result<Self, AmitaError> {
+        let tau = self.results.tau;
+        let (coef, n_iter) = fit(&self.x, &self.y, tau, self.fit_options())?;
+
+        let resid = &self.y - &self.x.dot(&coef);
+        let objective = check_loss(&resid, tau);
+        let unconditional = sample_quantile(&self.y, tau);
+        let null_objective = check_loss(&self.y.map(|y| y - unconditional), tau);
+
+        self.results.objective = Some(objective);
+        self.results.pseudo_r_sq = Some(1. - objective / null_objective);
+        self.results.resid = Some(resid);
+        self.results.n_iter = Some(n_iter);
+        self.results.coef = Some(coef);
+
+        Ok(self)
+    }
+
+    fn solve_se(mut self) -> Result<Self, AmitaError> {
+        let tau = self.results.tau;
+
+        let vcov = match self.se_type {
+            QuantileSEType::Powell => {
+                let h_inv = powell_bread(&self.x, &self.results.resid()?, tau)?;
+                let gram = self.x.t().dot(&self.x) / self.results.n_obs as f64;
+                h_inv.dot(&gram).dot(&h_inv) * tau * (1. - tau) / self.results.n_obs as f64
+            },
+            QuantileSEType::Bootstrap { replications, seed } => {
+                let draws = bootstrap_coef(&self.x, &self.y, &[tau], self.fit_options(), replications, seed)?;
+                draws_vcov(&draws)
+            },
+        };
+
+        self.results.se = Some(vcov.diag().map(|x| x.sqrt()));
+        self.results.vcov = Some(vcov);
+
+        Ok(self)
+    }
+
+    fn solve_t_pvals(mut self) -> Result<Self, AmitaError> {
+        let df = self.results.n_obs as f64 - self.results.n_regressors as f64;
+        let t = self.results.coef()? / self.results.se()?;
+        let p_vals = students_t_p_vals(&t, df);
+
+        self.results.t = Some(t);
+        self.results.p_vals = Some(p_vals);
+
+        Ok(self)
+    }
+}
+
+pub(crate) fn validate_data(
+    y: &Array1<f64>,
+    x: &Array2<f64>,
+    taus: &[f64],
+) -> Result<(), AmitaError> {
+    if x.shape()[0] != y.len() {
+        return Err(AmitaError::NotSameObservations);
+    }
+    if let Some(tau) = taus.iter().find(|tau| !(**tau > 0. && **tau < 1.)) {
+        return Err(AmitaError::InvalidQuantile { tau: *tau });
+    }
+
+    Ok(())
+}
+
+/// Algorithm and stopping rule of a single quantile regression fit
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct FitOptions {
+    pub(crate) method: QuantileMethod,
+    pub(crate) max_iter: u64,
+    pub(crate) max_tolerance: f64,
+}
+
+pub(crate) fn fit(
+    x: &Array2<f64>,
+    y: &Array1<f64>,
+    tau: f64,
+    options: FitOptions,
+) -> Result<(Array1<f64>, u64), AmitaError> {
+    match options.method {
+        QuantileMethod::InteriorPoint => interior_point(x, y, tau, options.max_iter, options.max_tolerance),
+        QuantileMethod::Simplex => simplex(x, y, tau, options.max_iter, options.max_tolerance),
+    }
+}
+
+/// H^{-1}, with H = Σ 1{|r_i| ≤ h} x_i x_i' / (2nh) Powell's estimate of
+/// E[f(0 | x) x x'] at the Hall–Sheather bandwidth h
+pub(crate) fn powell_bread(
+    x: &Array2<f64>,
+    resid: &Array1<f64>,
+    tau: f64,
+) -> Result<Array2<f64>, AmitaError> {
+    let n = resid.len() as f64;
+    let h = hall_sheather_bandwidth(resid, tau);
+
+    let inside = (0..resid.len()).filter(|i| resid[*i].abs() <= h).collect::<Vec<_>>();
+    let x_inside = x.select(Axis(0), &inside);
+    let h_matrix = x_inside.t().dot(&x_inside) / (2. * n * h);
+
+    inverse(&h_matrix, "Powell kernel estimate of the quantile regression Hessian")
+}
+
+/// Bandwidth in residual units κ (Φ^{-1}(τ + h_n) - Φ^{-1}(τ - h_n)), with
+/// κ = min(sd, IQR / 1.34) and Hall and Sheather's h_n at 95% coverage
+fn hall_sheather_bandwidth(resid: &Array1<f64>, tau: f64) -> f64 {
+    let normal = Normal::new(0., 1.).unwrap();
+    let n = resid.len() as f64;
+
+    let z = normal.inverse_cdf(0.975);
+    let q = normal.inverse_cdf(tau);
+    let h_n = n.powf(-1. / 3.) * z.powf(2. / 3.)
+        * ( 1.5 * normal.pdf(q).powi(2) / (2. * q * q + 1.) ).powf(1. / 3.);
+    let h_n = h_n.min(tau * 0.999).min((1. - tau) * 0.999);
+
+    let sd = resid.std(1.);
+    let iqr = sample_quantile(resid, 0.75) - sample_quantile(resid, 0.25);
+    let kappa = if iqr > 0. { sd.min(iqr / 1.34) } else { sd };
+
+    kappa * ( normal.inverse_cdf(tau + h_n) - normal.inverse_cdf(tau - h_n) )
+}
+
+/// Pairs-bootstrap coefficients, one row per resample and the k
+/// coefficients of each τ side by side, the same resamples across τ. Each
+/// resample draws from its own generator, seeded in turn from `seed`, so
+/// resamples can be fitted in parallel with the same draws.
+pub(crate) fn bootstrap_coef(
+    x: &Array2<f64>,
+    y: &Array1<f64>,
+    taus: &[f64],
+    options: FitOptions,
+    replications: usize,
+    seed: u64,
+) -> Result<Array2<f64>, AmitaError> {
+    let (n, k) = (x.shape()[0], x.shape()[1]);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let seeds = (0..replications).map(|_| rng.gen::<u64>()).collect::<Vec<_>>();
+
+    let resampled = map_indices(replications, |r| {
+        let mut rng = StdRng::seed_from_u64(seeds[r]);
+        let rows = (0..n).map(|_| rng.gen_range(0..n)).collect::<Vec<_>>();
+        let x_resampled = x.select(Axis(0), &rows);
+        let y_resampled = y.select(Axis(0), &rows);
+
+        let mut draw = Array1::zeros(k * taus.len());
+        for (t, tau) in taus.iter().enumerate() {
+            let (coef, _) = fit(&x_resampled, &y_resampled, *tau, options)?;
+            draw.slice_mut(s![t * k..(t + 1) * k]).assign(&coef);
+        }
+        Ok(draw)
+    });
+
+    let mut draws = Array2::zeros((replications, k * taus.len()));
+    for (mut row, draw) in draws.rows_mut().into_iter().zip(resampled) {
+        row.assign(&draw?);
+    }
+
+    Ok(draws)
+}
+
+/// Sample covariance of bootstrap draws stored by row
+pub(crate) fn draws_vcov(draws: &Array2<f64>) -> Array2<f64> {
+    let mean = draws.mean_axis(Axis(0)).unwrap();
+    let centered = draws - &mean;
+    centered.t().dot(&centered) / (draws.shape()[0] as f64 - 1.)
+}
+
+pub(crate) fn check_loss(resid: &Array1<f64>, tau: f64) -> f64 {
+    resid.iter().map(|u| if *u < 0. { (tau - 1.) * u } else { tau * u }).sum()
+}
+
+/// τ-quantile of a sample, the smallest minimizer of the check loss
+pub(crate) fn sample_quantile(values: &Array1<f64>, tau: f64) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+
+    let index = ( (tau * sorted.len() as f64).ceil() as usize ).clamp(1, sorted.len()) - 1;
+    sorted[index]
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+
+    fn data() -> (Array1<f64>, Array2<f64>) {
+        let x1 = array![0.3, 1.2, 2.5, 0.8, 3.1, 1.9, 2.2, 0.1, 2.8, 1.5, 3.6, 0.6, 1.1, 2.0, 3.3];
+        let e = array![0.4, -1.3, 2.2, -0.2, 0.9, -2.4, 0.1, 1.6, -0.7, 0.3, -1.1, 2.9, -0.5, 1.2, -3.0];
+        // scale increasing in x1, so that quantile slopes fan out
+        let y = 1. + 2. * &x1 + &e * &x1.map(|x| 0.5 + x);
+        let x = ndarray::stack![Axis(1), Array1::<f64>::ones(15), x1];
+        (y, x)
+    }
+
+    #[test]
+    fn test_methods_agree_and_median() -> Result<(), AmitaError> {
+        let (y, x) = data();
+
+        for tau in [0.25, 0.5, 0.9] {
+            let interior = QuantileSolver::new(&y, &x, tau)?.solve()?.results();
+            let simplex = QuantileSolver::new(&y, &x, tau)?
+                .with_method(QuantileMethod::Simplex)
+                .solve()?
+                .results();
+
+            assert!( (interior.objective()? - simplex.objective()?).abs() < 1e-7 );
+            assert!( (interior.coef()? - simplex.coef()?).iter().all(|d| d.abs() < 1e-5) );
+
+            // at least k residuals are zero at a vertex, and a share τ of
+            // the others are positive
+            let resid = simplex.resid()?;
+            assert!( resid.iter().filter(|r| r.abs() < 1e-9).count() >= 2 );
+            let below = resid.iter().filter(|r| **r < -1e-9).count() as f64;
+            assert!( below <= tau * 15. && tau * 15. - 2. <= below );
+        }
+
+        // the intercept-only median is the sample median
+        let ones = Array2::<f64>::ones((15, 1));
+        let median = QuantileSolver::new(&y, &ones, 0.5)?
+            .with_method(QuantileMethod::Simplex)
+            .solve()?
+            .results();
+        assert!( (median.coef()?[0] - sample_quantile(&y, 0.5)).abs() < 1e-10 );
+
+        let invalid = QuantileSolver::new(&y, &x, 1.);
+        assert!( matches!(invalid, Err(AmitaError::InvalidQuantile { .. })) );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_powell_and_bootstrap_se() -> Result<(), AmitaError> {
+        let (y, x) = data();
+
+        let powell = QuantileSolver::new(&y, &x, 0.5)?.solve()?.results();
+        let bootstrap = QuantileSolver::new(&y, &x, 0.5)?
+            .with_se_type(QuantileSEType::Bootstrap { replications: 200, seed: 42 })
+            .solve()?
+            .results();
+        let repeated = QuantileSolver::new(&y, &x, 0.5)?
+            .with_se_type(QuantileSEType::Bootstrap { replications: 200, seed: 42 })
+            .solve()?
+            .results();
+
+        assert_eq!(powell.coef()?, bootstrap.coef()?);
+        assert_eq!(bootstrap.se()?, repeated.se()?);
+        assert!( powell.se()?.iter().chain(bootstrap.se()?.iter()).all(|se| se.is_finite() && *se > 0.) );
+        // both estimate the same sampling variability
+        let ratio = powell.se()?[1] / bootstrap.se()?[1];
+        assert!( ratio > 0.33 && ratio < 3. );
+
+        assert!( powell.pseudo_r_sq()? > 0. && powell.pseudo_r_sq()? < 1. );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+//! Generalized least squares for a system of M equations sharing the same
+//! n observations, with errors correlated across equations as Σ ⊗ I_n.
+//!
+//! With X_j the regressors of equation j and W_j the regressors the
+//! estimator weighs by (X_j for SUR, the projections of X_j on the
+//! instruments for 3SLS), the coefficients solve
+//! Σ_j σ^{ij} W_i'X_j β_j = Σ_j σ^{ij} W_i'y_j, subject to Rβ = q.
+
+use amita_error::AmitaError;
+use amita_utils::iterations::TerminationCondition;
+use amita_utils::linalg::{inverse, solve};
+use ndarray::prelude::*;
+
+#[derive(Debug, Clone)]
+pub(crate) struct SystemData {
+    pub(crate) y: Vec<Array1<f64>>,
+    pub(crate) x: Vec<Array2<f64>>,
+    pub(crate) w: Vec<Array2<f64>>,
+    pub(crate) restrictions: Option<(Array2<f64>, Array1<f64>)>,
+}
+
+pub(crate) struct SystemFit {
+    pub(crate) coef: Array1<f64>,
+    pub(crate) vcov: Array2<f64>,
+    pub(crate) sigma: Array2<f64>,
+    pub(crate) resid: Array2<f64>,
+    pub(crate) n_iter: u64,
+    pub(crate) termination: TerminationCondition,
+}
+
+impl SystemData {
+    /// Offsets of each equation's coefficients in the stacked vector
+    pub(crate) fn offsets(&self) -> Vec<usize> {
+        let mut offsets = vec![0];
+        for x in self.x.iter() {
+            offsets.push(offsets.last().unwrap() + x.shape()[1]);
+        }
+        offsets
+    }
+
+    /// Residuals y_j - X_j β_j, one column per equation
+    pub(crate) fn resid(&self, coef: &Array1<f64>) -> Array2<f64> {
+        let offsets = self.offsets();
+        let n = self.y[0].len();
+
+        let mut resid = Array2::zeros((n, self.y.len()));
+        for (j, (y, x)) in self.y.iter().zip(self.x.iter()).enumerate() {
+            let coef_j = coef.slice(s![offsets[j]..offsets[j + 1]]);
+            resid.column_mut(j).assign(&(y - &x.dot(&coef_j)));
+        }
+        resid
+    }
+
+    /// Coefficients and their covariance under Σ^{-1} = `sigma_inv`
+    pub(crate) fn gls(&self, sigma_inv: &Array2<f64>) -> Result<(Array1<f64>, Array2<f64>), AmitaError> {
+        let offsets = self.offsets();
+        let k = *offsets.last().unwrap();
+        let m = self.y.len();
+
+        let mut gram = Array2::zeros((k, k));
+        let mut moment = Array1::zeros(k);
+        for i in 0..m {
+            for j in 0..m {
+                let weight = sigma_inv[[i, j]];
+                gram.slice_mut(s![offsets[i]..offsets[i + 1], offsets[j]..offsets[j + 1]])
+                    .assign(&(self.w[i].t().dot(&self.x[j]) * weight));
+                moment.slice_mut(s![offsets[i]..offsets[i + 1]])
+                    .scaled_add(weight, &self.w[i].t().dot(&self.y[j]));
+            }
+        }
+
+        let gram_inv = inverse(&gram, "system GLS moment matrix")?;
+        let coef = gram_inv.dot(&moment);
+
+        // restricted estimates β - A^{-1}R'(RA^{-1}R')^{-1}(Rβ - q), with the
+        // covariance A^{-1} - A^{-1}R'(RA^{-1}R')^{-1}RA^{-1}
+        match &self.restrictions {
+            Some( (r, q) ) => {
+                let projected = gram_inv.dot(&r.t());
+                let middle = inverse(&r.dot(&projected), "restrictions R A^{-1} R'")?;
+
+                let coef = &coef - &projected.dot(&middle.dot(&(r.dot(&coef) - q)));
+                let vcov = &gram_inv - &projected.dot(&middle).dot(&projected.t());
+                Ok( (coef, vcov) )
+            },
+            None => Ok( (coef, gram_inv) ),
+        }
+    }
+
+    /// Feasible GLS, starting from equation-by-equation estimates and
+    /// re-estimating Σ = E'E / n from the residuals until the coefficients
+    /// change by less than the tolerance. One iteration is two-step FGLS.
+    pub(crate) fn estimate(&self, max_iter: u64, max_tolerance: f64) -> Result<SystemFit, AmitaError> {
+        let m = self.y.len();
+        let n = self.y[0].len() as f64;
+
+        let (mut coef, _) = self.gls(&Array2::eye(m))?;
+        let mut n_iter = 0;
+
+        loop {
+            let resid = self.resid(&coef);
+            let sigma = resid.t().dot(&resid) / n;
+            let (new_coef, vcov) = self.gls(&inverse(&sigma, "cross-equation covariance")?)?;
+            n_iter += 1;
+
+            let change = (&new_coef - &coef).iter().fold(0., |acc: f64, d| acc.max(d.abs()));
+            coef = new_coef;
+
+            let termination = if change < max_tolerance {
+                TerminationCondition::SolverConverged
+            } else if n_iter >= max_iter {
+                TerminationCondition::MaxIterReached
+            } else {
+                continue;
+            };
+
+            let resid = self.resid(&coef);
+            let sigma = resid.t().dot(&resid) / n;
+            return Ok( SystemFit { coef, vcov, sigma, resid, n_iter, termination } );
+        }
+    }
+}
+
+/// Checks that every equation has the same observations, and that the
+/// restrictions R β = q match the stacked coefficients
+pub(crate) fn validate_system(
+    y: &[Array1<f64>],
+    x: &[Array2<f64>],
+    restrictions: Option<(&Array2<f64>, &Array1<f64>)>,
+) -> Result<(), AmitaError> {
+    if y.is_empty() || y.len() != x.len() {
+        return Err(AmitaError::NotSameObservations);
+    }
+
+    let n = y[0].len();
+    if y.iter().any(|y| y.len() != n) || x.iter().any(|x| x.shape()[0] != n) {
+        return Err(AmitaError::NotSameObservations);
+    }
+
+    if let Some( (r, q) ) = restrictions {
+        let k = x.iter().map(|x| x.shape()[1]).sum::<usize>();
+        if r.shape()[1] != k || r.shape()[0] != q.len() {
+            return Err(AmitaError::NotSameObservations);
+        }
+    }
+
+    Ok(())
+}
+
+/// Projection of `x` on the column space of `z`
+pub(crate) fn project(z: &Array2<f64>, x: &Array2<f64>) -> Result<Array2<f64>, AmitaError> {
+    let gram = z.t().dot(z);
+    let mut projected = Array2::zeros(x.raw_dim());
+    for (j, column) in x.columns().into_iter().enumerate() {
+        let coef = solve(&gram, &z.t().dot(&column), "instruments Z'Z")?;
+        projected.column_mut(j).assign(&z.dot(&coef));
+    }
+    Ok(projected)
+}
@@ -0,0 +1,4 @@
+mod gls;
+pub mod results;
+pub mod sur;
+pub mod three_sls;
@@ -0,0 +1,212 @@
+//! Results of the system estimators, with the coefficients of all equations
+//! stacked equation by equation, [β_1, ..., β_M].
+
+use amita_error::AmitaError;
+use amita_utils::hypothesis::TestStatistic;
+use amita_utils::iterations::TerminationCondition;
+use amita_utils::summary::coef_table;
+use amita_utils::traits::BaseResults;
+use ndarray::prelude::*;
+use statrs::distribution::{ContinuousCDF, Normal};
+
+use super::gls::SystemFit;
+
+#[derive(Debug, Clone)]
+pub struct SystemResults {
+    n_obs: usize,
+    n_regressors: Vec<usize>,
+
+    coef: Option<Array1<f64>>,
+    se: Option<Array1<f64>>,
+    z: Option<Array1<f64>>,
+    p_vals: Option<Array1<f64>>,
+    vcov: Option<Array2<f64>>,
+
+    sigma: Option<Array2<f64>>,
+    resid: Option<Array2<f64>>,
+    r_sq: Option<Array1<f64>>,
+    n_iter: Option<u64>,
+    termination: Option<TerminationCondition>,
+}
+
+impl SystemResults {
+    pub(crate) fn new(n_obs: usize, n_regressors: Vec<usize>) -> Self {
+        Self {
+            n_obs,
+            n_regressors,
+
+            coef: None,
+            se: None,
+            z: None,
+            p_vals: None,
+            vcov: None,
+
+            sigma: None,
+            resid: None,
+            r_sq: None,
+            n_iter: None,
+            termination: None,
+        }
+    }
+
+    pub(crate) fn set_fit(&mut self, fit: SystemFit, y: &[Array1<f64>]) {
+        let se = fit.vcov.diag().map(|x| x.sqrt());
+        let z = &fit.coef / &se;
+        let normal = Normal::new(0., 1.).unwrap();
+        let p_vals = z.map(|x| 2. * ( 1. - normal.cdf(x.abs()) ) );
+
+        let r_sq = y.iter().zip(fit.resid.columns())
+            .map(|(y, e)| {
+                let mean = y.mean().unwrap();
+                1. - e.dot(&e) / y.map(|y| (y - mean).powi(2)).sum()
+            })
+            .collect();
+
+        self.coef = Some(fit.coef);
+        self.se = Some(se);
+        self.z = Some(z);
+        self.p_vals = Some(p_vals);
+        self.vcov = Some(fit.vcov);
+
+        self.sigma = Some(fit.sigma);
+        self.resid = Some(fit.resid);
+        self.r_sq = Some(r_sq);
+        self.n_iter = Some(fit.n_iter);
+        self.termination = Some(fit.termination);
+    }
+
+    pub fn n_obs(&self) -> usize {
+        self.n_obs
+    }
+
+    pub fn n_equations(&self) -> usize {
+        self.n_regressors.len()
+    }
+
+    /// Cross-equation covariance of the errors, E'E / n
+    pub fn sigma(&self) -> Result<Array2<f64>, AmitaError> {
+        self.sigma.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    /// Residuals, one column per equation
+    pub fn resid(&self) -> Result<Array2<f64>, AmitaError> {
+        self.resid.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    pub fn n_iter(&self) -> Result<u64, AmitaError> {
+        self.n_iter.ok_or(AmitaError::NotSolved)
+    }
+
+    pub fn termination(&self) -> Result<TerminationCondition, AmitaError> {
+        self.termination.ok_or(AmitaError::NotSolved)
+    }
+
+    /// Results of equation j alone, with its block of the covariance
+    pub fn equation(&self, j: usize) -> Result<EquationResults, AmitaError> {
+        if j >= self.n_equations() {
+            return Err(AmitaError::ColumnNotFound { column: format!("equation {j}") });
+        }
+
+        let start = self.n_regressors[..j].iter().sum::<usize>();
+        let end = start + self.n_regressors[j];
+        let block = |v: Array1<f64>| v.slice(s![start..end]).to_owned();
+
+        Ok( EquationResults {
+            coef: block(self.coef()?),
+            se: block(self.se()?),
+            z: block(self.t()?),
+            p_vals: block(self.p_vals()?),
+            vcov: self.vcov()?.slice(s![start..end, start..end]).to_owned(),
+            r_sq: self.r_sq.as_ref().ok_or(AmitaError::NotSolved)?[j],
+        } )
+    }
+
+    /// Breusch–Pagan LM test of a diagonal Σ, n Σ_{i<j} r_ij^2 with r_ij
+    /// the correlations of the residuals, χ²(M(M - 1)/2)
+    pub fn breusch_pagan(&self) -> Result<TestStatistic, AmitaError> {
+        let sigma = self.sigma()?;
+        let m = self.n_equations();
+
+        let mut statistic = 0.;
+        for i in 0..m {
+            for j in 0..i {
+                statistic += sigma[[i, j]].powi(2) / (sigma[[i, i]] * sigma[[j, j]]);
+            }
+        }
+
+        Ok( TestStatistic::chi_squared(
+            "Breusch-Pagan test of independent equations",
+            self.n_obs as f64 * statistic,
+            (m * (m - 1) / 2) as f64,
+        ) )
+    }
+}
+
+impl BaseResults for SystemResults {
+    fn coef(&self) -> Result<Array1<f64>, AmitaError> {
+        self.coef.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn se(&self) -> Result<Array1<f64>, AmitaError> {
+        self.se.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn t(&self) -> Result<Array1<f64>, AmitaError> {
+        self.z.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn p_vals(&self) -> Result<Array1<f64>, AmitaError> {
+        self.p_vals.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn vcov(&self) -> Result<Array2<f64>, AmitaError> {
+        self.vcov.clone().ok_or(AmitaError::NotSolved)
+    }
+
+    fn summary(&self) -> Result<String, AmitaError> {
+        coef_table(self)
+    }
+}
+
+/// One equation of a system
+#[derive(Debug, Clone)]
+pub struct EquationResults {
+    coef: Array1<f64>,
+    se: Array1<f64>,
+    z: Array1<f64>,
+    p_vals: Array1<f64>,
+    vcov: Array2<f64>,
+    r_sq: f64,
+}
+
+impl EquationResults {
+    pub fn r_sq(&self) -> f64 {
+        self.r_sq
+    }
+}
+
+impl BaseResults for EquationResults {
+    fn coef(&self) -> Result<Array1<f64>, AmitaError> {
+        Ok( self.coef.clone() )
+    }
+
+    fn se(&self) -> Result<Array1<f64>, AmitaError> {
+        Ok( self.se.clone() )
+    }
+
+    fn t(&self) -> Result<Array1<f64>, AmitaError> {
+        Ok( self.z.clone() )
+    }
+
+    fn p_vals(&self) -> Result<Array1<f64>, AmitaError> {
+        Ok( self.p_vals.clone() )
+    }
+
+    fn vcov(&self) -> Result<Array2<f64>, AmitaError> {
+        Ok( self.vcov.clone() )
+    }
+
+    fn summary(&self) -> Result<String, AmitaError> {
+        coef_table(self)
+    }
+}
@@ -0,0 +1,168 @@
+//! Zellner's seemingly unrelated regressions by iterated feasible GLS,
+//! which converges to the maximum likelihood estimates under normality.
+
+use amita_error::AmitaError;
+use amita_utils::traits::BaseSolver;
+use ndarray::prelude::*;
+
+use super::gls::{validate_system, SystemData};
+use super::results::SystemResults;
+
+#[derive(Debug, Clone)]
+pub struct SURSolver {
+    data: SystemData,
+
+    max_iter: u64,
+    max_tolerance: f64,
+
+    results: SystemResults,
+}
+
+impl SURSolver {
+    /// Equation j regresses `y[j]` on `x[j]`, with the same observations in
+    /// every equation
+    pub fn new(
+        y: &[Array1<f64>],
+        x: &[Array2<f64>],
+    ) -> Result<Self, AmitaError> {
+        validate_system(y, x, None)?;
+
+        let results = SystemResults::new(y[0].len(), x.iter().map(|x| x.shape()[1]).collect());
+
+        Ok( Self {
+            data: SystemData {
+                y: y.to_vec(),
+                x: x.to_vec(),
+                w: x.to_vec(),
+                restrictions: None,
+            },
+
+            max_iter: 100,
+            max_tolerance: 1e-8,
+
+            results,
+        } )
+    }
+
+    /// Linear restrictions R β = q on the stacked coefficients
+    /// [β_1, ..., β_M], which may span equations
+    pub fn with_restrictions(
+        mut self,
+        r: &Array2<f64>,
+        q: &Array1<f64>,
+    ) -> Result<Self, AmitaError> {
+        validate_system(&self.data.y, &self.data.x, Some( (r, q) ))?;
+        self.data.restrictions = Some( (r.to_owned(), q.to_owned()) );
+        Ok(self)
+    }
+
+    /// One iteration is two-step feasible GLS
+    pub fn with_max_iter(mut self, max_iter: u64) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    pub fn with_max_tolerance(mut self, max_tolerance: f64) -> Self {
+        self.max_tolerance = max_tolerance;
+        self
+    }
+}
+
+impl BaseSolver<SystemResults> for SURSolver {
+    fn results(&self) -> SystemResults {
+        self.results.clone()
+    }
+
+    fn solve(mut self) -> Result<Self, AmitaError> {
+        let fit = self.data.estimate(self.max_iter, self.max_tolerance)?;
+        self.results.set_fit(fit, &self.data.y);
+
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use amita_utils::iterations::TerminationCondition;
+    use amita_utils::traits::BaseResults;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use statrs::distribution::Normal;
+
+    use super::*;
+    use crate::linear::ols::OLSSolver;
+
+    /// Two equations with errors correlated at 0.8 and common slope 2 on
+    /// their own regressor
+    fn data() -> (Vec<Array1<f64>>, Vec<Array2<f64>>) {
+        let mut rng = StdRng::seed_from_u64(3);
+        let normal = Normal::new(0., 1.).unwrap();
+
+        let n = 500;
+        let mut draw = || Array1::from_iter( (0..n).map(|_| rng.sample(normal)) );
+        let (x1, x2, e1, v) = (draw(), draw(), draw(), draw());
+        let e2 = 0.8 * &e1 + 0.6 * &v;
+
+        let y1 = 1. + 2. * &x1 + e1;
+        let y2 = -1. + 2. * &x2 + 0.5 * &x1 + e2;
+        let ones = Array1::<f64>::ones(n);
+
+        (
+            vec![y1, y2],
+            vec![ndarray::stack![Axis(1), ones, x1], ndarray::stack![Axis(1), ones, x2, x1]],
+        )
+    }
+
+    #[test]
+    fn test_sur() -> Result<(), AmitaError> {
+        let (y, x) = data();
+
+        let results = SURSolver::new(&y, &x)?.solve()?.results();
+        assert_eq!(results.termination()?, TerminationCondition::SolverConverged);
+
+        let first = results.equation(0)?;
+        let second = results.equation(1)?;
+        assert!( (first.coef()? - array![1., 2.]).iter().all(|d| d.abs() < 0.15) );
+        assert!( (second.coef()? - array![-1., 2., 0.5]).iter().all(|d| d.abs() < 0.15) );
+        assert_eq!(second.vcov()?.shape(), &[3, 3]);
+
+        // the first equation omits x2, so SUR gains precision over OLS
+        let ols = OLSSolver::new(&y[0], &x[0])?.solve()?.results();
+        assert!( first.se()?[1] < ols.se()?[1] );
+
+        let correlation = results.sigma()?[[0, 1]] / (results.sigma()?[[0, 0]] * results.sigma()?[[1, 1]]).sqrt();
+        assert!( (correlation - 0.8).abs() < 0.05 );
+        let bp = results.breusch_pagan()?;
+        assert_eq!(bp.df(), vec![1.]);
+        assert!( bp.p_val() < 1e-6 );
+
+        // with the same regressors in every equation, SUR is OLS
+        let same = SURSolver::new(&y, &[x[1].clone(), x[1].clone()])?.solve()?.results();
+        let ols = OLSSolver::new(&y[0], &x[1])?.solve()?.results();
+        assert!( (same.equation(0)?.coef()? - ols.coef()?).iter().all(|d| d.abs() < 1e-10) );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cross_equation_restrictions() -> Result<(), AmitaError> {
+        let (y, x) = data();
+
+        // slope on x1 in equation 1 equals slope on x2 in equation 2
+        let r = array![[0., 1., 0., -1., 0.]];
+        let results = SURSolver::new(&y, &x)?
+            .with_restrictions(&r, &array![0.])?
+            .solve()?
+            .results();
+
+        let coef = results.coef()?;
+        assert!( (coef[1] - coef[3]).abs() < 1e-10 );
+        assert!( (coef[1] - 2.).abs() < 0.1 );
+        assert!( r.dot(&results.vcov()?).dot(&r.t())[[0, 0]].abs() < 1e-10 );
+
+        let mismatched = SURSolver::new(&y, &x)?.with_restrictions(&array![[1., -1.]], &array![0.]);
+        assert!( matches!(mismatched, Err(AmitaError::NotSameObservations)) );
+
+        Ok(())
+    }
+}
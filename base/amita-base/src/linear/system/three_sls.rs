@@ -0,0 +1,147 @@
+//! Three-stage least squares for simultaneous equations: two-stage least
+//! squares equation by equation, then GLS across equations with the
+//! regressors replaced by their projections on the instruments.
+
+use amita_error::AmitaError;
+use amita_utils::traits::BaseSolver;
+use ndarray::prelude::*;
+
+use super::gls::{project, validate_system, SystemData};
+use super::results::SystemResults;
+
+#[derive(Debug, Clone)]
+pub struct ThreeSLSSolver {
+    data: SystemData,
+
+    max_iter: u64,
+    max_tolerance: f64,
+
+    results: SystemResults,
+}
+
+impl ThreeSLSSolver {
+    /// Equation j regresses `y[j]` on `x[j]`, which may include endogenous
+    /// variables. `instruments` are the exogenous variables of the whole
+    /// system, and must number at least the regressors of every equation.
+    pub fn new(
+        y: &[Array1<f64>],
+        x: &[Array2<f64>],
+        instruments: &Array2<f64>,
+    ) -> Result<Self, AmitaError> {
+        validate_system(y, x, None)?;
+        if instruments.shape()[0] != y[0].len() {
+            return Err(AmitaError::NotSameObservations);
+        }
+        if let Some(j) = x.iter().position(|x| x.shape()[1] > instruments.shape()[1]) {
+            return Err(AmitaError::SolverFailed { message: format!("equation {j} is underidentified") });
+        }
+
+        let w = x.iter()
+            .map(|x| project(instruments, x))
+            .collect::<Result<Vec<_>, AmitaError>>()?;
+
+        let results = SystemResults::new(y[0].len(), x.iter().map(|x| x.shape()[1]).collect());
+
+        Ok( Self {
+            data: SystemData {
+                y: y.to_vec(),
+                x: x.to_vec(),
+                w,
+                restrictions: None,
+            },
+
+            max_iter: 1,
+            max_tolerance: 1e-8,
+
+            results,
+        } )
+    }
+
+    /// Linear restrictions R β = q on the stacked coefficients
+    /// [β_1, ..., β_M], which may span equations
+    pub fn with_restrictions(
+        mut self,
+        r: &Array2<f64>,
+        q: &Array1<f64>,
+    ) -> Result<Self, AmitaError> {
+        validate_system(&self.data.y, &self.data.x, Some( (r, q) ))?;
+        self.data.restrictions = Some( (r.to_owned(), q.to_owned()) );
+        Ok(self)
+    }
+
+    /// Iterations beyond the first, the classic 3SLS, re-estimate Σ from
+    /// the 3SLS residuals
+    pub fn with_max_iter(mut self, max_iter: u64) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    pub fn with_max_tolerance(mut self, max_tolerance: f64) -> Self {
+        self.max_tolerance = max_tolerance;
+        self
+    }
+}
+
+impl BaseSolver<SystemResults> for ThreeSLSSolver {
+    fn results(&self) -> SystemResults {
+        self.results.clone()
+    }
+
+    fn solve(mut self) -> Result<Self, AmitaError> {
+        let fit = self.data.estimate(self.max_iter, self.max_tolerance)?;
+        self.results.set_fit(fit, &self.data.y);
+
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use amita_utils::traits::BaseResults;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use statrs::distribution::Normal;
+
+    use super::*;
+    use crate::linear::ols::OLSSolver;
+
+    #[test]
+    fn test_supply_and_demand() -> Result<(), AmitaError> {
+        // demand q = 10 - p + income + u_d, supply q = 2 + p - cost + u_s,
+        // with correlated shocks and price set in equilibrium
+        let mut rng = StdRng::seed_from_u64(5);
+        let normal = Normal::new(0., 1.).unwrap();
+
+        let n = 2000;
+        let mut draw = || Array1::from_iter( (0..n).map(|_| rng.sample(normal)) );
+        let (income, cost, u_d, v) = (draw(), draw(), draw(), draw());
+        let u_s = 0.5 * &u_d + v;
+
+        let p = (8. + &income + &cost + &u_d - &u_s) / 2.;
+        let q = 10. - &p + &income + &u_d;
+        let ones = Array1::<f64>::ones(n);
+
+        let y = vec![q.clone(), q];
+        let x = vec![
+            ndarray::stack![Axis(1), ones, p, income],
+            ndarray::stack![Axis(1), ones, p, cost],
+        ];
+        let instruments = ndarray::stack![Axis(1), ones, income, cost];
+
+        let results = ThreeSLSSolver::new(&y, &x, &instruments)?.solve()?.results();
+        for (j, truth) in [array![10., -1., 1.], array![2., 1., -1.]].iter().enumerate() {
+            let equation = results.equation(j)?;
+            let error = (equation.coef()? - truth) / equation.se()?;
+            assert!( error.iter().all(|z| z.abs() < 3.) );
+        }
+
+        // least squares is biased by the simultaneity
+        let ols = OLSSolver::new(&y[0], &x[0])?.solve()?.results();
+        assert!( (ols.coef()?[1] + 1.).abs() > 0.3 );
+
+        let underidentified = ThreeSLSSolver::new(&y, &x, &instruments.slice(s![.., ..2]).to_owned());
+        assert!( matches!(underidentified, Err(AmitaError::SolverFailed { .. })) );
+
+        Ok(())
+    }
+}
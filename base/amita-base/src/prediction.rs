@@ -0,0 +1,274 @@
+//! Predictions of fitted models at new observations, with intervals built
+//! on the scale of the linear predictor η = x'β and mapped through the
+//! inverse link, so they respect the range of the mean.
+
+use amita_error::AmitaError;
+use ndarray::{prelude::*, Zip};
+use statrs::distribution::{ContinuousCDF, Normal, StudentsT};
+
+use crate::glm::link::Link;
+
+#[derive(Debug, Clone)]
+pub struct Prediction {
+    linear: Array1<f64>,
+    linear_se: Array1<f64>,
+    link: Link,
+    df: Option<f64>, // small-sample degrees of freedom, normal otherwise
+    sigma_sq: Option<f64>, // error variance of linear models, for unit weight
+    weighted_errors: bool, // the error variance is σ^2 / w_i
+}
+
+impl Prediction {
+    /// Linear predictor x'β at the rows of `x` and its standard error
+    /// √(x' V x). Coefficients of omitted regressors, which are NaN, drop
+    /// out of both.
+    pub(crate) fn new(
+        x: &Array2<f64>,
+        coef: &Array1<f64>,
+        vcov: &Array2<f64>,
+        offset: Option<&Array1<f64>>,
+        link: Link,
+    ) -> Result<Self, AmitaError> {
+        if x.shape()[1] != coef.len() || offset.is_some_and(|offset| offset.len() != x.shape()[0]) {
+            return Err(AmitaError::NotSameObservations);
+        }
+
+        let coef = coef.map(|b| if b.is_nan() { 0. } else { *b });
+        let vcov = vcov.map(|v| if v.is_nan() { 0. } else { *v });
+
+        let mut linear = x.dot(&coef);
+        if let Some(offset) = offset {
+            linear += offset;
+        }
+        let linear_se = ( x.dot(&vcov) * x ).sum_axis(Axis(1)).map(|v| v.max(0.).sqrt());
+
+        Ok( Self { linear, linear_se, link, df: None, sigma_sq: None, weighted_errors: false } )
+    }
+
+    /// Degrees of freedom of Student's t for the intervals
+    pub(crate) fn with_df(mut self, df: Option<f64>) -> Self {
+        self.df = df;
+        self
+    }
+
+    /// Error variance of a linear model, for prediction intervals
+    pub(crate) fn with_error_variance(mut self, sigma_sq: f64) -> Self {
+        self.sigma_sq = Some(sigma_sq);
+        self
+    }
+
+    /// Error variance σ^2 / w_i of a model with analytic weights, for which
+    /// prediction intervals need the weights of the new observations
+    pub(crate) fn with_weighted_error_variance(mut self, sigma_sq: f64) -> Self {
+        self.sigma_sq = Some(sigma_sq);
+        self.weighted_errors = true;
+        self
+    }
+
+    /// Predicted means g^{-1}(x'β)
+    pub fn predicted(&self) -> Array1<f64> {
+        self.linear.map(|eta| self.link.inverse(*eta))
+    }
+
+    pub fn linear_predictor(&self) -> Array1<f64> {
+        self.linear.clone()
+    }
+
+    pub fn linear_predictor_se(&self) -> Array1<f64> {
+        self.linear_se.clone()
+    }
+
+    /// Standard errors of the predicted means by the delta method,
+    /// se(η) / |g'(μ)|
+    pub fn se(&self) -> Array1<f64> {
+        let predicted = self.predicted();
+        Zip::from(&self.linear_se).and(&predicted)
+            .map_collect(|se, mu| se / self.link.derivative(*mu).abs())
+    }
+
+    fn critical_value(&self, level: f64) -> Result<f64, AmitaError> {
+        if level.is_nan() || level <= 0. || level >= 1. {
+            return Err(AmitaError::InvalidConfidenceLevel { level });
+        }
+
+        let quantile = 1. - (1. - level) / 2.;
+        Ok( match self.df {
+            Some(df) => StudentsT::new(0., 1., df).map_or(f64::NAN, |t| t.inverse_cdf(quantile)),
+            None => Normal::new(0., 1.).unwrap().inverse_cdf(quantile),
+        } )
+    }
+
+    /// Confidence intervals of the means at `level`, as (lower, upper)
+    pub fn conf_int(&self, level: f64) -> Result<(Array1<f64>, Array1<f64>), AmitaError> {
+        let critical = self.critical_value(level)?;
+        let bound = |sign: f64| {
+            Zip::from(&self.linear).and(&self.linear_se)
+                .map_collect(|eta, se| self.link.inverse(eta + sign * critical * se))
+        };
+
+        // decreasing links such as 1 / μ swap the bounds
+        let (lower, upper) = (bound(-1.), bound(1.));
+        Ok( (
+            Zip::from(&lower).and(&upper).map_collect(|l, u| l.min(*u)),
+            Zip::from(&lower).and(&upper).map_collect(|l, u| l.max(*u)),
+        ) )
+    }
+
+    /// Prediction intervals of new outcomes at `level` for linear models,
+    /// x'β ± c √(se^2 + σ^2), as (lower, upper). Models with analytic
+    /// weights need those of the new observations, see
+    /// [`Prediction::pred_int_with_weights`].
+    pub fn pred_int(&self, level: f64) -> Result<(Array1<f64>, Array1<f64>), AmitaError> {
+        if self.weighted_errors {
+            return Err(AmitaError::NotAvailable {
+                feature: "Prediction intervals without the weights of the new observations".to_string()
+            });
+        }
+        self.pred_int_with_weights(level, &Array1::ones(self.linear.len()))
+    }
+
+    /// Prediction intervals of new outcomes with analytic weights `weights`,
+    /// x'β ± c √(se^2 + σ^2 / w_i), as (lower, upper)
+    pub fn pred_int_with_weights(
+        &self,
+        level: f64,
+        weights: &Array1<f64>,
+    ) -> Result<(Array1<f64>, Array1<f64>), AmitaError> {
+        let sigma_sq = match (self.link, self.sigma_sq) {
+            (Link::Identity, Some(sigma_sq)) => sigma_sq,
+            _ => return Err(AmitaError::NotAvailable { feature: "Prediction intervals".to_string() }),
+        };
+        if weights.len() != self.linear.len() {
+            return Err(AmitaError::NotSameObservations);
+        }
+        if weights.iter().any(|w| *w <= 0. || !w.is_finite()) {
+            return Err(AmitaError::NonPositive { matrix_name: "weights".to_string() });
+        }
+
+        let critical = self.critical_value(level)?;
+        let margin = Zip::from(&self.linear_se).and(weights)
+            .map_collect(|se, w| critical * (se.powi(2) + sigma_sq / w).sqrt());
+        Ok( (&self.linear - &margin, &self.linear + &margin) )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use amita_utils::traits::{BaseResults, BaseSolver};
+
+    use super::*;
+    use crate::discrete::logit::LogitSolver;
+    use crate::glm::family::Family;
+    use crate::glm::solver::GLMSolver;
+    use crate::linear::ols::{OLSSolver, WeightType};
+
+    fn data() -> (Array1<f64>, Array2<f64>) {
+        let y = array![1., 0., 3., 2., 0., 5., 2., 1., 4., 0., 6., 2., 1., 3., 7., 1.];
+        let x1 = array![
+            0.1, 0.4, 1.2, 0.3, -0.2, 1.5, 0.6, 0.0,
+            1.1, -0.5, 1.3, 0.2, 0.8, -0.1, 0.9, 0.5
+        ];
+        let x = ndarray::stack![Axis(1), Array1::<f64>::ones(16), x1];
+        (y, x)
+    }
+
+    #[test]
+    fn test_linear_prediction() -> Result<(), AmitaError> {
+        let (y, x) = data();
+        let results = OLSSolver::new(&y, &x)?.solve()?.results();
+
+        let prediction = results.predict(&x)?;
+        assert!( (prediction.predicted() - results.y_pred()?).iter().all(|d| d.abs() < 1e-10) );
+
+        // se of the mean at x0 is √(x0' V x0), and the prediction interval
+        // adds the error variance
+        let new_x = array![[1., 2.]];
+        let prediction = results.predict(&new_x)?;
+        let vcov = results.vcov()?;
+        let se = new_x.row(0).dot(&vcov.dot(&new_x.row(0))).sqrt();
+        assert!( (prediction.se()[0] - se).abs() < 1e-12 );
+
+        let critical = StudentsT::new(0., 1., 14.).unwrap().inverse_cdf(0.975);
+        let (lower, upper) = prediction.conf_int(0.95)?;
+        assert!( (upper[0] - lower[0] - 2. * critical * se).abs() < 1e-10 );
+        let (lower, upper) = prediction.pred_int(0.95)?;
+        let margin = critical * (se.powi(2) + results.sigma_sq()?).sqrt();
+        assert!( (upper[0] - prediction.predicted()[0] - margin).abs() < 1e-10 );
+        assert!( (prediction.predicted()[0] - lower[0] - margin).abs() < 1e-10 );
+
+        assert!( matches!(results.predict(&array![[1., 2., 3.]]), Err(AmitaError::NotSameObservations)) );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_weighted_prediction_intervals() -> Result<(), AmitaError> {
+        let (y, x) = data();
+        let weights = Array1::from_iter((0..16).map(|i| 1. + (i % 4) as f64));
+        let new_x = array![[1., 0.5], [1., 1.5]];
+        let new_weights = array![1., 4.];
+
+        // with analytic weights the error variance of a new observation is σ^2 / w
+        let analytic = OLSSolver::new(&y, &x)?
+            .with_weights(&weights, WeightType::Analytic)?
+            .solve()?
+            .results();
+        let prediction = analytic.predict(&new_x)?;
+        assert!( matches!(prediction.pred_int(0.95), Err(AmitaError::NotAvailable { .. })) );
+        let (lower, upper) = prediction.pred_int_with_weights(0.95, &new_weights)?;
+        let critical = StudentsT::new(0., 1., 14.).unwrap().inverse_cdf(0.975);
+        for i in 0..2 {
+            let margin = critical * (prediction.se()[i].powi(2) + analytic.sigma_sq()? / new_weights[i]).sqrt();
+            assert!( (upper[i] - lower[i] - 2. * margin).abs() < 1e-10 );
+        }
+
+        // frequency weights count observations, each with variance σ^2
+        let frequency = OLSSolver::new(&y, &x)?
+            .with_weights(&weights, WeightType::Frequency)?
+            .solve()?
+            .results();
+        let prediction = frequency.predict(&new_x)?;
+        assert_eq!(prediction.pred_int(0.95)?, prediction.pred_int_with_weights(0.95, &Array1::ones(2))?);
+
+        let probability = OLSSolver::new(&y, &x)?
+            .with_weights(&weights, WeightType::Probability)?
+            .with_robust_se()
+            .solve()?
+            .results();
+        let prediction = probability.predict(&new_x)?;
+        assert!( matches!(prediction.pred_int_with_weights(0.95, &new_weights), Err(AmitaError::NotAvailable { .. })) );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_nonlinear_prediction() -> Result<(), AmitaError> {
+        let (y, x) = data();
+        let new_x = array![[1., -1.], [1., 0.5], [1., 2.]];
+
+        // probabilities stay within (0, 1)
+        let y_binary = y.map(|y| if *y >= 2. { 1 } else { 0 });
+        let logit = LogitSolver::new(&y_binary, &x)?.solve()?.results();
+        let prediction = logit.predict(&new_x)?;
+        let (lower, upper) = prediction.conf_int(0.99)?;
+        assert!( lower.iter().all(|p| *p > 0.) && upper.iter().all(|p| *p < 1.) );
+        let p = prediction.predicted();
+        let expected_se = &prediction.linear_predictor_se() * &p.map(|p| p * (1. - p));
+        assert!( (prediction.se() - expected_se).iter().all(|d| d.abs() < 1e-12) );
+        assert!( matches!(prediction.pred_int(0.95), Err(AmitaError::NotAvailable { .. })) );
+
+        // log link intervals are exp(η ± z se), and offsets shift η
+        let poisson = GLMSolver::new(&y, &x, Family::Poisson)?.solve()?.results();
+        let prediction = poisson.predict(&new_x)?;
+        let (lower, upper) = prediction.conf_int(0.95)?;
+        let eta = prediction.linear_predictor();
+        let se = prediction.linear_predictor_se();
+        assert!( (lower - (&eta - &(1.959963984540054 * &se)).mapv(f64::exp)).iter().all(|d| d.abs() < 1e-8) );
+        assert!( (upper - (&eta + &(1.959963984540054 * &se)).mapv(f64::exp)).iter().all(|d| d.abs() < 1e-8) );
+
+        let exposed = poisson.predict_with_offset(&new_x, &Array1::from_elem(3, 2f64.ln()))?;
+        assert!( (exposed.predicted() - 2. * prediction.predicted()).iter().all(|d| d.abs() < 1e-10) );
+
+        Ok(())
+    }
+}
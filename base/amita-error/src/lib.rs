@@ -4,18 +4,55 @@ pub enum AmitaError {
     // Data Inputs
     #[error("Matrices consist of a different number of observations")]
     NotSameObservations,
+    #[error("{matrix_name:?} contains no observations")]
+    NoObservations { matrix_name: String },
     #[error("{matrix_name:?} is not QR-decomposable")]
     NotQRDecomposable { matrix_name: String },
     #[error("{matrix_name:?} is not invertible")]
     NotInvertible { matrix_name: String },
     #[error("Elements in {matrix_name:?} are non-binary")]
     NonBinary { matrix_name: String },
+    #[error("Elements in {matrix_name:?} are not integers")]
+    NonInteger { matrix_name: String },
+    #[error("Elements in {matrix_name:?} are not positive")]
+    NonPositive { matrix_name: String },
     #[error("Cluster {cluster:?} contains only 1 observation")]
     SingleObservationWithinCluster { cluster: String },
+    #[error("Clustered standard errors need at least 2 clusters, found {n_clusters:?}")]
+    TooFewClusters { n_clusters: usize },
+    #[error("Regressors {columns:?} perfectly or quasi-perfectly separate the outcome")]
+    Separation { columns: Vec<usize> },
+    #[error("Outcome {outcome:?} is invalid for this model")]
+    InvalidOutcome { outcome: String },
+    #[error("Choice set {group:?} must contain exactly one chosen alternative")]
+    InvalidChoiceSet { group: String },
+    #[error("Quantile {tau:?} is not in (0, 1)")]
+    InvalidQuantile { tau: f64 },
+    #[error("Confidence level {level:?} is not in (0, 1)")]
+    InvalidConfidenceLevel { level: f64 },
+    #[error("Hypothesis {hypothesis:?} is invalid: {message}")]
+    InvalidHypothesis { hypothesis: String, message: String },
+    #[error("Break point {index:?} leaves a regime with no more observations than regressors")]
+    InvalidBreakPoint { index: usize },
+    #[error("Trimming {trim:?} is not in (0, 0.5)")]
+    InvalidTrimming { trim: f64 },
+    #[error("Tolerance {tolerance:?} is not a positive finite number")]
+    InvalidTolerance { tolerance: f64 },
+    #[error("Bootstrap is invalid: {message}")]
+    InvalidBootstrap { message: String },
+    #[error("Standard error type {se_type:?} is not supported by this solver")]
+    UnsupportedSEType { se_type: String },
 
     // Solver
     #[error("Solver is not solved")]
     NotSolved,
+    #[error("Solver did not converge after {iterations:?} iterations")]
+    NotConverged { iterations: u64 },
+    #[error("Solver failed: {message:?}")]
+    SolverFailed { message: String },
+
+    #[error("{feature} not available for this model")]
+    NotAvailable { feature: String },
 
     // Model
     #[error("Model is not fitted")]
@@ -0,0 +1,570 @@
+//! Bootstrap inference for any solver, re-fitted on samples drawn from the
+//! data by one of several schemes. Replication r draws from its own
+//! generator, seeded in turn from the bootstrap's seed, so results are
+//! reproducible and the same with or without the `parallel` feature.
+
+use amita_error::AmitaError;
+use ndarray::{Array1, Array2, Axis};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use statrs::distribution::{ContinuousCDF, Normal};
+
+use crate::parallel::map_indices;
+use crate::traits::{BaseResults, BaseSolver};
+
+/// Distribution of the multipliers v_i of the wild bootstrap, each with
+/// mean 0 and variance 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WildWeights {
+    /// ±1 with probability 1/2
+    Rademacher,
+    /// -(√5 - 1) / 2 with probability (√5 + 1) / (2√5), and (√5 + 1) / 2
+    /// otherwise, whose third moment is also 1
+    Mammen,
+    /// ±√(1/2), ±1 and ±√(3/2) with probability 1/6, for few clusters
+    Webb,
+}
+
+impl WildWeights {
+    pub fn draw<G: Rng>(&self, rng: &mut G) -> f64 {
+        match self {
+            WildWeights::Rademacher => if rng.gen_bool(0.5) { 1. } else { -1. },
+            WildWeights::Mammen => {
+                let root_5 = 5_f64.sqrt();
+                if rng.gen_bool((root_5 + 1.) / (2. * root_5)) { -(root_5 - 1.) / 2. } else { (root_5 + 1.) / 2. }
+            },
+            WildWeights::Webb => {
+                let magnitude = [0.5_f64.sqrt(), 1., 1.5_f64.sqrt()][rng.gen_range(0..3)];
+                if rng.gen_bool(0.5) { magnitude } else { -magnitude }
+            },
+        }
+    }
+}
+
+/// How bootstrap samples are drawn from the data
+#[derive(Debug, Clone)]
+pub enum BootstrapScheme {
+    /// Rows (y_i, x_i) drawn with replacement
+    Pairs,
+    /// y* = Xβ + e*, with e* drawn with replacement from the centered
+    /// residuals y - Xβ of the fit on the data
+    Residual,
+    /// y* = Xβ + v_i e_i, with multipliers v_i drawn from `weights`
+    Wild { weights: WildWeights },
+    /// Whole clusters drawn with replacement, each copy a distinct cluster
+    Cluster { by: Array1<i32> },
+    /// Moving blocks of `length` consecutive rows drawn with replacement
+    /// and joined, for serially dependent data
+    MovingBlock { length: usize },
+}
+
+/// A sample the model is fitted on, the data themselves for the estimates
+#[derive(Debug, Clone)]
+pub struct Sample {
+    pub y: Array1<f64>,
+    pub x: Array2<f64>,
+    /// Cluster of each row under `BootstrapScheme::Cluster`
+    pub clusters: Option<Array1<i32>>,
+}
+
+/// Confidence intervals from the bootstrap draws
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BootstrapInterval {
+    /// Quantiles α/2 and 1 - α/2 of the draws
+    Percentile,
+    /// Percentiles corrected for the median bias of the draws and for the
+    /// skewness of the jackknife estimates (Efron, 1987)
+    BCa,
+    /// β - q_{1-α/2} se and β - q_{α/2} se, with q the quantiles of the
+    /// studentized draws (β* - β) / se*
+    BootstrapT,
+}
+
+/// Re-fits the solver built by `build` from each bootstrap sample.
+/// Residual and wild schemes perturb the linear predictor Xβ, so they suit
+/// linear models.
+#[derive(Debug, Clone)]
+pub struct Bootstrap<F> {
+    y: Array1<f64>,
+    x: Array2<f64>,
+    build: F,
+    scheme: BootstrapScheme,
+    replications: usize,
+    seed: u64,
+    jackknife: bool,
+}
+
+impl<F> Bootstrap<F> {
+    pub fn new(y: &Array1<f64>, x: &Array2<f64>, build: F) -> Result<Self, AmitaError> {
+        if x.shape()[0] != y.len() {
+            return Err(AmitaError::NotSameObservations);
+        }
+
+        Ok( Bootstrap {
+            y: y.to_owned(),
+            x: x.to_owned(),
+            build,
+            scheme: BootstrapScheme::Pairs,
+            replications: 999,
+            seed: 0,
+            jackknife: false,
+        } )
+    }
+
+    pub fn with_scheme(mut self, scheme: BootstrapScheme) -> Result<Self, AmitaError> {
+        let n = self.y.len();
+        match &scheme {
+            BootstrapScheme::Cluster { by } if by.len() != n => return Err(AmitaError::NotSameObservations),
+            BootstrapScheme::MovingBlock { length } if *length == 0 || *length > n => {
+                return Err(AmitaError::InvalidBootstrap { message: format!("block length {length} is not in [1, {n}]") });
+            },
+            _ => {},
+        }
+
+        self.scheme = scheme;
+        Ok(self)
+    }
+
+    /// Number of bootstrap samples, 999 by default
+    pub fn with_replications(mut self, replications: usize) -> Result<Self, AmitaError> {
+        if replications < 2 {
+            return Err(AmitaError::InvalidBootstrap { message: "fewer than 2 replications".to_string() });
+        }
+
+        self.replications = replications;
+        Ok(self)
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Also re-fits the model leaving out each observation, or each
+    /// cluster under the cluster scheme, for the acceleration of BCa
+    /// intervals
+    pub fn with_jackknife(mut self) -> Self {
+        self.jackknife = true;
+        self
+    }
+
+    /// Estimates on the data and on every bootstrap sample. A replication
+    /// fails when its fit does, or gives a NaN or infinite coefficient or
+    /// standard error where the fit on the data does not; failed ones are
+    /// left out of every statistic and counted by
+    /// [`BootstrapResults::n_failed`].
+    pub fn run<S, R>(&self) -> Result<BootstrapResults, AmitaError>
+    where
+        F: Fn(&Sample) -> Result<S, AmitaError> + Sync + Send,
+        S: BaseSolver<R>,
+        R: BaseResults,
+    {
+        let n = self.y.len();
+        let clusters = match &self.scheme {
+            BootstrapScheme::Cluster { by } => Some(by.clone()),
+            _ => None,
+        };
+
+        let fitted = self.fit(&Sample { y: self.y.clone(), x: self.x.clone(), clusters: clusters.clone() })?;
+        let estimates = fitted.coef()?;
+        let se_fitted = fitted.se()?;
+        let k = estimates.len();
+        if k != self.x.shape()[1] && matches!(self.scheme, BootstrapScheme::Residual | BootstrapScheme::Wild { .. }) {
+            return Err(AmitaError::InvalidBootstrap {
+                message: format!("{k} coefficients for the {} columns of x that residual and wild schemes perturb", self.x.shape()[1])
+            });
+        }
+
+        // Xβ and e of the fit, for the schemes that perturb them
+        let linear = self.x.dot(&estimates.map(|b| if b.is_nan() { 0. } else { *b }));
+        let resid = &self.y - &linear;
+        let centered = &resid - resid.mean().unwrap_or(0.);
+
+        let groups = clusters.as_ref().map(cluster_rows).unwrap_or_default();
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let seeds = (0..self.replications).map(|_| rng.gen::<u64>()).collect::<Vec<_>>();
+
+        let replicates = map_indices(self.replications, |r| {
+            let mut rng = StdRng::seed_from_u64(seeds[r]);
+            let sample = match &self.scheme {
+                BootstrapScheme::Pairs => {
+                    let rows = (0..n).map(|_| rng.gen_range(0..n)).collect::<Vec<_>>();
+                    self.rows(&rows, None)
+                },
+                BootstrapScheme::Residual => {
+                    let y = &linear + &Array1::from_shape_fn(n, |_| centered[rng.gen_range(0..n)]);
+                    Sample { y, x: self.x.clone(), clusters: None }
+                },
+                BootstrapScheme::Wild { weights } => {
+                    let y = &linear + &resid.map(|e| e * weights.draw(&mut rng));
+                    Sample { y, x: self.x.clone(), clusters: None }
+                },
+                BootstrapScheme::Cluster { .. } => {
+                    let mut rows = vec![];
+                    let mut ids = vec![];
+                    for copy in 0..groups.len() {
+                        let drawn = &groups[rng.gen_range(0..groups.len())];
+                        rows.extend_from_slice(drawn);
+                        ids.extend(std::iter::repeat_n(copy as i32, drawn.len()));
+                    }
+                    self.rows(&rows, Some(Array1::from_vec(ids)))
+                },
+                BootstrapScheme::MovingBlock { length } => {
+                    let mut rows = Vec::with_capacity(n + length);
+                    while rows.len() < n {
+                        let start = rng.gen_range(0..=n - length);
+                        rows.extend(start..start + length);
+                    }
+                    rows.truncate(n);
+                    self.rows(&rows, None)
+                },
+            };
+
+            let results = self.fit(&sample)?;
+            Ok::<_, AmitaError>( (results.coef()?, results.se()?) )
+        });
+
+        // NaN where the fit on the data is, as for omitted coefficients
+        let degenerate = |values: &Array1<f64>, fitted: &Array1<f64>| {
+            values.iter().zip(fitted.iter()).any(|(value, fitted)| !value.is_finite() && fitted.is_finite())
+        };
+
+        let mut coef_draws = Vec::with_capacity(self.replications * k);
+        let mut t_values = Vec::with_capacity(self.replications * k);
+        let mut n_failed = 0;
+        for replicate in replicates {
+            let Ok( (coef, se) ) = replicate else {
+                n_failed += 1;
+                continue;
+            };
+            if coef.len() != k || se.len() != k {
+                return Err(AmitaError::InvalidBootstrap {
+                    message: format!("a replication gave {} coefficients, the fit on the data {k}", coef.len())
+                });
+            }
+            if degenerate(&coef, &estimates) || degenerate(&se, &se_fitted) {
+                n_failed += 1;
+                continue;
+            }
+            t_values.extend((&coef - &estimates) / &se);
+            coef_draws.extend(coef);
+        }
+
+        let n_draws = self.replications - n_failed;
+        if n_draws < 2 {
+            return Err(AmitaError::InvalidBootstrap {
+                message: format!("{n_failed} of {} replications failed", self.replications)
+            });
+        }
+        let draws = Array2::from_shape_vec((n_draws, k), coef_draws).unwrap();
+        let t_draws = Array2::from_shape_vec((n_draws, k), t_values).unwrap();
+
+        let jackknife = if self.jackknife { Some(self.jackknife(&clusters)?) } else { None };
+
+        Ok( BootstrapResults { estimates, se: se_fitted, draws, t_draws, n_failed, jackknife } )
+    }
+
+    fn fit<S, R>(&self, sample: &Sample) -> Result<R, AmitaError>
+    where
+        F: Fn(&Sample) -> Result<S, AmitaError>,
+        S: BaseSolver<R>,
+        R: BaseResults,
+    {
+        Ok( (self.build)(sample)?.solve()?.results() )
+    }
+
+    fn rows(&self, rows: &[usize], clusters: Option<Array1<i32>>) -> Sample {
+        Sample { y: self.y.select(Axis(0), rows), x: self.x.select(Axis(0), rows), clusters }
+    }
+
+    /// Estimates leaving out each observation, or each cluster
+    fn jackknife<S, R>(&self, clusters: &Option<Array1<i32>>) -> Result<Array2<f64>, AmitaError>
+    where
+        F: Fn(&Sample) -> Result<S, AmitaError> + Sync + Send,
+        S: BaseSolver<R>,
+        R: BaseResults,
+    {
+        let groups = match clusters {
+            Some(by) => cluster_rows(by),
+            None => (0..self.y.len()).map(|i| vec![i]).collect(),
+        };
+
+        let estimates = map_indices(groups.len(), |g| {
+            let kept = groups.iter().enumerate()
+                .filter(|(h, _)| *h != g)
+                .flat_map(|(_, rows)| rows.iter().copied())
+                .collect::<Vec<_>>();
+            let sample_clusters = clusters.as_ref().map(|by| by.select(Axis(0), &kept));
+            self.fit(&self.rows(&kept, sample_clusters))?.coef()
+        });
+
+        let mut jackknife = Array2::zeros((groups.len(), 0));
+        for (g, coef) in estimates.into_iter().enumerate() {
+            let coef = coef?;
+            if g == 0 {
+                jackknife = Array2::zeros((groups.len(), coef.len()));
+            }
+            jackknife.row_mut(g).assign(&coef);
+        }
+        Ok(jackknife)
+    }
+}
+
+/// Rows of each cluster, in sorted order of the ids
+pub fn cluster_rows(by: &Array1<i32>) -> Vec<Vec<usize>> {
+    let mut ids = by.to_vec();
+    ids.sort_unstable();
+    ids.dedup();
+
+    let mut groups = vec![vec![]; ids.len()];
+    for (i, id) in by.iter().enumerate() {
+        groups[ids.binary_search(id).unwrap()].push(i);
+    }
+    groups
+}
+
+#[derive(Debug, Clone)]
+pub struct BootstrapResults {
+    estimates: Array1<f64>,
+    se: Array1<f64>, // standard errors of the fit on the data
+    draws: Array2<f64>, // one row per successful replication
+    t_draws: Array2<f64>, // (β* - β) / se*
+    n_failed: usize,
+    jackknife: Option<Array2<f64>>, // one row per observation or cluster left out
+}
+
+impl BootstrapResults {
+    /// Estimates on the data
+    pub fn estimates(&self) -> Array1<f64> {
+        self.estimates.clone()
+    }
+
+    /// Estimates on each bootstrap sample, one row per successful
+    /// replication
+    pub fn draws(&self) -> Array2<f64> {
+        self.draws.clone()
+    }
+
+    /// Number of replications that failed, which no statistic includes
+    pub fn n_failed(&self) -> usize {
+        self.n_failed
+    }
+
+    /// Mean of the draws less the estimates
+    pub fn bias(&self) -> Array1<f64> {
+        self.draws.mean_axis(Axis(0)).unwrap() - &self.estimates
+    }
+
+    /// Standard deviations of the draws
+    pub fn se(&self) -> Array1<f64> {
+        self.draws.std_axis(Axis(0), 1.)
+    }
+
+    /// Sample covariance of the draws
+    pub fn vcov(&self) -> Array2<f64> {
+        let centered = &self.draws - &self.draws.mean_axis(Axis(0)).unwrap();
+        centered.t().dot(&centered) / (self.draws.shape()[0] as f64 - 1.)
+    }
+
+    /// Confidence intervals at `level` by `method`, as (lower, upper)
+    pub fn conf_int(&self, method: BootstrapInterval, level: f64) -> Result<(Array1<f64>, Array1<f64>), AmitaError> {
+        if level.is_nan() || level <= 0. || level >= 1. {
+            return Err(AmitaError::InvalidConfidenceLevel { level });
+        }
+        let alpha = 1. - level;
+        let k = self.estimates.len();
+
+        let mut lower = Array1::zeros(k);
+        let mut upper = Array1::zeros(k);
+        for j in 0..k {
+            let draws = sorted(self.draws.column(j).iter().copied());
+            (lower[j], upper[j]) = match method {
+                BootstrapInterval::Percentile => (quantile(&draws, alpha / 2.), quantile(&draws, 1. - alpha / 2.)),
+                BootstrapInterval::BootstrapT => {
+                    let t = sorted(self.t_draws.column(j).iter().copied());
+                    (
+                        self.estimates[j] - quantile(&t, 1. - alpha / 2.) * self.se[j],
+                        self.estimates[j] - quantile(&t, alpha / 2.) * self.se[j],
+                    )
+                },
+                BootstrapInterval::BCa => {
+                    let jackknife = self.jackknife.as_ref().ok_or(AmitaError::NotAvailable {
+                        feature: "BCa intervals without the jackknife".to_string()
+                    })?;
+                    let (low, high) = bca_levels(&draws, self.estimates[j], &jackknife.column(j).to_owned(), alpha);
+                    (quantile(&draws, low), quantile(&draws, high))
+                },
+            };
+        }
+
+        Ok( (lower, upper) )
+    }
+}
+
+/// Levels of the percentiles BCa takes, from the bias correction z_0, the
+/// share of draws below the estimate, and the acceleration a of the
+/// jackknife estimates
+fn bca_levels(draws: &[f64], estimate: f64, jackknife: &Array1<f64>, alpha: f64) -> (f64, f64) {
+    let normal = Normal::new(0., 1.).unwrap();
+    let b = draws.len() as f64;
+
+    let below = draws.iter().filter(|d| **d < estimate).count() as f64;
+    let z_0 = normal.inverse_cdf((below / b).clamp(0.5 / b, 1. - 0.5 / b));
+
+    let mean = jackknife.mean().unwrap_or(0.);
+    let deviations = jackknife.map(|theta| mean - theta);
+    let squares = deviations.map(|d| d.powi(2)).sum();
+    let acceleration = if squares > 0. { deviations.map(|d| d.powi(3)).sum() / (6. * squares.powf(1.5)) } else { 0. };
+
+    let adjusted = |z: f64| normal.cdf(z_0 + (z_0 + z) / (1. - acceleration * (z_0 + z)));
+    (adjusted(normal.inverse_cdf(alpha / 2.)), adjusted(normal.inverse_cdf(1. - alpha / 2.)))
+}
+
+fn sorted(values: impl Iterator<Item = f64>) -> Vec<f64> {
+    let mut values = values.filter(|x| !x.is_nan()).collect::<Vec<_>>();
+    values.sort_by(|a, b| a.total_cmp(b));
+    values
+}
+
+/// p-quantile of sorted values, interpolating between order statistics
+fn quantile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() || p.is_nan() {
+        return f64::NAN;
+    }
+
+    let position = p.clamp(0., 1.) * (sorted.len() - 1) as f64;
+    let (below, above) = (position.floor() as usize, position.ceil() as usize);
+    sorted[below] + (position - below as f64) * (sorted[above] - sorted[below])
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+    use crate::test_fixtures::LeastSquares;
+
+    fn least_squares(sample: &Sample) -> Result<LeastSquares, AmitaError> {
+        Ok( LeastSquares::new(&sample.y, &sample.x) )
+    }
+
+    fn data() -> (Array1<f64>, Array2<f64>) {
+        let n = 60;
+        let x1 = Array1::from_shape_fn(n, |i| ((i * 17) % 23) as f64 / 4.);
+        let e = Array1::from_shape_fn(n, |i| ((i * 31) % 13) as f64 / 3. - 2.);
+        let y = 1. + 0.5 * &x1 + e;
+        let x = ndarray::stack![Axis(1), Array1::<f64>::ones(n), x1];
+        (y, x)
+    }
+
+    #[test]
+    fn test_schemes_estimate_sampling_variability() -> Result<(), AmitaError> {
+        let (y, x) = data();
+        let analytic = least_squares(&Sample { y: y.clone(), x: x.clone(), clusters: None })?.solve()?.results().se()?;
+        let clusters = Array1::from_shape_fn(y.len(), |i| (i / 3) as i32);
+
+        let schemes = [
+            BootstrapScheme::Pairs,
+            BootstrapScheme::Residual,
+            BootstrapScheme::Wild { weights: WildWeights::Rademacher },
+            BootstrapScheme::Wild { weights: WildWeights::Mammen },
+            BootstrapScheme::Wild { weights: WildWeights::Webb },
+            BootstrapScheme::Cluster { by: clusters },
+            BootstrapScheme::MovingBlock { length: 5 },
+        ];
+        for scheme in schemes {
+            let bootstrap = Bootstrap::new(&y, &x, least_squares)?
+                .with_scheme(scheme)?
+                .with_replications(400)?
+                .with_seed(49);
+            let results = bootstrap.run()?;
+
+            // the same seed gives the same draws
+            assert_eq!(results.draws(), bootstrap.run()?.draws());
+
+            let ratio = results.se()[1] / analytic[1];
+            assert!( ratio > 0.6 && ratio < 1.6 );
+            assert!( results.bias()[1].abs() < analytic[1] );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_intervals() -> Result<(), AmitaError> {
+        let (y, x) = data();
+        let results = Bootstrap::new(&y, &x, least_squares)?
+            .with_replications(500)?
+            .with_jackknife()
+            .run()?;
+        let estimates = results.estimates();
+
+        for method in [BootstrapInterval::Percentile, BootstrapInterval::BCa, BootstrapInterval::BootstrapT] {
+            let (lower, upper) = results.conf_int(method, 0.95)?;
+            assert!( (0..2).all(|j| lower[j] < estimates[j] && estimates[j] < upper[j]) );
+
+            let (narrow_lower, narrow_upper) = results.conf_int(method, 0.5)?;
+            assert!( (0..2).all(|j| lower[j] < narrow_lower[j] && narrow_upper[j] < upper[j]) );
+        }
+
+        // without skewness or bias BCa is close to the percentile interval
+        let (percentile, _) = results.conf_int(BootstrapInterval::Percentile, 0.9)?;
+        let (bca, _) = results.conf_int(BootstrapInterval::BCa, 0.9)?;
+        assert!( (&percentile - &bca).iter().zip(results.se().iter()).all(|(d, se)| d.abs() < 0.5 * se) );
+
+        let no_jackknife = Bootstrap::new(&y, &x, least_squares)?.with_replications(20)?.run()?;
+        assert!( matches!(no_jackknife.conf_int(BootstrapInterval::BCa, 0.95), Err(AmitaError::NotAvailable { .. })) );
+        assert!( matches!(results.conf_int(BootstrapInterval::Percentile, 1.), Err(AmitaError::InvalidConfidenceLevel { .. })) );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_replications() -> Result<(), AmitaError> {
+        // a dummy on 2 of 20 rows, missing from about one pairs sample in 8
+        let n = 20;
+        let x1 = Array1::from_shape_fn(n, |i| ((i * 7) % 11) as f64);
+        let dummy = Array1::from_shape_fn(n, |i| if i < 2 { 1. } else { 0. });
+        let y = 1. + 0.3 * &x1 + &dummy + Array1::from_shape_fn(n, |i| ((i * 13) % 5) as f64 / 2.);
+        let x = ndarray::stack![Axis(1), Array1::<f64>::ones(n), x1, dummy];
+
+        let results = Bootstrap::new(&y, &x, least_squares)?.with_replications(200)?.with_seed(5).run()?;
+        assert!( results.n_failed() > 0 );
+        assert_eq!(results.draws().shape()[0] + results.n_failed(), 200);
+        assert!( results.draws().iter().all(|d| d.is_finite()) );
+        assert!( results.bias().iter().chain(results.se().iter()).all(|v| v.is_finite()) );
+        assert!( results.vcov().iter().all(|v| v.is_finite()) );
+
+        // too few replications left
+        let refit_fails = |sample: &Sample| {
+            if sample.y == y { least_squares(sample) } else { Err(AmitaError::SolverFailed { message: "refit".to_string() }) }
+        };
+        let all_failed = Bootstrap::new(&y, &x, refit_fails)?.with_replications(20)?.run();
+        assert!( matches!(all_failed, Err(AmitaError::InvalidBootstrap { .. })) );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_wild_weights_and_validation() -> Result<(), AmitaError> {
+        let mut rng = StdRng::seed_from_u64(0);
+        for weights in [WildWeights::Rademacher, WildWeights::Mammen, WildWeights::Webb] {
+            let draws = Array1::from_shape_fn(100_000, |_| weights.draw(&mut rng));
+            assert!( draws.mean().unwrap().abs() < 0.02 );
+            assert!( (draws.map(|v| v.powi(2)).mean().unwrap() - 1.).abs() < 0.02 );
+        }
+
+        assert!( (quantile(&[1., 2., 3., 4., 5.], 0.25) - 2.).abs() < 1e-12 );
+        assert!( (quantile(&[1., 2.], 0.5) - 1.5).abs() < 1e-12 );
+
+        let (y, x) = data();
+        let too_long = Bootstrap::new(&y, &x, least_squares)?.with_scheme(BootstrapScheme::MovingBlock { length: 61 });
+        assert!( matches!(too_long, Err(AmitaError::InvalidBootstrap { .. })) );
+        let clusters = Bootstrap::new(&y, &x, least_squares)?.with_scheme(BootstrapScheme::Cluster { by: array![1, 2] });
+        assert!( matches!(clusters, Err(AmitaError::NotSameObservations)) );
+        assert!( Bootstrap::new(&y, &x, least_squares)?.with_replications(1).is_err() );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,197 @@
+//! Nonlinear functions of the coefficients of fitted results, with their
+//! covariance by the delta method, Var g(β) ≈ G V G' with G the Jacobian
+//! of g at β.
+
+use amita_error::AmitaError;
+use ndarray::{Array1, Array2, Axis};
+use statrs::distribution::{ContinuousCDF, Normal, StudentsT};
+
+use crate::hypothesis::students_t_p_vals;
+use crate::math::numerical_jacobian;
+use crate::traits::BaseResults;
+
+/// Estimates of g(β) with their delta-method covariance. Inference uses
+/// Student's t with the model's small-sample degrees of freedom, and the
+/// normal otherwise.
+#[derive(Debug, Clone)]
+pub struct NonlinearCombination {
+    estimates: Array1<f64>,
+    jacobian: Array2<f64>,
+    vcov: Array2<f64>,
+    df: Option<f64>,
+}
+
+impl NonlinearCombination {
+    pub fn estimates(&self) -> Array1<f64> {
+        self.estimates.clone()
+    }
+
+    /// Jacobian of g at β, one row per estimate
+    pub fn jacobian(&self) -> Array2<f64> {
+        self.jacobian.clone()
+    }
+
+    pub fn vcov(&self) -> Array2<f64> {
+        self.vcov.clone()
+    }
+
+    pub fn se(&self) -> Array1<f64> {
+        self.vcov.diag().map(|x| x.sqrt())
+    }
+
+    /// t statistics, or z statistics without small-sample degrees of freedom
+    pub fn t(&self) -> Array1<f64> {
+        &self.estimates / &self.se()
+    }
+
+    /// p values of H_0: g(β) = 0
+    pub fn p_vals(&self) -> Array1<f64> {
+        match self.df {
+            Some(df) => students_t_p_vals(&self.t(), df),
+            None => {
+                let normal = Normal::new(0., 1.).unwrap();
+                self.t().map(|x| 2. * ( 1. - normal.cdf(x.abs()) ) )
+            },
+        }
+    }
+
+    /// Confidence intervals at `level`, such as 0.95, as (lower, upper)
+    pub fn conf_int(&self, level: f64) -> Result<(Array1<f64>, Array1<f64>), AmitaError> {
+        if level.is_nan() || level <= 0. || level >= 1. {
+            return Err(AmitaError::InvalidConfidenceLevel { level });
+        }
+
+        let quantile = 1. - (1. - level) / 2.;
+        let critical = match self.df {
+            Some(df) => StudentsT::new(0., 1., df).map_or(f64::NAN, |t| t.inverse_cdf(quantile)),
+            None => Normal::new(0., 1.).unwrap().inverse_cdf(quantile),
+        };
+
+        let margin = self.se() * critical;
+        Ok( (&self.estimates - &margin, &self.estimates + &margin) )
+    }
+}
+
+/// Delta-method inference on functions of the coefficients, using the
+/// covariance the model was fitted with
+pub trait DeltaMethod {
+    /// g(β) with its Jacobian by central differences
+    fn nlcom<F>(&self, g: F) -> Result<NonlinearCombination, AmitaError>
+    where
+        F: Fn(&Array1<f64>) -> Array1<f64>;
+
+    /// g(β) with the analytic Jacobian `jacobian`, one row per element of g
+    fn nlcom_with_jacobian<F, J>(&self, g: F, jacobian: J) -> Result<NonlinearCombination, AmitaError>
+    where
+        F: Fn(&Array1<f64>) -> Array1<f64>,
+        J: Fn(&Array1<f64>) -> Array2<f64>;
+}
+
+impl<T: BaseResults> DeltaMethod for T {
+    fn nlcom<F>(&self, g: F) -> Result<NonlinearCombination, AmitaError>
+    where
+        F: Fn(&Array1<f64>) -> Array1<f64>,
+    {
+        self.nlcom_with_jacobian(&g, |coef| numerical_jacobian(&g, coef))
+    }
+
+    fn nlcom_with_jacobian<F, J>(&self, g: F, jacobian: J) -> Result<NonlinearCombination, AmitaError>
+    where
+        F: Fn(&Array1<f64>) -> Array1<f64>,
+        J: Fn(&Array1<f64>) -> Array2<f64>,
+    {
+        let coef = self.coef()?;
+        let vcov = self.vcov()?;
+
+        let estimates = g(&coef);
+        let jacobian = jacobian(&coef);
+        if jacobian.shape() != [estimates.len(), coef.len()] {
+            return Err(AmitaError::NotSameObservations);
+        }
+
+        // only the coefficients involved, as those of omitted regressors
+        // are NaN, and g may not depend on them
+        let involved = (0..coef.len())
+            .filter(|j| jacobian.column(*j).iter().any(|x| *x != 0.))
+            .collect::<Vec<_>>();
+        if let Some(j) = involved.iter().find(|j| !coef[**j].is_finite()) {
+            let name = self.coef_names()?.swap_remove(*j);
+            return Err(AmitaError::InvalidHypothesis {
+                hypothesis: "nonlinear combination".to_string(),
+                message: format!("depends on the omitted coefficient {name}"),
+            });
+        }
+        if estimates.iter().any(|x| !x.is_finite()) {
+            return Err(AmitaError::SolverFailed { message: "the combination is not finite at the estimates".to_string() });
+        }
+
+        let jacobian_involved = jacobian.select(Axis(1), &involved);
+        let vcov = vcov.select(Axis(0), &involved).select(Axis(1), &involved);
+        let vcov = jacobian_involved.dot(&vcov).dot(&jacobian_involved.t());
+
+        Ok( NonlinearCombination { estimates, jacobian, vcov, df: self.small_sample_df() } )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+    use crate::test_fixtures::Fitted;
+
+    #[test]
+    fn test_delta_method() -> Result<(), AmitaError> {
+        let fitted = Fitted {
+            coef: array![1., 2., 4.],
+            vcov: array![[0.5, 0., 0.], [0., 0.04, 0.01], [0., 0.01, 0.09]],
+        };
+
+        // a linear combination has the exact variance c'Vc
+        let sum = fitted.nlcom(|b| array![b[1] + b[2]])?;
+        assert!( (sum.estimates()[0] - 6.).abs() < 1e-10 );
+        assert!( (sum.se()[0] - 0.15_f64.sqrt()).abs() < 1e-8 );
+
+        // the ratio b1 / b2, with gradient (1 / b2, -b1 / b2^2)
+        let ratio = |b: &Array1<f64>| array![b[1] / b[2]];
+        let numerical = fitted.nlcom(ratio)?;
+        let analytic = fitted.nlcom_with_jacobian(ratio, |b| array![[0., 1. / b[2], -b[1] / b[2].powi(2)]])?;
+        let variance: f64 = 0.04 / 16. + 0.09 * 4. / 256. - 2. * 0.01 * 2. / 64.;
+        assert!( (analytic.se()[0] - variance.sqrt()).abs() < 1e-12 );
+        assert!( (numerical.se()[0] - analytic.se()[0]).abs() < 1e-8 );
+
+        // normal intervals without small-sample degrees of freedom
+        let (lower, upper) = analytic.conf_int(0.95)?;
+        assert!( (upper[0] - lower[0] - 2. * 1.959963984540054 * analytic.se()[0]).abs() < 1e-10 );
+        assert!( (analytic.p_vals()[0] - 2. * (1. - Normal::new(0., 1.).unwrap().cdf(analytic.t()[0]))).abs() < 1e-12 );
+
+        assert!( matches!(analytic.conf_int(1.5), Err(AmitaError::InvalidConfidenceLevel { .. })) );
+        let mismatched = fitted.nlcom_with_jacobian(ratio, |_| array![[1., 0.]]);
+        assert!( matches!(mismatched, Err(AmitaError::NotSameObservations)) );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_omitted_coefficient() -> Result<(), AmitaError> {
+        // the second regressor was collinear and dropped, leaving NaN
+        let nan = f64::NAN;
+        let fitted = Fitted {
+            coef: array![1., nan, 4.],
+            vcov: array![[0.5, nan, 0.1], [nan, nan, nan], [0.1, nan, 0.09]],
+        };
+
+        let ratio = fitted.nlcom(|b| array![b[0] / b[2]])?;
+        let variance: f64 = 0.5 / 16. + 0.09 / 256. - 2. * 0.1 / 64.;
+        assert!( (ratio.se()[0] - variance.sqrt()).abs() < 1e-8 );
+        assert_eq!(ratio.jacobian()[[0, 1]], 0.);
+
+        let analytic = fitted.nlcom_with_jacobian(|b| array![b[0] * b[2]], |b| array![[b[2], 0., b[0]]])?;
+        assert!( analytic.se().iter().all(|x| x.is_finite()) );
+
+        let omitted = fitted.nlcom(|b| array![b[0] + b[1]]);
+        assert!( matches!(omitted, Err(AmitaError::InvalidHypothesis { .. })) );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,188 @@
+//! Absorption of high-dimensional fixed effects by alternating projections
+
+use std::collections::HashMap;
+
+use amita_error::AmitaError;
+use ndarray::{Array1, Array2, Axis};
+
+/// One or more sets of fixed effects, stored as dense group indices
+/// 0, 1, ..., G_d - 1 per dimension d
+#[derive(Debug, Clone)]
+pub struct FixedEffects {
+    groups: Vec<Vec<usize>>,
+    n_groups: Vec<usize>,
+
+    max_iter: u64,
+    tolerance: f64,
+}
+
+impl FixedEffects {
+    /// `ids` holds one vector of group identifiers per dimension
+    pub fn new(ids: &[Array1<i32>]) -> Result<Self, AmitaError> {
+        if let Some(first) = ids.first() {
+            if ids.iter().any(|id| id.len() != first.len()) {
+                return Err(AmitaError::NotSameObservations);
+            }
+        }
+
+        let mut groups = vec![];
+        let mut n_groups = vec![];
+        for id in ids {
+            let mut dense = HashMap::new();
+            let g = id.iter()
+                .map(|id_i| {
+                    let next = dense.len();
+                    *dense.entry(*id_i).or_insert(next)
+                })
+                .collect::<Vec<_>>();
+            n_groups.push(dense.len());
+            groups.push(g);
+        }
+
+        Ok( Self { groups, n_groups, max_iter: 10_000, tolerance: 1e-10 } )
+    }
+
+    pub fn with_max_iter(mut self, max_iter: u64) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn n_dims(&self) -> usize {
+        self.groups.len()
+    }
+
+    pub fn n_obs(&self) -> usize {
+        self.groups.first().map_or(0, |g| g.len())
+    }
+
+    /// Number of groups in each dimension
+    pub fn n_groups(&self) -> Vec<usize> {
+        self.n_groups.clone()
+    }
+
+    /// Dense group index of every observation in dimension `dim`
+    pub fn groups(&self, dim: usize) -> &[usize] {
+        &self.groups[dim]
+    }
+
+    /// Fixed effects restricted to the observations in `rows`, renumbered
+    pub fn select(&self, rows: &[usize]) -> Self {
+        let ids = self.groups.iter()
+            .map(|g| rows.iter().map(|i| g[*i] as i32).collect::<Array1<i32>>())
+            .collect::<Vec<_>>();
+
+        let mut selected = Self::new(&ids).unwrap();
+        selected.max_iter = self.max_iter;
+        selected.tolerance = self.tolerance;
+        selected
+    }
+
+    /// Observations that are alone in their group in some dimension
+    pub fn singletons(&self) -> Vec<usize> {
+        let mut singleton = vec![false; self.n_obs()];
+        for (g, n_g) in self.groups.iter().zip(self.n_groups.iter()) {
+            let mut counts = vec![0_usize; *n_g];
+            g.iter().for_each(|g_i| counts[*g_i] += 1);
+            g.iter().enumerate()
+                .filter(|(_, g_i)| counts[**g_i] == 1)
+                .for_each(|(i, _)| singleton[i] = true);
+        }
+
+        (0..self.n_obs()).filter(|i| singleton[*i]).collect()
+    }
+
+    /// Residuals of the weighted projection of every column of `x` on the
+    /// fixed effects, by the method of alternating projections: group means
+    /// are swept out one dimension at a time until the largest mean removed
+    /// in a sweep falls below the tolerance, relative to the column scale
+    pub fn demean(
+        &self,
+        x: &Array2<f64>,
+        weights: &Array1<f64>,
+    ) -> Result<Array2<f64>, AmitaError> {
+        if x.shape()[0] != self.n_obs() || weights.len() != self.n_obs() {
+            return Err(AmitaError::NotSameObservations);
+        }
+
+        let group_weights = self.groups.iter().zip(self.n_groups.iter())
+            .map(|(g, n_g)| {
+                let mut total = vec![0.; *n_g];
+                g.iter().zip(weights.iter()).for_each(|(g_i, w)| total[*g_i] += w);
+                total
+            })
+            .collect::<Vec<_>>();
+
+        let mut demeaned = x.to_owned();
+        for mut column in demeaned.axis_iter_mut(Axis(1)) {
+            let scale = column.iter().fold(1., |acc: f64, x| acc.max(x.abs()));
+
+            let mut n_iter = 0;
+            loop {
+                let mut largest = 0_f64;
+                for (g, total) in self.groups.iter().zip(group_weights.iter()) {
+                    let mut means = vec![0.; total.len()];
+                    for ((g_i, x), w) in g.iter().zip(column.iter()).zip(weights.iter()) {
+                        means[*g_i] += w * x;
+                    }
+                    means.iter_mut().zip(total.iter())
+                        .for_each(|(m, t)| if *t > 0. { *m /= t });
+
+                    for (g_i, x) in g.iter().zip(column.iter_mut()) {
+                        *x -= means[*g_i];
+                    }
+                    largest = means.iter().fold(largest, |acc, m| acc.max(m.abs()));
+                }
+
+                n_iter += 1;
+                if largest < self.tolerance * scale || self.n_dims() == 1 {
+                    break;
+                }
+                if n_iter >= self.max_iter {
+                    return Err(AmitaError::NotConverged { iterations: n_iter });
+                }
+            }
+        }
+
+        Ok(demeaned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+
+    #[test]
+    fn test_two_way_demeaning() -> Result<(), AmitaError> {
+        // unbalanced two-way panel
+        let unit = array![0, 0, 0, 1, 1, 2, 2, 2];
+        let time = array![0, 1, 2, 0, 2, 0, 1, 2];
+        let x = array![[1.], [3.], [2.], [5.], [4.], [0.], [2.], [7.]];
+        let weights = array![1., 2., 1., 1., 3., 1., 1., 2.];
+
+        let fe = FixedEffects::new(&[unit.clone(), time.clone()])?;
+        let demeaned = fe.demean(&x, &weights)?;
+
+        // the residuals are weighted-orthogonal to every group dummy
+        for (ids, n_groups) in [(unit, 3), (time, 3)] {
+            for g in 0..n_groups {
+                let total = (0..8)
+                    .filter(|i| ids[*i] == g)
+                    .map(|i| weights[i] * demeaned[[i, 0]])
+                    .sum::<f64>();
+                assert!( total.abs() < 1e-8 );
+            }
+        }
+
+        assert_eq!(fe.singletons(), Vec::<usize>::new());
+        assert_eq!(fe.select(&[0, 1, 3, 5]).singletons(), vec![1, 2, 3]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+//! Conversions from polars DataFrame columns into ndarray inputs of solvers
+
+use std::collections::BTreeMap;
+
+use amita_error::AmitaError;
+use ndarray::{Array1, Array2};
+use polars::prelude::*;
+
+/// Columns of `data` as an n×k matrix of f64
+pub fn columns_to_array2(
+    data: &DataFrame,
+    columns: &[String],
+) -> Result<Array2<f64>, AmitaError> {
+    for column in columns {
+        validate_column_exists(data, column)?;
+    }
+
+    data
+        .select(columns)
+        .and_then(|df| df.to_ndarray::<Float64Type>(IndexOrder::C))
+        .map_err(|e| AmitaError::ColumnDataTypeError {
+            column: columns.join(", "),
+            expected: "numeric".to_string(),
+            found: e.to_string(),
+        })
+}
+
+/// A column of `data` as a vector of f64
+pub fn column_to_array1(
+    data: &DataFrame,
+    column: &str,
+) -> Result<Array1<f64>, AmitaError> {
+    let x = columns_to_array2(data, &[column.to_string()])?;
+    Ok( x.column(0).to_owned() )
+}
+
+/// A column of `data` as a vector of i32, for codes such as outcomes or
+/// alternatives, failing rather than truncating when a value is not an
+/// integer
+pub fn column_to_integers(
+    data: &DataFrame,
+    column: &str,
+) -> Result<Array1<i32>, AmitaError> {
+    let x = column_to_array1(data, column)?;
+    if x.iter().any(|x| x.fract() != 0. || *x < i32::MIN as f64 || *x > i32::MAX as f64) {
+        return Err(AmitaError::NonInteger { matrix_name: column.to_string() });
+    }
+
+    Ok( x.map(|x| *x as i32) )
+}
+
+/// Integer group identifiers 0, 1, ..., G - 1 for the distinct values of a
+/// bool, integer or string column, numbered in sorted order of the values
+pub fn column_to_group_ids(
+    data: &DataFrame,
+    column: &str,
+) -> Result<Array1<i32>, AmitaError> {
+    validate_column_exists(data, column)?;
+
+    let dtype_error = |found: String| AmitaError::ColumnDataTypeError {
+        column: column.to_string(),
+        expected: "bool, int, or string without nulls".to_string(),
+        found,
+    };
+
+    let series = data.column(column).map_err(|e| dtype_error(e.to_string()))?;
+    if series.null_count() > 0 || !(series.dtype().is_integer() || matches!(series.dtype(), DataType::Boolean | DataType::String)) {
+        return Err(dtype_error(series.dtype().to_string()));
+    }
+
+    // sort integers numerically rather than lexicographically
+    let keys: Vec<(i64, String)> = if series.dtype().is_integer() {
+        let values = series.cast(&DataType::Int64).map_err(|e| dtype_error(e.to_string()))?;
+        let values = values.i64().map_err(|e| dtype_error(e.to_string()))?;
+        values.into_no_null_iter().map(|x| (x, String::new())).collect()
+    } else {
+        let values = series.cast(&DataType::String).map_err(|e| dtype_error(e.to_string()))?;
+        let values = values.str().map_err(|e| dtype_error(e.to_string()))?;
+        values.into_no_null_iter().map(|x| (0, x.to_string())).collect()
+    };
+
+    let mut ids = BTreeMap::new();
+    for key in keys.iter() {
+        ids.insert(key.clone(), 0);
+    }
+    for (id, value) in ids.values_mut().enumerate() {
+        *value = id as i32;
+    }
+
+    Ok( keys.iter().map(|key| ids[key]).collect() )
+}
+
+fn validate_column_exists(
+    data: &DataFrame,
+    column: &str,
+) -> Result<(), AmitaError> {
+    data
+        .schema()
+        .get(column)
+        .ok_or(AmitaError::ColumnNotFound { column: column.to_string() })?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_ids() -> Result<(), AmitaError> {
+        let df = df!(
+            "state" => ["b", "a", "b", "c"],
+            "year" => [2001, 1999, 10, 2001],
+            "x" => [1., 2., 3., 4.],
+        ).unwrap();
+
+        assert_eq!(column_to_group_ids(&df, "state")?.to_vec(), vec![1, 0, 1, 2]);
+        assert_eq!(column_to_group_ids(&df, "year")?.to_vec(), vec![2, 1, 0, 2]);
+        assert!(matches!(column_to_group_ids(&df, "x"), Err(AmitaError::ColumnDataTypeError { .. })));
+        assert!(matches!(column_to_group_ids(&df, "z"), Err(AmitaError::ColumnNotFound { .. })));
+
+        assert_eq!(column_to_array1(&df, "x")?.to_vec(), vec![1., 2., 3., 4.]);
+
+        assert_eq!(column_to_integers(&df, "year")?.to_vec(), vec![2001, 1999, 10, 2001]);
+        let fractional = df!("code" => [1., 2.5]).unwrap();
+        assert!(matches!(column_to_integers(&fractional, "code"), Err(AmitaError::NonInteger { .. })));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,404 @@
+//! Test statistics and their reference distributions
+
+use std::fmt;
+
+use amita_error::AmitaError;
+use ndarray::{Array1, Array2, Axis};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use statrs::distribution::{ChiSquared, ContinuousCDF, FisherSnedecor, Normal, StudentsT};
+
+use crate::linalg::{inverse, positive_part_inverse};
+use crate::restrictions::LinearRestrictions;
+use crate::traits::BaseResults;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TestDistribution {
+    ChiSquared { df: f64 },
+    F { df_num: f64, df_denom: f64 },
+    StudentsT { df: f64 },
+    Normal,
+    /// 50:50 mixture of χ²(0) and χ²(1), for a single parameter on the
+    /// boundary of its space under H0
+    ChiBarSquared01,
+    /// Normal with the statistic's exact mean and standard deviation under
+    /// H0, for statistics such as Durbin–Watson's
+    NormalApproximation { mean: f64, sd: f64 },
+    /// Andrews' limit of the supremum of Wald statistics with `df`
+    /// restrictions over break fractions in [trim, 1 - trim]
+    SupWald { df: f64, trim: f64 },
+}
+
+/// A test statistic with its reference distribution and p-value
+#[derive(Debug, Clone)]
+pub struct TestStatistic {
+    name: String,
+    statistic: f64,
+    distribution: TestDistribution,
+    p_val: f64,
+}
+
+impl TestStatistic {
+    /// Upper-tail test against χ²(df)
+    pub fn chi_squared(name: &str, statistic: f64, df: f64) -> Self {
+        let p_val = if df > 0. && statistic.is_finite() {
+            1. - ChiSquared::new(df).unwrap().cdf(statistic.max(0.))
+        } else {
+            f64::NAN
+        };
+
+        Self {
+            name: name.to_string(),
+            statistic,
+            distribution: TestDistribution::ChiSquared { df },
+            p_val,
+        }
+    }
+
+    /// Upper-tail test against F(df_num, df_denom)
+    pub fn f(name: &str, statistic: f64, df_num: f64, df_denom: f64) -> Self {
+        let p_val = if df_num > 0. && df_denom > 0. && statistic.is_finite() {
+            1. - FisherSnedecor::new(df_num, df_denom).unwrap().cdf(statistic.max(0.))
+        } else {
+            f64::NAN
+        };
+
+        Self {
+            name: name.to_string(),
+            statistic,
+            distribution: TestDistribution::F { df_num, df_denom },
+            p_val,
+        }
+    }
+
+    /// Two-sided test against Student's t(df)
+    pub fn students_t(name: &str, statistic: f64, df: f64) -> Self {
+        let p_val = if df > 0. && statistic.is_finite() {
+            2. * ( 1. - StudentsT::new(0., 1., df).unwrap().cdf(statistic.abs()) )
+        } else {
+            f64::NAN
+        };
+
+        Self {
+            name: name.to_string(),
+            statistic,
+            distribution: TestDistribution::StudentsT { df },
+            p_val,
+        }
+    }
+
+    /// Two-sided test against the standard normal
+    pub fn normal(name: &str, statistic: f64) -> Self {
+        let p_val = if statistic.is_finite() {
+            2. * ( 1. - Normal::new(0., 1.).unwrap().cdf(statistic.abs()) )
+        } else {
+            f64::NAN
+        };
+
+        Self {
+            name: name.to_string(),
+            statistic,
+            distribution: TestDistribution::Normal,
+            p_val,
+        }
+    }
+
+    /// Upper-tail test against the 50:50 mixture of χ²(0) and χ²(1)
+    pub fn chi_bar_squared_01(name: &str, statistic: f64) -> Self {
+        let p_val = if statistic.is_finite() {
+            if statistic > 0. {
+                0.5 * ( 1. - ChiSquared::new(1.).unwrap().cdf(statistic) )
+            } else {
+                1.
+            }
+        } else {
+            f64::NAN
+        };
+
+        Self {
+            name: name.to_string(),
+            statistic,
+            distribution: TestDistribution::ChiBarSquared01,
+            p_val,
+        }
+    }
+
+    /// Two-sided test of (statistic - mean) / sd against the standard normal
+    pub fn normal_approximation(name: &str, statistic: f64, mean: f64, sd: f64) -> Self {
+        let z = (statistic - mean) / sd;
+        let p_val = if z.is_finite() {
+            2. * ( 1. - Normal::new(0., 1.).unwrap().cdf(z.abs()) )
+        } else {
+            f64::NAN
+        };
+
+        Self {
+            name: name.to_string(),
+            statistic,
+            distribution: TestDistribution::NormalApproximation { mean, sd },
+            p_val,
+        }
+    }
+
+    /// Upper-tail test of a supremum of Wald statistics against Andrews'
+    /// limit sup ||B(π) - πB(1)||^2 / (π(1 - π)) over π in [trim, 1 - trim],
+    /// with B a Brownian motion of dimension `df`. The p-value is simulated
+    /// with a fixed seed, so is the same on every call.
+    pub fn sup_wald(name: &str, statistic: f64, df: f64, trim: f64) -> Self {
+        const N_STEPS: usize = 500;
+        const N_DRAWS: usize = 5000;
+
+        let p_val = if df >= 1. && trim > 0. && trim < 0.5 && statistic.is_finite() {
+            let normal = Normal::new(0., (1. / N_STEPS as f64).sqrt()).unwrap();
+            let mut rng = StdRng::seed_from_u64(0);
+            let dims = df as usize;
+            let first = (trim * N_STEPS as f64).ceil() as usize;
+            let last = ((1. - trim) * N_STEPS as f64).floor() as usize;
+
+            let mut exceeded = 0;
+            let mut path = vec![0.; (N_STEPS + 1) * dims];
+            for _ in 0..N_DRAWS {
+                for step in 1..=N_STEPS {
+                    for dim in 0..dims {
+                        path[step * dims + dim] = path[(step - 1) * dims + dim] + rng.sample(normal);
+                    }
+                }
+
+                let end = &path[N_STEPS * dims..];
+                let sup = (first..=last)
+                    .map(|step| {
+                        let pi = step as f64 / N_STEPS as f64;
+                        let squared_norm = (0..dims)
+                            .map(|dim| (path[step * dims + dim] - pi * end[dim]).powi(2))
+                            .sum::<f64>();
+                        squared_norm / (pi * (1. - pi))
+                    })
+                    .fold(f64::NEG_INFINITY, f64::max);
+                if sup >= statistic {
+                    exceeded += 1;
+                }
+            }
+            exceeded as f64 / N_DRAWS as f64
+        } else {
+            f64::NAN
+        };
+
+        Self {
+            name: name.to_string(),
+            statistic,
+            distribution: TestDistribution::SupWald { df, trim },
+            p_val,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn statistic(&self) -> f64 {
+        self.statistic
+    }
+
+    pub fn distribution(&self) -> TestDistribution {
+        self.distribution
+    }
+
+    /// Degrees of freedom of the reference distribution, empty for the normal
+    pub fn df(&self) -> Vec<f64> {
+        match self.distribution {
+            TestDistribution::ChiSquared { df } => vec![df],
+            TestDistribution::F { df_num, df_denom } => vec![df_num, df_denom],
+            TestDistribution::StudentsT { df } => vec![df],
+            TestDistribution::Normal => vec![],
+            TestDistribution::ChiBarSquared01 => vec![0., 1.],
+            TestDistribution::NormalApproximation { .. } => vec![],
+            TestDistribution::SupWald { df, .. } => vec![df],
+        }
+    }
+
+    pub fn p_val(&self) -> f64 {
+        self.p_val
+    }
+}
+
+impl fmt::Display for TestStatistic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (statistic, reference) = match self.distribution {
+            TestDistribution::ChiSquared { df } => (format!("chi2({df})"), "chi2"),
+            TestDistribution::F { df_num, df_denom } => (format!("F({df_num}, {df_denom})"), "F"),
+            TestDistribution::StudentsT { df } => (format!("t({df})"), "|t|"),
+            TestDistribution::Normal => ("z".to_string(), "|z|"),
+            TestDistribution::ChiBarSquared01 => ("chibar2(01)".to_string(), "chibar2"),
+            TestDistribution::NormalApproximation { .. } => ("statistic".to_string(), "|z|"),
+            TestDistribution::SupWald { df, .. } => (format!("sup Wald({df})"), "sup Wald"),
+        };
+
+        write!(
+            f,
+            "{}: {} = {:.4}, Prob > {} = {:.4}",
+            self.name, statistic, self.statistic, reference, self.p_val,
+        )
+    }
+}
+
+/// Two-sided p values of t statistics against Student's t(df): NaN for NaN
+/// statistics, such as those of omitted coefficients, and for all of them
+/// without degrees of freedom, as in a saturated model
+pub fn students_t_p_vals(t: &Array1<f64>, df: f64) -> Array1<f64> {
+    match StudentsT::new(0., 1., df) {
+        Ok(t_dist) => t.map(|x| if x.is_nan() { f64::NAN } else { 2. * ( 1. - t_dist.cdf(x.abs()) ) }),
+        Err(_) => Array1::from_elem(t.len(), f64::NAN),
+    }
+}
+
+/// Hausman test of H0: both estimators are consistent and the second one is
+/// efficient, H = (b_c - b_e)' (V_c - V_e)^- (b_c - b_e) ~ χ²(r). In finite
+/// samples V_c - V_e need not be positive definite, so its inverse is taken
+/// over the positive eigenvalues, r of them, as Stata's `hausman` does.
+pub fn hausman(
+    name: &str,
+    coef_consistent: &Array1<f64>,
+    vcov_consistent: &Array2<f64>,
+    coef_efficient: &Array1<f64>,
+    vcov_efficient: &Array2<f64>,
+) -> Result<TestStatistic, AmitaError> {
+    if coef_consistent.len() != coef_efficient.len() {
+        return Err(AmitaError::NotSameObservations);
+    }
+
+    let diff = coef_consistent - coef_efficient;
+    let vcov_diff = vcov_consistent - vcov_efficient;
+    let (vcov_diff_inverse, rank) = positive_part_inverse(&vcov_diff, "difference of covariance matrices")?;
+    if rank == 0 {
+        return Err(AmitaError::NonPositive { matrix_name: "difference of covariance matrices".to_string() });
+    }
+    let statistic = diff.dot(&vcov_diff_inverse.dot(&diff));
+
+    Ok( TestStatistic::chi_squared(name, statistic, rank as f64) )
+}
+
+/// Wald test of linear restrictions, with its F form when the model makes
+/// small-sample inference
+#[derive(Debug, Clone)]
+pub struct LinearTest {
+    wald: TestStatistic,
+    f: Option<TestStatistic>,
+}
+
+impl LinearTest {
+    /// W = (Rβ - q)' (R V R')^{-1} (Rβ - q) ~ χ²(J)
+    pub fn wald(&self) -> &TestStatistic {
+        &self.wald
+    }
+
+    /// W / J ~ F(J, df) with the model's small-sample degrees of freedom
+    pub fn f(&self) -> Option<&TestStatistic> {
+        self.f.as_ref()
+    }
+}
+
+/// Tests of linear restrictions on the coefficients of fitted results,
+/// using the covariance the model was fitted with
+pub trait LinearHypothesis {
+    fn test_linear(&self, restrictions: &LinearRestrictions) -> Result<LinearTest, AmitaError>;
+
+    /// Restrictions written over the coefficient names, such as
+    /// `"x1 + x2 = 1"`; see [`LinearRestrictions::parse`]
+    fn test_hypothesis(&self, hypothesis: &str) -> Result<LinearTest, AmitaError>;
+}
+
+impl<T: BaseResults> LinearHypothesis for T {
+    fn test_linear(&self, restrictions: &LinearRestrictions) -> Result<LinearTest, AmitaError> {
+        let coef = self.coef()?;
+        let vcov = self.vcov()?;
+        let r = restrictions.r();
+        if r.shape()[1] != coef.len() {
+            return Err(AmitaError::NotSameObservations);
+        }
+
+        // only the coefficients involved, as those of omitted regressors
+        // are NaN, and the restrictions may not involve them
+        let involved = (0..coef.len())
+            .filter(|j| r.column(*j).iter().any(|x| *x != 0.))
+            .collect::<Vec<_>>();
+        if let Some(j) = involved.iter().find(|j| !coef[**j].is_finite()) {
+            let name = self.coef_names()?.swap_remove(*j);
+            return Err(AmitaError::InvalidHypothesis {
+                hypothesis: "linear restrictions".to_string(),
+                message: format!("involve the omitted coefficient {name}"),
+            });
+        }
+        let r = r.select(Axis(1), &involved);
+        let coef = coef.select(Axis(0), &involved);
+        let vcov = vcov.select(Axis(0), &involved).select(Axis(1), &involved);
+
+        let diff = r.dot(&coef) - restrictions.q();
+        let middle = inverse(&r.dot(&vcov).dot(&r.t()), "restrictions R V R'")?;
+        let statistic = diff.dot(&middle.dot(&diff));
+
+        let n_restrictions = restrictions.n_restrictions() as f64;
+        Ok( LinearTest {
+            wald: TestStatistic::chi_squared("Wald test", statistic, n_restrictions),
+            f: self.small_sample_df()
+                .map(|df| TestStatistic::f("F test", statistic / n_restrictions, n_restrictions, df)),
+        } )
+    }
+
+    fn test_hypothesis(&self, hypothesis: &str) -> Result<LinearTest, AmitaError> {
+        let restrictions = LinearRestrictions::parse(hypothesis, &self.coef_names()?)?;
+        self.test_linear(&restrictions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_p_vals() {
+        let chi2 = TestStatistic::chi_squared("Wald", 3.841458820694124, 1.);
+        assert!( (chi2.p_val() - 0.05).abs() < 1e-9 );
+
+        let z = TestStatistic::normal("z", 1.959963984540054);
+        assert!( (z.p_val() - 0.05).abs() < 1e-9 );
+
+        assert_eq!(format!("{chi2}"), "Wald: chi2(1) = 3.8415, Prob > chi2 = 0.0500");
+
+        let chibar2 = TestStatistic::chi_bar_squared_01("LR", 2.705543454095404);
+        assert!( (chibar2.p_val() - 0.05).abs() < 1e-9 );
+
+        // Andrews' 5% critical value with one restriction and 15% trimming
+        let sup_wald = TestStatistic::sup_wald("Quandt-Andrews", 8.85, 1., 0.15);
+        assert!( (sup_wald.p_val() - 0.05).abs() < 0.015 );
+
+        let p_vals = students_t_p_vals(&ndarray::array![2.2281388519649385, f64::NAN], 10.);
+        assert!( (p_vals[0] - 0.05).abs() < 1e-9 && p_vals[1].is_nan() );
+        assert!( students_t_p_vals(&ndarray::array![2.], 0.).iter().all(|p| p.is_nan()) );
+    }
+
+    #[test]
+    fn test_hausman() -> Result<(), AmitaError> {
+        let coef_consistent = ndarray::array![1., 2.];
+        let vcov_consistent = ndarray::array![[0.5, 0.1], [0.1, 0.3]];
+        let coef_efficient = ndarray::array![0.8, 2.3];
+
+        let vcov_efficient = ndarray::array![[0.3, 0.1], [0.1, 0.1]];
+        let test = hausman("Hausman", &coef_consistent, &vcov_consistent, &coef_efficient, &vcov_efficient)?;
+        let expected: f64 = 0.2_f64.powi(2) / 0.2 + 0.3_f64.powi(2) / 0.2;
+        assert!( (test.statistic() - expected).abs() < 1e-10 );
+        assert_eq!(test.df(), vec![2.]);
+
+        // the efficient estimator is less precise for the second coefficient,
+        // which drops out along with a degree of freedom
+        let vcov_efficient = ndarray::array![[0.3, 0.1], [0.1, 0.4]];
+        let test = hausman("Hausman", &coef_consistent, &vcov_consistent, &coef_efficient, &vcov_efficient)?;
+        assert!( (test.statistic() - 0.2_f64.powi(2) / 0.2).abs() < 1e-10 );
+        assert_eq!(test.df(), vec![1.]);
+
+        let less_precise = ndarray::array![[0.3, 0.1], [0.1, 0.1]];
+        let negative = hausman("Hausman", &coef_consistent, &less_precise, &coef_efficient, &vcov_consistent);
+        assert!( matches!(negative, Err(AmitaError::NonPositive { .. })) );
+
+        Ok(())
+    }
+}
@@ -1,7 +1,9 @@
 //! Provides utilities for statistical inference
 
+use std::collections::BTreeMap;
+
 use amita_error::AmitaError;
-use ndarray::Array1;
+use ndarray::{Array1, Array2};
 use polars::frame::DataFrame;
 use polars::prelude::DataFrameJoinOps;
 use polars::prelude::DataType;
@@ -15,28 +17,36 @@ use polars::prelude::JoinType;
 use polars::prelude::Literal;
 use polars::series::Series;
 
-#[derive(Debug)]
+use crate::parallel::{gramian, map_row_blocks};
+
+#[derive(Debug, Clone)]
 pub enum SolverSEType {
     Homoscedastic,
     HC1,
     HC2,
     HC3,
     Clustered { by: Array1<i32> },
+    MultiwayClustered { by: Vec<Array1<i32>> },
+    /// Clustered, as reported by solvers that sum the scores of each cluster
+    /// as the data streams by and keep only the number of clusters, not the
+    /// ids. Solvers given the ids reject it.
+    ClusterSums { n_clusters: usize },
 
     NonRobust, // alias for SolverSEType::Homoscedastic
-    Robust, // alias for SolverSEType::HC3
+    Robust, // alias for SolverSEType::HC1
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ModelSEType {
     Homoscedastic,
     HC1,
     HC2,
     HC3,
     Clustered { by: String },
+    MultiwayClustered { by: Vec<String> },
 
     NonRobust, // alias for ModelSEType::Homoscedastic
-    Robust, // alias for ModelSEType::HC3
+    Robust, // alias for ModelSEType::HC1
 }
 
 impl ModelSEType {
@@ -57,6 +67,13 @@ impl ModelSEType {
                     Self::cluster_col_name_into_array(&by, data)?;
                 Ok( SolverSEType::Clustered { by: arr } )
             },
+
+            ModelSEType::MultiwayClustered { by } => {
+                let arrs = by.iter()
+                    .map(|column| Self::cluster_col_name_into_array(column, data))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok( SolverSEType::MultiwayClustered { by: arrs } )
+            },
         }
     }
 
@@ -92,7 +109,7 @@ impl ModelSEType {
             } )
         }
 
-        let tag = (0..uniques.height()).into_iter().map(|x| x as i32).collect::<Series>();
+        let tag = (0..uniques.height()).map(|x| x as i32).collect::<Series>();
         let tag = 
             uniques
             .clone()
@@ -157,3 +174,124 @@ impl ModelSEType {
     }
 }
 
+
+/// Sandwich covariance B M B of an M-estimator, with bread B and the meat M
+/// built from per-observation scores (n×k) as `se_type` prescribes: robust
+/// with an n/(n - 1) correction, or one- or multi-way clustered. Model-based
+/// types return the bread alone. HC2 and HC3 need leverages, which only
+/// least squares provides.
+pub fn sandwich(
+    bread: &Array2<f64>,
+    scores: &Array2<f64>,
+    se_type: &SolverSEType,
+) -> Result<Array2<f64>, AmitaError> {
+    let n = scores.shape()[0] as f64;
+
+    let meat = match se_type {
+        SolverSEType::Homoscedastic | SolverSEType::NonRobust => return Ok( bread.to_owned() ),
+        SolverSEType::HC1 | SolverSEType::Robust => gramian(scores.view()) * n / (n - 1.),
+        SolverSEType::Clustered { by } => multiway_cluster_meat(scores, std::slice::from_ref(by))?,
+        SolverSEType::MultiwayClustered { by } => multiway_cluster_meat(scores, by)?,
+        SolverSEType::HC2 | SolverSEType::HC3 | SolverSEType::ClusterSums { .. } => {
+            return Err(AmitaError::UnsupportedSEType { se_type: format!("{se_type:?}") })
+        },
+    };
+
+    Ok( bread.dot(&meat).dot(bread) )
+}
+
+/// Cluster-robust "meat" Σ_g s_g s_g' of per-observation scores (n×k),
+/// clustered along one or more dimensions.
+///
+/// With several dimensions, follows Cameron, Gelbach and Miller (2011): the
+/// meats clustered by every intersection of dimensions are added with sign
+/// (-1)^{|S| + 1}, each scaled by G_S / (G_S - 1).
+pub fn multiway_cluster_meat(
+    scores: &Array2<f64>,
+    clusters: &[Array1<i32>],
+) -> Result<Array2<f64>, AmitaError> {
+    if clusters.iter().any(|c| c.len() != scores.shape()[0]) {
+        return Err(AmitaError::NotSameObservations);
+    }
+
+    let k = scores.shape()[1];
+    let mut meat = Array2::zeros((k, k));
+    for subset in 1..(1_usize << clusters.len()) {
+        let dims = (0..clusters.len()).filter(|d| subset & (1 << d) != 0).collect::<Vec<_>>();
+
+        // score sums by cluster within blocks of rows, merged in order
+        let blocks = map_row_blocks(scores.shape()[0], |rows| {
+            let mut sums = BTreeMap::new();
+            for i in rows {
+                let key = dims.iter().map(|d| clusters[*d][i]).collect::<Vec<_>>();
+                *sums.entry(key).or_insert_with(|| Array1::zeros(k)) += &scores.row(i);
+            }
+            sums
+        });
+        let mut sums = BTreeMap::new();
+        for block in blocks {
+            for (key, s) in block {
+                *sums.entry(key).or_insert_with(|| Array1::<f64>::zeros(k)) += &s;
+            }
+        }
+
+        if sums.len() < 2 {
+            return Err(AmitaError::TooFewClusters { n_clusters: sums.len() });
+        }
+        let n_clusters = sums.len() as f64;
+        let mut term = Array2::<f64>::zeros((k, k));
+        for s in sums.values() {
+            let s = s.view().insert_axis(ndarray::Axis(1));
+            term += &s.dot(&s.t());
+        }
+        term *= n_clusters / (n_clusters - 1.);
+
+        if dims.len() % 2 == 1 {
+            meat += &term;
+        } else {
+            meat -= &term;
+        }
+    }
+
+    Ok(meat)
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+
+    #[test]
+    fn test_multiway_cluster_meat() -> Result<(), AmitaError> {
+        let scores = array![[1., 0.5], [-2., 0.1], [0.5, -1.], [0.3, 0.2], [-0.4, 0.6]];
+        let firm = array![0, 0, 1, 1, 2];
+        let year = array![0, 1, 0, 1, 1];
+
+        // one dimension: G/(G - 1) Σ_g s_g s_g'
+        let one_way = multiway_cluster_meat(&scores, std::slice::from_ref(&firm))?;
+        let s_0 = array![-1., 0.6];
+        let s_1 = array![0.8, -0.8];
+        let s_2 = array![-0.4, 0.6];
+        let mut expected = Array2::<f64>::zeros((2, 2));
+        for s in [s_0, s_1, s_2] {
+            let s = s.insert_axis(ndarray::Axis(1));
+            expected += &s.dot(&s.t());
+        }
+        expected *= 1.5;
+        assert!( (one_way - expected).iter().all(|d| d.abs() < 1e-12) );
+
+        // two dimensions: firm + year - firm×year
+        let two_way = multiway_cluster_meat(&scores, &[firm.clone(), year.clone()])?;
+        let intersection = firm.iter().zip(year.iter()).map(|(f, y)| f * 2 + y).collect::<Array1<i32>>();
+        let expected = multiway_cluster_meat(&scores, &[firm])?
+            + multiway_cluster_meat(&scores, &[year])?
+            - multiway_cluster_meat(&scores, &[intersection])?;
+        assert!( (two_way - expected).iter().all(|d| d.abs() < 1e-12) );
+
+        let single = multiway_cluster_meat(&scores, &[Array1::from_iter(0..5), Array1::zeros(5)]);
+        assert!( matches!(single, Err(AmitaError::TooFewClusters { n_clusters: 1 })) );
+
+        Ok(())
+    }
+}
@@ -1,6 +1,180 @@
+//! Iterative optimization shared by the maximum likelihood solvers
 
+use amita_error::AmitaError;
+use ndarray::{Array1, Array2};
 
+use crate::linalg::solve;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TerminationCondition {
+    /// The iteration limit was reached before convergence
     MaxIterReached,
+    /// Step halving found no decrease of the objective along the search
+    /// direction away from the optimum
+    LineSearchFailed,
     SolverConverged,
-}
\ No newline at end of file
+}
+
+impl TerminationCondition {
+    /// Ok if converged, else the error for how the solver stopped after
+    /// `n_iter` iterations
+    pub fn check(self, n_iter: u64) -> Result<(), AmitaError> {
+        match self {
+            TerminationCondition::SolverConverged => Ok(()),
+            TerminationCondition::MaxIterReached => Err(AmitaError::NotConverged { iterations: n_iter }),
+            TerminationCondition::LineSearchFailed => Err(AmitaError::SolverFailed {
+                message: format!("step halving found no decrease of the objective at iteration {n_iter}"),
+            }),
+        }
+    }
+}
+
+/// State of a finished Newton–Raphson run
+#[derive(Debug, Clone)]
+pub struct NewtonRaphsonState {
+    pub param: Array1<f64>,
+    pub cost: f64,
+    pub gradient: Array1<f64>,
+    pub hessian: Array2<f64>,
+    pub n_iter: u64,
+    pub termination: TerminationCondition,
+}
+
+impl NewtonRaphsonState {
+    /// Max-norm of the final gradient
+    pub fn grad_norm(&self) -> f64 {
+        max_norm(&self.gradient)
+    }
+}
+
+/// Newton–Raphson minimizer with step halving.
+///
+/// The objective returns the cost, its gradient and its Hessian at a given
+/// parameter. Iterations stop once the max-norm of the gradient falls below
+/// the tolerance, after `max_iter` iterations, or when step halving finds
+/// no decrease, which counts as convergence if the gradient is below the
+/// square root of the tolerance.
+#[derive(Debug, Clone)]
+pub struct NewtonRaphson {
+    max_iter: u64,
+    tolerance: f64,
+    max_step_halving: u32,
+}
+
+impl Default for NewtonRaphson {
+    fn default() -> Self {
+        Self::new(100, 1e-8)
+    }
+}
+
+impl NewtonRaphson {
+    pub fn new(max_iter: u64, tolerance: f64) -> Self {
+        Self { max_iter, tolerance, max_step_halving: 40 }
+    }
+
+    pub fn minimize<F>(
+        &self,
+        init_param: Array1<f64>,
+        objective: F,
+    ) -> Result<NewtonRaphsonState, AmitaError>
+    where
+        F: Fn(&Array1<f64>) -> Result<(f64, Array1<f64>, Array2<f64>), AmitaError>,
+    {
+        let mut param = init_param;
+        let (mut cost, mut gradient, mut hessian) = objective(&param)?;
+
+        if !cost.is_finite() {
+            return Err(AmitaError::SolverFailed { message: "cost is not finite at the initial parameter".to_string() });
+        }
+
+        let mut n_iter = 0;
+        let mut termination = TerminationCondition::MaxIterReached;
+
+        while n_iter < self.max_iter {
+            if max_norm(&gradient) < self.tolerance {
+                termination = TerminationCondition::SolverConverged;
+                break;
+            }
+            n_iter += 1;
+
+            let direction = solve(&hessian, &gradient, "Hessian")?;
+
+            let mut step = 1.;
+            let mut halvings = 0;
+            loop {
+                let candidate = &param - &(&direction * step);
+                let (c_cost, c_gradient, c_hessian) = objective(&candidate)?;
+
+                if c_cost.is_finite() && c_cost <= cost {
+                    param = candidate;
+                    cost = c_cost;
+                    gradient = c_gradient;
+                    hessian = c_hessian;
+                    break;
+                }
+
+                halvings += 1;
+                if halvings > self.max_step_halving {
+                    // no further decrease is attainable along the Newton direction
+                    let termination = if max_norm(&gradient) < self.tolerance.sqrt() {
+                        TerminationCondition::SolverConverged
+                    } else {
+                        TerminationCondition::LineSearchFailed
+                    };
+                    return Ok( NewtonRaphsonState { param, cost, gradient, hessian, n_iter, termination } );
+                }
+                step /= 2.;
+            }
+        }
+
+        if max_norm(&gradient) < self.tolerance {
+            termination = TerminationCondition::SolverConverged;
+        }
+
+        Ok( NewtonRaphsonState { param, cost, gradient, hessian, n_iter, termination } )
+    }
+}
+
+fn max_norm(x: &Array1<f64>) -> f64 {
+    x.iter().fold(0., |acc: f64, x| acc.max(x.abs()))
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+
+    #[test]
+    fn test_newton_raphson_quadratic() -> Result<(), AmitaError> {
+        // f(b) = (b0 - 1)^2 + 2 (b1 + 3)^2 + b0 b1
+        let objective = |b: &Array1<f64>| {
+            let cost = (b[0] - 1.).powi(2) + 2. * (b[1] + 3.).powi(2) + b[0] * b[1];
+            let gradient = array![2. * (b[0] - 1.) + b[1], 4. * (b[1] + 3.) + b[0]];
+            let hessian = array![[2., 1.], [1., 4.]];
+            Ok( (cost, gradient, hessian) )
+        };
+
+        let state = NewtonRaphson::default().minimize(array![0., 0.], objective)?;
+
+        assert_eq!(state.termination, TerminationCondition::SolverConverged);
+        assert!( (state.param[0] - 20. / 7.).abs() < 1e-10 );
+        assert!( (state.param[1] + 26. / 7.).abs() < 1e-10 );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_line_search_failed() -> Result<(), AmitaError> {
+        // a gradient of the wrong sign makes every Newton step an ascent
+        let objective = |b: &Array1<f64>| Ok( (b[0].powi(2), array![- 2. * b[0]], array![[2.]]) );
+
+        let state = NewtonRaphson::default().minimize(array![1.], objective)?;
+
+        assert_eq!(state.termination, TerminationCondition::LineSearchFailed);
+        assert_eq!(state.param, array![1.]);
+        assert!( matches!(state.termination.check(state.n_iter), Err(AmitaError::SolverFailed { .. })) );
+
+        Ok(())
+    }
+}
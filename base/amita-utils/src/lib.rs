@@ -1,5 +1,17 @@
 
+pub mod bootstrap;
+pub mod delta;
+pub mod fixed_effects;
+pub mod frame;
+pub mod hypothesis;
 pub mod inference;
 pub mod iterations;
+pub mod likelihood;
+pub mod linalg;
 pub mod math;
-pub mod traits;
\ No newline at end of file
+pub mod parallel;
+pub mod restrictions;
+pub mod summary;
+#[cfg(test)]
+mod test_fixtures;
+pub mod traits;
@@ -0,0 +1,79 @@
+//! Maximum likelihood estimation from per-observation log-likelihood
+//! contributions and their analytic scores
+
+use amita_error::AmitaError;
+use linfa_linalg::cholesky::Cholesky;
+use ndarray::{Array1, Array2, Axis};
+
+use crate::inference::{sandwich, SolverSEType};
+use crate::iterations::{NewtonRaphson, NewtonRaphsonState};
+use crate::linalg::inverse;
+use crate::math::numerical_hessian;
+
+/// Maximizes Σ_i l_i(θ) by Newton–Raphson, given the per-observation
+/// log-likelihood contributions l_i and scores ∂l_i/∂θ (n×p).
+///
+/// The Hessian is differenced from the analytic scores. Where it is not
+/// positive definite, away from the optimum of the non-concave mixtures,
+/// it is shifted towards the identity so that Newton steps keep descending.
+pub fn maximize<F>(
+    init: Array1<f64>,
+    max_iter: u64,
+    max_tolerance: f64,
+    loglik_obs: F,
+) -> Result<NewtonRaphsonState, AmitaError>
+where
+    F: Fn(&Array1<f64>) -> (Array1<f64>, Array2<f64>),
+{
+    let objective = |param: &Array1<f64>| {
+        let (loglik, scores) = loglik_obs(param);
+        let n = loglik.len() as f64;
+
+        let gradient = - scores.sum_axis(Axis(0)) / n;
+        let hessian = numerical_hessian(|p| - loglik_obs(p).1.sum_axis(Axis(0)) / n, param);
+
+        Ok( (- loglik.sum() / n, gradient, positive_definite(hessian)) )
+    };
+
+    let state = NewtonRaphson::new(max_iter, max_tolerance).minimize(init, objective)?;
+
+    state.termination.check(state.n_iter)?;
+
+    Ok(state)
+}
+
+/// Covariance of the maximum likelihood estimator at `param`: the inverse
+/// information, or the sandwich H^{-1} (Σ s s') H^{-1} with robust or
+/// one- or multi-way cluster-summed scores. HC2 and HC3 have no likelihood
+/// analogue.
+pub fn ml_vcov<F>(
+    param: &Array1<f64>,
+    se_type: &SolverSEType,
+    loglik_obs: F,
+) -> Result<Array2<f64>, AmitaError>
+where
+    F: Fn(&Array1<f64>) -> (Array1<f64>, Array2<f64>),
+{
+    let (_, scores) = loglik_obs(param);
+
+    let information = numerical_hessian(|p| - loglik_obs(p).1.sum_axis(Axis(0)), param);
+    let bread = inverse(&information, "information matrix")?;
+
+    sandwich(&bread, &scores, se_type)
+}
+
+fn positive_definite(hessian: Array2<f64>) -> Array2<f64> {
+    if hessian.cholesky().is_ok() {
+        return hessian;
+    }
+
+    let scale = hessian.diag().iter().fold(0., |acc: f64, x| acc.max(x.abs())).max(1e-8);
+    let mut shift = 1e-6 * scale;
+    loop {
+        let shifted = &hessian + &(Array2::<f64>::eye(hessian.shape()[0]) * shift);
+        if shifted.cholesky().is_ok() || !shift.is_finite() {
+            return shifted;
+        }
+        shift *= 10.;
+    }
+}
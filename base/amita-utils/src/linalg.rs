@@ -0,0 +1,326 @@
+//! Thin wrappers over `linfa_linalg` that map failures into `AmitaError`,
+//! and a rank-revealing QR decomposition it lacks
+
+use amita_error::AmitaError;
+use linfa_linalg::eigh::Eigh;
+use linfa_linalg::qr::QR;
+use ndarray::{s, Array1, Array2, Axis, ShapeBuilder, Zip};
+
+/// Inverse of a square matrix via QR decomposition
+pub fn inverse(
+    matrix: &Array2<f64>,
+    matrix_name: &str,
+) -> Result<Array2<f64>, AmitaError> {
+    matrix
+        .qr().map_err(|_| AmitaError::NotQRDecomposable {
+            matrix_name: matrix_name.to_string()
+        })?
+        .inverse().map_err(|_| AmitaError::NotInvertible {
+            matrix_name: matrix_name.to_string()
+        })
+}
+
+/// Solves `matrix * x = b` for a square `matrix` via QR decomposition
+pub fn solve(
+    matrix: &Array2<f64>,
+    b: &Array1<f64>,
+    matrix_name: &str,
+) -> Result<Array1<f64>, AmitaError> {
+    let decomp = matrix
+        .qr().map_err(|_| AmitaError::NotQRDecomposable {
+            matrix_name: matrix_name.to_string()
+        })?;
+
+    if !decomp.is_invertible() {
+        return Err(AmitaError::NotInvertible { matrix_name: matrix_name.to_string() });
+    }
+
+    let x = decomp
+        .solve(&b.view().insert_axis(Axis(1))).map_err(|_| AmitaError::NotInvertible {
+            matrix_name: matrix_name.to_string()
+        })?;
+
+    Ok( x.remove_axis(Axis(1)) )
+}
+
+/// Natural log of the absolute determinant of a square matrix via QR decomposition
+pub fn log_abs_det(
+    matrix: &Array2<f64>,
+    matrix_name: &str,
+) -> Result<f64, AmitaError> {
+    let r = matrix
+        .qr().map_err(|_| AmitaError::NotQRDecomposable {
+            matrix_name: matrix_name.to_string()
+        })?
+        .into_r();
+
+    Ok( r.diag().iter().map(|x| x.abs().ln()).sum() )
+}
+
+/// Householder QR decomposition with limited column pivoting, as in
+/// LINPACK's `dqrdc2`: a column whose norm orthogonal to the columns before
+/// it falls below `tolerance` times its own norm is moved to the end, so the
+/// first `rank` pivots are linearly independent columns in their original
+/// order and the rest are collinear with them
+#[derive(Debug, Clone)]
+pub struct PivotedQR {
+    /// Upper-triangular factor of the first `rank` pivoted columns
+    pub r: Array2<f64>,
+    /// Original index of each column of the pivoted matrix
+    pub pivots: Vec<usize>,
+    pub rank: usize,
+}
+
+pub fn pivoted_qr(matrix: &Array2<f64>, tolerance: f64) -> PivotedQR {
+    let mut a = Array2::zeros(matrix.raw_dim().f());
+    a.assign(matrix);
+    householder_pivoted(a, None, tolerance)
+}
+
+/// Pivoted QR of `matrix` that applies the same reflections to `rhs`,
+/// returning the first `rank` entries of Q'rhs, so least squares needs
+/// neither Q nor a copy of `matrix`, whose storage it reuses when
+/// column-major
+pub fn pivoted_qr_with_rhs(
+    matrix: Array2<f64>,
+    mut rhs: Array1<f64>,
+    tolerance: f64,
+) -> Result<(PivotedQR, Array1<f64>), AmitaError> {
+    if rhs.len() != matrix.shape()[0] {
+        return Err(AmitaError::NotSameObservations);
+    }
+
+    let a = if matrix.t().is_standard_layout() {
+        matrix
+    } else {
+        let mut a = Array2::zeros(matrix.raw_dim().f());
+        a.assign(&matrix);
+        a
+    };
+
+    let qr = householder_pivoted(a, Some(&mut rhs), tolerance);
+    let qty = rhs.slice(s![..qr.rank]).to_owned();
+    Ok( (qr, qty) )
+}
+
+/// The pivoted QR of X from X'X alone, for data seen only through its Gram
+/// matrix: a Cholesky decomposition that omits, as `pivoted_qr` would, each
+/// column whose squared norm orthogonal to those kept before it falls below
+/// `tolerance`^2 times its own. R is the same up to the signs of its rows.
+pub fn pivoted_qr_from_gramian(gramian: &Array2<f64>, tolerance: f64) -> PivotedQR {
+    let k = gramian.shape()[0];
+    let mut r = Array2::<f64>::zeros((k, k));
+    let mut kept = Vec::with_capacity(k);
+    let mut omitted = vec![];
+
+    for j in 0..k {
+        // column j of R, solving R' r_j = (X'X)_{kept, j} by forward substitution
+        let rank = kept.len();
+        let mut column = Array1::<f64>::zeros(rank);
+        for a in 0..rank {
+            let projection = (0..a).map(|b| r[[b, a]] * column[b]).sum::<f64>();
+            column[a] = (gramian[[kept[a], j]] - projection) / r[[a, a]];
+        }
+
+        let norm_sq = gramian[[j, j]];
+        let orthogonal_sq = norm_sq - column.dot(&column);
+        if norm_sq == 0. || orthogonal_sq <= tolerance.powi(2) * norm_sq {
+            omitted.push(j);
+            continue;
+        }
+
+        r.slice_mut(s![..rank, rank]).assign(&column);
+        r[[rank, rank]] = orthogonal_sq.sqrt();
+        kept.push(j);
+    }
+
+    let rank = kept.len();
+    let r = r.slice(s![..rank, ..rank]).to_owned();
+    kept.extend(omitted);
+
+    PivotedQR { r, pivots: kept, rank }
+}
+
+/// Reflects column-major `a` in place, column by column, and `rhs` with it
+fn householder_pivoted(
+    mut a: Array2<f64>,
+    mut rhs: Option<&mut Array1<f64>>,
+    tolerance: f64,
+) -> PivotedQR {
+    let (n, k) = a.dim();
+    let norms = a.columns().into_iter().map(|c| c.dot(&c).sqrt()).collect::<Vec<_>>();
+    let mut pivots = (0..k).collect::<Vec<_>>();
+
+    let mut rank = k;
+    let mut j = 0;
+    while j < rank && j < n {
+        let column = a.slice(s![j.., j]);
+        let norm = column.dot(&column).sqrt();
+
+        if norm <= tolerance * norms[pivots[j]] || norms[pivots[j]] == 0. {
+            // defer the collinear column to the end
+            for l in j..k - 1 {
+                let (mut current, mut next) = a.multi_slice_mut((s![.., l], s![.., l + 1]));
+                Zip::from(&mut current).and(&mut next).for_each(std::mem::swap);
+            }
+            pivots[j..].rotate_left(1);
+            rank -= 1;
+            continue;
+        }
+
+        // reflect a[j.., j] onto -sign(a_jj) |a[j.., j]| e_1
+        let alpha = if a[[j, j]] >= 0. { -norm } else { norm };
+        let mut v = a.slice(s![j.., j]).to_owned();
+        v[0] -= alpha;
+        let v_norm_sq = v.dot(&v);
+        if v_norm_sq > 0. {
+            for l in j..k {
+                let projection = 2. * v.dot(&a.slice(s![j.., l])) / v_norm_sq;
+                a.slice_mut(s![j.., l]).scaled_add(-projection, &v);
+            }
+            if let Some(rhs) = rhs.as_deref_mut() {
+                let projection = 2. * v.dot(&rhs.slice(s![j..])) / v_norm_sq;
+                rhs.slice_mut(s![j..]).scaled_add(-projection, &v);
+            }
+        }
+        j += 1;
+    }
+    let rank = j;
+
+    let mut r = a.slice(s![..rank, ..rank]).to_owned();
+    for row in 0..rank {
+        for column in 0..row {
+            r[[row, column]] = 0.;
+        }
+    }
+
+    PivotedQR { r, pivots, rank }
+}
+
+/// Solves `r * x = b` for an upper-triangular `r` by back substitution
+pub fn solve_upper_triangular(
+    r: &Array2<f64>,
+    b: &Array1<f64>,
+    matrix_name: &str,
+) -> Result<Array1<f64>, AmitaError> {
+    let k = b.len();
+    if r.shape() != [k, k] {
+        return Err(AmitaError::NotSameObservations);
+    }
+
+    let mut x = b.clone();
+    for i in (0..k).rev() {
+        if r[[i, i]] == 0. {
+            return Err(AmitaError::NotInvertible { matrix_name: matrix_name.to_string() });
+        }
+        let known = r.slice(s![i, i + 1..]).dot(&x.slice(s![i + 1..]));
+        x[i] = (x[i] - known) / r[[i, i]];
+    }
+
+    Ok(x)
+}
+
+/// Inverse of an upper-triangular `r`, itself upper triangular, by back
+/// substitution on each column of the identity
+pub fn upper_triangular_inverse(
+    r: &Array2<f64>,
+    matrix_name: &str,
+) -> Result<Array2<f64>, AmitaError> {
+    let k = r.shape()[0];
+    let mut inverse = Array2::zeros((k, k));
+    for j in 0..k {
+        let mut e = Array1::zeros(j + 1);
+        e[j] = 1.;
+        let column = solve_upper_triangular(&r.slice(s![..=j, ..=j]).to_owned(), &e, matrix_name)?;
+        inverse.slice_mut(s![..=j, j]).assign(&column);
+    }
+
+    Ok(inverse)
+}
+
+/// Generalized inverse of a symmetric matrix over its positive eigenvalues,
+/// Σ_{λ_j > 0} v_j v_j' / λ_j, with the number of them. Eigenvalues within
+/// rounding error of zero, relative to the largest one, count as zero.
+pub fn positive_part_inverse(
+    matrix: &Array2<f64>,
+    matrix_name: &str,
+) -> Result<(Array2<f64>, usize), AmitaError> {
+    let symmetric = (matrix + &matrix.t()) / 2.;
+    let (values, vectors) = symmetric.eigh().map_err(|_| AmitaError::NotInvertible {
+        matrix_name: matrix_name.to_string()
+    })?;
+
+    let largest = values.iter().fold(0., |acc: f64, x| acc.max(x.abs()));
+    let tolerance = largest * values.len() as f64 * f64::EPSILON;
+    let positive = (0..values.len()).filter(|j| values[*j] > tolerance).collect::<Vec<_>>();
+
+    let vectors = vectors.select(Axis(1), &positive);
+    let scaled = &vectors / &values.select(Axis(0), &positive);
+    Ok( (scaled.dot(&vectors.t()), positive.len()) )
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+
+    #[test]
+    fn test_pivoted_qr() {
+        // a dummy trap, d1 + d2 = const, and a duplicated column
+        let x = array![
+            [1., 1., 0., 0.5, 0.5],
+            [1., 0., 1., 1.5, 1.5],
+            [1., 1., 0., -0.3, -0.3],
+            [1., 0., 1., 2.2, 2.2],
+            [1., 1., 0., 0.9, 0.9],
+        ];
+
+        let qr = pivoted_qr(&x, 1e-7);
+        assert_eq!(qr.rank, 3);
+        assert_eq!(qr.pivots, vec![0, 1, 3, 2, 4]);
+
+        // R'R is the Gram matrix of the kept columns
+        let kept = x.select(Axis(1), &qr.pivots[..qr.rank]);
+        let difference = qr.r.t().dot(&qr.r) - kept.t().dot(&kept);
+        assert!( difference.iter().all(|d| d.abs() < 1e-10) );
+
+        let full_rank = pivoted_qr(&kept, 1e-7);
+        assert_eq!(full_rank.rank, 3);
+        assert_eq!(full_rank.pivots, vec![0, 1, 2]);
+
+        // the same columns and R'R from X'X alone
+        let from_gramian = pivoted_qr_from_gramian(&x.t().dot(&x), 1e-7);
+        assert_eq!(from_gramian.rank, 3);
+        assert_eq!(from_gramian.pivots, qr.pivots);
+        let difference = from_gramian.r.t().dot(&from_gramian.r) - kept.t().dot(&kept);
+        assert!( difference.iter().all(|d| d.abs() < 1e-10) );
+
+        // least squares on the kept columns from R and Q'y alone
+        let y = array![1.2, 3.1, 0.8, 4.0, 2.2];
+        let (qr, qty) = pivoted_qr_with_rhs(x.clone(), y.clone(), 1e-7).unwrap();
+        let coef = solve_upper_triangular(&qr.r, &qty, "R").unwrap();
+        let gramian = kept.t().dot(&kept);
+        let expected = solve(&gramian, &kept.t().dot(&y), "X'X").unwrap();
+        assert!( (&coef - &expected).iter().all(|d| d.abs() < 1e-10) );
+
+        let r_inverse = upper_triangular_inverse(&qr.r, "R").unwrap();
+        let identity = qr.r.dot(&r_inverse);
+        assert!( (identity - Array2::<f64>::eye(3)).iter().all(|d| d.abs() < 1e-12) );
+        assert!( r_inverse[[2, 0]] == 0. && r_inverse[[1, 0]] == 0. );
+    }
+
+    #[test]
+    fn test_positive_part_inverse() {
+        let positive_definite = array![[2., 0.5], [0.5, 1.]];
+        let (inverse_pd, rank) = positive_part_inverse(&positive_definite, "A").unwrap();
+        assert_eq!(rank, 2);
+        assert!( (inverse_pd - inverse(&positive_definite, "A").unwrap()).iter().all(|d| d.abs() < 1e-12) );
+
+        // eigenvalues 3 and -1 along (1, 1) and (1, -1)
+        let indefinite = array![[1., 2.], [2., 1.]];
+        let (inverse_positive, rank) = positive_part_inverse(&indefinite, "A").unwrap();
+        assert_eq!(rank, 1);
+        assert!( (inverse_positive - array![[1., 1.], [1., 1.]] / 6.).iter().all(|d| d.abs() < 1e-12) );
+    }
+}
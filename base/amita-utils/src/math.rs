@@ -1,5 +1,44 @@
 use std::f64::consts::E;
 
+use ndarray::{Array1, Array2};
+
 pub fn sigmoid(x: f64) -> f64 {
     1. / ( 1. + E.powf(-x) )
-}
\ No newline at end of file
+}
+
+/// ln(1 + e^x), evaluated without overflow for large |x|
+pub fn softplus(x: f64) -> f64 {
+    x.max(0.) + ( -x.abs() ).exp().ln_1p()
+}
+
+/// Jacobian of `f` at `x` by central differences, with step scaled to |x_j|
+pub fn numerical_jacobian<F>(f: F, x: &Array1<f64>) -> Array2<f64>
+where
+    F: Fn(&Array1<f64>) -> Array1<f64>,
+{
+    let f_x = f(x);
+    let mut jacobian = Array2::zeros((f_x.len(), x.len()));
+
+    for j in 0..x.len() {
+        let h = 1e-6 * x[j].abs().max(1.);
+        let mut up = x.clone();
+        up[j] += h;
+        let mut down = x.clone();
+        down[j] -= h;
+
+        let column = ( f(&up) - f(&down) ) / (2. * h);
+        jacobian.column_mut(j).assign(&column);
+    }
+
+    jacobian
+}
+
+/// Hessian of a scalar function from its analytic gradient, by central
+/// differences of the gradient, symmetrized
+pub fn numerical_hessian<G>(gradient: G, x: &Array1<f64>) -> Array2<f64>
+where
+    G: Fn(&Array1<f64>) -> Array1<f64>,
+{
+    let jacobian = numerical_jacobian(gradient, x);
+    ( &jacobian + &jacobian.t() ) / 2.
+}
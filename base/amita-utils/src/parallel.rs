@@ -0,0 +1,105 @@
+//! Embarrassingly parallel loops, run on rayon's thread pool with the
+//! `parallel` feature and serially without it. Work is split into pieces
+//! fixed by the data, never by the number of threads, and their results are
+//! combined in order, so every result is bit-for-bit the same either way.
+
+use ndarray::{s, Array2, ArrayView1, ArrayView2, Axis};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Rows per block of the blocked reductions over observations
+pub const BLOCK_ROWS: usize = 4096;
+
+/// f(0), ..., f(n - 1), in order
+pub fn map_indices<T, F>(n: usize, f: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(usize) -> T + Sync + Send,
+{
+    #[cfg(feature = "parallel")]
+    {
+        (0..n).into_par_iter().map(f).collect()
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    {
+        (0..n).map(f).collect()
+    }
+}
+
+/// f on each block of `BLOCK_ROWS` consecutive rows out of `n_rows`, given
+/// the block's range of rows, in order
+pub fn map_row_blocks<T, F>(n_rows: usize, f: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(std::ops::Range<usize>) -> T + Sync + Send,
+{
+    map_indices(n_rows.div_ceil(BLOCK_ROWS), |block| {
+        f(block * BLOCK_ROWS..((block + 1) * BLOCK_ROWS).min(n_rows))
+    })
+}
+
+/// X' diag(w) X = Σ_i w_i x_i x_i', summed over blocks of rows
+pub fn weighted_gramian(x: ArrayView2<f64>, weights: ArrayView1<f64>) -> Array2<f64> {
+    let k = x.shape()[1];
+    map_row_blocks(x.shape()[0], |rows| {
+        let x = x.slice(s![rows.clone(), ..]);
+        let weighted = &x * &weights.slice(s![rows]).insert_axis(Axis(1));
+        x.t().dot(&weighted)
+    })
+    .into_iter()
+    .fold(Array2::zeros((k, k)), |sum, block| sum + block)
+}
+
+/// X'X = Σ_i x_i x_i', summed over blocks of rows
+pub fn gramian(x: ArrayView2<f64>) -> Array2<f64> {
+    let k = x.shape()[1];
+    map_row_blocks(x.shape()[0], |rows| {
+        let x = x.slice(s![rows, ..]);
+        x.t().dot(&x)
+    })
+    .into_iter()
+    .fold(Array2::zeros((k, k)), |sum, block| sum + block)
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::Array1;
+
+    use super::*;
+
+    fn data() -> (Array2<f64>, Array1<f64>) {
+        let n = 3 * BLOCK_ROWS + 17;
+        let x = Array2::from_shape_fn((n, 3), |(i, j)| ((i * (j + 3)) % 11) as f64 / 7. - 0.6);
+        let w = Array1::from_shape_fn(n, |i| 0.5 + (i % 5) as f64);
+        (x, w)
+    }
+
+    #[test]
+    fn test_blocked_gramians() {
+        let (x, w) = data();
+
+        let direct = x.t().dot(&(&x * &w.view().insert_axis(Axis(1))));
+        let blocked = weighted_gramian(x.view(), w.view());
+        assert!( (&blocked - &direct).iter().all(|d| d.abs() < 1e-8) );
+        assert_eq!(gramian(x.view()), weighted_gramian(x.view(), Array1::ones(x.shape()[0]).view()));
+
+        let sizes = map_row_blocks(x.shape()[0], |rows| rows.len());
+        assert_eq!(sizes, vec![BLOCK_ROWS, BLOCK_ROWS, BLOCK_ROWS, 17]);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_same_results_on_any_number_of_threads() {
+        let (x, w) = data();
+        let on_threads = |n_threads: usize| {
+            rayon::ThreadPoolBuilder::new().num_threads(n_threads).build().unwrap()
+                .install(|| weighted_gramian(x.view(), w.view()))
+        };
+
+        let serial = on_threads(1);
+        for n_threads in [2, 3, 8] {
+            assert_eq!(on_threads(n_threads), serial);
+        }
+    }
+}
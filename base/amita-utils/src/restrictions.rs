@@ -0,0 +1,236 @@
+//! Linear restrictions R β = q on the coefficients of a model, given as
+//! matrices or parsed from equations over coefficient names.
+
+use amita_error::AmitaError;
+use ndarray::{Array1, Array2};
+
+#[derive(Debug, Clone)]
+pub struct LinearRestrictions {
+    r: Array2<f64>,
+    q: Array1<f64>,
+}
+
+impl LinearRestrictions {
+    /// Restrictions R β = q, one row of R per restriction
+    pub fn new(r: &Array2<f64>, q: &Array1<f64>) -> Result<Self, AmitaError> {
+        if r.shape()[0] != q.len() {
+            return Err(AmitaError::NotSameObservations);
+        }
+
+        Ok( Self { r: r.to_owned(), q: q.to_owned() } )
+    }
+
+    /// Restrictions written as comma-separated linear equations over the
+    /// coefficient `names`, such as `"x1 + x2 = 1"` or `"x1 = x2 = 0"`,
+    /// the latter chaining into x1 = x2 and x2 = 0. Terms are names,
+    /// numbers, or products of the two such as `2*x1` or `0.5 x1`; names
+    /// with other characters than letters, digits, `_` and `.` go within
+    /// backquotes, as in `` `treat*post` = 0 ``.
+    pub fn parse(hypothesis: &str, names: &[String]) -> Result<Self, AmitaError> {
+        let invalid = |message: &str| AmitaError::InvalidHypothesis {
+            hypothesis: hypothesis.to_string(),
+            message: message.to_string(),
+        };
+
+        // split the tokens rather than the text, so that quoted names may
+        // contain commas and equals signs
+        let tokens = tokenize(hypothesis)?;
+
+        let mut rows = vec![];
+        let mut q = vec![];
+        for equation in tokens.split(|token| *token == Token::Comma) {
+            let sides = equation.split(|token| *token == Token::Equals)
+                .map(|side| parse_expression(side, names, hypothesis))
+                .collect::<Result<Vec<_>, AmitaError>>()?;
+            if sides.len() < 2 {
+                return Err(invalid("each restriction needs an equals sign"));
+            }
+
+            for pair in sides.windows(2) {
+                let (left, right) = (&pair[0], &pair[1]);
+                let row = &left.0 - &right.0;
+                if row.iter().all(|c| *c == 0.) {
+                    return Err(invalid("a restriction involves no coefficients"));
+                }
+                rows.push(row);
+                q.push(right.1 - left.1);
+            }
+        }
+
+        let mut r = Array2::zeros((rows.len(), names.len()));
+        for (mut r_row, row) in r.rows_mut().into_iter().zip(rows.iter()) {
+            r_row.assign(row);
+        }
+
+        Ok( Self { r, q: Array1::from_vec(q) } )
+    }
+
+    pub fn r(&self) -> &Array2<f64> {
+        &self.r
+    }
+
+    pub fn q(&self) -> &Array1<f64> {
+        &self.q
+    }
+
+    pub fn n_restrictions(&self) -> usize {
+        self.q.len()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Plus,
+    Minus,
+    Times,
+    Equals,
+    Comma,
+}
+
+fn tokenize(hypothesis: &str) -> Result<Vec<Token>, AmitaError> {
+    let invalid = |message: String| AmitaError::InvalidHypothesis { hypothesis: hypothesis.to_string(), message };
+
+    let mut tokens = vec![];
+    let mut chars = hypothesis.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' | '\t' => {},
+            '+' => tokens.push(Token::Plus),
+            '-' => tokens.push(Token::Minus),
+            '*' => tokens.push(Token::Times),
+            '=' => tokens.push(Token::Equals),
+            ',' => tokens.push(Token::Comma),
+            '`' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('`') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(invalid("a backquoted name is not closed".to_string())),
+                    }
+                }
+                tokens.push(Token::Name(name));
+            },
+            c if c.is_ascii_digit() || c == '.' => {
+                let mut number = c.to_string();
+                while let Some(next) = chars.next_if(|c| c.is_ascii_digit() || *c == '.') {
+                    number.push(next);
+                }
+                let value = number.parse::<f64>().map_err(|_| invalid(format!("{number:?} is not a number")))?;
+                tokens.push(Token::Number(value));
+            },
+            c if c.is_alphabetic() || c == '_' => {
+                let mut name = c.to_string();
+                while let Some(next) = chars.next_if(|c| c.is_alphanumeric() || *c == '_' || *c == '.') {
+                    name.push(next);
+                }
+                tokens.push(Token::Name(name));
+            },
+            c => return Err(invalid(format!("unexpected character {c:?}"))),
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Coefficients on β and the constant of one side of an equation
+fn parse_expression(
+    tokens: &[Token],
+    names: &[String],
+    hypothesis: &str,
+) -> Result<(Array1<f64>, f64), AmitaError> {
+    let invalid = |message: &str| AmitaError::InvalidHypothesis {
+        hypothesis: hypothesis.to_string(),
+        message: message.to_string(),
+    };
+
+    if tokens.is_empty() {
+        return Err(invalid("a side of an equation is empty"));
+    }
+
+    let mut coef = Array1::zeros(names.len());
+    let mut constant = 0.;
+
+    let mut position = 0;
+    while position < tokens.len() {
+        let mut sign = 1.;
+        while let Some(token @ (Token::Plus | Token::Minus)) = tokens.get(position) {
+            if *token == Token::Minus {
+                sign = - sign;
+            }
+            position += 1;
+        }
+
+        // a term is a number, a name, or a number times a name
+        let mut value = sign;
+        let mut name = None;
+        let mut expects_factor = true;
+        while expects_factor {
+            match tokens.get(position) {
+                Some(Token::Number(number)) => value *= number,
+                Some(Token::Name(n)) if name.is_none() => name = Some(n.clone()),
+                Some(Token::Name(_)) => return Err(invalid("products of coefficients are not linear")),
+                _ => return Err(invalid("expected a number or a coefficient name")),
+            }
+            position += 1;
+
+            expects_factor = match tokens.get(position) {
+                Some(Token::Times) => {
+                    position += 1;
+                    true
+                },
+                Some(Token::Name(_) | Token::Number(_)) => true,
+                _ => false,
+            };
+        }
+
+        match name {
+            Some(name) => {
+                let column = names.iter().position(|n| *n == name)
+                    .ok_or(AmitaError::ColumnNotFound { column: name })?;
+                coef[column] += value;
+            },
+            None => constant += value,
+        }
+    }
+
+    Ok( (coef, constant) )
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+
+    #[test]
+    fn test_parse() -> Result<(), AmitaError> {
+        let names = ["const", "x1", "x2", "treat*post"].map(String::from);
+
+        let restrictions = LinearRestrictions::parse("x1 + x2 = 1", &names)?;
+        assert_eq!(restrictions.r(), array![[0., 1., 1., 0.]]);
+        assert_eq!(restrictions.q(), array![1.]);
+
+        // chained equalities, products, constants on both sides, quoting
+        let restrictions = LinearRestrictions::parse("x1 = x2 = 0, 2*x1 - 0.5 x2 + 1 = `treat*post` - 3", &names)?;
+        assert_eq!(restrictions.r(), array![[0., 1., -1., 0.], [0., 0., 1., 0.], [0., 2., -0.5, -1.]]);
+        assert_eq!(restrictions.q(), array![0., 0., -4.]);
+
+        // quoted names may hold the separators
+        let names = ["const", "f(a, b)", "x=y"].map(String::from);
+        let restrictions = LinearRestrictions::parse("`f(a, b)` = `x=y`, `x=y` = 1", &names)?;
+        assert_eq!(restrictions.r(), array![[0., 1., -1.], [0., 0., 1.]]);
+        assert_eq!(restrictions.q(), array![0., 1.]);
+        assert!( matches!(LinearRestrictions::parse("`x=y = 1", &names), Err(AmitaError::InvalidHypothesis { .. })) );
+
+        let names = ["const", "x1", "x2", "treat*post"].map(String::from);
+        assert!( matches!(LinearRestrictions::parse("x3 = 0", &names), Err(AmitaError::ColumnNotFound { .. })) );
+        for invalid in ["x1 + x2", "x1 * x2 = 0", "1 = 2", "x1 = ", "x1 + = 0", "x1 ^ 2 = 0"] {
+            assert!( matches!(LinearRestrictions::parse(invalid, &names), Err(AmitaError::InvalidHypothesis { .. })) );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,60 @@
+//! Plain-text tables of fitted results
+
+use std::fmt::Write;
+
+use amita_error::AmitaError;
+
+use crate::traits::BaseResults;
+
+/// One row per coefficient with its standard error, test statistic and p
+/// value: t with small-sample degrees of freedom, z otherwise. Omitted
+/// coefficients show as NaN.
+pub fn coef_table<R: BaseResults + ?Sized>(results: &R) -> Result<String, AmitaError> {
+    let names = results.coef_names()?;
+    let coef = results.coef()?;
+    let se = results.se()?;
+    let t = results.t()?;
+    let p_vals = results.p_vals()?;
+
+    let statistic = if results.small_sample_df().is_some() { "t" } else { "z" };
+    let width = names.iter().map(|name| name.chars().count()).max().unwrap_or(0).max(5);
+
+    let mut table = String::new();
+    let _ = writeln!(
+        table,
+        "{:<width$} {:>12} {:>12} {:>10} {:>8}",
+        "", "coef", "std err", statistic, format!("P>|{statistic}|"),
+    );
+    for j in 0..coef.len() {
+        let _ = writeln!(
+            table,
+            "{:<width$} {:>12.4} {:>12.4} {:>10.3} {:>8.4}",
+            names[j], coef[j], se[j], t[j], p_vals[j],
+        );
+    }
+
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+    use crate::test_fixtures::Fitted;
+
+    #[test]
+    fn test_coef_table() -> Result<(), AmitaError> {
+        let nan = f64::NAN;
+        let fitted = Fitted { coef: array![1.5, nan], vcov: array![[0.25, nan], [nan, nan]] };
+        let table = fitted.summary()?;
+        let lines = table.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 3);
+        assert!( lines[0].ends_with("P>|z|") );
+        assert_eq!(lines[1], "x0          1.5000       0.5000      3.000   0.0027");
+        assert!( lines[2].starts_with("x1") && lines[2].contains("NaN") );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,75 @@
+//! Minimal fitted results and solver for the tests of the inference
+//! utilities, which need a model but not any particular one
+
+use amita_error::AmitaError;
+use ndarray::{Array1, Array2};
+use statrs::distribution::{ContinuousCDF, Normal};
+
+use crate::linalg::inverse;
+use crate::summary::coef_table;
+use crate::traits::{BaseResults, BaseSolver};
+
+/// Coefficients and their covariance, with asymptotic z inference
+#[derive(Debug, Clone)]
+pub(crate) struct Fitted {
+    pub(crate) coef: Array1<f64>,
+    pub(crate) vcov: Array2<f64>,
+}
+
+impl BaseResults for Fitted {
+    fn coef(&self) -> Result<Array1<f64>, AmitaError> {
+        Ok( self.coef.clone() )
+    }
+
+    fn se(&self) -> Result<Array1<f64>, AmitaError> {
+        Ok( self.vcov.diag().map(|v| v.sqrt()) )
+    }
+
+    fn t(&self) -> Result<Array1<f64>, AmitaError> {
+        Ok( &self.coef / &self.se()? )
+    }
+
+    fn p_vals(&self) -> Result<Array1<f64>, AmitaError> {
+        let normal = Normal::new(0., 1.).unwrap();
+        Ok( self.t()?.map(|t| 2. * ( 1. - normal.cdf(t.abs()) )) )
+    }
+
+    fn vcov(&self) -> Result<Array2<f64>, AmitaError> {
+        Ok( self.vcov.clone() )
+    }
+
+    fn summary(&self) -> Result<String, AmitaError> {
+        coef_table(self)
+    }
+}
+
+/// Least squares with homoskedastic standard errors
+#[derive(Debug, Clone)]
+pub(crate) struct LeastSquares {
+    y: Array1<f64>,
+    x: Array2<f64>,
+    results: Option<Fitted>,
+}
+
+impl LeastSquares {
+    pub(crate) fn new(y: &Array1<f64>, x: &Array2<f64>) -> Self {
+        Self { y: y.clone(), x: x.clone(), results: None }
+    }
+}
+
+impl BaseSolver<Fitted> for LeastSquares {
+    fn results(&self) -> Fitted {
+        self.results.clone().unwrap()
+    }
+
+    fn solve(mut self) -> Result<Self, AmitaError> {
+        let (n, k) = self.x.dim();
+        let gramian_inverse = inverse(&self.x.t().dot(&self.x), "X'X")?;
+        let coef = gramian_inverse.dot(&self.x.t().dot(&self.y));
+        let resid = &self.y - &self.x.dot(&coef);
+        let vcov = gramian_inverse * resid.dot(&resid) / (n - k) as f64;
+
+        self.results = Some(Fitted { coef, vcov });
+        Ok(self)
+    }
+}
@@ -1,4 +1,4 @@
-use ndarray::Array1;
+use ndarray::{Array1, Array2};
 
 use amita_error::AmitaError;
 
@@ -34,5 +34,19 @@ pub trait BaseResults {
 
     fn p_vals(&self) -> Result<Array1<f64>, AmitaError>;
 
+    fn vcov(&self) -> Result<Array2<f64>, AmitaError>;
+
     fn summary(&self) -> Result<String, AmitaError>;
+
+    /// Names of the coefficients, which hypotheses refer to: x0, x1, ...
+    /// by position unless the model stores its own
+    fn coef_names(&self) -> Result<Vec<String>, AmitaError> {
+        Ok( (0..self.coef()?.len()).map(|j| format!("x{j}")).collect() )
+    }
+
+    /// Denominator degrees of freedom of small-sample t and F tests, or
+    /// None when inference is asymptotic
+    fn small_sample_df(&self) -> Option<f64> {
+        None
+    }
 }
\ No newline at end of file
@@ -3,6 +3,7 @@
 
 mod models;
 mod miscellaneous;
+mod ols;
 
 #[allow(dead_code)]
 mod datasets;
\ No newline at end of file
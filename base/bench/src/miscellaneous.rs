@@ -34,7 +34,7 @@ mod tests {
             .unwrap()
             .shape().0 > 0;
 
-        let tag = (0..uniques.height()).into_iter().map(|x| x as i32).collect::<Series>();
+        let tag = (0..uniques.height()).map(|x| x as i32).collect::<Series>();
         let tag = 
             uniques
             .clone()
@@ -1,7 +1,7 @@
 
 #[cfg(test)]
 mod tests {
-    use amita_models::did::twfe::TWFE;
+    use panel::did::twfe::TWFE;
 
     use crate::datasets::banks;
 
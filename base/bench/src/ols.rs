@@ -0,0 +1,65 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use amita_base::linear::ols::OLSSolver;
+    use amita_utils::inference::SolverSEType;
+    use amita_utils::traits::{BaseResults, BaseSolver};
+    use linfa_linalg::qr::QR;
+    use ndarray::prelude::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    /// The former solver: thin Q materialized, R inverted by a second QR and
+    /// R'R by a third, HC1 meat from cloned inputs
+    fn materialized_q_ols(y: &Array1<f64>, x: &Array2<f64>) -> (Array1<f64>, Array1<f64>) {
+        let (n, k) = x.dim();
+        let qr = x.clone().qr().unwrap();
+        let q = qr.generate_q();
+        let r = qr.into_r();
+
+        let r_inverse = r.clone().qr().unwrap().inverse().unwrap();
+        let coef = r_inverse.dot(&q.t()).dot(&y.clone());
+        let resid = y.clone() - x.clone().dot(&coef);
+
+        let x_gramian_inverse = r.t().dot(&r).qr().unwrap().inverse().unwrap();
+        let meat = x.t().dot(&(x * &resid.map(|e| e.powi(2)).insert_axis(Axis(1))));
+        let vcov = x_gramian_inverse.dot(&meat).dot(&x_gramian_inverse) * n as f64 / (n - k) as f64;
+
+        (coef, vcov.diag().map(|v| v.sqrt()))
+    }
+
+    /// Run with `cargo test -p bench --release -- --ignored bench_ols`, and
+    /// `AMITA_BENCH_ROWS` to change the number of observations
+    #[test]
+    #[ignore]
+    fn bench_ols() {
+        let n = std::env::var("AMITA_BENCH_ROWS").ok()
+            .and_then(|rows| rows.parse().ok())
+            .unwrap_or(1_000_000);
+        let k = 10;
+
+        let mut rng = StdRng::seed_from_u64(47);
+        let mut x = Array2::from_shape_fn((n, k), |_| rng.gen_range(-1. .. 1.));
+        x.column_mut(0).fill(1.);
+        let y = x.dot(&Array1::linspace(1., 2., k)) + Array1::from_shape_fn(n, |_| rng.gen_range(-1. .. 1.));
+
+        let start = Instant::now();
+        let (baseline_coef, baseline_se) = materialized_q_ols(&y, &x);
+        let baseline = start.elapsed();
+
+        let start = Instant::now();
+        let results = OLSSolver::new(&y, &x).unwrap()
+            .with_se_type(SolverSEType::HC1)
+            .solve().unwrap()
+            .results();
+        let current = start.elapsed();
+
+        println!("OLS with HC1 errors, {n} × {k}");
+        println!("  materialized Q: {baseline:?}");
+        println!("  OLSSolver:      {current:?}");
+
+        assert!( (results.coef().unwrap() - baseline_coef).iter().all(|d| d.abs() < 1e-8) );
+        assert!( (results.se().unwrap() - baseline_se).iter().all(|d| d.abs() < 1e-8) );
+    }
+}
@@ -0,0 +1,2 @@
+pub mod ols;
+pub mod streaming;
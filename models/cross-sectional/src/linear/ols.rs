@@ -0,0 +1,151 @@
+use amita_base::linear::ols::wild_cluster::{WildClusterBootstrap, WildClusterTest};
+use amita_base::linear::ols::{OLSResults, OLSSolver, WeightType};
+use amita_base::prediction::Prediction;
+use amita_error::AmitaError;
+use amita_utils::frame::{column_to_array1, column_to_group_ids, columns_to_array2};
+use amita_utils::inference::ModelSEType;
+use amita_utils::traits::BaseSolver;
+use ndarray::{concatenate, Array2, Axis};
+use polars::prelude::*;
+
+/// Linear regression of `outcome` on `regressors` and a constant, the last
+/// coefficient, optionally weighted by a column of the data.
+#[derive(Debug, Clone)]
+pub struct OLS {
+    data: DataFrame,
+    outcome: String,
+    regressors: Vec<String>,
+    weights: Option<(String, WeightType)>,
+    se_type: ModelSEType,
+}
+
+impl OLS {
+    pub fn new(
+        data: &DataFrame,
+        outcome: &str,
+        regressors: Vec<String>,
+    ) -> OLS {
+        OLS {
+            data: data.clone(),
+            outcome: outcome.to_string(),
+            regressors,
+            weights: None,
+            se_type: ModelSEType::NonRobust,
+        }
+    }
+
+    pub fn with_weights(mut self, weights: &str, weight_type: WeightType) -> Self {
+        self.weights = Some( (weights.to_string(), weight_type) );
+        self
+    }
+
+    pub fn with_se_type(mut self, se_type: ModelSEType) -> Self {
+        self.se_type = se_type;
+        self
+    }
+
+    pub fn fit(&self) -> Result<OLSResults, AmitaError> {
+        let solver = self.get_solver()?;
+        let solver = solver.solve()?;
+        Ok( solver.results() )
+    }
+
+    /// Predictions from `results` of this model at the rows of `data`,
+    /// which holds the same regressors
+    pub fn predict(&self, results: &OLSResults, data: &DataFrame) -> Result<Prediction, AmitaError> {
+        results.predict(&self.design(data)?)
+    }
+
+    /// Wild cluster restricted bootstrap of the coefficient of the
+    /// regressor `coefficient`, or "const", clustered by the column `cluster`
+    pub fn wild_cluster_bootstrap(
+        &self,
+        coefficient: &str,
+        cluster: &str,
+        bootstrap: &WildClusterBootstrap,
+    ) -> Result<WildClusterTest, AmitaError> {
+        let clusters = column_to_group_ids(&self.data, cluster)?;
+        self.get_solver()?.wild_cluster_bootstrap(coefficient, &clusters, bootstrap)
+    }
+
+    /// Regressors of `data` followed by the constant
+    fn design(&self, data: &DataFrame) -> Result<Array2<f64>, AmitaError> {
+        let x = columns_to_array2(data, &self.regressors)?;
+        Ok( concatenate![Axis(1), x, Array2::ones((data.height(), 1))] )
+    }
+
+    fn get_solver(&self) -> Result<OLSSolver, AmitaError> {
+        let y = column_to_array1(&self.data, &self.outcome)?;
+        let x = self.design(&self.data)?;
+
+        let mut names = self.regressors.clone();
+        names.push("const".to_string());
+
+        let solver = OLSSolver::from_owned(y, x)?
+            .with_coef_names(names)?
+            .with_se_type(self.se_type.clone().to_solver_se_type(&self.data)?);
+
+        match &self.weights {
+            Some( (weights, weight_type) ) => {
+                let weights = column_to_array1(&self.data, weights)?;
+                solver.with_weights(&weights, *weight_type)
+            },
+            None => Ok(solver),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use amita_utils::hypothesis::LinearHypothesis;
+    use amita_utils::traits::BaseResults;
+
+    use super::*;
+
+    #[test]
+    fn test_weighted_ols() -> Result<(), AmitaError> {
+        // cell means of y = 1 + 2 x, weighted by cell sizes
+        let df = df!(
+            "y" => [3.2, 4.8, 7.1, 9.3, 10.6, 13.2],
+            "x" => [1., 2., 3., 4., 5., 6.],
+            "n" => [12., 3., 25., 8., 30., 4.],
+            "region" => [1, 1, 2, 2, 3, 3],
+        ).unwrap();
+
+        let unweighted = OLS::new(&df, "y", vec!["x".to_string()]).fit()?;
+        let weighted = OLS::new(&df, "y", vec!["x".to_string()])
+            .with_weights("n", WeightType::Analytic)
+            .with_se_type(ModelSEType::HC1)
+            .fit()?;
+
+        assert_eq!(weighted.coef()?.len(), 2);
+        assert!( (weighted.coef()? - unweighted.coef()?).iter().any(|d| d.abs() > 1e-3) );
+        assert!( (weighted.coef()?[0] - 2.).abs() < 0.2 );
+
+        // hypotheses refer to the columns, with the robust covariance
+        let test = weighted.test_hypothesis("x = 2, const = 1")?;
+        assert_eq!(test.f().unwrap().df(), vec![2., 4.]);
+        assert_eq!(weighted.coef_names()?, vec!["x".to_string(), "const".to_string()]);
+
+        // predictions from new data only need the regressors
+        let model = OLS::new(&df, "y", vec!["x".to_string()]);
+        let results = model.fit()?;
+        let prediction = model.predict(&results, &df!("x" => [2.5, 7.]).unwrap())?;
+        let coef = results.coef()?;
+        assert!( (prediction.predicted()[1] - (coef[1] + 7. * coef[0])).abs() < 1e-10 );
+        let (lower, upper) = prediction.pred_int(0.95)?;
+        assert!( lower[0] < prediction.predicted()[0] && prediction.predicted()[0] < upper[0] );
+
+        // three regions enumerate all 2^3 sign patterns
+        let bootstrap = model.wild_cluster_bootstrap("x", "region", &WildClusterBootstrap::new())?;
+        assert!( bootstrap.enumerated() && bootstrap.n_draws() == 8 );
+        assert!( (bootstrap.estimate() - coef[0]).abs() < 1e-10 );
+
+        let missing = OLS::new(&df, "y", vec!["x".to_string()])
+            .with_weights("weight", WeightType::Analytic)
+            .fit();
+        assert!( matches!(missing, Err(AmitaError::ColumnNotFound { .. })) );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+
+use amita_base::linear::ols::streaming::{StreamingOLSSolver, StreamingSEType};
+use amita_base::linear::ols::OLSResults;
+use amita_error::AmitaError;
+use amita_utils::frame::{column_to_array1, columns_to_array2};
+use amita_utils::inference::ModelSEType;
+use amita_utils::traits::BaseSolver;
+use ndarray::{concatenate, Array1, Array2, Axis};
+use polars::prelude::*;
+
+/// Linear regression of `outcome` on `regressors` and a constant, the last
+/// coefficient, collecting the columns used `chunk_size` rows of a
+/// LazyFrame at a time, so the data, such as a CSV scanned with
+/// `LazyCsvReader`, need not fit in memory. The query runs once per chunk
+/// and must give its rows in the same order every time. Robust and
+/// clustered standard errors read the data twice.
+#[derive(Clone)]
+pub struct StreamingOLS {
+    data: LazyFrame,
+    outcome: String,
+    regressors: Vec<String>,
+    se_type: ModelSEType,
+    chunk_size: usize,
+}
+
+impl StreamingOLS {
+    pub fn new(
+        data: LazyFrame,
+        outcome: &str,
+        regressors: Vec<String>,
+    ) -> StreamingOLS {
+        StreamingOLS {
+            data,
+            outcome: outcome.to_string(),
+            regressors,
+            se_type: ModelSEType::NonRobust,
+            chunk_size: 100_000,
+        }
+    }
+
+    /// Rows per chunk, 100,000 by default
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Standard errors, all but multiway clustering
+    pub fn with_se_type(mut self, se_type: ModelSEType) -> Self {
+        self.se_type = se_type;
+        self
+    }
+
+    pub fn fit(&self) -> Result<OLSResults, AmitaError> {
+        let (se_type, cluster) = match &self.se_type {
+            ModelSEType::Homoscedastic | ModelSEType::NonRobust => (StreamingSEType::NonRobust, None),
+            ModelSEType::HC1 | ModelSEType::Robust => (StreamingSEType::HC1, None),
+            ModelSEType::HC2 => (StreamingSEType::HC2, None),
+            ModelSEType::HC3 => (StreamingSEType::HC3, None),
+            ModelSEType::Clustered { by } => (StreamingSEType::Clustered, Some(by.as_str())),
+            ModelSEType::MultiwayClustered { .. } => {
+                return Err(AmitaError::UnsupportedSEType {
+                    se_type: "multiway clustered standard errors in streaming OLS".to_string()
+                });
+            },
+        };
+
+        let mut columns = vec![col(&self.outcome)];
+        columns.extend(self.regressors.iter().map(|regressor| col(regressor)));
+        columns.extend(cluster.map(col));
+        let query = self.data.clone().select(columns);
+
+        let mut names = self.regressors.clone();
+        names.push("const".to_string());
+        let mut solver = StreamingOLSSolver::new(names.len())
+            .with_coef_names(names)?
+            .with_se_type(se_type);
+
+        self.for_each_chunk(&query, |chunk| {
+            let (y, x) = self.design(chunk)?;
+            solver.update(&y, &x)
+        })?;
+
+        if solver.needs_scores() {
+            // cluster values keep their ids from one chunk to the next
+            let mut cluster_ids = HashMap::new();
+            self.for_each_chunk(&query, |chunk| {
+                let (y, x) = self.design(chunk)?;
+                let clusters = match cluster {
+                    Some(cluster) => Some(stable_group_ids(chunk, cluster, &mut cluster_ids)?),
+                    None => None,
+                };
+                solver.update_scores(&y, &x, clusters.as_ref())
+            })?;
+        }
+
+        Ok( solver.solve()?.results() )
+    }
+
+    /// Collects consecutive slices of `chunk_size` rows of `query`, one at
+    /// a time, until one comes back short
+    fn for_each_chunk<F>(&self, query: &LazyFrame, mut f: F) -> Result<(), AmitaError>
+    where
+        F: FnMut(&DataFrame) -> Result<(), AmitaError>,
+    {
+        let mut offset = 0;
+        loop {
+            let chunk = query.clone()
+                .slice(offset, self.chunk_size as IdxSize)
+                .collect()
+                .map_err(|e| AmitaError::SolverFailed { message: e.to_string() })?;
+            if chunk.height() > 0 {
+                f(&chunk)?;
+            }
+            if chunk.height() < self.chunk_size {
+                return Ok(());
+            }
+
+            offset += chunk.height() as i64;
+        }
+    }
+
+    /// Outcome, and regressors followed by the constant
+    fn design(&self, chunk: &DataFrame) -> Result<(Array1<f64>, Array2<f64>), AmitaError> {
+        let y = column_to_array1(chunk, &self.outcome)?;
+        let x = columns_to_array2(chunk, &self.regressors)?;
+        Ok( (y, concatenate![Axis(1), x, Array2::ones((chunk.height(), 1))]) )
+    }
+}
+
+/// Ids of the values of `column`, numbered in order of first appearance
+/// across every chunk seen with `ids`
+fn stable_group_ids(
+    chunk: &DataFrame,
+    column: &str,
+    ids: &mut HashMap<String, i32>,
+) -> Result<Array1<i32>, AmitaError> {
+    let dtype_error = |found: String| AmitaError::ColumnDataTypeError {
+        column: column.to_string(),
+        expected: "bool, int, or string without nulls".to_string(),
+        found,
+    };
+
+    let series = chunk.column(column).map_err(|_| AmitaError::ColumnNotFound { column: column.to_string() })?;
+    if series.null_count() > 0 {
+        return Err(dtype_error("nulls".to_string()));
+    }
+    let values = series.cast(&DataType::String).map_err(|e| dtype_error(e.to_string()))?;
+    let values = values.str().map_err(|e| dtype_error(e.to_string()))?;
+
+    Ok( values.into_no_null_iter()
+        .map(|value| {
+            let next = ids.len() as i32;
+            *ids.entry(value.to_string()).or_insert(next)
+        })
+        .collect() )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use amita_utils::traits::BaseResults;
+
+    use super::*;
+    use crate::linear::ols::OLS;
+
+    #[test]
+    fn test_streaming_matches_in_memory() -> Result<(), AmitaError> {
+        let n = 200;
+        let x = (0..n).map(|i| ((i * 37) % 101) as f64 / 10.).collect::<Vec<_>>();
+        let z = (0..n).map(|i| ((i * 13) % 7) as f64).collect::<Vec<_>>();
+        let y = (0..n).map(|i| 1. + 0.5 * x[i] - 0.3 * z[i] + ((i * 71) % 29) as f64 / 5.).collect::<Vec<_>>();
+        let firm = (0..n).map(|i| format!("firm{}", i % 17)).collect::<Vec<_>>();
+        let df = df!("y" => y, "x" => x, "z" => z, "firm" => firm).unwrap();
+        let regressors = vec!["x".to_string(), "z".to_string()];
+
+        let se_types = [
+            ModelSEType::NonRobust,
+            ModelSEType::HC3,
+            ModelSEType::Clustered { by: "firm".to_string() },
+        ];
+        for se_type in se_types {
+            let in_memory = OLS::new(&df, "y", regressors.clone()).with_se_type(se_type.clone()).fit()?;
+            let streamed = StreamingOLS::new(df.clone().lazy(), "y", regressors.clone())
+                .with_chunk_size(33)
+                .with_se_type(se_type)
+                .fit()?;
+
+            assert!( (streamed.coef()? - in_memory.coef()?).iter().all(|d| d.abs() < 1e-10) );
+            assert!( (streamed.se()? - in_memory.se()?).iter().all(|d| d.abs() < 1e-10) );
+            assert_eq!(streamed.coef_names()?, in_memory.coef_names()?);
+        }
+
+        let multiway = StreamingOLS::new(df.lazy(), "y", regressors)
+            .with_se_type(ModelSEType::MultiwayClustered { by: vec!["firm".to_string(), "z".to_string()] })
+            .fit();
+        assert!( matches!(multiway, Err(AmitaError::UnsupportedSEType { .. })) );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_robust_se_over_a_query() -> Result<(), AmitaError> {
+        let n = 150;
+        let x = (0..n).map(|i| ((i * 53) % 97) as f64 / 10.).collect::<Vec<_>>();
+        let y = (0..n).map(|i| 2. - 0.4 * x[i] + ((i * 31) % 23) as f64 / 4.).collect::<Vec<_>>();
+        let df = df!("y" => y, "x" => x).unwrap();
+
+        // both passes over the robust standard errors run the query again, chunk by chunk
+        let query = df.lazy()
+            .filter(col("x").gt(lit(1.)))
+            .sort(["x"], SortMultipleOptions::default());
+        let collected = query.clone().collect().unwrap();
+
+        let in_memory = OLS::new(&collected, "y", vec!["x".to_string()]).with_se_type(ModelSEType::HC1).fit()?;
+        let streamed = StreamingOLS::new(query, "y", vec!["x".to_string()])
+            .with_chunk_size(16)
+            .with_se_type(ModelSEType::HC1)
+            .fit()?;
+
+        assert_eq!(streamed.n_obs(), collected.height());
+        assert!( (streamed.coef()? - in_memory.coef()?).iter().all(|d| d.abs() < 1e-10) );
+        assert!( (streamed.se()? - in_memory.se()?).iter().all(|d| d.abs() < 1e-10) );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_collects_one_chunk_at_a_time() -> Result<(), AmitaError> {
+        let n = 100;
+        let x = (0..n).map(|i| ((i * 41) % 89) as f64 / 10.).collect::<Vec<_>>();
+        let y = (0..n).map(|i| 1. + 0.8 * x[i] + ((i * 17) % 11) as f64 / 3.).collect::<Vec<_>>();
+        let df = df!("y" => y, "x" => x, "unused" => vec![0.; n]).unwrap();
+
+        // records the rows and columns of every frame the query produces
+        let seen = Arc::new(Mutex::new(vec![]));
+        let record = Arc::clone(&seen);
+        let query = df.clone().lazy().map(
+            move |chunk| {
+                record.lock().unwrap().push(chunk.shape());
+                Ok(chunk)
+            },
+            AllowedOptimizations::default(),
+            None,
+            None,
+        );
+
+        let in_memory = OLS::new(&df, "y", vec!["x".to_string()]).with_se_type(ModelSEType::HC1).fit()?;
+        let streamed = StreamingOLS::new(query, "y", vec!["x".to_string()])
+            .with_chunk_size(30)
+            .with_se_type(ModelSEType::HC1)
+            .fit()?;
+        assert!( (streamed.se()? - in_memory.se()?).iter().all(|d| d.abs() < 1e-10) );
+
+        // two passes of four chunks, each of at most 30 rows of the two columns used
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 8);
+        assert!( seen.iter().all(|(rows, columns)| *rows <= 30 && *columns == 2) );
+        assert_eq!(seen.iter().map(|(rows, _)| rows).sum::<usize>(), 2 * n);
+
+        Ok(())
+    }
+}
@@ -1,5 +1,7 @@
-use amita_base::linear::ols::{OLSResults, OLSSolver};
+use amita_base::linear::ols::wild_cluster::{WildClusterBootstrap, WildClusterTest};
+use amita_base::linear::ols::{OLSResults, OLSSolver, WeightType};
 use amita_error::AmitaError;
+use amita_utils::frame::{column_to_array1, column_to_group_ids};
 use amita_utils::traits::BaseSolver;
 use polars::prelude::*;
 
@@ -10,6 +12,7 @@ pub struct TWFE {
     treat: String,
     post: String,
     covariates: Option<Vec<String>>,
+    weights: Option<(String, WeightType)>,
 }
 
 impl TWFE {
@@ -26,28 +29,43 @@ impl TWFE {
             treat: treat.to_string(),
             post: post.to_string(),
             covariates: covarites,
+            weights: None,
         }
     }
 
+    pub fn with_weights(mut self, weights: &str, weight_type: WeightType) -> Self {
+        self.weights = Some( (weights.to_string(), weight_type) );
+        self
+    }
+
     pub fn fit(&self) -> Result<OLSResults, AmitaError> {
         let solver = self.get_solver()?;
         let solver = solver.solve()?;
         Ok( solver.results() )
     }
 
+    /// Wild cluster restricted bootstrap of the treatment effect, the
+    /// coefficient of treat*post, clustered by the column `cluster`, for
+    /// inference with few clusters such as states
+    pub fn wild_cluster_bootstrap(
+        &self,
+        cluster: &str,
+        bootstrap: &WildClusterBootstrap,
+    ) -> Result<WildClusterTest, AmitaError> {
+        let clusters = column_to_group_ids(&self.data, cluster)?;
+        self.get_solver()?.wild_cluster_bootstrap("treat*post", &clusters, bootstrap)
+    }
+
     fn get_solver(&self) -> Result<OLSSolver, AmitaError> {
         let df = self.data.clone();
-        let mut c = vec![];
-        for _ in 0..df.height() {
-            c.push(1.0);
-        }
-        let c = Series::new("_const", c);
+        let c = Series::new("_const", vec![1.0; df.height()]);
 
         let mut columns = self.covariates.clone().unwrap_or(vec![]);
         columns.push(self.treat.clone());
         columns.push(self.post.clone());
         columns.push("treat*post".to_string());
         columns.push("_const".to_string());
+        let names = columns.clone();
         let columns = columns.iter().map(|x| {col(x)}).collect::<Vec<_>>();
 
 
@@ -73,14 +91,52 @@ impl TWFE {
             .into_shape((df.height(),))
             .unwrap();
 
-        let solver = OLSSolver::new(
-            &outcome,
-            &regressors,
-        )?;
+        let solver = OLSSolver::from_owned(
+            outcome,
+            regressors,
+        )?
+        .with_coef_names(names)?;
 
         // println!("{:#?}", solver);
 
-        Ok(solver)
+        match &self.weights {
+            Some( (weights, weight_type) ) => {
+                let weights = column_to_array1(&df, weights)?;
+                solver.with_weights(&weights, *weight_type)
+            },
+            None => Ok(solver),
+        }
 
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use amita_utils::traits::BaseResults;
+
+    use super::*;
+
+    #[test]
+    fn test_wild_cluster_bootstrap() -> Result<(), AmitaError> {
+        // two periods of 12 states, a third of them treated
+        let n_states = 12;
+        let state = (0..2 * n_states).map(|i| (i % n_states) as i32).collect::<Vec<_>>();
+        let post = (0..2 * n_states).map(|i| (i / n_states) as f64).collect::<Vec<_>>();
+        let treat = state.iter().map(|s| if s % 3 == 0 { 1. } else { 0. }).collect::<Vec<_>>();
+        let y = (0..2 * n_states)
+            .map(|i| 2. + 0.4 * post[i] + 0.8 * treat[i] * post[i] + ((i * 7) % 5) as f64 / 5. + (state[i] % 4) as f64 / 2.)
+            .collect::<Vec<_>>();
+        let df = df!("y" => y, "treat" => treat, "post" => post, "state" => state).unwrap();
+
+        let twfe = TWFE::new(&df, "y", "treat", "post", None);
+        let test = twfe.wild_cluster_bootstrap("state", &WildClusterBootstrap::new().with_replications(4096)?)?;
+        assert!( test.enumerated() && test.n_clusters() == 12 );
+        assert!( (test.estimate() - twfe.fit()?.coef()?[2]).abs() < 1e-10 );
+
+        let (lower, upper) = test.conf_int(0.9)?;
+        assert!( lower < test.estimate() && test.estimate() < upper );
+        assert!( test.p_value() > 0. && test.p_value() <= 1. );
+
+        Ok(())
+    }
+}
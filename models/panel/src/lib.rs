@@ -1,3 +1 @@
-fn main() {
-    println!("Hello, world!");
-}
+pub mod did;
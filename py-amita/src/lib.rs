@@ -46,11 +46,7 @@ impl TWFE {
 
     fn get_solver(&self) -> Result<OLSSolver, AmitaError> {
         let df = self.data.clone();
-        let mut c = vec![];
-        for _ in 0..df.height() {
-            c.push(1.0);
-        }
-        let c = Series::new("_const", c);
+        let c = Series::new("_const", vec![1.0; df.height()]);
 
         let mut columns = self.covariates.clone().unwrap_or(vec![]);
         columns.push(self.treat.clone());